@@ -1,33 +1,99 @@
-use std::io::{BufRead, Write};
+use std::io::{Read, Seek, SeekFrom, Write};
+#[cfg(target_os = "windows")]
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use bytes::{Buf, BufMut, BytesMut};
-use junowen_lib::{
-    structs::{
-        selection::{Difficulty, PlayerMatchup},
-        settings::GameSettings,
-    },
-    Th19,
+use junowen_lib::structs::{
+    selection::{Difficulty, PlayerMatchup},
+    settings::GameSettings,
 };
+#[cfg(target_os = "windows")]
+use junowen_lib::Th19;
+
+const MAGIC: &[u8; 4] = b"JNRP";
+pub const LATEST_VERSION: u16 = 2;
+const V1_HEADER_LEN: usize = 13;
 
 pub enum FileInputList {
     HumanVsHuman(Vec<(u16, u16)>),
     HumanVsCpu(Vec<u16>),
 }
 
+impl FileInputList {
+    pub fn new(player_matchup: PlayerMatchup) -> Self {
+        match player_matchup {
+            PlayerMatchup::HumanVsHuman
+            | PlayerMatchup::CpuVsCpu
+            | PlayerMatchup::YoukaiVsYoukai => Self::HumanVsHuman(Vec::new()),
+            PlayerMatchup::HumanVsCpu => Self::HumanVsCpu(Vec::new()),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Self::HumanVsHuman(vec) => vec.len(),
+            Self::HumanVsCpu(vec) => vec.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn input_size(&self) -> usize {
+        match self {
+            Self::HumanVsHuman(_) => 4,
+            Self::HumanVsCpu(_) => 2,
+        }
+    }
+
+    fn read_inputs(&mut self, buf: &mut &[u8], count: usize) -> Result<()> {
+        ensure_remaining(buf, count * self.input_size())?;
+        match self {
+            Self::HumanVsHuman(vec) => {
+                vec.extend((0..count).map(|_| (buf.get_u16_le(), buf.get_u16_le())))
+            }
+            Self::HumanVsCpu(vec) => vec.extend((0..count).map(|_| buf.get_u16_le())),
+        }
+        Ok(())
+    }
+
+    fn write_inputs(&self, buf: &mut BytesMut) {
+        match self {
+            Self::HumanVsHuman(vec) => {
+                for input in vec {
+                    buf.put_u16_le(input.0);
+                    buf.put_u16_le(input.1);
+                }
+            }
+            Self::HumanVsCpu(vec) => {
+                for input in vec {
+                    buf.put_u16_le(*input);
+                }
+            }
+        }
+    }
+}
+
 impl Default for FileInputList {
     fn default() -> Self {
         Self::HumanVsHuman(Vec::new())
     }
 }
 
+/// 1 ラウンド分の記録。inputs の添字はラウンド内のフレーム番号に対応する
 #[derive(Default)]
-pub struct ReplayFile {
+pub struct ReplayRound {
+    /// UNIX 時間 (秒)。v1 から読み込んだ場合は 0
+    pub started_at: i64,
     pub rand_seed1: u16,
     pub rand_seed2: u16,
+    /// v1 から読み込んだ場合は 0
+    pub rand_seed3: u16,
+    /// v1 から読み込んだ場合は 0
+    pub rand_seed4: u16,
     pub difficulty: Difficulty,
-    pub player_matchup: PlayerMatchup,
-    pub battle_settings: GameSettings,
     pub p1_character: u8,
     pub p1_card: u8,
     pub p2_character: u8,
@@ -35,33 +101,120 @@ pub struct ReplayFile {
     pub inputs: FileInputList,
 }
 
+impl ReplayRound {
+    fn write_to(&self, buf: &mut BytesMut) {
+        buf.put_u16_le(self.rand_seed1);
+        buf.put_u16_le(self.rand_seed2);
+        buf.put_u16_le(self.rand_seed3);
+        buf.put_u16_le(self.rand_seed4);
+        buf.put_i64_le(self.started_at);
+        buf.put_u8(self.difficulty as u8);
+        buf.put_u8(self.p1_character);
+        buf.put_u8(self.p1_card);
+        buf.put_u8(self.p2_character);
+        buf.put_u8(self.p2_card);
+        buf.put_u32_le(self.inputs.len() as u32);
+        self.inputs.write_inputs(buf);
+    }
+}
+
+/**
+ * リプレイファイル
+ *
+ * v2 のレイアウト (リトルエンディアン):
+ * magic "JNRP", version: u16, recorded_at: i64,
+ * p1_name, p2_name (u16 長 + UTF-8), player_matchup: u8, battle_settings: u32 * 3,
+ * ラウンド数: u32, ラウンド * n, CRC32: u32
+ *
+ * ラウンド:
+ * rand_seed1-4: u16 * 4, started_at: i64, difficulty, p1_character, p1_card,
+ * p2_character, p2_card: u8 * 5, 入力数: u32, 入力 * n
+ *
+ * v1 はマジックのない 13 バイトのヘッダーと 1 ラウンド分の入力のみ
+ */
+#[derive(Default)]
+pub struct ReplayFile {
+    /// 読み込み元のバージョン
+    pub version: u16,
+    /// UNIX 時間 (秒)。v1 から読み込んだ場合は 0
+    pub recorded_at: i64,
+    pub p1_name: String,
+    pub p2_name: String,
+    pub player_matchup: PlayerMatchup,
+    pub battle_settings: GameSettings,
+    pub rounds: Vec<ReplayRound>,
+}
+
+fn ensure_remaining(buf: &[u8], len: usize) -> Result<()> {
+    if buf.len() < len {
+        bail!("unexpected end of replay file");
+    }
+    Ok(())
+}
+
+fn read_string(buf: &mut &[u8]) -> Result<String> {
+    ensure_remaining(buf, 2)?;
+    let len = buf.get_u16_le() as usize;
+    ensure_remaining(buf, len)?;
+    let string = String::from_utf8(buf[..len].to_vec())?;
+    buf.advance(len);
+    Ok(string)
+}
+
+fn write_string(buf: &mut BytesMut, string: &str) -> Result<()> {
+    let Ok(len) = u16::try_from(string.len()) else {
+        bail!("string too long: {}", string.len());
+    };
+    buf.put_u16_le(len);
+    buf.put_slice(string.as_bytes());
+    Ok(())
+}
+
 impl ReplayFile {
+    #[cfg(target_os = "windows")]
     pub fn read_header_from_memory(th19: &Th19) -> Result<Self> {
         let player_matchup = th19.selection().player_matchup;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs() as i64)
+            .unwrap_or_default();
         Ok(Self {
-            rand_seed1: th19.rand_seed1()?,
-            rand_seed2: th19.rand_seed2()?,
-            difficulty: th19.selection().difficulty,
+            version: LATEST_VERSION,
+            recorded_at: now,
+            p1_name: String::new(),
+            p2_name: String::new(),
             player_matchup,
             battle_settings: th19.game_settings_in_game()?,
-            p1_character: th19.selection().p1().character as u8,
-            p1_card: th19.selection().p1().card as u8,
-            p2_character: th19.selection().p2().character as u8,
-            p2_card: th19.selection().p2().card as u8,
-            inputs: if player_matchup == PlayerMatchup::HumanVsHuman
-                || player_matchup == PlayerMatchup::YoukaiVsYoukai
-            {
-                FileInputList::HumanVsHuman(Vec::new())
-            } else {
-                FileInputList::HumanVsCpu(Vec::new())
-            },
+            rounds: vec![ReplayRound {
+                started_at: now,
+                rand_seed1: th19.rand_seed1()?,
+                rand_seed2: th19.rand_seed2()?,
+                rand_seed3: th19.rand_seed3()?,
+                rand_seed4: th19.rand_seed4()?,
+                difficulty: th19.selection().difficulty,
+                p1_character: th19.selection().p1().character as u8,
+                p1_card: th19.selection().p1().card as u8,
+                p2_character: th19.selection().p2().character as u8,
+                p2_card: th19.selection().p2().card as u8,
+                inputs: FileInputList::new(player_matchup),
+            }],
         })
     }
 
-    pub fn read_from_reader(reader: &mut impl BufRead) -> Result<Self> {
-        let mut buf = BytesMut::new();
-        buf.resize(13, 0);
-        reader.read_exact(&mut buf)?;
+    /** v1 と v2 のどちらも読み込める */
+    pub fn read_from_reader(reader: &mut impl Read) -> Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        if data.starts_with(MAGIC) {
+            Self::read_v2(&data)
+        } else {
+            Self::read_v1(&data)
+        }
+    }
+
+    fn read_v1(data: &[u8]) -> Result<Self> {
+        let mut buf = data;
+        ensure_remaining(buf, V1_HEADER_LEN)?;
         let rand_seed1 = buf.get_u16_le();
         let rand_seed2 = buf.get_u16_le();
         let difficulty = Difficulty::try_from(buf.get_u8() as u32)?;
@@ -76,83 +229,383 @@ impl ReplayFile {
         let p2_character = buf.get_u8();
         let p2_card = buf.get_u8();
 
-        let inputs = match player_matchup {
-            PlayerMatchup::HumanVsHuman
-            | PlayerMatchup::CpuVsCpu
-            | PlayerMatchup::YoukaiVsYoukai => {
-                let mut vec = Vec::new();
-                loop {
-                    if reader.fill_buf()?.is_empty() {
-                        break;
-                    }
-                    buf.clear();
-                    buf.resize(4, 0);
-                    reader.read_exact(&mut buf)?;
-                    vec.push((buf.get_u16_le(), buf.get_u16_le()));
-                }
-                FileInputList::HumanVsHuman(vec)
-            }
-            PlayerMatchup::HumanVsCpu => {
-                let mut vec = Vec::new();
-                loop {
-                    if reader.fill_buf()?.is_empty() {
-                        break;
-                    }
-                    buf.clear();
-                    buf.resize(2, 0);
-                    reader.read_exact(&mut buf)?;
-                    vec.push(buf.get_u16_le());
-                }
-                FileInputList::HumanVsCpu(vec)
-            }
-        };
+        let mut inputs = FileInputList::new(player_matchup);
+        let input_size = inputs.input_size();
+        if !buf.len().is_multiple_of(input_size) {
+            bail!("unexpected end of replay file");
+        }
+        let input_count = buf.len() / input_size;
+        inputs.read_inputs(&mut buf, input_count)?;
+        Ok(Self {
+            version: 1,
+            recorded_at: 0,
+            p1_name: String::new(),
+            p2_name: String::new(),
+            player_matchup,
+            battle_settings,
+            rounds: vec![ReplayRound {
+                started_at: 0,
+                rand_seed1,
+                rand_seed2,
+                rand_seed3: 0,
+                rand_seed4: 0,
+                difficulty,
+                p1_character,
+                p1_card,
+                p2_character,
+                p2_card,
+                inputs,
+            }],
+        })
+    }
+
+    fn read_v2(data: &[u8]) -> Result<Self> {
+        ensure_remaining(data, MAGIC.len() + 4)?;
+        let (body, mut checksum) = data.split_at(data.len() - 4);
+        if crc32fast::hash(body) != checksum.get_u32_le() {
+            bail!("replay file checksum mismatch");
+        }
+
+        let mut buf = &body[MAGIC.len()..];
+        ensure_remaining(buf, 2 + 8)?;
+        let version = buf.get_u16_le();
+        if version != 2 {
+            bail!("unsupported replay file version: {}", version);
+        }
+        let recorded_at = buf.get_i64_le();
+        let p1_name = read_string(&mut buf)?;
+        let p2_name = read_string(&mut buf)?;
+        ensure_remaining(buf, 1 + 4 * 3 + 4)?;
+        let player_matchup = PlayerMatchup::try_from(buf.get_u8() as u32)?;
+        let battle_settings =
+            GameSettings::new(buf.get_u32_le(), buf.get_u32_le(), buf.get_u32_le());
+        let round_count = buf.get_u32_le();
+
+        let mut rounds = Vec::new();
+        for _ in 0..round_count {
+            ensure_remaining(buf, 2 * 4 + 8 + 5 + 4)?;
+            let rand_seed1 = buf.get_u16_le();
+            let rand_seed2 = buf.get_u16_le();
+            let rand_seed3 = buf.get_u16_le();
+            let rand_seed4 = buf.get_u16_le();
+            let started_at = buf.get_i64_le();
+            let difficulty = Difficulty::try_from(buf.get_u8() as u32)?;
+            let p1_character = buf.get_u8();
+            let p1_card = buf.get_u8();
+            let p2_character = buf.get_u8();
+            let p2_card = buf.get_u8();
+            let input_count = buf.get_u32_le() as usize;
+            let mut inputs = FileInputList::new(player_matchup);
+            inputs.read_inputs(&mut buf, input_count)?;
+            rounds.push(ReplayRound {
+                started_at,
+                rand_seed1,
+                rand_seed2,
+                rand_seed3,
+                rand_seed4,
+                difficulty,
+                p1_character,
+                p1_card,
+                p2_character,
+                p2_card,
+                inputs,
+            });
+        }
+        if buf.has_remaining() {
+            bail!("unexpected trailing data in replay file");
+        }
+
         Ok(Self {
-            rand_seed1,
-            rand_seed2,
-            difficulty,
+            version,
+            recorded_at,
+            p1_name,
+            p2_name,
             player_matchup,
             battle_settings,
-            p1_character,
-            p1_card,
-            p2_character,
-            p2_card,
-            inputs,
+            rounds,
         })
     }
 
+    /** ラウンド数の手前までを書く */
+    fn write_header(&self, buf: &mut BytesMut) -> Result<()> {
+        buf.put_slice(MAGIC);
+        buf.put_u16_le(LATEST_VERSION);
+        buf.put_i64_le(self.recorded_at);
+        write_string(buf, &self.p1_name)?;
+        write_string(buf, &self.p2_name)?;
+        buf.put_u8(self.player_matchup as u8);
+        buf.put_u32_le(self.battle_settings.common());
+        buf.put_u32_le(self.battle_settings.p1());
+        buf.put_u32_le(self.battle_settings.p2());
+        Ok(())
+    }
+
+    /** 常に最新のバージョンで書き込む */
     pub fn write_to(&self, writer: &mut impl Write) -> Result<()> {
         let mut buf = BytesMut::new();
-        buf.put_u16_le(self.rand_seed1);
-        buf.put_u16_le(self.rand_seed2);
-        buf.put_u8(self.difficulty as u8);
+        self.write_header(&mut buf)?;
+        buf.put_u32_le(self.rounds.len() as u32);
+        for round in &self.rounds {
+            round.write_to(&mut buf);
+        }
+        let checksum = crc32fast::hash(&buf);
+        buf.put_u32_le(checksum);
+        writer.write_all(&buf)?;
+        Ok(())
+    }
+
+    /** 旧バージョンのプレイヤー向け。1 ラウンドのみで、名前やタイムスタンプは失われる */
+    pub fn write_v1_to(&self, writer: &mut impl Write) -> Result<()> {
+        let [round] = &self.rounds[..] else {
+            bail!("v1 supports only a single round: {}", self.rounds.len());
+        };
+        let mut buf = BytesMut::new();
+        buf.put_u16_le(round.rand_seed1);
+        buf.put_u16_le(round.rand_seed2);
+        buf.put_u8(round.difficulty as u8);
         buf.put_u8(self.player_matchup as u8);
         buf.put_u8(self.battle_settings.common() as u8);
         buf.put_u8(self.battle_settings.p1() as u8);
         buf.put_u8(self.battle_settings.p2() as u8);
-        buf.put_u8(self.p1_character);
-        buf.put_u8(self.p1_card);
-        buf.put_u8(self.p2_character);
-        buf.put_u8(self.p2_card);
+        buf.put_u8(round.p1_character);
+        buf.put_u8(round.p1_card);
+        buf.put_u8(round.p2_character);
+        buf.put_u8(round.p2_card);
+        round.inputs.write_inputs(&mut buf);
         writer.write_all(&buf)?;
-        buf.clear();
+        Ok(())
+    }
+}
 
-        match &self.inputs {
-            FileInputList::HumanVsHuman(vec) => {
-                for input in vec {
-                    buf.put_u16_le(input.0);
-                    buf.put_u16_le(input.1);
-                    writer.write_all(&buf)?;
-                    buf.clear();
-                }
-            }
-            FileInputList::HumanVsCpu(vec) => {
-                for input in vec {
-                    buf.put_u16_le(*input);
-                    writer.write_all(&buf)?;
-                    buf.clear();
-                }
-            }
-        }
+/**
+ * v2 のファイルにラウンドを追記していく
+ *
+ * ヘッダーは最初に一度だけ書き、ラウンドを追記するたびにラウンド数とチェックサムだけを書き直す。
+ * 途中で中断しても、それまでに追記したラウンドは読み込める
+ */
+pub struct ReplayFileAppender<W: Write + Seek> {
+    writer: W,
+    header_len: u64,
+    header_hasher: crc32fast::Hasher,
+    rounds_hasher: crc32fast::Hasher,
+    round_count: u32,
+}
+
+impl<W: Write + Seek> ReplayFileAppender<W> {
+    /** header のラウンドは無視し、0 ラウンドのファイルを書く */
+    pub fn new(mut writer: W, header: &ReplayFile) -> Result<Self> {
+        let mut buf = BytesMut::new();
+        header.write_header(&mut buf)?;
+        writer.write_all(&buf)?;
+        let mut header_hasher = crc32fast::Hasher::new();
+        header_hasher.update(&buf);
+        let mut appender = Self {
+            writer,
+            header_len: buf.len() as u64,
+            header_hasher,
+            rounds_hasher: crc32fast::Hasher::new(),
+            round_count: 0,
+        };
+        let mut trailer = BytesMut::new();
+        trailer.put_u32_le(0);
+        trailer.put_u32_le(appender.checksum());
+        appender.writer.write_all(&trailer)?;
+        appender.writer.flush()?;
+        Ok(appender)
+    }
+
+    pub fn round_count(&self) -> u32 {
+        self.round_count
+    }
+
+    pub fn append_round(&mut self, round: &ReplayRound) -> Result<()> {
+        let mut buf = BytesMut::new();
+        round.write_to(&mut buf);
+        self.rounds_hasher.update(&buf);
+        self.round_count += 1;
+        // 末尾のチェックサムをラウンドで上書きし、その後ろに新しいチェックサムを書く
+        buf.put_u32_le(self.checksum());
+        self.writer.seek(SeekFrom::End(-4))?;
+        self.writer.write_all(&buf)?;
+        self.writer.seek(SeekFrom::Start(self.header_len))?;
+        self.writer.write_all(&self.round_count.to_le_bytes())?;
+        self.writer.flush()?;
         Ok(())
     }
+
+    fn checksum(&self) -> u32 {
+        let mut hasher = self.header_hasher.clone();
+        hasher.update(&self.round_count.to_le_bytes());
+        hasher.combine(&self.rounds_hasher);
+        hasher.finalize()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round(started_at: i64, inputs: Vec<(u16, u16)>) -> ReplayRound {
+        ReplayRound {
+            started_at,
+            rand_seed1: 1,
+            rand_seed2: 2,
+            rand_seed3: 3,
+            rand_seed4: 4,
+            difficulty: Difficulty::Lunatic,
+            p1_character: 5,
+            p1_card: 6,
+            p2_character: 7,
+            p2_card: 8,
+            inputs: FileInputList::HumanVsHuman(inputs),
+        }
+    }
+
+    fn replay_file(rounds: Vec<ReplayRound>) -> ReplayFile {
+        ReplayFile {
+            version: LATEST_VERSION,
+            recorded_at: 1_700_000_000,
+            p1_name: "霊夢".to_owned(),
+            p2_name: "marisa".to_owned(),
+            player_matchup: PlayerMatchup::HumanVsHuman,
+            battle_settings: GameSettings::new(0x31, 2, 3),
+            rounds,
+        }
+    }
+
+    fn human_vs_human_inputs(round: &ReplayRound) -> &[(u16, u16)] {
+        let FileInputList::HumanVsHuman(inputs) = &round.inputs else {
+            panic!("unexpected input list");
+        };
+        inputs
+    }
+
+    #[test]
+    fn v2_round_trip() {
+        let original = replay_file(vec![
+            round(1_700_000_010, vec![(0, 0), (1, 2), (3, 4)]),
+            round(1_700_000_100, vec![(0, 0), (0xffff, 0x8000)]),
+        ]);
+        let mut data = Vec::new();
+        original.write_to(&mut data).unwrap();
+        assert!(data.starts_with(MAGIC));
+
+        let read = ReplayFile::read_from_reader(&mut data.as_slice()).unwrap();
+        assert_eq!(read.version, 2);
+        assert_eq!(read.recorded_at, original.recorded_at);
+        assert_eq!(read.p1_name, "霊夢");
+        assert_eq!(read.p2_name, "marisa");
+        assert_eq!(read.player_matchup, PlayerMatchup::HumanVsHuman);
+        assert_eq!(read.battle_settings.common(), 0x31);
+        assert_eq!(read.battle_settings.p1(), 2);
+        assert_eq!(read.battle_settings.p2(), 3);
+        assert_eq!(read.rounds.len(), 2);
+        for (read, original) in read.rounds.iter().zip(&original.rounds) {
+            assert_eq!(read.started_at, original.started_at);
+            assert_eq!(
+                (
+                    read.rand_seed1,
+                    read.rand_seed2,
+                    read.rand_seed3,
+                    read.rand_seed4
+                ),
+                (1, 2, 3, 4)
+            );
+            assert_eq!(read.difficulty, Difficulty::Lunatic);
+            assert_eq!(
+                (
+                    read.p1_character,
+                    read.p1_card,
+                    read.p2_character,
+                    read.p2_card
+                ),
+                (5, 6, 7, 8)
+            );
+            assert_eq!(human_vs_human_inputs(read), human_vs_human_inputs(original));
+        }
+    }
+
+    #[test]
+    fn appender_matches_write_to() {
+        let original = replay_file(vec![
+            round(1_700_000_010, vec![(0, 0), (1, 2), (3, 4)]),
+            round(1_700_000_100, vec![(0, 0), (0xffff, 0x8000)]),
+        ]);
+        let mut appender =
+            ReplayFileAppender::new(std::io::Cursor::new(Vec::new()), &original).unwrap();
+        let read = ReplayFile::read_from_reader(&mut appender.writer.get_ref().as_slice()).unwrap();
+        assert!(read.rounds.is_empty());
+
+        for (i, round) in original.rounds.iter().enumerate() {
+            appender.append_round(round).unwrap();
+            let read =
+                ReplayFile::read_from_reader(&mut appender.writer.get_ref().as_slice()).unwrap();
+            assert_eq!(read.rounds.len(), i + 1);
+            assert_eq!(read.p1_name, "霊夢");
+        }
+        assert_eq!(appender.round_count(), 2);
+
+        let mut data = Vec::new();
+        original.write_to(&mut data).unwrap();
+        assert_eq!(appender.writer.into_inner(), data);
+    }
+
+    #[test]
+    fn v1_import() {
+        let original = replay_file(vec![round(1_700_000_010, vec![(0, 0), (1, 2), (3, 4)])]);
+        let mut data = Vec::new();
+        original.write_v1_to(&mut data).unwrap();
+        assert_eq!(data.len(), V1_HEADER_LEN + 3 * 4);
+
+        let read = ReplayFile::read_from_reader(&mut data.as_slice()).unwrap();
+        assert_eq!(read.version, 1);
+        assert_eq!(read.recorded_at, 0);
+        assert!(read.p1_name.is_empty());
+        assert_eq!(read.battle_settings.common(), 0x31);
+        let [round] = &read.rounds[..] else {
+            panic!("unexpected rounds: {}", read.rounds.len());
+        };
+        assert_eq!(round.started_at, 0);
+        assert_eq!(
+            (
+                round.rand_seed1,
+                round.rand_seed2,
+                round.rand_seed3,
+                round.rand_seed4
+            ),
+            (1, 2, 0, 0)
+        );
+        assert_eq!(round.difficulty, Difficulty::Lunatic);
+        assert_eq!(human_vs_human_inputs(round), [(0, 0), (1, 2), (3, 4)]);
+    }
+
+    #[test]
+    fn v1_rejects_multiple_rounds() {
+        let original = replay_file(vec![round(0, vec![]), round(0, vec![])]);
+        assert!(original.write_v1_to(&mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn crc_mismatch() {
+        let original = replay_file(vec![round(1_700_000_010, vec![(0, 0), (1, 2)])]);
+        let mut data = Vec::new();
+        original.write_to(&mut data).unwrap();
+        let index = data.len() - 5;
+        data[index] ^= 0x01;
+
+        let err = ReplayFile::read_from_reader(&mut data.as_slice())
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("checksum"));
+    }
+
+    #[test]
+    fn truncated_v2() {
+        let original = replay_file(vec![round(1_700_000_010, vec![(0, 0)])]);
+        let mut data = Vec::new();
+        original.write_to(&mut data).unwrap();
+        data.truncate(MAGIC.len() + 2);
+
+        assert!(ReplayFile::read_from_reader(&mut data.as_slice()).is_err());
+    }
 }
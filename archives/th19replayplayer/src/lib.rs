@@ -6,14 +6,13 @@ use std::{
     thread::spawn,
 };
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use bytes::{Buf, BytesMut};
 use interprocess::os::windows::named_pipe::{ByteReaderPipeStream, PipeListenerOptions, PipeMode};
 use junowen_lib::{
     structs::{
         app::{MainMenu, ScreenId},
         input_devices::{InputDevices, InputValue},
-        others::RoundFrame,
         selection::{Difficulty, GameMode, PlayerMatchup},
         settings::GameSettings,
     },
@@ -132,7 +131,11 @@ fn read_file(reader: &mut impl Read) -> Result<ReplayFile> {
 
     let file = File::open(file_path)?;
 
-    ReplayFile::read_from_reader(&mut BufReader::new(file))
+    let replay_file = ReplayFile::read_from_reader(&mut BufReader::new(file))?;
+    if replay_file.rounds.is_empty() {
+        bail!("no rounds in replay file");
+    }
+    Ok(replay_file)
 }
 
 fn init_interprecess(tx: mpsc::Sender<ReplayFile>) {
@@ -167,21 +170,23 @@ fn init_interprecess(tx: mpsc::Sender<ReplayFile>) {
 }
 
 fn init_battle(th19: &mut Th19, replay_file: &ReplayFile) {
-    th19.set_rand_seed1(replay_file.rand_seed1).unwrap();
-    th19.set_rand_seed2(replay_file.rand_seed2).unwrap();
+    let round = &replay_file.rounds[0];
+    th19.set_rand_seed1(round.rand_seed1).unwrap();
+    th19.set_rand_seed2(round.rand_seed2).unwrap();
+    // v1 のファイルには seed3, seed4 が記録されていない
+    if replay_file.version >= 2 {
+        th19.set_rand_seed3(round.rand_seed3).unwrap();
+        th19.set_rand_seed4(round.rand_seed4).unwrap();
+    }
 }
 
-fn tick_battle(
-    input_devices: &mut InputDevices,
-    round_frame: &RoundFrame,
-    replay_file: &ReplayFile,
-) -> bool {
-    match &replay_file.inputs {
+fn tick_battle(input_devices: &mut InputDevices, frame: u32, replay_file: &ReplayFile) -> bool {
+    match &replay_file.rounds[0].inputs {
         FileInputList::HumanVsHuman(vec) => {
-            if round_frame.frame as usize >= vec.len() {
+            if frame as usize >= vec.len() {
                 return false;
             }
-            let (p1_input, p2_input) = vec[round_frame.frame as usize];
+            let (p1_input, p2_input) = vec[frame as usize];
             input_devices
                 .p1_input_mut()
                 .set_current((p1_input as u32).try_into().unwrap());
@@ -190,10 +195,10 @@ fn tick_battle(
                 .set_current((p2_input as u32).try_into().unwrap());
         }
         FileInputList::HumanVsCpu(vec) => {
-            if round_frame.frame as usize >= vec.len() {
+            if frame as usize >= vec.len() {
                 return false;
             }
-            let p1_input = vec[round_frame.frame as usize];
+            let p1_input = vec[frame as usize];
             input_devices
                 .p1_input_mut()
                 .set_current((p1_input as u32).try_into().unwrap());
@@ -202,13 +207,18 @@ fn tick_battle(
     true
 }
 
+fn main_menu_mut(th19: &mut Th19) -> &mut MainMenu {
+    th19.app_mut()
+        .main_loop_tasks_mut()
+        .find_main_menu_mut()
+        .unwrap()
+}
+
 fn move_to_battle_menu_input(
     th19: &mut Th19,
-    main_menu: &mut MainMenu,
+    screen_id: ScreenId,
     inits: &InitialBattleInformation,
 ) -> bool {
-    let screen_id = main_menu.screen_id();
-    let menu = main_menu.menu_mut();
     match (
         screen_id,
         th19.selection().game_mode,
@@ -218,7 +228,7 @@ fn move_to_battle_menu_input(
         | (ScreenId::Title, _, _)
         | (ScreenId::PlayerMatchupSelect, _, _) => {
             AutomaticInputs::TransitionToLocalVersusDifficultySelect(inits.player_matchup)
-                .on_input_menu(th19, main_menu);
+                .on_input_menu(th19, screen_id);
             false
         }
         (
@@ -226,11 +236,10 @@ fn move_to_battle_menu_input(
             GameMode::Versus,
             PlayerMatchup::HumanVsHuman | PlayerMatchup::HumanVsCpu | PlayerMatchup::CpuVsCpu,
         ) => {
-            th19.menu_input_mut().set_current(select_cursor(
-                th19.menu_input().prev(),
-                menu.cursor_mut(),
-                inits.difficulty as u32,
-            ));
+            let prev = th19.menu_input().prev();
+            let cursor = main_menu_mut(th19).menu_mut().cursor_mut();
+            let input = select_cursor(prev, cursor, inits.difficulty as u32);
+            th19.menu_input_mut().set_current(input);
             false
         }
         (ScreenId::CharacterSelect, GameMode::Versus, _) => {
@@ -247,12 +256,9 @@ fn move_to_battle_menu_input(
 
 fn move_to_battle_player_inputs(
     th19: &mut Th19,
-    main_menu: &mut MainMenu,
+    screen_id: ScreenId,
     inits: &InitialBattleInformation,
 ) -> bool {
-    let input_devices = th19.input_devices_mut();
-    let screen_id = main_menu.screen_id();
-    let menu = main_menu.menu_mut();
     match (
         screen_id,
         th19.selection().game_mode,
@@ -262,6 +268,7 @@ fn move_to_battle_player_inputs(
         | (ScreenId::Title, _, _)
         | (ScreenId::PlayerMatchupSelect, _, _)
         | (ScreenId::DifficultySelect, _, _) => {
+            let input_devices = th19.input_devices_mut();
             input_devices
                 .p1_input_mut()
                 .set_current(InputValue::empty());
@@ -271,12 +278,14 @@ fn move_to_battle_player_inputs(
             false
         }
         (ScreenId::CharacterSelect, GameMode::Versus, _) => {
+            let menu = main_menu_mut(th19).menu_mut();
             menu.p1_cursor_mut().cursor = inits.p1_character as u32;
-            th19.selection_mut().p1_mut().card = inits.p1_card as u32;
             menu.p2_cursor_mut().cursor = inits.p2_character as u32;
+            th19.selection_mut().p1_mut().card = inits.p1_card as u32;
             th19.selection_mut().p2_mut().card = inits.p2_card as u32;
             th19.put_game_settings_in_game(inits.battle_settings)
                 .unwrap();
+            let input_devices = th19.input_devices_mut();
             let p1 = shot_repeatedly(input_devices.p1_input().prev());
             input_devices.p1_input_mut().set_current(p1);
             let p2 = shot_repeatedly(input_devices.p2_input().prev());
@@ -302,20 +311,21 @@ fn on_input_players_internal() {
             on_input_players_internal();
         }
         ReplayPlayerState::Prepare { th19, replay_file } => {
-            let Some(main_menu) = th19.app_mut().main_loop_tasks_mut().find_main_menu_mut() else {
+            let Some(main_menu) = th19.app().main_loop_tasks().find_main_menu() else {
                 return;
             };
+            let screen_id = main_menu.screen_id();
             if move_to_battle_player_inputs(
                 th19,
-                main_menu,
+                screen_id,
                 &InitialBattleInformation {
-                    difficulty: replay_file.difficulty,
+                    difficulty: replay_file.rounds[0].difficulty,
                     player_matchup: replay_file.player_matchup,
                     battle_settings: &replay_file.battle_settings,
-                    p1_character: replay_file.p1_character,
-                    p1_card: replay_file.p1_card,
-                    p2_character: replay_file.p2_character,
-                    p2_card: replay_file.p2_card,
+                    p1_character: replay_file.rounds[0].p1_character,
+                    p1_card: replay_file.rounds[0].p1_card,
+                    p2_character: replay_file.rounds[0].p2_character,
+                    p2_card: replay_file.rounds[0].p2_card,
                 },
             ) {
                 init_battle(th19, replay_file);
@@ -323,8 +333,8 @@ fn on_input_players_internal() {
             }
         }
         ReplayPlayerState::InGame { th19, replay_file } => {
-            if let Some(round_frame) = th19.round_frame() {
-                if tick_battle(th19.input_devices_mut(), round_frame, replay_file) {
+            if let Some(frame) = th19.round_frame().map(|round_frame| round_frame.frame) {
+                if tick_battle(th19.input_devices_mut(), frame, replay_file) {
                     return;
                 }
             }
@@ -349,20 +359,21 @@ extern "fastcall" fn on_input_menu() {
             return;
         }
         ReplayPlayerState::Prepare { th19, replay_file } => {
-            let Some(main_menu) = th19.app_mut().main_loop_tasks_mut().find_main_menu_mut() else {
+            let Some(main_menu) = th19.app().main_loop_tasks().find_main_menu() else {
                 return;
             };
+            let screen_id = main_menu.screen_id();
             move_to_battle_menu_input(
                 th19,
-                main_menu,
+                screen_id,
                 &InitialBattleInformation {
-                    difficulty: replay_file.difficulty,
+                    difficulty: replay_file.rounds[0].difficulty,
                     player_matchup: replay_file.player_matchup,
                     battle_settings: &replay_file.battle_settings,
-                    p1_character: replay_file.p1_character,
-                    p1_card: replay_file.p1_card,
-                    p2_character: replay_file.p2_character,
-                    p2_card: replay_file.p2_card,
+                    p1_character: replay_file.rounds[0].p1_character,
+                    p1_card: replay_file.rounds[0].p1_card,
+                    p2_character: replay_file.rounds[0].p2_character,
+                    p2_card: replay_file.rounds[0].p2_card,
                 },
             );
         }
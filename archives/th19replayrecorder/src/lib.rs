@@ -152,7 +152,11 @@ fn on_input() {
     } else {
         if let Some(round_frame) = props.th19.round_frame() {
             let frame = round_frame.frame;
-            tick_recording(&mut state.replay_file.inputs, frame, input_devices);
+            tick_recording(
+                &mut state.replay_file.rounds[0].inputs,
+                frame,
+                input_devices,
+            );
             return;
         };
         end_recording(props, state);
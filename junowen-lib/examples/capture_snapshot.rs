@@ -0,0 +1,17 @@
+//! 実行中の th19.exe から主要な値を記録する。記録したものは inspect_snapshot で読める
+
+#[cfg(target_os = "windows")]
+fn main() -> anyhow::Result<()> {
+    let path = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "th19.snapshot".to_owned());
+    let th19 = junowen_lib::Th19::new_external_process("th19.exe")?;
+    th19.capture_snapshot()?.save(&path)?;
+    println!("saved: {}", path);
+    Ok(())
+}
+
+#[cfg(not(target_os = "windows"))]
+fn main() {
+    eprintln!("capture_snapshot is only available on Windows");
+}
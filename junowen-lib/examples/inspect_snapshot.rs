@@ -0,0 +1,66 @@
+//! capture_snapshot で記録したメモリーを Th19 として読み、構造体の値を表示する
+//!
+//! ゲームの無い環境でも動くが、構造体は 32 bit のレイアウトで定義しているので、
+//! ポインターを含む構造体 (App など) は i686 向けにビルドしたときだけ正しく読める
+
+use std::env;
+
+use anyhow::{anyhow, Result};
+use junowen_lib::{MemorySnapshot, Th19};
+
+fn main() -> Result<()> {
+    let path = env::args()
+        .nth(1)
+        .ok_or_else(|| anyhow!("usage: inspect_snapshot <snapshot file>"))?;
+    let th19 = Th19::new_snapshot(MemorySnapshot::load(path)?)?;
+
+    println!("scene: {}", th19.scene()?);
+    println!("difficulty_cursor: {}", th19.difficulty_cursor()?);
+    println!(
+        "rand_seeds: {} {} {} {}",
+        th19.rand_seed1()?,
+        th19.rand_seed2()?,
+        th19.rand_seed3()?,
+        th19.rand_seed4()?
+    );
+
+    let selection = th19.selection();
+    println!(
+        "selection: game_mode={} player_matchup={} difficulty={:?}",
+        selection.game_mode as u32, selection.player_matchup as u32, selection.difficulty
+    );
+    println!(
+        "p1: character={} card={}",
+        selection.p1().character,
+        selection.p1().card
+    );
+    println!(
+        "p2: character={} card={}",
+        selection.p2().character,
+        selection.p2().card
+    );
+    println!("game_settings_in_menu: {:?}", th19.game_settings_in_menu()?);
+
+    let input_devices = th19.input_devices();
+    println!(
+        "input_devices: p1_idx={} p1={:?} p2={:?}",
+        input_devices.p1_idx(),
+        input_devices.p1_input().current(),
+        input_devices.p2_input().current()
+    );
+
+    match th19.round_frame() {
+        Some(round_frame) => println!("round_frame: {:?}", round_frame),
+        None => println!("round_frame: none"),
+    }
+
+    let vs_mode = th19.vs_mode();
+    println!(
+        "vs_mode: player_name={:?} room_name={:?} cards={}/{}",
+        vs_mode.player_name(),
+        vs_mode.room_name(),
+        vs_mode.p1_card(),
+        vs_mode.p2_card()
+    );
+    Ok(())
+}
@@ -0,0 +1,42 @@
+//! アドレス表を検証する。引数が無ければ埋め込まれたものを検証する
+//!
+//! すべてのバージョンがすべてのアドレスとオフセットを持ち、知らないアドレスや重複したハッシュ、
+//! structs と違うオフセットが無いことを確かめる。埋め込まれたものはテストでも確かめる
+
+use std::{env, fs, process::ExitCode};
+
+use junowen_lib::{
+    addresses::{parse_game_versions, Addresses, EMBEDDED_ADDRESS_TABLES},
+    structs::field_offsets,
+};
+
+fn main() -> ExitCode {
+    let toml = match env::args().nth(1) {
+        Some(path) => match fs::read_to_string(&path) {
+            Ok(toml) => toml,
+            Err(err) => {
+                eprintln!("{}: {}", path, err);
+                return ExitCode::FAILURE;
+            }
+        },
+        None => EMBEDDED_ADDRESS_TABLES.to_owned(),
+    };
+    match parse_game_versions(&toml) {
+        Ok(versions) => {
+            for version in versions {
+                println!(
+                    "ok: {} ({} hashes, {} addresses, {} offsets)",
+                    version.name(),
+                    version.hashes().len(),
+                    Addresses::NAMES.len(),
+                    field_offsets().count()
+                );
+            }
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
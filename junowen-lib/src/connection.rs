@@ -1,5 +1,10 @@
 mod data_channel;
+#[cfg(feature = "network-simulator")]
+pub mod network_simulator;
 mod peer_connection;
 pub mod signaling;
 
-pub use self::{data_channel::DataChannel, peer_connection::PeerConnection};
+pub use self::{
+    data_channel::DataChannel,
+    peer_connection::{set_ice_servers, IceServer, PeerConnection},
+};
@@ -0,0 +1,164 @@
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    sync::mpsc::{self, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use rand::Rng;
+
+/** 片方向の回線の状態 */
+#[derive(Clone, Copy, Debug)]
+pub struct NetworkConditions {
+    pub latency: Duration,
+    /// 0 から jitter の範囲でパケットごとに遅延を足す
+    pub jitter: Duration,
+    /// パケットが追加で reorder_delay だけ遅れ、後続のパケットに追い越される確率
+    pub reorder_rate: f64,
+    pub reorder_delay: Duration,
+    /// パケットが失われ、retransmission_timeout 後に再送される確率
+    pub loss_rate: f64,
+    pub retransmission_timeout: Duration,
+    /// DataChannel と同じく、追い越されたパケットが揃うまで後続を届けない
+    pub ordered: bool,
+}
+
+impl Default for NetworkConditions {
+    fn default() -> Self {
+        Self {
+            latency: Duration::ZERO,
+            jitter: Duration::ZERO,
+            reorder_rate: 0.0,
+            reorder_delay: Duration::ZERO,
+            loss_rate: 0.0,
+            retransmission_timeout: Duration::from_millis(200),
+            ordered: true,
+        }
+    }
+}
+
+impl NetworkConditions {
+    fn arrival_delay(&self, rng: &mut impl Rng) -> Duration {
+        let mut delay = self.latency;
+        if !self.jitter.is_zero() {
+            delay += self.jitter.mul_f64(rng.gen());
+        }
+        if rng.gen_bool(self.reorder_rate) {
+            delay += self.reorder_delay;
+        }
+        // 再送も失われることがある
+        while rng.gen_bool(self.loss_rate) {
+            delay += self.retransmission_timeout;
+        }
+        delay
+    }
+}
+
+struct Packet<T> {
+    deliver_at: Instant,
+    seq: u64,
+    msg: T,
+}
+
+impl<T> PartialEq for Packet<T> {
+    fn eq(&self, other: &Self) -> bool {
+        (self.deliver_at, self.seq) == (other.deliver_at, other.seq)
+    }
+}
+
+impl<T> Eq for Packet<T> {}
+
+impl<T> PartialOrd for Packet<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for Packet<T> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.deliver_at, self.seq).cmp(&(other.deliver_at, other.seq))
+    }
+}
+
+fn relay<T>(conditions: NetworkConditions, rx: mpsc::Receiver<T>, tx: mpsc::Sender<T>) {
+    let mut rng = rand::thread_rng();
+    let mut queue: BinaryHeap<Reverse<Packet<T>>> = BinaryHeap::new();
+    let mut seq = 0;
+    let mut last_deliver_at = Instant::now();
+    let mut disconnected = false;
+    loop {
+        let now = Instant::now();
+        while queue
+            .peek()
+            .is_some_and(|Reverse(packet)| packet.deliver_at <= now)
+        {
+            let Reverse(packet) = queue.pop().unwrap();
+            if tx.send(packet.msg).is_err() {
+                return;
+            }
+        }
+        let next_deliver_at = queue.peek().map(|Reverse(packet)| packet.deliver_at);
+        if disconnected {
+            let Some(next_deliver_at) = next_deliver_at else {
+                return;
+            };
+            thread::sleep(next_deliver_at - now);
+            continue;
+        }
+        let result = match next_deliver_at {
+            Some(next_deliver_at) => rx.recv_timeout(next_deliver_at - now),
+            None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match result {
+            Ok(msg) => {
+                let mut deliver_at = Instant::now() + conditions.arrival_delay(&mut rng);
+                if conditions.ordered {
+                    // 先に送ったパケットより先には届けない
+                    deliver_at = deliver_at.max(last_deliver_at);
+                    last_deliver_at = deliver_at;
+                }
+                queue.push(Reverse(Packet {
+                    deliver_at,
+                    seq,
+                    msg,
+                }));
+                seq += 1;
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => disconnected = true,
+        }
+    }
+}
+
+/**
+ * 遅延、ジッター、順序の入れ替わり、パケットロスを再現する片方向の回線
+ *
+ * 返した Sender に送ったメッセージが、条件に従って遅れて Receiver に届く
+ */
+pub fn simulated_channel<T>(conditions: NetworkConditions) -> (mpsc::Sender<T>, mpsc::Receiver<T>)
+where
+    T: Send + 'static,
+{
+    let (input_tx, input_rx) = mpsc::channel();
+    let (output_tx, output_rx) = mpsc::channel();
+    thread::spawn(move || relay(conditions, input_rx, output_tx));
+    (input_tx, output_rx)
+}
+
+/// 双方向の回線を作る。それぞれの端は (送信, 受信) の組
+#[allow(clippy::type_complexity)]
+pub fn simulated_link<T>(
+    a_to_b: NetworkConditions,
+    b_to_a: NetworkConditions,
+) -> (
+    (mpsc::Sender<T>, mpsc::Receiver<T>),
+    (mpsc::Sender<T>, mpsc::Receiver<T>),
+)
+where
+    T: Send + 'static,
+{
+    let (a_tx, b_rx) = simulated_channel(a_to_b);
+    let (b_tx, a_rx) = simulated_channel(b_to_a);
+    ((a_tx, a_rx), (b_tx, b_rx))
+}
@@ -1,6 +1,7 @@
-use std::time::Duration;
+use std::{sync::OnceLock, time::Duration};
 
 use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
 use tokio::{
     select,
     sync::{broadcast, oneshot},
@@ -21,12 +22,41 @@ use super::{
     signaling::{decompress_session_description, CompressedSdp},
 };
 
+/** STUN や TURN のサーバー */
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct IceServer {
+    pub urls: Vec<String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub username: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub credential: String,
+}
+
+static ICE_SERVERS: OnceLock<Vec<IceServer>> = OnceLock::new();
+
+/// 既定の STUN サーバーの代わりに使う ICE サーバーを設定する。二度目以降の呼び出しは無視する
+pub fn set_ice_servers(ice_servers: Vec<IceServer>) {
+    let _ = ICE_SERVERS.set(ice_servers);
+}
+
 fn create_default_config() -> RTCConfiguration {
-    RTCConfiguration {
-        ice_servers: vec![RTCIceServer {
+    let ice_servers = match ICE_SERVERS.get() {
+        Some(ice_servers) => ice_servers
+            .iter()
+            .map(|server| RTCIceServer {
+                urls: server.urls.clone(),
+                username: server.username.clone(),
+                credential: server.credential.clone(),
+                ..Default::default()
+            })
+            .collect(),
+        None => vec![RTCIceServer {
             urls: vec!["stun:stun.l.google.com:19302".to_owned()],
             ..Default::default()
         }],
+    };
+    RTCConfiguration {
+        ice_servers,
         ..Default::default()
     }
 }
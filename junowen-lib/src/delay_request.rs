@@ -0,0 +1,127 @@
+use crate::delayed_inputs::MAX_DELAY;
+
+/**
+ * ゲストがホストにディレイの変更を頼むやりとり
+ *
+ * ホストなら答えていないゲストの希望、ゲストなら送って答えを待っている希望を持つ
+ */
+#[derive(Debug, Default)]
+pub struct DelayRequest {
+    pending: Option<u8>,
+}
+
+impl DelayRequest {
+    pub fn pending(&self) -> Option<u8> {
+        self.pending
+    }
+
+    /// ゲストとして頼む。ホストに送るべきなら true
+    pub fn request(&mut self, delay: u8, current_delay: u8) -> bool {
+        if self.pending == Some(delay) || self.pending.is_none() && delay == current_delay {
+            return false;
+        }
+        self.pending = Some(delay);
+        true
+    }
+
+    /// ゲストとして、希望したディレイになったら答えを待つのをやめる
+    pub fn on_delay_changed(&mut self, current_delay: u8) {
+        if self.pending == Some(current_delay) {
+            self.pending = None;
+        }
+    }
+
+    /// ゲストとして断られた。その後に頼み直したものへの答えでなければ true
+    pub fn on_declined(&mut self, delay: u8) -> bool {
+        if self.pending != Some(delay) {
+            return false;
+        }
+        self.pending = None;
+        true
+    }
+
+    /// ホストとしてゲストの希望を受け取る。範囲外なら無視して false
+    pub fn on_requested(&mut self, delay: u8) -> bool {
+        if delay > MAX_DELAY {
+            return false;
+        }
+        self.pending = Some(delay);
+        true
+    }
+
+    /// ホストとして答える。答えるべき希望があれば返す
+    pub fn answer(&mut self) -> Option<u8> {
+        self.pending.take()
+    }
+
+    /// ラウンドが終わったら取り下げる
+    pub fn cancel(&mut self) {
+        self.pending = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use crate::{delayed_inputs::DelayedInputs, session_message::SessionMessage};
+
+    use super::*;
+
+    /// 相手から届いた、入力と同期しないメッセージ
+    fn recv_unsynced(delayed_inputs: &mut DelayedInputs) -> Vec<SessionMessage> {
+        assert!(delayed_inputs.try_recv_acceptance().unwrap().is_none());
+        delayed_inputs.take_remote_unsynced()
+    }
+
+    #[test]
+    fn request_again_after_decline() {
+        let (host_tx, guest_rx) = mpsc::channel();
+        let (guest_tx, host_rx) = mpsc::channel();
+        let mut host_inputs = DelayedInputs::new(host_tx, host_rx, true);
+        let mut guest_inputs = DelayedInputs::new(guest_tx, guest_rx, false);
+        let mut host = DelayRequest::default();
+        let mut guest = DelayRequest::default();
+
+        for _ in 0..2 {
+            assert!(guest.request(5, 2));
+            guest_inputs.send_delay_request(5);
+            let [SessionMessage::DelayRequest(delay)] = recv_unsynced(&mut host_inputs)[..] else {
+                panic!("no delay request");
+            };
+            assert!(host.on_requested(delay));
+
+            let delay = host.answer().unwrap();
+            host_inputs.send_delay_request_declined(delay);
+            let [SessionMessage::DelayRequestDeclined(delay)] =
+                recv_unsynced(&mut guest_inputs)[..]
+            else {
+                panic!("no decline");
+            };
+            assert!(guest.on_declined(delay));
+            assert_eq!((host.pending(), guest.pending()), (None, None));
+        }
+    }
+
+    #[test]
+    fn ignore_declines_of_older_requests() {
+        let mut guest = DelayRequest::default();
+        assert!(guest.request(5, 2));
+        assert!(!guest.request(5, 2));
+        assert!(guest.request(6, 2));
+        assert!(!guest.on_declined(5));
+        assert_eq!(guest.pending(), Some(6));
+        guest.on_delay_changed(6);
+        assert_eq!(guest.pending(), None);
+        assert!(!guest.request(2, 2));
+    }
+
+    #[test]
+    fn ignore_too_large_requests() {
+        let mut host = DelayRequest::default();
+        assert!(!host.on_requested(MAX_DELAY + 1));
+        assert_eq!(host.answer(), None);
+        assert!(host.on_requested(MAX_DELAY));
+        assert_eq!(host.answer(), Some(MAX_DELAY));
+    }
+}
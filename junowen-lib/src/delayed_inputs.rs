@@ -0,0 +1,439 @@
+use std::{
+    collections::LinkedList,
+    mem,
+    sync::mpsc::{self, RecvError, TryRecvError},
+};
+
+use anyhow::Result;
+use getset::CopyGetters;
+use tracing::{debug, error, trace};
+
+use crate::session_message::{
+    AcceptanceMessage, ChatMessage, IdentityMessage, InitMatch, RoundInitial, SessionMessage, Side,
+    PROTOCOL_VERSION,
+};
+
+/// ホストが設定できるディレイの上限
+pub const MAX_DELAY: u8 = 30;
+
+fn check_protocol_version(init: &InitMatch) -> Result<(), RecvError> {
+    if init.protocol_version != PROTOCOL_VERSION {
+        error!(
+            "{} uses an incompatible version of Ju.N.Owen (protocol version {}, expected {})",
+            init.name, init.protocol_version, PROTOCOL_VERSION
+        );
+        return Err(RecvError);
+    }
+    Ok(())
+}
+
+#[derive(CopyGetters)]
+pub struct DelayedInputs {
+    host: bool,
+    local: LinkedList<SessionMessage>,
+    remote_sender: mpsc::Sender<SessionMessage>,
+    remote_receiver: mpsc::Receiver<SessionMessage>,
+    remote_round_initial: Option<Option<RoundInitial>>,
+    /// 入力と同期せずに扱うメッセージ
+    remote_unsynced: Vec<SessionMessage>,
+    #[getset(get_copy = "pub")]
+    delay: u8,
+    /// 入力を送らないフレームに指定されたため、まだ送っていないディレイ
+    unsent_delay: Option<u8>,
+}
+
+impl DelayedInputs {
+    pub fn new(
+        remote_sender: mpsc::Sender<SessionMessage>,
+        remote_receiver: mpsc::Receiver<SessionMessage>,
+        host: bool,
+    ) -> Self {
+        Self {
+            host,
+            local: LinkedList::new(),
+            remote_sender,
+            remote_receiver,
+            remote_round_initial: None,
+            remote_unsynced: Vec::new(),
+            delay: 1,
+            unsent_delay: None,
+        }
+    }
+
+    /// positive value when buffer data is too much,
+    /// negative value when buffer data is not enough
+    fn delay_gap(&self) -> i32 {
+        let current_delay = self
+            .local
+            .iter()
+            .filter(|x| matches!(x, SessionMessage::Input(_)))
+            .count() as i32;
+        current_delay - self.delay as i32
+    }
+
+    /// 指定済みでまだ反映されていないディレイ
+    pub fn pending_delay(&self) -> Option<u8> {
+        self.unsent_delay.or_else(|| {
+            self.local.iter().rev().find_map(|x| match x {
+                SessionMessage::Delay(delay) => Some(*delay),
+                _ => None,
+            })
+        })
+    }
+
+    pub fn send_identity(&mut self, msg: IdentityMessage) {
+        let _ = self.remote_sender.send(SessionMessage::Identity(msg));
+    }
+
+    pub fn recv_identity(&mut self) -> Result<IdentityMessage, RecvError> {
+        loop {
+            match self.remote_receiver.recv()? {
+                SessionMessage::Identity(msg) => return Ok(msg),
+                // 本人確認をせずにルールを送ってくるのは古い版
+                SessionMessage::InitMatch(init) => {
+                    check_protocol_version(&init)?;
+                    error!("unexpected message: {:?}", init);
+                    return Err(RecvError);
+                }
+                msg @ (SessionMessage::Chat(_)
+                | SessionMessage::MatchResult(_)
+                | SessionMessage::DelayRequest(_)
+                | SessionMessage::DelayRequestDeclined(_)
+                | SessionMessage::SpectatorChat(_)) => self.remote_unsynced.push(msg),
+                SessionMessage::Acceptance(msg) => trace!("acceptance message ignored: {:?}", msg),
+                msg => {
+                    error!("unexpected message: {:?}", msg);
+                    return Err(RecvError);
+                }
+            }
+        }
+    }
+
+    pub fn send_init_match(&mut self, init: InitMatch) {
+        let _ = self.remote_sender.send(SessionMessage::InitMatch(init));
+    }
+
+    /// 相手の版が違えば Err を返す
+    pub fn recv_init_match(&mut self) -> Result<InitMatch, RecvError> {
+        loop {
+            match self.remote_receiver.recv()? {
+                SessionMessage::InitMatch(init) => {
+                    check_protocol_version(&init)?;
+                    return Ok(init);
+                }
+                msg @ (SessionMessage::Chat(_)
+                | SessionMessage::MatchResult(_)
+                | SessionMessage::DelayRequest(_)
+                | SessionMessage::DelayRequestDeclined(_)
+                | SessionMessage::SpectatorChat(_)) => self.remote_unsynced.push(msg),
+                SessionMessage::Acceptance(msg) => trace!("acceptance message ignored: {:?}", msg),
+                msg => {
+                    error!("unexpected message: {:?}", msg);
+                    return Err(RecvError);
+                }
+            }
+        }
+    }
+
+    /// 入力の遅延とは関係なく即座に送信する
+    pub fn send_chat(&mut self, text: String) {
+        let _ = self.remote_sender.send(SessionMessage::Chat(text));
+    }
+
+    /// 入力の遅延とは関係なく即座に送信する
+    pub fn send_spectator_chat(&mut self, message: ChatMessage) {
+        let _ = self
+            .remote_sender
+            .send(SessionMessage::SpectatorChat(message));
+    }
+
+    /// 入力の遅延とは関係なく即座に送信する
+    pub fn send_match_result(&mut self, winner: Side) {
+        let _ = self.remote_sender.send(SessionMessage::MatchResult(winner));
+    }
+
+    /// 入力の遅延とは関係なく即座に送信する
+    pub fn send_delay_request(&mut self, delay: u8) {
+        debug_assert!(!self.host);
+        let _ = self.remote_sender.send(SessionMessage::DelayRequest(delay));
+    }
+
+    /// 入力の遅延とは関係なく即座に送信する
+    pub fn send_delay_request_declined(&mut self, delay: u8) {
+        debug_assert!(self.host);
+        let _ = self
+            .remote_sender
+            .send(SessionMessage::DelayRequestDeclined(delay));
+    }
+
+    /// 入力の遅延とは関係なく即座に送信する
+    pub fn send_acceptance(&mut self, msg: AcceptanceMessage) {
+        let _ = self.remote_sender.send(SessionMessage::Acceptance(msg));
+    }
+
+    /// 届いていなければ待たずに None を返す
+    pub fn try_recv_acceptance(&mut self) -> Result<Option<AcceptanceMessage>, RecvError> {
+        loop {
+            match self.remote_receiver.try_recv() {
+                Ok(SessionMessage::Acceptance(msg)) => return Ok(Some(msg)),
+                Ok(
+                    msg @ (SessionMessage::Chat(_)
+                    | SessionMessage::MatchResult(_)
+                    | SessionMessage::DelayRequest(_)
+                    | SessionMessage::DelayRequestDeclined(_)
+                    | SessionMessage::SpectatorChat(_)),
+                ) => self.remote_unsynced.push(msg),
+                Ok(msg) => {
+                    error!("unexpected message: {:?}", msg);
+                    return Err(RecvError);
+                }
+                Err(TryRecvError::Empty) => return Ok(None),
+                Err(TryRecvError::Disconnected) => return Err(RecvError),
+            }
+        }
+    }
+
+    pub fn take_remote_unsynced(&mut self) -> Vec<SessionMessage> {
+        mem::take(&mut self.remote_unsynced)
+    }
+
+    pub fn send_init_round(&mut self, init: Option<RoundInitial>) {
+        let _ = self.remote_sender.send(SessionMessage::InitRound(init));
+    }
+
+    pub fn recv_init_round(&mut self) -> Result<Option<RoundInitial>, RecvError> {
+        let mut local_delay = None;
+        while let Some((_, delay)) = self.dequeue_local()? {
+            if let Some(delay) = delay {
+                debug_assert!(self.host);
+                local_delay = Some(delay);
+            }
+            trace!("local input skipped");
+        }
+        let mut remote_delay = None;
+        let round_initial = loop {
+            if let Some(round_initial) = self.remote_round_initial.take() {
+                break round_initial;
+            }
+            let (_, delay) = self.dequeue_remote()?;
+            if let Some(delay) = delay {
+                debug_assert!(self.host);
+                remote_delay = Some(delay);
+            }
+            trace!("remote input skipped");
+        };
+        let delay = if self.host { local_delay } else { remote_delay };
+        if let Some(delay) = delay {
+            self.update_delay(delay);
+        }
+        Ok(round_initial)
+    }
+
+    pub fn enqueue_input_and_dequeue(
+        &mut self,
+        input: u16,
+        delay: Option<u8>,
+    ) -> Result<(u16, u16), RecvError> {
+        let delay = delay.or(self.unsent_delay.take());
+        let delay_gap = self.delay_gap();
+        if delay_gap > 0 {
+            self.unsent_delay = delay;
+        } else {
+            if let Some(delay) = delay {
+                let _ = self.remote_sender.send(SessionMessage::Delay(delay));
+                self.local.push_back(SessionMessage::Delay(delay));
+            }
+            let _ = self.remote_sender.send(SessionMessage::Input(input));
+            self.local.push_back(SessionMessage::Input(input));
+        }
+        if delay_gap < 0 {
+            trace!("delay gap updated: {}", self.delay_gap());
+            return Ok((0, 0));
+        }
+        let Some((local, local_delay)) = self.dequeue_local()? else {
+            error!("local input queue is empty");
+            return Err(RecvError);
+        };
+        let (remote, remote_delay) = self.dequeue_remote()?;
+        let (p1, p2, delay) = if self.host {
+            (local, remote, local_delay)
+        } else {
+            (remote, local, remote_delay)
+        };
+        if let Some(delay) = delay {
+            self.update_delay(delay);
+        }
+        Ok((p1, p2))
+    }
+
+    fn update_delay(&mut self, delay: u8) {
+        debug!("delay update: {} -> {}", self.delay, delay);
+        self.delay = delay;
+        debug!("delay gap={}", self.delay_gap());
+    }
+
+    /// 自分が積んだものなので、入力とディレイ以外が出てきたら Err を返す
+    fn dequeue_local(&mut self) -> Result<Option<(u16, Option<u8>)>, RecvError> {
+        let mut delay = None;
+        loop {
+            let Some(local) = self.local.pop_front() else {
+                return Ok(None);
+            };
+            debug_assert!(matches!(local, SessionMessage::Input(_)) || self.host);
+            match local {
+                SessionMessage::Delay(d) => {
+                    debug_assert!(self.host);
+                    delay = Some(d);
+                    continue;
+                }
+                SessionMessage::Input(input) => return Ok(Some((input, delay))),
+                SessionMessage::InitMatch(_)
+                | SessionMessage::InitRound(_)
+                | SessionMessage::Chat(_)
+                | SessionMessage::MatchResult(_)
+                | SessionMessage::DelayRequest(_)
+                | SessionMessage::DelayRequestDeclined(_)
+                | SessionMessage::Acceptance(_)
+                | SessionMessage::Identity(_)
+                | SessionMessage::SpectatorChat(_) => {
+                    error!("unexpected local message: {:?}", local);
+                    return Err(RecvError);
+                }
+            }
+        }
+    }
+
+    fn dequeue_remote(&mut self) -> Result<(u16, Option<u8>), RecvError> {
+        if self.remote_round_initial.is_some() {
+            return Ok((0, None));
+        }
+        let mut delay = None;
+        loop {
+            let remote = self.remote_receiver.recv()?;
+            match remote {
+                SessionMessage::InitMatch(_) | SessionMessage::Identity(_) => {
+                    error!("unexpected message: {:?}", remote);
+                    return Err(RecvError);
+                }
+                SessionMessage::Delay(d) => {
+                    debug_assert!(!self.host);
+                    delay = Some(d);
+                    continue;
+                }
+                SessionMessage::Input(input) => return Ok((input, delay)),
+                SessionMessage::Chat(_)
+                | SessionMessage::MatchResult(_)
+                | SessionMessage::DelayRequest(_)
+                | SessionMessage::DelayRequestDeclined(_)
+                | SessionMessage::SpectatorChat(_) => {
+                    self.remote_unsynced.push(remote);
+                    continue;
+                }
+                SessionMessage::Acceptance(msg) => {
+                    trace!("acceptance message ignored: {:?}", msg);
+                    continue;
+                }
+                SessionMessage::InitRound(round_initial) => {
+                    debug_assert!(self.remote_round_initial.is_none());
+                    self.remote_round_initial = Some(round_initial);
+                    return Ok((0, None));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    fn input(host: bool, frame: u32) -> u16 {
+        (frame * 2 + host as u32) as u16
+    }
+
+    /// delay は各フレームで入力と一緒に指定するディレイを返す
+    fn spawn_peer(
+        host: bool,
+        frames: u32,
+        sender: mpsc::Sender<SessionMessage>,
+        receiver: mpsc::Receiver<SessionMessage>,
+        mut delay: impl FnMut(&DelayedInputs, u32) -> Option<u8> + Send + 'static,
+    ) -> thread::JoinHandle<(Vec<(u16, u16)>, u8)> {
+        thread::spawn(move || {
+            let mut delayed_inputs = DelayedInputs::new(sender, receiver, host);
+            let log = (0..frames)
+                .map(|frame| {
+                    let delay = delay(&delayed_inputs, frame);
+                    delayed_inputs
+                        .enqueue_input_and_dequeue(input(host, frame), delay)
+                        .unwrap()
+                })
+                .collect();
+            (log, delayed_inputs.delay())
+        })
+    }
+
+    /// ホストとゲストを frames フレーム進め、それぞれが得た入力と最後のディレイを返す
+    fn run(
+        frames: u32,
+        host_delay: impl FnMut(&DelayedInputs, u32) -> Option<u8> + Send + 'static,
+    ) -> [(Vec<(u16, u16)>, u8); 2] {
+        let (host_tx, guest_rx) = mpsc::channel();
+        let (guest_tx, host_rx) = mpsc::channel();
+        let host = spawn_peer(true, frames, host_tx, host_rx, host_delay);
+        let guest = spawn_peer(false, frames, guest_tx, guest_rx, |_, _| None);
+        [host.join().unwrap(), guest.join().unwrap()]
+    }
+
+    #[test]
+    fn large_delays() {
+        for delay in 10..=MAX_DELAY {
+            let [(host_log, host_delay), (guest_log, guest_delay)] =
+                run(100, move |_, frame| (frame == 0).then_some(delay));
+            assert_eq!(host_log, guest_log, "delay {}", delay);
+            assert_eq!((host_delay, guest_delay), (delay, delay));
+            // 最初の入力は初期値の 1 フレーム遅れで届き、その後は指定したディレイの分だけ遅れる
+            let delay = delay as usize;
+            assert_eq!(host_log[0], (0, 0));
+            assert_eq!(host_log[1], (input(true, 0), input(false, 0)));
+            assert!(host_log[2..=delay].iter().all(|&x| x == (0, 0)));
+            for (i, &inputs) in host_log[delay + 1..].iter().enumerate() {
+                let frame = i as u32 + 1;
+                assert_eq!(inputs, (input(true, frame), input(false, frame)));
+            }
+        }
+    }
+
+    #[test]
+    fn delay_change_waits_for_delay_gap() {
+        let mut requested = false;
+        let [(host_log, host_delay), (guest_log, guest_delay)] =
+            run(200, move |delayed_inputs, frame| {
+                if frame == 0 {
+                    return Some(20);
+                }
+                if frame == 50 {
+                    return Some(2);
+                }
+                if requested {
+                    // 入力が余っている間は送らずに持っておく
+                    if delayed_inputs.delay_gap() > 0 {
+                        assert_eq!(delayed_inputs.delay(), 2);
+                        assert_eq!(delayed_inputs.unsent_delay, Some(5));
+                        assert_eq!(delayed_inputs.pending_delay(), Some(5));
+                    }
+                    return None;
+                }
+                if delayed_inputs.delay() == 2 {
+                    assert!(delayed_inputs.delay_gap() > 0);
+                    requested = true;
+                    return Some(5);
+                }
+                None
+            });
+        assert_eq!(host_log, guest_log);
+        assert_eq!((host_delay, guest_delay), (5, 5));
+    }
+}
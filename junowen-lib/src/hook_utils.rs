@@ -1,7 +1,7 @@
 mod dll_injection;
 mod load_library_w_addr;
 
-use std::{fs::File, io::Read};
+use std::{fs, io};
 
 use sha3::digest::Digest; // using for Sha3_224::new()
 use sha3::{digest::generic_array::GenericArray, Sha3_224};
@@ -33,13 +33,15 @@ pub fn calc_th19_hash() -> Vec<u8> {
         panic!();
     }
     let exe_file_path = unsafe { PCWSTR::from_raw(buf.as_ptr()).to_string() }.unwrap();
-    let mut file = File::open(exe_file_path).unwrap();
-    let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer).unwrap();
+    calc_file_hash(&exe_file_path).unwrap()
+}
+
+pub fn calc_file_hash(path: &str) -> io::Result<Vec<u8>> {
+    let buffer = fs::read(path)?;
     let mut hasher: Sha3_224 = Sha3_224::new();
     hasher.update(&buffer);
     let hash: GenericArray<_, _> = hasher.finalize();
-    hash.to_vec()
+    Ok(hash.to_vec())
 }
 
 pub struct WellKnownVersionHashes {
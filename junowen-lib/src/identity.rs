@@ -0,0 +1,105 @@
+use anyhow::{bail, Result};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use rand::rngs::OsRng;
+use sha3::{Digest, Sha3_256};
+
+pub type PublicKey = [u8; 32];
+pub type Nonce = [u8; 16];
+
+const HANDSHAKE_CONTEXT: &[u8] = b"junowen-identity-v1";
+
+pub fn new_nonce() -> Nonce {
+    rand::random()
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+pub fn from_hex<const N: usize>(hex: &str) -> Result<[u8; N]> {
+    let hex = hex.trim();
+    if hex.len() != N * 2 || !hex.is_ascii() {
+        bail!("invalid length: {}", hex.len());
+    }
+    let mut bytes = [0; N];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)?;
+    }
+    Ok(bytes)
+}
+
+/// 公開鍵を人が見比べられる長さに縮めたもの
+pub fn fingerprint(public_key: &PublicKey) -> String {
+    let hash = Sha3_256::digest(public_key);
+    hash[..8]
+        .chunks(2)
+        .map(to_hex)
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// 相手が生成した nonce に対して署名することで、使い回された署名を受け付けないようにする
+fn handshake_message(nonce: &Nonce, public_key: &PublicKey, name: &str) -> Vec<u8> {
+    [HANDSHAKE_CONTEXT, nonce, public_key, name.as_bytes()].concat()
+}
+
+pub fn verify(public_key: &PublicKey, message: &[u8], signature: &[u8]) -> Result<()> {
+    let verifying_key = VerifyingKey::from_bytes(public_key)?;
+    let signature = Signature::from_slice(signature)?;
+    verifying_key.verify(message, &signature)?;
+    Ok(())
+}
+
+pub fn verify_handshake(
+    public_key: &PublicKey,
+    nonce: &Nonce,
+    name: &str,
+    signature: &[u8],
+) -> Result<()> {
+    verify(
+        public_key,
+        &handshake_message(nonce, public_key, name),
+        signature,
+    )
+}
+
+/** プレイヤーを識別するための鍵ペア */
+#[derive(Clone)]
+pub struct Identity {
+    signing_key: SigningKey,
+}
+
+impl Identity {
+    pub fn generate() -> Self {
+        Self {
+            signing_key: SigningKey::generate(&mut OsRng),
+        }
+    }
+
+    pub fn from_secret_key(secret_key: &[u8; 32]) -> Self {
+        Self {
+            signing_key: SigningKey::from_bytes(secret_key),
+        }
+    }
+
+    pub fn secret_key(&self) -> [u8; 32] {
+        self.signing_key.to_bytes()
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        self.signing_key.verifying_key().to_bytes()
+    }
+
+    pub fn fingerprint(&self) -> String {
+        fingerprint(&self.public_key())
+    }
+
+    pub fn sign(&self, message: &[u8]) -> Vec<u8> {
+        self.signing_key.sign(message).to_bytes().to_vec()
+    }
+
+    /// 相手の nonce と自分の名前に署名する
+    pub fn sign_handshake(&self, remote_nonce: &Nonce, name: &str) -> Vec<u8> {
+        self.sign(&handshake_message(remote_nonce, &self.public_key(), name))
+    }
+}
@@ -20,11 +20,16 @@ impl Lang {
         Self { lang }
     }
 
+    /// 訳が無ければ msg をそのまま返す
+    pub fn get<'a>(&'a self, msg: &'a str) -> &'a str {
+        self.lang.get(msg).map(|s| s.as_str()).unwrap_or(msg)
+    }
+
     pub fn print(&self, msg: &str) {
-        print!("{}", self.lang.get(msg).map(|s| s.as_str()).unwrap_or(msg));
+        print!("{}", self.get(msg));
     }
 
     pub fn println(&self, msg: &str) {
-        println!("{}", self.lang.get(msg).map(|s| s.as_str()).unwrap_or(msg));
+        println!("{}", self.get(msg));
     }
 }
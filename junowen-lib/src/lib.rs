@@ -1,18 +1,19 @@
 pub mod connection;
+pub mod delay_request;
+pub mod delayed_inputs;
 #[cfg(target_os = "windows")]
 mod find_process_id;
 #[cfg(target_os = "windows")]
 pub mod hook_utils;
+pub mod identity;
 #[cfg(target_os = "windows")]
 pub mod lang;
-#[cfg(target_os = "windows")]
 mod macros;
-#[cfg(target_os = "windows")]
 mod memory_accessors;
+pub mod session_message;
 pub mod signaling_server;
-#[cfg(target_os = "windows")]
 mod th19;
 #[cfg(target_os = "windows")]
 mod win_api_wrappers;
-#[cfg(target_os = "windows")]
+pub use crate::memory_accessors::MemorySnapshot;
 pub use crate::th19::*;
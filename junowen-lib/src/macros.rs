@@ -1,100 +1,102 @@
 #[macro_export]
 macro_rules! hook {
-    ($addr:expr, $hook:ident, $type:ty) => {
+    ($hook:ident, $type:ty) => {
         pub fn $hook(&mut self, target: $type) -> ($type, ApplyFn) {
-            unsafe { transmute(self.hook_call($addr, target as _)) }
+            unsafe { transmute(self.hook_call(self.addresses.$hook, target as _)) }
         }
     };
 }
 
 #[macro_export]
 macro_rules! u16_prop {
-    ($addr:expr, $getter:ident) => {
+    ($getter:ident) => {
         pub fn $getter(&self) -> Result<u16> {
-            self.memory_accessor.read_u16($addr)
+            self.memory_accessor.read_u16(self.addresses.$getter)
         }
     };
 
-    ($addr:expr, $getter:ident, $setter:ident) => {
-        $crate::u16_prop!($addr, $getter);
+    ($getter:ident, $setter:ident) => {
+        $crate::u16_prop!($getter);
         pub fn $setter(&mut self, value: u16) -> Result<()> {
-            self.memory_accessor.write_u16($addr, value)
+            self.memory_accessor
+                .write_u16(self.addresses.$getter, value)
         }
     };
 }
 
 #[macro_export]
 macro_rules! u32_prop {
-    ($addr:expr, $getter:ident) => {
+    ($getter:ident) => {
         pub fn $getter(&self) -> Result<u32> {
-            self.memory_accessor.read_u32($addr)
+            self.memory_accessor.read_u32(self.addresses.$getter)
         }
     };
 
-    ($addr:expr, $getter:ident, $setter:ident) => {
-        $crate::u32_prop!($addr, $getter);
+    ($getter:ident, $setter:ident) => {
+        $crate::u32_prop!($getter);
         pub fn $setter(&mut self, value: u32) -> Result<()> {
-            self.memory_accessor.write_u32($addr, value)
+            self.memory_accessor
+                .write_u32(self.addresses.$getter, value)
         }
     };
 }
 
 #[macro_export]
 macro_rules! pointer {
-    ($addr:expr, $getter:ident, $type:ty) => {
-        pub fn $getter(&self) -> &'static $type {
-            self.pointer($addr).unwrap()
+    ($getter:ident, $getter_mut:ident, $type:ty) => {
+        pointer!($getter, $type);
+        pub fn $getter_mut(&mut self) -> &mut $type {
+            self.pointer_mut(self.addresses.$getter).unwrap()
         }
     };
-    ($addr:expr, $getter:ident, $getter_mut:ident, $type:ty) => {
-        pointer!($addr, $getter, $type);
-        pub fn $getter_mut(&mut self) -> &'static mut $type {
-            self.pointer_mut($addr).unwrap()
+    ($getter:ident, $type:ty) => {
+        pub fn $getter(&self) -> &$type {
+            self.pointer(self.addresses.$getter).unwrap()
         }
     };
 }
 
 #[macro_export]
 macro_rules! ptr_opt {
-    ($addr:expr, $getter:ident, $type:ty) => {
-        pub fn $getter(&self) -> Option<&'static $type> {
-            self.pointer($addr)
+    ($getter:ident, $getter_mut:ident, $type:ty) => {
+        ptr_opt!($getter, $type);
+        pub fn $getter_mut(&mut self) -> Option<&mut $type> {
+            self.pointer_mut(self.addresses.$getter)
         }
     };
-    ($addr:expr, $getter:ident, $getter_mut:ident, $type:ty) => {
-        ptr_opt!($addr, $getter, $type);
-        pub fn $getter_mut(&mut self) -> Option<&'static mut $type> {
-            self.pointer_mut($addr)
+    ($getter:ident, $type:ty) => {
+        pub fn $getter(&self) -> Option<&$type> {
+            self.pointer(self.addresses.$getter)
         }
     };
 }
 
 #[macro_export]
 macro_rules! value {
-    ($addr:expr, $getter:ident, $type:ty) => {
-        pub fn $getter(&self) -> $type {
-            self.value($addr)
+    ($getter:ident, $setter:ident, $type:ty) => {
+        value!($getter, $type);
+        pub fn $setter(&mut self, value: $type) {
+            self.set_value(self.addresses.$getter, value)
         }
     };
-    ($addr:expr, $getter:ident, $setter:ident, $type:ty) => {
-        value!($addr, $getter, $type);
-        pub fn $setter(&mut self, value: $type) {
-            self.set_value($addr, value)
+    ($getter:ident, $type:ty) => {
+        pub fn $getter(&self) -> $type {
+            self.value(self.addresses.$getter)
         }
     };
 }
 
 #[macro_export]
 macro_rules! value_ref {
-    ($addr:expr, $getter:ident, $type:ty) => {
-        pub fn $getter(&self) -> &'static $type {
-            self.value_ref($addr)
+    ($getter:ident, $getter_mut:ident, $type:ty) => {
+        value_ref!($getter, $type);
+        pub fn $getter_mut(&mut self) -> &mut $type {
+            self.value_mut(self.addresses.$getter)
         }
     };
-    ($addr:expr, $getter:ident, $getter_mut:ident, $type:ty) => {
-        value_ref!($addr, $getter, $type);
-        pub fn $getter_mut(&mut self) -> &'static mut $type {
-            self.value_mut($addr)
+    ($getter:ident, $type:ty) => {
+        pub fn $getter(&self) -> &$type {
+            self.value_ref(self.addresses.$getter)
         }
     };
 }
@@ -1,18 +1,40 @@
+#[cfg(target_os = "windows")]
 mod external_process;
+#[cfg(target_os = "windows")]
 mod hooked_process;
+mod snapshot;
+
+use std::ffi::c_void;
 
 use anyhow::Result;
 
+#[cfg(target_os = "windows")]
 pub use external_process::ExternalProcess;
+#[cfg(target_os = "windows")]
 pub use hooked_process::FnOfHookAssembly;
+#[cfg(target_os = "windows")]
 pub use hooked_process::HookedProcess;
+pub use snapshot::MemorySnapshot;
 
 pub enum MemoryAccessor {
+    #[cfg(target_os = "windows")]
     ExternalProcess(ExternalProcess),
+    #[cfg(target_os = "windows")]
     HookedProcess(HookedProcess),
+    Snapshot(MemorySnapshot),
 }
 
 impl MemoryAccessor {
+    pub fn base_address(&self) -> usize {
+        match self {
+            #[cfg(target_os = "windows")]
+            MemoryAccessor::ExternalProcess(accessor) => accessor.base_address(),
+            #[cfg(target_os = "windows")]
+            MemoryAccessor::HookedProcess(accessor) => accessor.base_address(),
+            MemoryAccessor::Snapshot(accessor) => accessor.base_address(),
+        }
+    }
+
     pub fn read_u16(&self, addr: usize) -> Result<u16> {
         let mut buffer = [0; 2];
         self.read(addr, &mut buffer)?;
@@ -36,21 +58,81 @@ impl MemoryAccessor {
 
     pub fn read(&self, addr: usize, buffer: &mut [u8]) -> Result<()> {
         match self {
+            #[cfg(target_os = "windows")]
             MemoryAccessor::ExternalProcess(accessor) => accessor.read(addr, buffer),
+            #[cfg(target_os = "windows")]
             MemoryAccessor::HookedProcess(accessor) => {
                 accessor.read(addr, buffer);
                 Ok(())
             }
+            MemoryAccessor::Snapshot(accessor) => accessor.read(addr, buffer),
         }
     }
 
     pub fn write(&mut self, addr: usize, buffer: &[u8]) -> Result<()> {
         match self {
+            #[cfg(target_os = "windows")]
             MemoryAccessor::ExternalProcess(accessor) => accessor.write(addr, buffer),
+            #[cfg(target_os = "windows")]
             MemoryAccessor::HookedProcess(accessor) => {
                 accessor.write(addr, buffer);
                 Ok(())
             }
+            MemoryAccessor::Snapshot(accessor) => accessor.write(addr, buffer),
+        }
+    }
+
+    /**
+     * addr から size バイトを直接参照できるポインター。ExternalProcess では使えない
+     *
+     * スナップショットに記録していなければ None
+     */
+    pub fn raw_ptr(&self, addr: usize, size: usize) -> Option<*const c_void> {
+        match self {
+            #[cfg(target_os = "windows")]
+            MemoryAccessor::ExternalProcess(_) => {
+                panic!("MemoryAccessor::raw_ptr is not available for ExternalProcess")
+            }
+            #[cfg(target_os = "windows")]
+            MemoryAccessor::HookedProcess(accessor) => Some(accessor.raw_ptr(addr)),
+            MemoryAccessor::Snapshot(accessor) => accessor.raw_ptr(addr, size).map(|x| x as _),
+        }
+    }
+
+    pub fn raw_ptr_mut(&mut self, addr: usize, size: usize) -> Option<*mut c_void> {
+        match self {
+            #[cfg(target_os = "windows")]
+            MemoryAccessor::ExternalProcess(_) => {
+                panic!("MemoryAccessor::raw_ptr_mut is not available for ExternalProcess")
+            }
+            #[cfg(target_os = "windows")]
+            MemoryAccessor::HookedProcess(accessor) => Some(accessor.raw_ptr(addr) as _),
+            MemoryAccessor::Snapshot(accessor) => accessor.raw_ptr_mut(addr, size).map(|x| x as _),
+        }
+    }
+
+    /// addr に書かれたポインターが指す先の size バイトを直接参照できるポインター。null なら None
+    pub fn deref_ptr(&self, addr: usize, size: usize) -> Option<*const c_void> {
+        match self {
+            MemoryAccessor::Snapshot(accessor) => accessor.deref_ptr(addr, size).map(|x| x as _),
+            #[cfg(target_os = "windows")]
+            _ => {
+                let p_p_obj = self.raw_ptr(addr, size_of::<usize>())? as *const *const c_void;
+                Some(unsafe { *p_p_obj }).filter(|p_obj| !p_obj.is_null())
+            }
+        }
+    }
+
+    pub fn deref_ptr_mut(&mut self, addr: usize, size: usize) -> Option<*mut c_void> {
+        match self {
+            MemoryAccessor::Snapshot(accessor) => {
+                accessor.deref_ptr_mut(addr, size).map(|x| x as _)
+            }
+            #[cfg(target_os = "windows")]
+            _ => {
+                let p_p_obj = self.raw_ptr(addr, size_of::<usize>())? as *const *mut c_void;
+                Some(unsafe { *p_p_obj }).filter(|p_obj| !p_obj.is_null())
+            }
         }
     }
 }
@@ -5,7 +5,7 @@ use windows::Win32::{
     Foundation::{CloseHandle, FALSE, HANDLE, HMODULE, MAX_PATH},
     System::{
         Diagnostics::Debug::{ReadProcessMemory, WriteProcessMemory},
-        ProcessStatus::{EnumProcessModules, GetModuleBaseNameA},
+        ProcessStatus::{EnumProcessModules, GetModuleBaseNameA, GetModuleFileNameExW},
         Threading::{
             OpenProcess, PROCESS_QUERY_INFORMATION, PROCESS_VM_OPERATION, PROCESS_VM_READ,
             PROCESS_VM_WRITE,
@@ -66,12 +66,25 @@ impl ExternalProcess {
         })
     }
 
+    pub fn base_address(&self) -> usize {
+        self.base_module.0 as usize
+    }
+
+    pub fn module_file_path(&self) -> Result<String> {
+        let mut buf = [0u16; MAX_PATH as usize];
+        let len = unsafe { GetModuleFileNameExW(self.process, self.base_module, &mut buf) };
+        if len == 0 {
+            bail!("GetModuleFileNameExW failed");
+        }
+        Ok(String::from_utf16(&buf[0..len as usize])?)
+    }
+
     pub fn read(&self, addr: usize, buffer: &mut [u8]) -> Result<()> {
         let mut number_of_bytes_read: usize = 0;
         unsafe {
             ReadProcessMemory(
                 self.process,
-                (self.base_module.0 as usize + addr) as *const c_void,
+                (self.base_module.0 as usize).wrapping_add(addr) as *const c_void,
                 buffer.as_mut_ptr() as *mut c_void,
                 buffer.len(),
                 Some(&mut number_of_bytes_read),
@@ -88,7 +101,7 @@ impl ExternalProcess {
         unsafe {
             WriteProcessMemory(
                 self.process,
-                (self.base_module.0 as usize + addr) as *const c_void,
+                (self.base_module.0 as usize).wrapping_add(addr) as *const c_void,
                 buffer.as_ptr() as *const c_void,
                 buffer.len(),
                 Some(&mut number_of_bytes_written),
@@ -93,12 +93,21 @@ impl HookedProcess {
         })
     }
 
+    pub fn base_address(&self) -> usize {
+        self.base_addr
+    }
+
     pub fn read(&self, addr: usize, buffer: &mut [u8]) {
-        unsafe { ((self.base_addr + addr) as *mut u8).copy_to(buffer.as_mut_ptr(), buffer.len()) };
+        unsafe {
+            (self.base_addr.wrapping_add(addr) as *mut u8)
+                .copy_to(buffer.as_mut_ptr(), buffer.len())
+        };
     }
 
     pub fn write(&mut self, addr: usize, buffer: &[u8]) {
-        unsafe { ((self.base_addr + addr) as *mut u8).copy_from(buffer.as_ptr(), buffer.len()) };
+        unsafe {
+            (self.base_addr.wrapping_add(addr) as *mut u8).copy_from(buffer.as_ptr(), buffer.len())
+        };
     }
 
     pub fn raw_ptr(&self, addr: usize) -> *const c_void {
@@ -0,0 +1,217 @@
+use std::{fs, mem::size_of, path::Path, slice};
+
+use anyhow::{bail, Result};
+
+const MAGIC: &[u8; 8] = b"JNOWSNAP";
+const VERSION: u32 = 2;
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.0.len() < len {
+            bail!("unexpected end of snapshot");
+        }
+        let (head, tail) = self.0.split_at(len);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+}
+
+struct Region {
+    /// プロセス内での絶対アドレス
+    address: usize,
+    len: usize,
+    /// 構造体を参照として読めるよう、プロセス内と同じく 8 バイト境界からのずれを保って持つ
+    data: Box<[u64]>,
+}
+
+impl Region {
+    fn new(address: usize, bytes: &[u8]) -> Self {
+        let padding = address % size_of::<u64>();
+        let mut region = Self {
+            address,
+            len: bytes.len(),
+            data: vec![0u64; (padding + bytes.len()).div_ceil(size_of::<u64>())].into_boxed_slice(),
+        };
+        region.bytes_mut().copy_from_slice(bytes);
+        region
+    }
+
+    fn bytes(&self) -> &[u8] {
+        let padding = self.address % size_of::<u64>();
+        unsafe { slice::from_raw_parts((self.data.as_ptr() as *const u8).add(padding), self.len) }
+    }
+
+    fn bytes_mut(&mut self) -> &mut [u8] {
+        let padding = self.address % size_of::<u64>();
+        unsafe {
+            slice::from_raw_parts_mut((self.data.as_mut_ptr() as *mut u8).add(padding), self.len)
+        }
+    }
+
+    fn contains(&self, address: usize, len: usize) -> bool {
+        self.address <= address
+            && address
+                .checked_add(len)
+                .is_some_and(|end| end <= self.address + self.len)
+    }
+}
+
+/**
+ * 実行中のゲームから記録したメモリーの断片
+ *
+ * アドレスは Th19 と同じくモジュールからの相対アドレスで扱う。
+ * メモリー上のポインターは絶対アドレスなので、記録した範囲内のものだけ辿れる
+ */
+pub struct MemorySnapshot {
+    base_address: usize,
+    /// 記録した th19.exe の Sha3-224
+    exe_hash: Vec<u8>,
+    regions: Vec<Region>,
+}
+
+impl MemorySnapshot {
+    pub fn new(base_address: usize, exe_hash: Vec<u8>) -> Self {
+        Self {
+            base_address,
+            exe_hash,
+            regions: Vec::new(),
+        }
+    }
+
+    pub fn base_address(&self) -> usize {
+        self.base_address
+    }
+
+    pub fn exe_hash(&self) -> &[u8] {
+        &self.exe_hash
+    }
+
+    /// 絶対アドレスで範囲を追加する
+    pub fn add_region(&mut self, address: usize, bytes: &[u8]) {
+        self.regions.push(Region::new(address, bytes));
+    }
+
+    fn find(&self, address: usize, len: usize) -> Option<&Region> {
+        self.regions
+            .iter()
+            .find(|region| region.contains(address, len))
+    }
+
+    fn find_mut(&mut self, address: usize, len: usize) -> Option<&mut Region> {
+        self.regions
+            .iter_mut()
+            .find(|region| region.contains(address, len))
+    }
+
+    pub fn read(&self, addr: usize, buffer: &mut [u8]) -> Result<()> {
+        let address = self.base_address.wrapping_add(addr);
+        let Some(region) = self.find(address, buffer.len()) else {
+            bail!("not captured: 0x{:x}", address);
+        };
+        let offset = address - region.address;
+        buffer.copy_from_slice(&region.bytes()[offset..offset + buffer.len()]);
+        Ok(())
+    }
+
+    pub fn write(&mut self, addr: usize, buffer: &[u8]) -> Result<()> {
+        let address = self.base_address.wrapping_add(addr);
+        let Some(region) = self.find_mut(address, buffer.len()) else {
+            bail!("not captured: 0x{:x}", address);
+        };
+        let offset = address - region.address;
+        region.bytes_mut()[offset..offset + buffer.len()].copy_from_slice(buffer);
+        Ok(())
+    }
+
+    /// 絶対アドレスから len バイトを、記録したデータへのポインターに変換する。記録していなければ None
+    fn translate(&self, address: usize, len: usize) -> Option<*const u8> {
+        let region = self.find(address, len)?;
+        Some(region.bytes()[address - region.address..].as_ptr())
+    }
+
+    fn translate_mut(&mut self, address: usize, len: usize) -> Option<*mut u8> {
+        let region = self.find_mut(address, len)?;
+        let offset = address - region.address;
+        Some(region.bytes_mut()[offset..].as_mut_ptr())
+    }
+
+    pub fn raw_ptr(&self, addr: usize, len: usize) -> Option<*const u8> {
+        self.translate(self.base_address.wrapping_add(addr), len)
+    }
+
+    pub fn raw_ptr_mut(&mut self, addr: usize, len: usize) -> Option<*mut u8> {
+        self.translate_mut(self.base_address.wrapping_add(addr), len)
+    }
+
+    /// addr に書かれた 32 bit のポインター。null か記録していなければ None
+    fn pointee(&self, addr: usize) -> Option<usize> {
+        let mut buffer = [0; 4];
+        self.read(addr, &mut buffer).ok()?;
+        match u32::from_le_bytes(buffer) {
+            0 => None,
+            address => Some(address as usize),
+        }
+    }
+
+    /// addr に書かれたポインターが指す先の len バイト。null か記録していなければ None
+    pub fn deref_ptr(&self, addr: usize, len: usize) -> Option<*const u8> {
+        self.translate(self.pointee(addr)?, len)
+    }
+
+    pub fn deref_ptr_mut(&mut self, addr: usize, len: usize) -> Option<*mut u8> {
+        let address = self.pointee(addr)?;
+        self.translate_mut(address, len)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader(bytes);
+        if reader.bytes(MAGIC.len())? != MAGIC {
+            bail!("not a snapshot");
+        }
+        let version = reader.u32()?;
+        if version != VERSION {
+            bail!("unsupported snapshot version: {}", version);
+        }
+        let base_address = reader.u32()? as usize;
+        let exe_hash_len = reader.u32()? as usize;
+        let mut snapshot = Self::new(base_address, reader.bytes(exe_hash_len)?.to_vec());
+        for _ in 0..reader.u32()? {
+            let address = reader.u32()? as usize;
+            let len = reader.u32()? as usize;
+            snapshot.add_region(address, reader.bytes(len)?);
+        }
+        Ok(snapshot)
+    }
+
+    /// MAGIC, VERSION, base_address, exe_hash の長さ, exe_hash, 範囲の数, (address, len, bytes)...
+    /// 数値はすべて 32 bit のリトルエンディアン
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(self.base_address as u32).to_le_bytes());
+        bytes.extend_from_slice(&(self.exe_hash.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&self.exe_hash);
+        bytes.extend_from_slice(&(self.regions.len() as u32).to_le_bytes());
+        for region in &self.regions {
+            bytes.extend_from_slice(&(region.address as u32).to_le_bytes());
+            bytes.extend_from_slice(&(region.len as u32).to_le_bytes());
+            bytes.extend_from_slice(region.bytes());
+        }
+        bytes
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        Ok(fs::write(path, self.to_bytes())?)
+    }
+}
@@ -0,0 +1,200 @@
+use derive_new::new;
+use getset::{CopyGetters, Getters};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    identity::{Nonce, PublicKey},
+    structs::settings::GameSettings,
+};
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MatchInitial {
+    pub game_settings: GameSettings,
+    /// 何本先取のセットとして扱うか。None ならセットとして扱わない
+    #[serde(default)]
+    pub first_to: Option<u8>,
+    /// ホストが選んだルールプリセットの名前。None ならゲームのメニューの設定
+    #[serde(default)]
+    pub rule_preset: Option<String>,
+    /// ホストが対戦の開始時に設定するディレイ。None なら変えない
+    #[serde(default)]
+    pub delay: Option<u8>,
+}
+
+/// 互いに解釈できるメッセージの版。InitMatch で交換し、一致しなければ対戦しない
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/**
+ * 対戦の最初に交換する名前とルール
+ *
+ * 配列としては以前の (名前, ルール) と同じ形なので、protocol_version を送らない版からも読める
+ */
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct InitMatch {
+    pub name: String,
+    /// ホストのみ送る
+    pub initial: Option<MatchInitial>,
+    /// 送らない版は 0
+    #[serde(default)]
+    pub protocol_version: u32,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RoundInitial {
+    pub seed1: u16,
+    pub seed2: u16,
+    pub seed3: u16,
+    pub seed4: u16,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum Side {
+    P1,
+    P2,
+}
+
+/** 対戦を始める前に互いに提示する内容 */
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MatchProposal {
+    pub name: String,
+    /// ホストのみ提示する
+    pub rules: Option<MatchInitial>,
+}
+
+/** 対戦を始める前の確認 */
+#[derive(Debug, Deserialize, Serialize)]
+pub enum AcceptanceMessage {
+    Proposal(MatchProposal),
+    Ping(u32),
+    Pong(u32),
+    /// 承諾するなら true
+    Answer(bool),
+    /// 断られたことを受け取った。断った側はこれを受け取ってから切断する
+    DeclineReceived,
+}
+
+/**
+ * 対戦を始める前に互いの鍵を確かめる。鍵を持っていない場合も None を送る
+ *
+ * ゲストの InitMatch の後、ホストがルールを送る前に行う
+ */
+#[derive(Debug, Deserialize, Serialize)]
+pub enum IdentityMessage {
+    Hello {
+        /// 署名する名前。ゲストはホストの名前をルールより先にここで受け取る
+        name: String,
+        public_key: Option<PublicKey>,
+        nonce: Nonce,
+    },
+    /// 相手の nonce と自分の名前への署名
+    Proof(Option<Vec<u8>>),
+}
+
+/**
+ * input と chat と match result と acceptance と identity 以外はホストのみ発行できる
+ *
+ * delay request はゲストのみ、delay request declined はホストのみ発行できる
+ */
+#[derive(Debug, Deserialize, Serialize)]
+pub enum SessionMessage {
+    InitMatch(InitMatch),
+    InitRound(Option<RoundInitial>),
+    Delay(u8),
+    Input(u16),
+    Chat(String),
+    /// 報告する試合の勝者。両者の報告が一致したら記録する
+    MatchResult(Side),
+    /// ゲストが希望するディレイ
+    DelayRequest(u8),
+    /// ホストが断ったゲストの希望
+    DelayRequestDeclined(u8),
+    Acceptance(AcceptanceMessage),
+    Identity(IdentityMessage),
+    /// 自分の観戦者が送ったチャット。受け取った側は自分の観戦者にだけ転送する
+    SpectatorChat(ChatMessage),
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub enum Screen {
+    DifficultySelect,
+    CharacterSelect,
+    Game,
+}
+
+#[derive(new, Clone, Debug, Deserialize, CopyGetters, Serialize)]
+pub struct InitialState {
+    #[get_copy = "pub"]
+    screen: Screen,
+    #[get_copy = "pub"]
+    difficulty: u8,
+    #[get_copy = "pub"]
+    p1_character: u8,
+    #[get_copy = "pub"]
+    p1_card: u8,
+    #[get_copy = "pub"]
+    p2_character: u8,
+    #[get_copy = "pub"]
+    p2_card: u8,
+}
+
+#[derive(new, Clone, Debug, Deserialize, CopyGetters, Getters, Serialize)]
+pub struct SpectatorInitial {
+    #[get = "pub"]
+    p1_name: String,
+    #[get = "pub"]
+    p2_name: String,
+    #[get = "pub"]
+    game_settings: GameSettings,
+    #[get = "pub"]
+    initial_state: InitialState,
+    #[serde(default)]
+    #[get_copy = "pub"]
+    delay_frames: u32,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ChatMessage {
+    pub name: String,
+    pub text: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub enum SpectatorSessionMessage {
+    InitSpectator(SpectatorInitial),
+    InitRound(RoundInitial),
+    Inputs(u16, u16),
+    Chat(ChatMessage),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_init_match_without_protocol_version() {
+        #[derive(Serialize)]
+        enum OldSessionMessage {
+            InitMatch((String, Option<MatchInitial>)),
+        }
+
+        let bytes =
+            rmp_serde::to_vec(&OldSessionMessage::InitMatch(("old".to_owned(), None))).unwrap();
+        let SessionMessage::InitMatch(init) = rmp_serde::from_slice(&bytes).unwrap() else {
+            unreachable!();
+        };
+        assert_eq!(init.name, "old");
+        assert!(init.initial.is_none());
+        assert_eq!(init.protocol_version, 0);
+
+        let bytes = rmp_serde::to_vec(&SessionMessage::InitMatch(InitMatch {
+            name: "new".to_owned(),
+            initial: None,
+            protocol_version: PROTOCOL_VERSION,
+        }))
+        .unwrap();
+        let SessionMessage::InitMatch(init) = rmp_serde::from_slice(&bytes).unwrap() else {
+            unreachable!();
+        };
+        assert_eq!(init.protocol_version, PROTOCOL_VERSION);
+    }
+}
@@ -1,3 +1,5 @@
 pub mod custom;
+pub mod invitation;
+pub mod ladder;
 pub mod reserved_room;
 pub mod room;
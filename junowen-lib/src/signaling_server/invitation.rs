@@ -0,0 +1,152 @@
+use anyhow::{anyhow, bail, Result};
+use derive_new::new;
+use getset::Getters;
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+
+use crate::identity::{from_hex, to_hex, verify, Identity, PublicKey};
+
+use super::room::PostRoomKeepResponse;
+
+/// 署名に含める時刻と、サーバーの時刻とのずれの許容量
+const TIMESTAMP_TOLERANCE_SEC: u64 = 5 * 60;
+
+fn signed_message(context: &[u8], to: &PublicKey, timestamp: u64) -> Vec<u8> {
+    [context, to, &timestamp.to_le_bytes()].concat()
+}
+
+fn verify_timestamp(timestamp: u64, now_sec: u64) -> Result<()> {
+    if timestamp.abs_diff(now_sec) > TIMESTAMP_TOLERANCE_SEC {
+        bail!("timestamp out of range: {}", timestamp);
+    }
+    Ok(())
+}
+
+// PUT /invitation/{to}
+
+const PUT_INVITATION_CONTEXT: &[u8] = b"junowen-invitation-v1";
+
+/// 招待する側が、招待先と時刻に署名する
+#[derive(Deserialize, Serialize, Getters)]
+pub struct PutInvitationRequestBody {
+    #[get = "pub"]
+    from: String,
+    #[get = "pub"]
+    from_name: String,
+    timestamp: u64,
+    signature: String,
+}
+
+impl PutInvitationRequestBody {
+    pub fn new(identity: &Identity, to: &PublicKey, from_name: String, timestamp: u64) -> Self {
+        let message = signed_message(PUT_INVITATION_CONTEXT, to, timestamp);
+        Self {
+            from: to_hex(&identity.public_key()),
+            from_name,
+            timestamp,
+            signature: to_hex(&identity.sign(&message)),
+        }
+    }
+
+    /// 招待した側の公開鍵を返す
+    pub fn verify(&self, to: &PublicKey, now_sec: u64) -> Result<PublicKey> {
+        verify_timestamp(self.timestamp, now_sec)?;
+        let from = from_hex(&self.from)?;
+        let signature: [u8; 64] = from_hex(&self.signature)?;
+        let message = signed_message(PUT_INVITATION_CONTEXT, to, self.timestamp);
+        verify(&from, &message, &signature)?;
+        Ok(from)
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, new)]
+pub struct PutInvitationResponseCreatedBody {
+    /// サーバーが生成した、招待した側とされた側だけが知る予約部屋の名前
+    room_name: String,
+}
+
+impl PutInvitationResponseCreatedBody {
+    pub fn into_room_name(self) -> String {
+        self.room_name
+    }
+}
+
+#[derive(Debug)]
+pub enum PutInvitationResponse {
+    Created(PutInvitationResponseCreatedBody),
+    BadRequest,
+    /// 招待先が別の招待をまだ受け取っていない
+    Conflict,
+}
+
+impl PutInvitationResponse {
+    pub fn parse(status: StatusCode, text: Option<&str>) -> Result<Self> {
+        match status {
+            StatusCode::CREATED => Ok(Self::Created(serde_json::from_str(
+                text.ok_or_else(|| anyhow!("invalid response"))?,
+            )?)),
+            StatusCode::BAD_REQUEST => Ok(Self::BadRequest),
+            StatusCode::CONFLICT => Ok(Self::Conflict),
+            _ => bail!("invalid response"),
+        }
+    }
+
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            Self::Created(_) => StatusCode::CREATED,
+            Self::BadRequest => StatusCode::BAD_REQUEST,
+            Self::Conflict => StatusCode::CONFLICT,
+        }
+    }
+
+    pub fn to_body(&self) -> Option<String> {
+        match self {
+            Self::Created(body) => Some(serde_json::to_string(&body).unwrap()),
+            Self::BadRequest | Self::Conflict => None,
+        }
+    }
+}
+
+// POST /invitation/{to}/receive
+
+const RECEIVE_INVITATION_CONTEXT: &[u8] = b"junowen-inbox-v1";
+
+/// 招待された本人だけが受け取れるよう、自分の公開鍵と時刻に署名する
+#[derive(Deserialize, Serialize)]
+pub struct PostInvitationReceiveRequestBody {
+    timestamp: u64,
+    signature: String,
+}
+
+impl PostInvitationReceiveRequestBody {
+    pub fn new(identity: &Identity, timestamp: u64) -> Self {
+        let message = signed_message(
+            RECEIVE_INVITATION_CONTEXT,
+            &identity.public_key(),
+            timestamp,
+        );
+        Self {
+            timestamp,
+            signature: to_hex(&identity.sign(&message)),
+        }
+    }
+
+    pub fn verify(&self, to: &PublicKey, now_sec: u64) -> Result<()> {
+        verify_timestamp(self.timestamp, now_sec)?;
+        let signature: [u8; 64] = from_hex(&self.signature)?;
+        let message = signed_message(RECEIVE_INVITATION_CONTEXT, to, self.timestamp);
+        verify(to, &message, &signature)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Getters, Serialize, new)]
+pub struct PostInvitationReceiveResponseOkBody {
+    #[get = "pub"]
+    from: String,
+    #[get = "pub"]
+    from_name: String,
+    #[get = "pub"]
+    room_name: String,
+}
+
+pub type PostInvitationReceiveResponse = PostRoomKeepResponse<PostInvitationReceiveResponseOkBody>;
@@ -0,0 +1,186 @@
+use anyhow::{anyhow, bail, Result};
+use derive_new::new;
+use getset::{CopyGetters, Getters};
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
+
+use crate::{
+    identity::{from_hex, to_hex, verify, Identity, Nonce, PublicKey},
+    session_message::MatchInitial,
+};
+
+const MATCH_RESULT_CONTEXT: &[u8] = b"junowen-match-result-v1";
+
+/// 両者の nonce から作る、セッションごとに一意な ID
+pub fn match_id(host_nonce: &Nonce, guest_nonce: &Nonce) -> String {
+    let hash = Sha3_256::digest([host_nonce.as_slice(), guest_nonce].concat());
+    to_hex(&hash[..16])
+}
+
+/// 両者が同じルールで対戦したことを確かめるためのハッシュ
+pub fn rules_hash(match_initial: &MatchInitial) -> String {
+    // ディレイは対戦相手ごとに変わるのでルールに含めない
+    let rules = MatchInitial {
+        delay: None,
+        ..match_initial.clone()
+    };
+    let hash = Sha3_256::digest(rmp_serde::to_vec(&rules).unwrap());
+    to_hex(&hash[..8])
+}
+
+/** 対戦した両者がそれぞれ署名して報告する、セットの結果 */
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct MatchResult {
+    pub match_id: String,
+    /// 公開鍵
+    pub p1: String,
+    pub p2: String,
+    pub p1_name: String,
+    pub p2_name: String,
+    /// セットの最後の試合のキャラクター
+    pub p1_character: u8,
+    pub p2_character: u8,
+    /// セットで勝った試合の数
+    pub p1_wins: u8,
+    pub p2_wins: u8,
+    pub rules_hash: String,
+}
+
+impl MatchResult {
+    fn signed_message(&self) -> Vec<u8> {
+        [MATCH_RESULT_CONTEXT, &serde_json::to_vec(self).unwrap()].concat()
+    }
+}
+
+// POST /ladder/match-result
+
+#[derive(Deserialize, Serialize)]
+pub struct PostMatchResultRequestBody {
+    result: MatchResult,
+    reporter: String,
+    signature: String,
+}
+
+impl PostMatchResultRequestBody {
+    pub fn new(identity: &Identity, result: MatchResult) -> Self {
+        let signature = to_hex(&identity.sign(&result.signed_message()));
+        Self {
+            result,
+            reporter: to_hex(&identity.public_key()),
+            signature,
+        }
+    }
+
+    pub fn result(&self) -> &MatchResult {
+        &self.result
+    }
+
+    /// 報告者が対戦した本人であることを確かめ、その公開鍵を返す
+    pub fn verify(&self) -> Result<PublicKey> {
+        if self.reporter != self.result.p1 && self.reporter != self.result.p2 {
+            bail!("reporter is not a player");
+        }
+        if self.result.p1 == self.result.p2 {
+            bail!("same players");
+        }
+        let reporter = from_hex(&self.reporter)?;
+        let signature: [u8; 64] = from_hex(&self.signature)?;
+        verify(&reporter, &self.result.signed_message(), &signature)?;
+        Ok(reporter)
+    }
+
+    pub fn into_result(self) -> MatchResult {
+        self.result
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, new)]
+pub struct PostMatchResultResponseRecordedBody {
+    pub p1_rating: i32,
+    pub p2_rating: i32,
+}
+
+#[derive(Debug)]
+pub enum PostMatchResultResponse {
+    /// 相手の報告を待っている
+    Accepted,
+    Recorded(PostMatchResultResponseRecordedBody),
+    BadRequest,
+    /// 相手の報告と内容が一致しなかった
+    Conflict,
+}
+
+impl PostMatchResultResponse {
+    pub fn parse(status: StatusCode, text: Option<&str>) -> Result<Self> {
+        match status {
+            StatusCode::ACCEPTED => Ok(Self::Accepted),
+            StatusCode::CREATED => Ok(Self::Recorded(serde_json::from_str(
+                text.ok_or_else(|| anyhow!("invalid response"))?,
+            )?)),
+            StatusCode::BAD_REQUEST => Ok(Self::BadRequest),
+            StatusCode::CONFLICT => Ok(Self::Conflict),
+            _ => bail!("invalid response"),
+        }
+    }
+
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            Self::Accepted => StatusCode::ACCEPTED,
+            Self::Recorded(_) => StatusCode::CREATED,
+            Self::BadRequest => StatusCode::BAD_REQUEST,
+            Self::Conflict => StatusCode::CONFLICT,
+        }
+    }
+
+    pub fn to_body(&self) -> Option<String> {
+        match self {
+            Self::Recorded(body) => Some(serde_json::to_string(&body).unwrap()),
+            Self::Accepted | Self::BadRequest | Self::Conflict => None,
+        }
+    }
+}
+
+// GET /ladder/leaderboard
+
+#[derive(Clone, CopyGetters, Debug, Deserialize, Getters, Serialize, new)]
+pub struct PlayerRating {
+    #[get = "pub"]
+    public_key: String,
+    #[get = "pub"]
+    name: String,
+    #[get_copy = "pub"]
+    rating: i32,
+    #[get_copy = "pub"]
+    wins: u32,
+    #[get_copy = "pub"]
+    losses: u32,
+}
+
+#[derive(Debug, Deserialize, Serialize, new)]
+pub struct GetLeaderboardResponseOkBody {
+    pub players: Vec<PlayerRating>,
+}
+
+// GET /ladder/players/{public_key}/history
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct HistoryEntry {
+    pub match_id: String,
+    /// UNIX 時間
+    pub recorded_at: u64,
+    pub opponent: String,
+    pub opponent_name: String,
+    pub character: u8,
+    pub opponent_character: u8,
+    pub wins: u8,
+    pub opponent_wins: u8,
+    pub rating_after: i32,
+}
+
+#[derive(Debug, Deserialize, Serialize, new)]
+pub struct GetPlayerHistoryResponseOkBody {
+    pub player: PlayerRating,
+    /// 新しい順
+    pub history: Vec<HistoryEntry>,
+}
@@ -3,6 +3,9 @@ mod post_room_join;
 mod post_room_keep;
 mod put_room;
 
+/// 利用者が入力する部屋名の文字数の上限。招待用にサーバーが生成する部屋名はこれより長い
+pub const MAX_ROOM_NAME_LEN: usize = 32;
+
 pub use put_room::RequestBody as PutRoomRequestBody;
 pub use put_room::Response as PutRoomResponse;
 pub use put_room::ResponseAnswerBody as PutRoomResponseAnswerBody;
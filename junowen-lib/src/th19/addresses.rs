@@ -0,0 +1,313 @@
+use std::{collections::BTreeMap, sync::OnceLock};
+
+use anyhow::{bail, Result};
+use serde::Deserialize;
+
+use crate::{
+    identity::{from_hex, to_hex},
+    structs::field_offsets,
+};
+
+pub const EMBEDDED_ADDRESS_TABLES: &str = include_str!("addresses.toml");
+
+macro_rules! addresses {
+    ($($name:ident),* $(,)?) => {
+        /** ゲームのバージョンごとに異なるアドレス。すべてモジュールからの相対アドレス */
+        #[derive(Clone, Debug)]
+        pub struct Addresses {
+            $(pub $name: usize,)*
+        }
+
+        impl Addresses {
+            pub const NAMES: &'static [&'static str] = &[$(stringify!($name),)*];
+
+            fn from_table(table: &BTreeMap<String, usize>) -> Self {
+                Self {
+                    $($name: table[stringify!($name)],)*
+                }
+            }
+        }
+    };
+}
+
+addresses! {
+    hook_on_waiting_online_vs_connection,
+    hook_0a9540_0175,
+    hook_on_input_players,
+    hook_on_input_menu,
+    play_sound,
+    hook_0bed70_00fc,
+    render_text,
+    hook_0d6e10_0039,
+    hook_0d7180_0008,
+    hook_107540_0046,
+    hook_107540_0937,
+    hook_11f870_034c,
+    hook_1243f0_00f9,
+    hook_1243f0_0320,
+    hook_130ed0_03ec,
+    hook_13f9d0_0345,
+    hook_13f9d0_0446,
+    static_data_start,
+    static_data_end,
+    difficulty_cursor,
+    input_devices,
+    rand_seed1,
+    app,
+    rand_seed2,
+    rand_seed3,
+    rand_seed4,
+    round_frame,
+    vs_mode,
+    p1_input,
+    p2_input,
+    menu_input,
+    sound_manager,
+    selection,
+    game_settings_in_game,
+    direct_3d_device,
+    no_wait,
+    game_settings_in_menu,
+    scene,
+    window_inner,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawGameVersion {
+    name: String,
+    hashes: Vec<String>,
+    addresses: BTreeMap<String, usize>,
+    /// 構造体の名前、フィールドの名前、オフセット
+    offsets: BTreeMap<String, BTreeMap<String, usize>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawAddressTables {
+    versions: Vec<RawGameVersion>,
+}
+
+pub struct GameVersion {
+    name: String,
+    hashes: Vec<[u8; 28]>,
+    addresses: Addresses,
+}
+
+impl GameVersion {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn hashes(&self) -> &[[u8; 28]] {
+        &self.hashes
+    }
+
+    pub fn addresses(&self) -> &Addresses {
+        &self.addresses
+    }
+}
+
+/// 対応していないバージョンの th19.exe
+#[derive(Debug, thiserror::Error)]
+#[error(
+    "unsupported version of th19.exe: {} (supported: {})",
+    to_hex(.hash),
+    supported_versions().join(", ")
+)]
+pub struct UnsupportedVersionError {
+    pub hash: Vec<u8>,
+}
+
+/**
+ * structs のレイアウトと照合する
+ *
+ * structs はバージョンごとにレイアウトを切り替えられないので、違っていればそのバージョンには対応できない
+ */
+fn check_offsets(
+    version_name: &str,
+    offsets: &BTreeMap<String, BTreeMap<String, usize>>,
+    errors: &mut Vec<String>,
+) {
+    let mut missing = Vec::new();
+    let mut different = Vec::new();
+    for &(struct_name, field, offset) in field_offsets() {
+        match offsets
+            .get(struct_name)
+            .and_then(|fields| fields.get(field))
+        {
+            None => missing.push(format!("{}.{}", struct_name, field)),
+            Some(&table_offset) if table_offset != offset => different.push(format!(
+                "{}.{} (0x{:x}, structs: 0x{:x})",
+                struct_name, field, table_offset, offset
+            )),
+            Some(_) => {}
+        }
+    }
+    let unknown: Vec<_> = offsets
+        .iter()
+        .flat_map(|(struct_name, fields)| {
+            fields
+                .keys()
+                .filter(move |&field| {
+                    !field_offsets().any(|&(x, y, _)| x == struct_name && y == field)
+                })
+                .map(move |field| format!("{}.{}", struct_name, field))
+        })
+        .collect();
+    if !missing.is_empty() {
+        errors.push(format!("{}: missing offsets {:?}", version_name, missing));
+    }
+    if !unknown.is_empty() {
+        errors.push(format!("{}: unknown offsets {:?}", version_name, unknown));
+    }
+    if !different.is_empty() {
+        errors.push(format!(
+            "{}: offsets differ from structs {:?}",
+            version_name, different
+        ));
+    }
+}
+
+/**
+ * アドレス表を読み込む
+ *
+ * 足りないアドレスや知らないアドレス、structs と違うオフセットがあれば、それらをすべて挙げてエラーにする
+ */
+pub fn parse_game_versions(toml: &str) -> Result<Vec<GameVersion>> {
+    let raw: RawAddressTables = toml::from_str(toml)?;
+    let mut errors = Vec::new();
+    let mut versions = Vec::new();
+    for version in raw.versions {
+        let missing: Vec<_> = Addresses::NAMES
+            .iter()
+            .filter(|&&name| !version.addresses.contains_key(name))
+            .collect();
+        if !missing.is_empty() {
+            errors.push(format!("{}: missing {:?}", version.name, missing));
+        }
+        let unknown: Vec<_> = version
+            .addresses
+            .keys()
+            .filter(|name| !Addresses::NAMES.contains(&name.as_str()))
+            .collect();
+        if !unknown.is_empty() {
+            errors.push(format!("{}: unknown {:?}", version.name, unknown));
+        }
+        let errors_len = errors.len();
+        check_offsets(&version.name, &version.offsets, &mut errors);
+        let hashes: Result<Vec<_>> = version.hashes.iter().map(|hash| from_hex(hash)).collect();
+        let hashes = match hashes {
+            Ok(hashes) => hashes,
+            Err(err) => {
+                errors.push(format!("{}: invalid hash: {}", version.name, err));
+                continue;
+            }
+        };
+        if !missing.is_empty() || errors.len() > errors_len {
+            continue;
+        }
+        versions.push(GameVersion {
+            addresses: Addresses::from_table(&version.addresses),
+            name: version.name,
+            hashes,
+        });
+    }
+    for (i, version) in versions.iter().enumerate() {
+        for other in &versions[i + 1..] {
+            if version
+                .hashes
+                .iter()
+                .any(|hash| other.hashes.contains(hash))
+            {
+                errors.push(format!("{}, {}: same hash", version.name, other.name));
+            }
+        }
+    }
+    if !errors.is_empty() {
+        bail!("{}", errors.join("\n"));
+    }
+    Ok(versions)
+}
+
+/// 埋め込まれたアドレス表
+pub fn game_versions() -> &'static [GameVersion] {
+    static VERSIONS: OnceLock<Vec<GameVersion>> = OnceLock::new();
+    VERSIONS.get_or_init(|| parse_game_versions(EMBEDDED_ADDRESS_TABLES).unwrap())
+}
+
+fn supported_versions() -> Vec<&'static str> {
+    game_versions()
+        .iter()
+        .map(|version| version.name())
+        .collect()
+}
+
+pub fn find_game_version(hash: &[u8]) -> Result<&'static GameVersion, UnsupportedVersionError> {
+    game_versions()
+        .iter()
+        .find(|version| version.hashes.iter().any(|valid_hash| valid_hash == hash))
+        .ok_or_else(|| UnsupportedVersionError {
+            hash: hash.to_vec(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn embedded_address_tables_are_valid() {
+        let versions = parse_game_versions(EMBEDDED_ADDRESS_TABLES).unwrap();
+        assert!(!versions.is_empty());
+        for version in &versions {
+            assert_eq!(
+                find_game_version(&version.hashes()[0]).unwrap().name(),
+                version.name()
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_missing_and_unknown_addresses() {
+        let toml = EMBEDDED_ADDRESS_TABLES.replace("\nplay_sound = ", "\nplay_sound2 = ");
+        let err = parse_game_versions(&toml).err().unwrap().to_string();
+        assert!(err.contains(r#"missing ["play_sound"]"#), "{}", err);
+        assert!(err.contains(r#"unknown ["play_sound2"]"#), "{}", err);
+    }
+
+    #[test]
+    fn rejects_offsets_different_from_structs() {
+        let toml = EMBEDDED_ADDRESS_TABLES.replace("\np1_card = 0x2ea14", "\np1_card = 0x2ea18");
+        let err = parse_game_versions(&toml).err().unwrap().to_string();
+        assert!(
+            err.contains("VSMode.p1_card (0x2ea18, structs: 0x2ea14)"),
+            "{}",
+            err
+        );
+
+        let toml = EMBEDDED_ADDRESS_TABLES.replace("\np2_card = ", "\np3_card = ");
+        let err = parse_game_versions(&toml).err().unwrap().to_string();
+        assert!(
+            err.contains(r#"missing offsets ["VSMode.p2_card"]"#),
+            "{}",
+            err
+        );
+        assert!(
+            err.contains(r#"unknown offsets ["VSMode.p3_card"]"#),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn rejects_same_hashes() {
+        let toml = format!(
+            "{}\n{}",
+            EMBEDDED_ADDRESS_TABLES,
+            EMBEDDED_ADDRESS_TABLES.replace("name = \"v1.00a\"", "name = \"copy\"")
+        );
+        let err = parse_game_versions(&toml).err().unwrap().to_string();
+        assert!(err.contains("v1.00a, copy: same hash"), "{}", err);
+    }
+}
@@ -0,0 +1,272 @@
+use std::{arch::asm, ffi::c_void, mem::transmute};
+
+use anyhow::{anyhow, Result};
+use windows::{
+    core::Interface,
+    Win32::{Graphics::Direct3D9::IDirect3DDevice9, System::Memory::PAGE_EXECUTE_WRITECOPY},
+};
+
+pub use crate::memory_accessors::FnOfHookAssembly;
+use crate::{
+    hook,
+    hook_utils::{calc_file_hash, calc_th19_hash},
+    memory_accessors::{ExternalProcess, HookedProcess, MemoryAccessor},
+};
+
+use super::{
+    structs::{others::RenderingText, selection::Selection},
+    Th19,
+};
+
+pub type Fn002530 = extern "thiscall" fn(*const c_void);
+pub type Fn009fa0 = extern "thiscall" fn(*const c_void, u32) -> u32;
+pub type Fn011560 = extern "thiscall" fn(*const Selection) -> u8;
+pub type Fn012480 = extern "thiscall" fn(*const c_void, u32) -> u32;
+pub type Fn0a9000 = extern "thiscall" fn(*const c_void);
+pub type Fn0b7d40 = extern "thiscall" fn(*const c_void, *const c_void);
+pub type Fn0d5ae0 = extern "thiscall" fn(*const c_void, *mut RenderingText) -> u32;
+pub type Fn0d6e10 = extern "thiscall" fn(*const c_void, *const c_void) -> u32;
+pub type Fn102ff0 = extern "fastcall" fn(*const c_void);
+pub type Fn1049e0 = extern "fastcall" fn();
+pub type Fn10f720 = extern "fastcall" fn();
+
+extern "fastcall" fn dummy_from_02d1f0_007c() {
+    unsafe {
+        asm! {
+            "NOP",
+            "NOP",
+            "NOP",
+            "NOP",
+            "NOP",
+            "NOP",
+            "NOP",
+            //
+            "NOP",
+            "NOP",
+            "NOP",
+            "NOP",
+            "NOP",
+            "NOP",
+            "NOP",
+            "NOP",
+            "NOP",
+            "NOP",
+            "NOP",
+        }
+    }
+}
+
+extern "fastcall" fn dummy_from_0aba30_00fb() {
+    unsafe {
+        asm! {
+            "NOP",
+            "NOP",
+            "NOP",
+            "NOP",
+            "NOP",
+            "NOP",
+            "NOP",
+            "NOP",
+            "NOP",
+            "NOP",
+            //
+            "NOP",
+            "NOP",
+            "NOP",
+            "NOP",
+            "NOP",
+            "NOP",
+            "NOP",
+            "NOP",
+            "NOP",
+            "NOP",
+            "NOP",
+        }
+    }
+}
+
+extern "fastcall" fn dummy_from_0aba30_018e() {
+    unsafe {
+        asm! {
+            "NOP",
+            "NOP",
+            "NOP",
+            "NOP",
+            "NOP",
+            //
+            "NOP",
+            "NOP",
+            "NOP",
+            "NOP",
+            "NOP",
+            "NOP",
+            "NOP",
+            "NOP",
+            "NOP",
+            "NOP",
+            "NOP",
+        }
+    }
+}
+
+pub type ApplyFn = Box<dyn FnOnce(&mut Th19)>;
+
+impl Th19 {
+    pub fn new_external_process(exe_file: &str) -> Result<Self> {
+        let process = ExternalProcess::new(exe_file)?;
+        let exe_hash = calc_file_hash(&process.module_file_path()?)?;
+        Self::new(MemoryAccessor::ExternalProcess(process), exe_hash)
+    }
+
+    pub fn new_hooked_process(exe_file: &str) -> Result<Self> {
+        Self::new(
+            MemoryAccessor::HookedProcess(HookedProcess::new(exe_file)?),
+            calc_th19_hash(),
+        )
+    }
+
+    pub fn hook_on_waiting_online_vs_connection(
+        &mut self,
+        target: FnOfHookAssembly,
+    ) -> (Option<FnOfHookAssembly>, ApplyFn) {
+        const SIZE: usize = 7;
+        let addr = self.addresses.hook_on_waiting_online_vs_connection;
+        self.hook_assembly(addr, SIZE, dummy_from_02d1f0_007c, target)
+    }
+
+    hook!(hook_0a9540_0175, Fn0a9000);
+
+    pub fn hook_on_input_players(
+        &mut self,
+        target: FnOfHookAssembly,
+    ) -> (Option<FnOfHookAssembly>, ApplyFn) {
+        const SIZE: usize = 10;
+        let addr = self.addresses.hook_on_input_players;
+        self.hook_assembly(addr, SIZE, dummy_from_0aba30_00fb, target)
+    }
+
+    pub fn hook_on_input_menu(
+        &mut self,
+        target: FnOfHookAssembly,
+    ) -> (Option<FnOfHookAssembly>, ApplyFn) {
+        const SIZE: usize = 5;
+        let addr = self.addresses.hook_on_input_menu;
+        self.hook_assembly(addr, SIZE, dummy_from_0aba30_018e, target)
+    }
+
+    /// 01: カード送り
+    /// 02: ピチューン
+    /// 07: 決定
+    /// 08: 決定(重)
+    /// 09: キャンセル
+    /// 0a: 選択
+    /// 10: ブブー
+    /// 11: エクステンド
+    /// 1f: ガシャコン
+    /// 2e: ボム回収効果音
+    /// 57: ガシャコン(重)
+    pub fn play_sound(&self, this: *const c_void, id: u32, arg2: u32) {
+        type Fn = extern "thiscall" fn(*const c_void, u32, u32);
+        let ptr = self
+            .hooked_process_memory_accessor()
+            .raw_ptr(self.addresses.play_sound);
+        (unsafe { transmute::<_, Fn>(ptr) })(this, id, arg2)
+    }
+
+    hook!(hook_0bed70_00fc, Fn0b7d40);
+
+    pub fn render_text(&self, text_renderer: *const c_void, text: &RenderingText) -> u32 {
+        let ptr = self
+            .hooked_process_memory_accessor()
+            .raw_ptr(self.addresses.render_text);
+        (unsafe { transmute::<_, Fn0d5ae0>(ptr) })(text_renderer, text as *const _ as _)
+    }
+
+    hook!(hook_0d6e10_0039, Fn0d5ae0);
+
+    hook!(hook_0d7180_0008, Fn0d6e10);
+
+    hook!(hook_107540_0046, Fn012480);
+    hook!(hook_107540_0937, Fn002530);
+
+    hook!(hook_11f870_034c, Fn1049e0);
+
+    hook!(hook_1243f0_00f9, Fn011560);
+    hook!(hook_1243f0_0320, Fn011560);
+
+    hook!(hook_130ed0_03ec, Fn102ff0);
+
+    hook!(hook_13f9d0_0345, Fn10f720);
+    hook!(hook_13f9d0_0446, Fn009fa0);
+
+    pub fn direct_3d_device(&self) -> Result<&'static IDirect3DDevice9> {
+        let memory_accessor = self.hooked_process_memory_accessor();
+        let p_p_direct_3d_device =
+            memory_accessor.raw_ptr(self.addresses.direct_3d_device) as *const *mut c_void;
+        unsafe { IDirect3DDevice9::from_raw_borrowed(&*p_p_direct_3d_device) }
+            .ok_or_else(|| anyhow!("IDirect3DDevice9::from_raw_borrowed failed"))
+    }
+
+    fn hook_call(&mut self, addr: usize, target: usize) -> (usize, ApplyFn) {
+        let memory_accessor = self.hooked_process_memory_accessor_mut();
+        let old_target = memory_accessor.current_callback_of_hook_call(addr);
+        (
+            old_target,
+            Box::new(move |zelf: &mut Th19| {
+                let memory_accessor = zelf.hooked_process_memory_accessor_mut();
+                let old_flag = memory_accessor
+                    .virtual_protect(addr, 5, PAGE_EXECUTE_WRITECOPY)
+                    .unwrap();
+                let old = memory_accessor.hook_call(addr, target);
+                assert!(old == old_target);
+                memory_accessor.virtual_protect(addr, 5, old_flag).unwrap();
+            }),
+        )
+    }
+
+    fn hook_assembly(
+        &mut self,
+        addr: usize,
+        size: usize,
+        dummy_func: extern "fastcall" fn(),
+        target: FnOfHookAssembly,
+    ) -> (Option<FnOfHookAssembly>, ApplyFn) {
+        let memory_accessor = self.hooked_process_memory_accessor();
+        let old_target = memory_accessor.current_callback_of_hook_assembly(addr);
+        (
+            old_target,
+            Box::new(move |zelf: &mut Th19| {
+                let memory_accessor = zelf.hooked_process_memory_accessor_mut();
+
+                let parent_old = memory_accessor
+                    .virtual_protect(addr, size, PAGE_EXECUTE_WRITECOPY)
+                    .unwrap();
+                let my_old = memory_accessor
+                    .virtual_protect_global(dummy_func as _, size + 5 + 6, PAGE_EXECUTE_WRITECOPY)
+                    .unwrap();
+
+                let old = memory_accessor.hook_assembly(addr, size, dummy_func, target as _);
+                assert!(old == old_target);
+
+                memory_accessor
+                    .virtual_protect_global(dummy_func as _, size + 5 + 6, my_old)
+                    .unwrap();
+                memory_accessor
+                    .virtual_protect(addr, size, parent_old)
+                    .unwrap();
+            }),
+        )
+    }
+    fn hooked_process_memory_accessor(&self) -> &HookedProcess {
+        let MemoryAccessor::HookedProcess(memory_accessor) = &self.memory_accessor else {
+            panic!("Th19::hooked_process_memory_accessor is only available for HookedProcess");
+        };
+        memory_accessor
+    }
+    fn hooked_process_memory_accessor_mut(&mut self) -> &mut HookedProcess {
+        let MemoryAccessor::HookedProcess(memory_accessor) = &mut self.memory_accessor else {
+            panic!("Th19::hooked_process_memory_accessor_mut is only available for HookedProcess");
+        };
+        memory_accessor
+    }
+}
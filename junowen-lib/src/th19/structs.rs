@@ -1,5 +1,37 @@
+//! ゲームのメモリー上の構造体。レイアウトは v1.00a のもので、バージョンごとには切り替えない
+//!
+//! 各モジュールの FIELD_OFFSETS は、addresses.toml の offsets と照合する
+
+/// 構造体の名前、フィールドの名前、オフセット
+pub type FieldOffset = (&'static str, &'static str, usize);
+
+macro_rules! field_offsets {
+    ($($struct:ident { $($field:ident),* $(,)? }),* $(,)?) => {
+        pub(super) const FIELD_OFFSETS: &[super::FieldOffset] = &[
+            $($((
+                stringify!($struct),
+                stringify!($field),
+                ::std::mem::offset_of!($struct, $field),
+            ),)*)*
+        ];
+    };
+}
+
 pub mod app;
 pub mod input_devices;
 pub mod others;
 pub mod selection;
 pub mod settings;
+
+/// 名前を付けたフィールドのオフセット
+pub fn field_offsets() -> impl Iterator<Item = &'static FieldOffset> {
+    [
+        app::FIELD_OFFSETS,
+        input_devices::FIELD_OFFSETS,
+        others::FIELD_OFFSETS,
+        selection::FIELD_OFFSETS,
+        settings::FIELD_OFFSETS,
+    ]
+    .into_iter()
+    .flatten()
+}
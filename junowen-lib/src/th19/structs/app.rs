@@ -1,5 +1,16 @@
 use getset::{CopyGetters, Getters, MutGetters, Setters};
 
+// MainLoopTasksLinkedList はポインターの大きさで決まるので含めない
+field_offsets! {
+    ControllerSelect { cursor, max_cursor, depth },
+    CharacterCursor { cursor, prev_cursor },
+    Menu { cursor, max_cursor, num_disabled, p1_cursor, p2_cursor },
+    MainMenu { screen_id, menu },
+    Game { cursor, depth, pause },
+    MainLoopTasksLinkedListItem { id, func, arg },
+    App { main_loop_tasks },
+}
+
 #[repr(C)]
 pub struct ControllerSelect {
     _unknown1: [u8; 0x14],
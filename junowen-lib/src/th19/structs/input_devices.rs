@@ -1,6 +1,20 @@
 use flagset::{flags, FlagSet, InvalidBits};
 use getset::{CopyGetters, Getters, MutGetters, Setters};
 
+field_offsets! {
+    Input {
+        current,
+        prev,
+        repeat,
+        up_repeat_count,
+        down_repeat_count,
+        left_repeat_count,
+        right_repeat_count,
+    },
+    InputDevice { input, raw_keys },
+    InputDevices { input_device_array, p1_idx, p2_idx },
+}
+
 flags! {
     pub enum InputFlags: u32 {
         SHOT,
@@ -7,6 +7,26 @@ use anyhow::Result;
 use derivative::Derivative;
 use getset::CopyGetters;
 
+field_offsets! {
+    RoundFrame { pre_frame, frame },
+    VSMode { player_name, room_name, p1_card, p2_card },
+    WindowInner { width, height },
+    RenderingText {
+        raw_text,
+        x,
+        y,
+        color,
+        scale_x,
+        scale_y,
+        rotate,
+        font_type,
+        drop_shadow,
+        hide,
+        horizontal_align,
+        vertical_align,
+    },
+}
+
 #[derive(Debug)]
 #[repr(C)]
 pub struct RoundFrame {
@@ -56,6 +76,7 @@ impl VSMode {
 }
 
 #[derive(CopyGetters)]
+#[repr(C)]
 pub struct WindowInner {
     #[get_copy = "pub"]
     width: u32,
@@ -129,6 +150,15 @@ impl RenderingText {
         self.raw_text = raw_text;
     }
 
+    /// UTF-8 の文字の途中で切らないように、バッファーに収まる長さまで切り詰めて設定する
+    pub fn set_str(&mut self, text: &str) {
+        let mut len = text.len().min(self.raw_text.len() - 1);
+        while !text.is_char_boundary(len) {
+            len -= 1;
+        }
+        self.set_text(&text.as_bytes()[..len]);
+    }
+
     pub fn set_x(&mut self, x: u32, window_inner: &WindowInner) {
         self.x = (x * window_inner.width() / 1280) as f32;
     }
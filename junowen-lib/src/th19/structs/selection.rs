@@ -3,6 +3,11 @@ use std::mem::transmute;
 use anyhow::{bail, Result};
 use getset::{Getters, MutGetters};
 
+field_offsets! {
+    Player { character, card },
+    Selection { p1, p2, difficulty, game_mode, player_matchup },
+}
+
 /// length=c0
 #[repr(C)]
 pub struct Player {
@@ -15,28 +20,23 @@ pub struct Player {
     _unknown3: [u8; 0x2c],
 }
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 #[repr(u32)]
 pub enum Difficulty {
     Easy,
+    #[default]
     Normal,
     Hard,
     Lunatic,
 }
 
-impl Default for Difficulty {
-    fn default() -> Self {
-        Self::Normal
-    }
-}
-
 impl TryFrom<u32> for Difficulty {
     type Error = anyhow::Error;
     fn try_from(value: u32) -> Result<Self> {
         if !(0..4).contains(&value) {
             bail!("Invalid Difficulty: {}", value);
         }
-        Ok(unsafe { transmute(value) })
+        Ok(unsafe { transmute::<u32, Self>(value) })
     }
 }
 
@@ -54,32 +54,27 @@ impl TryFrom<u32> for GameMode {
         if !(0..3).contains(&value) {
             bail!("Invalid GameMode: {}", value);
         }
-        Ok(unsafe { transmute(value) })
+        Ok(unsafe { transmute::<u32, Self>(value) })
     }
 }
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 #[repr(u32)]
 pub enum PlayerMatchup {
+    #[default]
     HumanVsHuman,
     HumanVsCpu,
     CpuVsCpu,
     YoukaiVsYoukai,
 }
 
-impl Default for PlayerMatchup {
-    fn default() -> Self {
-        Self::HumanVsHuman
-    }
-}
-
 impl TryFrom<u32> for PlayerMatchup {
     type Error = anyhow::Error;
     fn try_from(value: u32) -> Result<Self> {
         if !(0..4).contains(&value) {
             bail!("Invalid PlayerMatchup: {}", value);
         }
-        Ok(unsafe { transmute(value) })
+        Ok(unsafe { transmute::<u32, Self>(value) })
     }
 }
 
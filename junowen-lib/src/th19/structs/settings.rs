@@ -5,6 +5,11 @@ use getset::{CopyGetters, Setters};
 use num_enum::TryFromPrimitive;
 use serde::{Deserialize, Serialize};
 
+field_offsets! {
+    GameSettings { common, p1, p2 },
+    Settings { game_settings },
+}
+
 #[derive(Clone, Copy, Default, TryFromPrimitive)]
 #[repr(u8)]
 pub enum TimeLimit {
@@ -64,6 +69,17 @@ pub enum AbilityCard {
     AllCard = 3,
 }
 
+impl Display for AbilityCard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoUse => f.write_str("No Use"),
+            Self::Random => f.write_str("Random"),
+            Self::SelfCard => f.write_str("Self Card"),
+            Self::AllCard => f.write_str("All Card"),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Default, TryFromPrimitive)]
 #[repr(u8)]
 pub enum Barrier {
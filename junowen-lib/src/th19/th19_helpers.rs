@@ -2,7 +2,7 @@ use tracing::{trace, warn};
 
 use crate::{
     structs::{
-        app::{MainMenu, ScreenId},
+        app::ScreenId,
         input_devices::{InputFlags, InputValue},
         selection::{GameMode, PlayerMatchup},
     },
@@ -73,65 +73,63 @@ impl AutomaticInputs {
         }
     }
 
-    pub fn on_input_menu(&self, th19: &mut Th19, main_menu: &mut MainMenu) -> bool {
+    pub fn on_input_menu(&self, th19: &mut Th19, screen_id: ScreenId) -> bool {
         match self {
-            Self::TransitionToTitle => transfer_to_title_on_input_menu(th19, main_menu),
-            Self::ResolveKeyboardFullConflict => resolve_input_device_conflict(th19, main_menu),
+            Self::TransitionToTitle => transfer_to_title_on_input_menu(th19, screen_id),
+            Self::ResolveKeyboardFullConflict => resolve_input_device_conflict(th19, screen_id),
             Self::TransitionToLocalVersusDifficultySelect(target_player_matchup) => {
-                transfer_to_local_versus_difficulty_select(th19, main_menu, *target_player_matchup)
+                transfer_to_local_versus_difficulty_select(th19, screen_id, *target_player_matchup)
             }
         }
     }
 }
 
 fn transfer_to_title_on_input_players(th19: &mut Th19) {
-    let input_devices = th19.input_devices_mut();
+    let p1_prev = th19.input_devices().p1_input().prev();
+    let p2_prev = th19.input_devices().p2_input().prev();
+    let menu_prev = th19.menu_input().prev();
     let main_menu = th19.app_mut().main_loop_tasks_mut().find_main_menu_mut();
     let (p1, p2) = if let Some(main_menu) = main_menu {
         match main_menu.screen_id() {
-            ScreenId::CharacterSelect => (
-                escape_repeatedly(input_devices.p1_input().prev()),
-                escape_repeatedly(input_devices.p2_input().prev()),
-            ),
+            ScreenId::CharacterSelect => (escape_repeatedly(p1_prev), escape_repeatedly(p2_prev)),
             ScreenId::Archievements => (
                 InputFlags::SHOT.into(), // skip ending
                 InputValue::empty(),
             ),
             ScreenId::Option => return,
-            _ => (
-                escape_repeatedly(th19.menu_input().prev()),
-                InputValue::empty(),
-            ),
+            _ => (escape_repeatedly(menu_prev), InputValue::empty()),
         }
     } else if let Some(game) = th19.app_mut().main_loop_tasks_mut().find_game_mut() {
         if game.pause() == 0 {
-            (
-                escape_repeatedly(input_devices.p1_input().prev()),
-                escape_repeatedly(input_devices.p2_input().prev()),
-            )
+            (escape_repeatedly(p1_prev), escape_repeatedly(p2_prev))
         } else if game.depth() == 0 {
             game.set_cursor(1);
-            (
-                shot_repeatedly(input_devices.p1_input().prev()),
-                shot_repeatedly(input_devices.p2_input().prev()),
-            )
+            (shot_repeatedly(p1_prev), shot_repeatedly(p2_prev))
         } else {
             game.set_cursor(0);
-            (
-                shot_repeatedly(input_devices.p1_input().prev()),
-                shot_repeatedly(input_devices.p2_input().prev()),
-            )
+            (shot_repeatedly(p1_prev), shot_repeatedly(p2_prev))
         }
     } else {
         (InputValue::empty(), InputValue::empty())
     };
+    let input_devices = th19.input_devices_mut();
     input_devices.p1_input_mut().set_current(p1);
     input_devices.p2_input_mut().set_current(p2);
 }
 
-fn transfer_to_title_on_input_menu(th19: &mut Th19, main_menu: &MainMenu) -> bool {
-    trace!("menu.screen_id: {:x?}", main_menu.screen_id());
-    let menu_input = match main_menu.screen_id() {
+fn main_menu_cursor_mut(th19: &mut Th19) -> &mut u32 {
+    th19.app_mut()
+        .main_loop_tasks_mut()
+        .find_main_menu_mut()
+        .unwrap()
+        .menu_mut()
+        .cursor_mut()
+}
+
+fn transfer_to_title_on_input_menu(th19: &mut Th19, screen_id: ScreenId) -> bool {
+    trace!("menu.screen_id: {:x?}", screen_id);
+    let menu_prev = th19.menu_input().prev();
+    let menu_input = match screen_id {
         ScreenId::TitleLoading => return false,
         ScreenId::Title => InputValue::empty(),
         ScreenId::ControllerSelect => 'a: {
@@ -145,7 +143,7 @@ fn transfer_to_title_on_input_menu(th19: &mut Th19, main_menu: &MainMenu) -> boo
             if ctrler_select.depth == 1 {
                 return false;
             }
-            select_cursor(th19.menu_input().prev(), &mut ctrler_select.cursor, 3)
+            select_cursor(menu_prev, &mut ctrler_select.cursor, 3)
         }
         ScreenId::Option => 'a: {
             if th19
@@ -154,32 +152,31 @@ fn transfer_to_title_on_input_menu(th19: &mut Th19, main_menu: &MainMenu) -> boo
                 .find_controller_select_mut()
                 .is_none()
             {
-                break 'a escape_repeatedly(th19.menu_input().prev());
+                break 'a escape_repeatedly(menu_prev);
             }
             // NOTE: Can't determine whether it is in key config or not,
             //       so control is not possible.
             return false;
         }
-        _ => escape_repeatedly(th19.menu_input().prev()),
+        _ => escape_repeatedly(menu_prev),
     };
     th19.menu_input_mut().set_current(menu_input);
     true
 }
 
-fn resolve_input_device_conflict(th19: &mut Th19, main_menu: &mut MainMenu) -> bool {
+fn resolve_input_device_conflict(th19: &mut Th19, screen_id: ScreenId) -> bool {
     if !th19.input_devices().is_conflict_input_device() {
         return true;
     }
-    let screen_id = main_menu.screen_id();
-    let menu = main_menu.menu_mut();
+    let menu_prev = th19.menu_input().prev();
     let menu_input = match (
         screen_id,
         th19.selection().game_mode,
         th19.selection().player_matchup,
     ) {
-        (ScreenId::Title, _, _) => select_cursor(th19.menu_input().prev(), menu.cursor_mut(), 1),
+        (ScreenId::Title, _, _) => select_cursor(menu_prev, main_menu_cursor_mut(th19), 1),
         (ScreenId::PlayerMatchupSelect, _, _) => {
-            select_cursor(th19.menu_input().prev(), menu.cursor_mut(), 4)
+            select_cursor(menu_prev, main_menu_cursor_mut(th19), 4)
         }
         (ScreenId::ControllerSelect, _, _) => {
             if let Some(ctrler_select) = th19
@@ -188,7 +185,7 @@ fn resolve_input_device_conflict(th19: &mut Th19, main_menu: &mut MainMenu) -> b
                 .find_controller_select_mut()
             {
                 ctrler_select.cursor = 1;
-                if th19.menu_input().prev() == InputFlags::LEFT.into() {
+                if menu_prev == InputFlags::LEFT.into() {
                     InputValue::empty()
                 } else {
                     InputFlags::LEFT.into()
@@ -197,7 +194,7 @@ fn resolve_input_device_conflict(th19: &mut Th19, main_menu: &mut MainMenu) -> b
                 InputValue::empty()
             }
         }
-        _ => escape_repeatedly(th19.menu_input().prev()),
+        _ => escape_repeatedly(menu_prev),
     };
     th19.menu_input_mut().set_current(menu_input);
     true
@@ -205,39 +202,33 @@ fn resolve_input_device_conflict(th19: &mut Th19, main_menu: &mut MainMenu) -> b
 
 fn transfer_to_local_versus_difficulty_select(
     th19: &mut Th19,
-    main_menu: &mut MainMenu,
+    screen_id: ScreenId,
     target_player_matchup: PlayerMatchup,
 ) -> bool {
-    let screen_id = main_menu.screen_id();
-    let menu = main_menu.menu_mut();
-    th19.menu_input_mut().set_current(
-        match (
-            screen_id,
-            th19.selection().game_mode,
-            th19.selection().player_matchup,
-        ) {
-            (ScreenId::TitleLoading, _, _) => InputValue::empty(),
-            (ScreenId::Title, _, _) => {
-                select_cursor(th19.menu_input_mut().prev(), menu.cursor_mut(), 1)
-            }
-            (ScreenId::PlayerMatchupSelect, _, _) => {
-                let target = if target_player_matchup == PlayerMatchup::HumanVsCpu {
-                    1
-                } else {
-                    0
-                };
-                select_cursor(th19.menu_input_mut().prev(), menu.cursor_mut(), target)
-            }
-            (
-                ScreenId::DifficultySelect,
-                GameMode::Versus,
-                PlayerMatchup::HumanVsHuman | PlayerMatchup::HumanVsCpu | PlayerMatchup::CpuVsCpu,
-            ) => InputValue::empty(),
-            _ => {
-                warn!("unsupported screen {}", screen_id as u32);
-                InputValue::empty()
-            }
-        },
-    );
+    let menu_prev = th19.menu_input().prev();
+    let selection = th19.selection();
+    let (game_mode, player_matchup) = (selection.game_mode, selection.player_matchup);
+    let menu_input = match (screen_id, game_mode, player_matchup) {
+        (ScreenId::TitleLoading, _, _) => InputValue::empty(),
+        (ScreenId::Title, _, _) => select_cursor(menu_prev, main_menu_cursor_mut(th19), 1),
+        (ScreenId::PlayerMatchupSelect, _, _) => {
+            let target = if target_player_matchup == PlayerMatchup::HumanVsCpu {
+                1
+            } else {
+                0
+            };
+            select_cursor(menu_prev, main_menu_cursor_mut(th19), target)
+        }
+        (
+            ScreenId::DifficultySelect,
+            GameMode::Versus,
+            PlayerMatchup::HumanVsHuman | PlayerMatchup::HumanVsCpu | PlayerMatchup::CpuVsCpu,
+        ) => InputValue::empty(),
+        _ => {
+            warn!("unsupported screen {}", screen_id as u32);
+            InputValue::empty()
+        }
+    };
+    th19.menu_input_mut().set_current(menu_input);
     true
 }
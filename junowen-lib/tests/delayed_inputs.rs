@@ -0,0 +1,83 @@
+//! 劣悪な回線を再現した上で 2 つの DelayedInputs を同じプロセス内で対戦させ、
+//! ディレイの変更やラウンドの境目を挟んでも両者が同じ入力列を得ることを確かめる
+
+use std::{
+    sync::mpsc::{self, RecvError},
+    thread,
+    time::Duration,
+};
+
+use junowen_lib::{
+    connection::network_simulator::{simulated_link, NetworkConditions},
+    delayed_inputs::DelayedInputs,
+    session_message::{RoundInitial, SessionMessage},
+};
+
+const ROUNDS: u32 = 3;
+const FRAMES_PER_ROUND: u32 = 600;
+/// ホストがディレイを変えるフレームと値
+const DELAY_CHANGES: [(u32, u8); 6] = [(0, 2), (100, 12), (200, 0), (220, 30), (350, 9), (450, 21)];
+
+/// 再現可能なように、フレームと側から入力を決める
+fn input(host: bool, round: u32, frame: u32) -> u16 {
+    let x = (round * FRAMES_PER_ROUND + frame) * 2 + host as u32;
+    (x.wrapping_mul(2654435761) >> 16) as u16
+}
+
+fn run_peer(
+    host: bool,
+    sender: mpsc::Sender<SessionMessage>,
+    receiver: mpsc::Receiver<SessionMessage>,
+) -> Result<Vec<(u16, u16)>, RecvError> {
+    let mut delayed_inputs = DelayedInputs::new(sender, receiver, host);
+    let mut log = Vec::new();
+    for round in 0..ROUNDS {
+        let init = host.then_some(RoundInitial {
+            seed1: round as u16,
+            seed2: 0,
+            seed3: 0,
+            seed4: 0,
+        });
+        delayed_inputs.send_init_round(init);
+        delayed_inputs.recv_init_round()?;
+        for frame in 0..FRAMES_PER_ROUND {
+            let delay = host
+                .then(|| {
+                    DELAY_CHANGES
+                        .iter()
+                        .find(|&&(f, _)| f == frame)
+                        .map(|&(_, delay)| delay)
+                })
+                .flatten();
+            let inputs =
+                delayed_inputs.enqueue_input_and_dequeue(input(host, round, frame), delay)?;
+            log.push(inputs);
+            // ゲームの 1 フレームより短くして、回線の遅れを目立たせる
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+    Ok(log)
+}
+
+#[test]
+fn inputs_stay_in_sync_over_a_bad_network() {
+    let conditions = NetworkConditions {
+        latency: Duration::from_millis(30),
+        jitter: Duration::from_millis(20),
+        reorder_rate: 0.05,
+        reorder_delay: Duration::from_millis(40),
+        loss_rate: 0.02,
+        retransmission_timeout: Duration::from_millis(100),
+        ordered: true,
+    };
+    let ((host_tx, host_rx), (guest_tx, guest_rx)) = simulated_link(conditions, conditions);
+    let host = thread::spawn(move || run_peer(true, host_tx, host_rx));
+    let guest = thread::spawn(move || run_peer(false, guest_tx, guest_rx));
+    let host_log = host.join().unwrap().unwrap();
+    let guest_log = guest.join().unwrap().unwrap();
+
+    assert_eq!(host_log.len(), (ROUNDS * FRAMES_PER_ROUND) as usize);
+    let desynced =
+        (0..host_log.len().max(guest_log.len())).find(|&i| host_log.get(i) != guest_log.get(i));
+    assert_eq!(desynced, None, "host and guest inputs desynced");
+}
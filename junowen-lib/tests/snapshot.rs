@@ -0,0 +1,261 @@
+//! ゲームの中で記録するのと同じ形のスナップショットを組み立てて Th19 として読み、
+//! 構造体の定義がゲームのメモリー上のオフセットと合っていることを確かめる
+//!
+//! 構造体の定義を変えたときに気付けるよう、オフセットは offset_of などで求めずに数値で書く
+
+use std::{fs, mem::size_of, path::Path};
+
+use junowen_lib::{
+    addresses::game_versions,
+    structs::{
+        input_devices::{InputDevice, InputFlags, InputValue},
+        others::{RoundFrame, VSMode},
+        selection::{Difficulty, GameMode, Player, PlayerMatchup},
+    },
+    MemorySnapshot, Th19,
+};
+
+const BASE_ADDRESS: usize = 0x00400000;
+const INPUT_DEVICES_ADDRESS: usize = 0x10000000;
+const ROUND_FRAME_ADDRESS: usize = 0x10100000;
+const VS_MODE_ADDRESS: usize = 0x10200000;
+
+/// ポインターが並んでいる静的な領域 (input_devices から vs_mode まで)
+const POINTERS_START: usize = 0x1ae3a0;
+const POINTERS_END: usize = 0x1ae610;
+
+struct Region(Vec<u8>);
+
+impl Region {
+    fn new(len: usize) -> Self {
+        Self(vec![0; len])
+    }
+
+    fn u32(mut self, offset: usize, value: u32) -> Self {
+        self.0[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    fn bytes(mut self, offset: usize, value: &[u8]) -> Self {
+        self.0[offset..offset + value.len()].copy_from_slice(value);
+        self
+    }
+}
+
+struct Fixture {
+    selection: Region,
+    input_devices: Region,
+    round_frame: Option<Region>,
+    vs_mode: Region,
+}
+
+fn character_select() -> Fixture {
+    let input = |flag: InputFlags| InputValue::from(flag).bits();
+    Fixture {
+        selection: Region::new(0x18c)
+            // p1: character, card
+            .u32(0x00c, 3)
+            .u32(0x090, 7)
+            // p2: character, card
+            .u32(0x0c0 + 0x00c, 12)
+            .u32(0x0c0 + 0x090, 1)
+            .u32(0x180, Difficulty::Hard as u32)
+            .u32(0x184, GameMode::Versus as u32)
+            .u32(0x188, PlayerMatchup::HumanVsHuman as u32),
+        // 0 番がキーボード、それ以降がコントローラー
+        input_devices: Region::new(0x2e2c)
+            .u32(0x20 + 0x3d4 + 0x10, input(InputFlags::SHOT))
+            .u32(0x20 + 0x3d4 * 2 + 0x10, input(InputFlags::LEFT))
+            .u32(0x20 + 0x3d4 * 2 + 0x14, input(InputFlags::LEFT))
+            .u32(0x2e24, 1)
+            .u32(0x2e28, 2),
+        round_frame: None,
+        vs_mode: Region::new(0x2ea16)
+            .bytes(0x2e8c8, b"reimu\0")
+            .bytes(0x2e8ea, b"room\0")
+            .bytes(0x2ea14, &[7, 1]),
+    }
+}
+
+fn in_game(pre_frame: u32, frame: u32) -> Fixture {
+    Fixture {
+        round_frame: Some(Region::new(0x18).u32(0x10, pre_frame).u32(0x14, frame)),
+        ..character_select()
+    }
+}
+
+/** 記録したファイルと同じ経路で読むため、一度バイト列にしてから読み直す */
+fn load(fixture: Fixture) -> Th19 {
+    let version = &game_versions()[0];
+    let addresses = version.addresses();
+    let mut snapshot = MemorySnapshot::new(BASE_ADDRESS, version.hashes()[0].to_vec());
+
+    let pointer = |addr: usize| addr - POINTERS_START;
+    let mut pointers = Region::new(POINTERS_END - POINTERS_START)
+        .u32(
+            pointer(addresses.input_devices),
+            INPUT_DEVICES_ADDRESS as u32,
+        )
+        .u32(pointer(addresses.vs_mode), VS_MODE_ADDRESS as u32);
+    if fixture.round_frame.is_some() {
+        pointers = pointers.u32(pointer(addresses.round_frame), ROUND_FRAME_ADDRESS as u32);
+    }
+    snapshot.add_region(BASE_ADDRESS + POINTERS_START, &pointers.0);
+    snapshot.add_region(BASE_ADDRESS + addresses.selection, &fixture.selection.0);
+    snapshot.add_region(INPUT_DEVICES_ADDRESS, &fixture.input_devices.0);
+    if let Some(round_frame) = &fixture.round_frame {
+        snapshot.add_region(ROUND_FRAME_ADDRESS, &round_frame.0);
+    }
+    snapshot.add_region(VS_MODE_ADDRESS, &fixture.vs_mode.0);
+
+    let snapshot = MemorySnapshot::from_bytes(&snapshot.to_bytes()).unwrap();
+    Th19::new_snapshot(snapshot).unwrap()
+}
+
+#[test]
+fn struct_sizes() {
+    assert_eq!(size_of::<Player>(), 0xc0);
+    assert_eq!(size_of::<InputDevice>(), 0x3d4);
+    assert_eq!(size_of::<RoundFrame>(), 0x18);
+    assert_eq!(size_of::<VSMode>(), 0x2ea16);
+}
+
+#[test]
+fn selection() {
+    let th19 = load(character_select());
+    let selection = th19.selection();
+    assert_eq!(selection.p1().character, 3);
+    assert_eq!(selection.p1().card, 7);
+    assert_eq!(selection.p2().character, 12);
+    assert_eq!(selection.p2().card, 1);
+    assert_eq!(selection.difficulty, Difficulty::Hard);
+    assert!(selection.game_mode == GameMode::Versus);
+    assert_eq!(selection.player_matchup, PlayerMatchup::HumanVsHuman);
+}
+
+#[test]
+fn vs_mode() {
+    let th19 = load(character_select());
+    let vs_mode = th19.vs_mode();
+    assert_eq!(vs_mode.player_name(), "reimu");
+    assert_eq!(vs_mode.room_name(), "room");
+    assert_eq!(vs_mode.p1_card(), 7);
+    assert_eq!(vs_mode.p2_card(), 1);
+}
+
+#[test]
+fn input_devices() {
+    let th19 = load(character_select());
+    let input_devices = th19.input_devices();
+    assert_eq!(input_devices.p1_idx(), 1);
+    assert!(!input_devices.is_conflict_input_device());
+    assert_eq!(input_devices.p1_input().current(), InputFlags::SHOT.into());
+    assert!(input_devices.p1_input().decide());
+    assert_eq!(input_devices.p2_input().current(), InputFlags::LEFT.into());
+    assert!(!input_devices.p2_input().decide());
+    assert_eq!(
+        input_devices.keyboard_input().input().current(),
+        InputValue::empty()
+    );
+}
+
+#[test]
+fn round_frame() {
+    assert!(load(character_select()).round_frame().is_none());
+
+    let th19 = load(in_game(0xffffffff, 0));
+    let round_frame = th19.round_frame().unwrap();
+    assert!(round_frame.is_first_frame());
+
+    let th19 = load(in_game(119, 120));
+    let round_frame = th19.round_frame().unwrap();
+    assert!(!round_frame.is_first_frame());
+    assert_eq!(round_frame.pre_frame, 119);
+    assert_eq!(round_frame.frame, 120);
+}
+
+#[test]
+fn rejects_broken_snapshots() {
+    let mut snapshot = MemorySnapshot::new(BASE_ADDRESS, game_versions()[0].hashes()[0].to_vec());
+    snapshot.add_region(VS_MODE_ADDRESS, &[1, 2, 3, 4]);
+    let bytes = snapshot.to_bytes();
+    assert!(MemorySnapshot::from_bytes(&bytes).is_ok());
+    assert!(MemorySnapshot::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    let mut bytes = bytes;
+    bytes[0] = b'X';
+    assert!(MemorySnapshot::from_bytes(&bytes).is_err());
+}
+
+#[test]
+fn reads_only_captured_ranges() {
+    let mut snapshot = MemorySnapshot::new(BASE_ADDRESS, game_versions()[0].hashes()[0].to_vec());
+    snapshot.add_region(BASE_ADDRESS + 0x1000, &[1, 2, 3, 4]);
+    snapshot.add_region(BASE_ADDRESS + 0x2000, &0u32.to_le_bytes());
+    snapshot.add_region(
+        BASE_ADDRESS + 0x2004,
+        &((BASE_ADDRESS + 0x1000) as u32).to_le_bytes(),
+    );
+    snapshot.add_region(BASE_ADDRESS + 0x2008, &0x20000000u32.to_le_bytes());
+
+    assert!(snapshot.raw_ptr(0x1000, 4).is_some());
+    assert!(snapshot.raw_ptr(0x1002, 2).is_some());
+    // 範囲の端をまたいで読まない
+    assert!(snapshot.raw_ptr(0x1000, 5).is_none());
+    assert!(snapshot.raw_ptr(0x1002, 4).is_none());
+    assert!(snapshot.raw_ptr(0x0fff, 1).is_none());
+    assert!(snapshot.raw_ptr(usize::MAX, 4).is_none());
+
+    // null と記録していない先を指すポインターは辿らない
+    assert!(snapshot.deref_ptr(0x2000, 1).is_none());
+    assert!(snapshot.deref_ptr(0x2004, 4).is_some());
+    assert!(snapshot.deref_ptr(0x2004, 8).is_none());
+    assert!(snapshot.deref_ptr(0x2008, 1).is_none());
+}
+
+#[test]
+fn rejects_partially_captured_structs() {
+    let mut fixture = in_game(119, 120);
+    fixture.round_frame = Some(Region::new(0x14));
+    let th19 = load(fixture);
+    assert!(th19.round_frame().is_none());
+}
+
+#[test]
+#[should_panic]
+fn panics_on_uncaptured_structs() {
+    let mut fixture = character_select();
+    fixture.vs_mode = Region::new(0x100);
+    load(fixture).vs_mode();
+}
+
+/**
+ * examples/capture_snapshot で実際のゲームから記録したファイルを読む
+ *
+ * tests/snapshots/<ゲームのバージョン>.snapshot に置いたものを、どれも矛盾なく読めることを確かめる
+ */
+#[test]
+fn captured_snapshots() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/snapshots");
+    let mut paths: Vec<_> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "snapshot"))
+        .collect();
+    paths.sort();
+    for path in paths {
+        let snapshot = MemorySnapshot::load(&path).unwrap();
+        assert!(
+            game_versions()
+                .iter()
+                .flat_map(|version| version.hashes())
+                .any(|hash| hash[..] == *snapshot.exe_hash()),
+            "{:?}",
+            path
+        );
+        let th19 = Th19::new_snapshot(snapshot).unwrap();
+        th19.selection();
+        th19.input_devices();
+        th19.round_frame();
+        th19.vs_mode();
+    }
+}
@@ -0,0 +1,157 @@
+use std::io::{BufWriter, Write};
+
+use anyhow::Result;
+use flagset::FlagSet;
+use junowen_lib::structs::input_devices::InputFlags;
+use serde_json::{json, Value};
+use th19replayplayer_lib::ReplayFile;
+
+use crate::{frames, BUTTONS};
+
+fn write_csv_buttons(writer: &mut impl Write, input: Option<u16>) -> Result<()> {
+    let flags = input.map(|input| FlagSet::<InputFlags>::new_truncated(input as u32));
+    for &(_, flag) in &BUTTONS {
+        match flags {
+            Some(flags) => write!(writer, ",{}", flags.contains(flag) as u8)?,
+            None => write!(writer, ",")?,
+        }
+    }
+    Ok(())
+}
+
+/** 1 行 1 フレーム。入力の生の値と、ボタンごとの 0/1 を出力する */
+pub fn csv(replay_file: &ReplayFile, writer: &mut impl Write) -> Result<()> {
+    let mut writer = BufWriter::new(writer);
+    write!(writer, "round,frame,p1,p2")?;
+    for player in ["p1", "p2"] {
+        for (name, _) in &BUTTONS {
+            write!(writer, ",{}_{}", player, name)?;
+        }
+    }
+    writeln!(writer)?;
+    for (i, round) in replay_file.rounds.iter().enumerate() {
+        for (frame, (p1, p2)) in frames(&round.inputs).into_iter().enumerate() {
+            write!(writer, "{},{},{},", i + 1, frame, p1)?;
+            if let Some(p2) = p2 {
+                write!(writer, "{}", p2)?;
+            }
+            write_csv_buttons(&mut writer, Some(p1))?;
+            write_csv_buttons(&mut writer, p2)?;
+            writeln!(writer)?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn json(replay_file: &ReplayFile, writer: &mut impl Write) -> Result<()> {
+    let settings = &replay_file.battle_settings;
+    let rounds: Vec<Value> = replay_file
+        .rounds
+        .iter()
+        .map(|round| {
+            let inputs: Vec<Value> = frames(&round.inputs)
+                .into_iter()
+                .map(|(p1, p2)| match p2 {
+                    Some(p2) => json!([p1, p2]),
+                    None => json!([p1]),
+                })
+                .collect();
+            json!({
+                "started_at": round.started_at,
+                "seeds": [round.rand_seed1, round.rand_seed2, round.rand_seed3, round.rand_seed4],
+                "difficulty": format!("{:?}", round.difficulty),
+                "p1_character": round.p1_character,
+                "p1_card": round.p1_card,
+                "p2_character": round.p2_character,
+                "p2_card": round.p2_card,
+                "inputs": inputs,
+            })
+        })
+        .collect();
+    let value = json!({
+        "version": replay_file.version,
+        "recorded_at": replay_file.recorded_at,
+        "p1_name": replay_file.p1_name,
+        "p2_name": replay_file.p2_name,
+        "player_matchup": format!("{:?}", replay_file.player_matchup),
+        "battle_settings": {
+            "common": settings.common(),
+            "p1": settings.p1(),
+            "p2": settings.p2(),
+        },
+        "rounds": rounds,
+    });
+    serde_json::to_writer(&mut *writer, &value)?;
+    writeln!(writer)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use junowen_lib::structs::selection::PlayerMatchup;
+    use th19replayplayer_lib::FileInputList;
+
+    use super::*;
+    use crate::tests::replay_file;
+
+    fn bits(flag: InputFlags) -> u16 {
+        FlagSet::from(flag).bits() as u16
+    }
+
+    fn export(f: fn(&ReplayFile, &mut Vec<u8>) -> Result<()>, replay_file: &ReplayFile) -> String {
+        let mut buf = Vec::new();
+        f(replay_file, &mut buf).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn exports_csv() {
+        let shot = bits(InputFlags::SHOT);
+        let right = bits(InputFlags::RIGHT);
+        let csv = export(
+            csv,
+            &replay_file(vec![vec![(0, 0)], vec![(shot, shot | right)]]),
+        );
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("round,frame,p1,p2,p1_shot,p1_charge,"));
+        assert_eq!(lines[0].split(',').count(), 4 + BUTTONS.len() * 2);
+        assert_eq!(lines[1], "1,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0");
+        assert_eq!(
+            lines[2],
+            format!(
+                "2,0,{},{},1,0,0,0,0,0,0,0,0,0,1,0,0,0,0,0,0,1,0,0",
+                shot,
+                shot | right
+            )
+        );
+    }
+
+    #[test]
+    fn exports_csv_without_cpu_inputs() {
+        let mut replay_file = replay_file(vec![vec![]]);
+        replay_file.player_matchup = PlayerMatchup::HumanVsCpu;
+        replay_file.rounds[0].inputs = FileInputList::HumanVsCpu(vec![bits(InputFlags::BOMB)]);
+        let csv = export(csv, &replay_file);
+        assert_eq!(
+            csv.lines().nth(1).unwrap(),
+            format!(
+                "1,0,{},,0,0,1,0,0,0,0,0,0,0,,,,,,,,,,",
+                bits(InputFlags::BOMB)
+            )
+        );
+    }
+
+    #[test]
+    fn exports_json() {
+        let json = export(json, &replay_file(vec![vec![(0, 0), (1, 2)]]));
+        let value: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["version"], 2);
+        assert_eq!(value["p1_name"], "reimu");
+        assert_eq!(value["player_matchup"], "HumanVsHuman");
+        assert_eq!(value["battle_settings"]["common"], 0x31);
+        assert_eq!(value["rounds"][0]["difficulty"], "Lunatic");
+        assert_eq!(value["rounds"][0]["inputs"], json!([[0, 0], [1, 2]]));
+    }
+}
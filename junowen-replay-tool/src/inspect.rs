@@ -0,0 +1,149 @@
+use anyhow::Result;
+use flagset::FlagSet;
+use junowen_lib::structs::{input_devices::InputFlags, settings::GameSettings};
+use th19replayplayer_lib::{ReplayFile, ReplayRound};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+use crate::{frames, BUTTONS};
+
+/// ボタンごとの押下フレーム数と押した回数
+#[derive(Default)]
+struct ButtonStats {
+    held: [u32; BUTTONS.len()],
+    pressed: [u32; BUTTONS.len()],
+}
+
+impl ButtonStats {
+    fn new(inputs: impl Iterator<Item = u16>) -> Self {
+        let mut stats = Self::default();
+        let mut prev: FlagSet<InputFlags> = None.into();
+        for input in inputs {
+            let current = FlagSet::<InputFlags>::new_truncated(input as u32);
+            for (i, &(_, flag)) in BUTTONS.iter().enumerate() {
+                if current.contains(flag) {
+                    stats.held[i] += 1;
+                    if !prev.contains(flag) {
+                        stats.pressed[i] += 1;
+                    }
+                }
+            }
+            prev = current;
+        }
+        stats
+    }
+}
+
+fn format_timestamp(timestamp: i64) -> String {
+    if timestamp == 0 {
+        return "-".to_owned();
+    }
+    OffsetDateTime::from_unix_timestamp(timestamp)
+        .ok()
+        .and_then(|date_time| date_time.format(&Rfc3339).ok())
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
+fn format_frames(frames: usize) -> String {
+    let seconds = frames as f64 / 60.0;
+    format!(
+        "{} frames ({}:{:05.2})",
+        frames,
+        (seconds / 60.0) as u32,
+        seconds % 60.0
+    )
+}
+
+fn print_settings(settings: &GameSettings) {
+    println!("time limit: {}", settings.time_limit());
+    println!("round: {}", settings.round());
+    println!("ability card: {:?}", settings.ability_card());
+    println!(
+        "P1 life: {}, barrier: {}",
+        settings.p1_life() + 1,
+        settings.p1_barrier()
+    );
+    println!(
+        "P2 life: {}, barrier: {}",
+        settings.p2_life() + 1,
+        settings.p2_barrier()
+    );
+}
+
+fn print_round(number: usize, round: &ReplayRound) {
+    let frames = frames(&round.inputs);
+    println!();
+    println!("round {}", number);
+    println!("  started at: {}", format_timestamp(round.started_at));
+    println!(
+        "  seeds: {} {} {} {}",
+        round.rand_seed1, round.rand_seed2, round.rand_seed3, round.rand_seed4
+    );
+    println!("  difficulty: {:?}", round.difficulty);
+    println!(
+        "  P1: character {}, card {}",
+        round.p1_character, round.p1_card
+    );
+    println!(
+        "  P2: character {}, card {}",
+        round.p2_character, round.p2_card
+    );
+    println!("  length: {}", format_frames(frames.len()));
+
+    let p1 = ButtonStats::new(frames.iter().map(|&(p1, _)| p1));
+    let p2 = frames
+        .iter()
+        .all(|(_, p2)| p2.is_some())
+        .then(|| ButtonStats::new(frames.iter().filter_map(|&(_, p2)| p2)));
+    println!(
+        "  {:<8} {:>16} {:>16}",
+        "button", "P1 held/pressed", "P2 held/pressed"
+    );
+    for (i, (name, _)) in BUTTONS.iter().enumerate() {
+        let p1 = format!("{}/{}", p1.held[i], p1.pressed[i]);
+        let p2 = p2
+            .as_ref()
+            .map(|p2| format!("{}/{}", p2.held[i], p2.pressed[i]))
+            .unwrap_or_else(|| "-".to_owned());
+        println!("  {:<8} {:>16} {:>16}", name, p1, p2);
+    }
+}
+
+pub fn print(replay_file: &ReplayFile) -> Result<()> {
+    println!("version: {}", replay_file.version);
+    println!("recorded at: {}", format_timestamp(replay_file.recorded_at));
+    println!("P1 name: {}", replay_file.p1_name);
+    println!("P2 name: {}", replay_file.p2_name);
+    println!("player matchup: {:?}", replay_file.player_matchup);
+    print_settings(&replay_file.battle_settings);
+    println!("rounds: {}", replay_file.rounds.len());
+    for (i, round) in replay_file.rounds.iter().enumerate() {
+        print_round(i + 1, round);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_held_and_pressed_frames() {
+        let shot = FlagSet::from(InputFlags::SHOT).bits() as u16;
+        let slow = FlagSet::from(InputFlags::SLOW).bits() as u16;
+        let stats = ButtonStats::new([shot, shot | slow, 0, shot].into_iter());
+        let index = |flag| BUTTONS.iter().position(|&(_, f)| f == flag).unwrap();
+        assert_eq!(stats.held[index(InputFlags::SHOT)], 3);
+        assert_eq!(stats.pressed[index(InputFlags::SHOT)], 2);
+        assert_eq!(stats.held[index(InputFlags::SLOW)], 1);
+        assert_eq!(stats.pressed[index(InputFlags::SLOW)], 1);
+        assert_eq!(stats.held[index(InputFlags::BOMB)], 0);
+    }
+
+    #[test]
+    fn formats_values() {
+        assert_eq!(format_frames(0), "0 frames (0:00.00)");
+        assert_eq!(format_frames(3690), "3690 frames (1:01.50)");
+        assert_eq!(format_timestamp(0), "-");
+        assert_eq!(format_timestamp(1_700_000_000), "2023-11-14T22:13:20Z");
+    }
+}
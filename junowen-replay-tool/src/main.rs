@@ -0,0 +1,298 @@
+mod export;
+mod inspect;
+
+use std::{
+    env::args,
+    fs::{self, File},
+    io::{stdout, BufReader},
+    path::Path,
+};
+
+use anyhow::{bail, Context, Result};
+use junowen_lib::structs::input_devices::InputFlags;
+use th19replayplayer_lib::{FileInputList, ReplayFile, LATEST_VERSION};
+
+const USAGE: &str = "\
+usage:
+  junowen-replay-tool info <file>
+  junowen-replay-tool convert <input> <output> <version>
+  junowen-replay-tool trim <input> <output> <first> [<last>]
+  junowen-replay-tool split <input> <output-prefix>
+  junowen-replay-tool export <input> <csv|json>";
+
+const BUTTONS: [(&str, InputFlags); 10] = [
+    ("shot", InputFlags::SHOT),
+    ("charge", InputFlags::CHARGE),
+    ("bomb", InputFlags::BOMB),
+    ("slow", InputFlags::SLOW),
+    ("up", InputFlags::UP),
+    ("down", InputFlags::DOWN),
+    ("left", InputFlags::LEFT),
+    ("right", InputFlags::RIGHT),
+    ("pause", InputFlags::PAUSE),
+    ("enter", InputFlags::ENTER),
+];
+
+/// 各フレームの (P1 の入力, P2 の入力)。CPU 戦では P2 の入力は記録されていない
+fn frames(inputs: &FileInputList) -> Vec<(u16, Option<u16>)> {
+    match inputs {
+        FileInputList::HumanVsHuman(vec) => vec.iter().map(|&(p1, p2)| (p1, Some(p2))).collect(),
+        FileInputList::HumanVsCpu(vec) => vec.iter().map(|&p1| (p1, None)).collect(),
+    }
+}
+
+fn read(path: &str) -> Result<ReplayFile> {
+    let file = File::open(path).with_context(|| format!("failed to open {}", path))?;
+    ReplayFile::read_from_reader(&mut BufReader::new(file))
+        .with_context(|| format!("failed to read {}", path))
+}
+
+fn write(path: &Path, replay_file: &ReplayFile, version: u16) -> Result<()> {
+    let mut buf = Vec::new();
+    match version {
+        1 => replay_file.write_v1_to(&mut buf)?,
+        LATEST_VERSION => replay_file.write_to(&mut buf)?,
+        _ => bail!("unsupported version: {}", version),
+    }
+    fs::write(path, buf)?;
+    Ok(())
+}
+
+fn parse_round_number(arg: &str, round_count: usize) -> Result<usize> {
+    let number: usize = arg.parse()?;
+    if !(1..=round_count).contains(&number) {
+        bail!("round must be between 1 and {}: {}", round_count, number);
+    }
+    Ok(number)
+}
+
+fn trim(input: &str, output: &str, first: &str, last: Option<&str>) -> Result<()> {
+    let mut replay_file = read(input)?;
+    let round_count = replay_file.rounds.len();
+    let first = parse_round_number(first, round_count)?;
+    let last = last
+        .map(|last| parse_round_number(last, round_count))
+        .transpose()?
+        .unwrap_or(round_count);
+    if first > last {
+        bail!("first round is after last round: {} > {}", first, last);
+    }
+    replay_file.rounds.truncate(last);
+    replay_file.rounds.drain(..first - 1);
+    write(Path::new(output), &replay_file, LATEST_VERSION)
+}
+
+fn split(input: &str, output_prefix: &str) -> Result<()> {
+    let mut replay_file = read(input)?;
+    let rounds = std::mem::take(&mut replay_file.rounds);
+    for (i, round) in rounds.into_iter().enumerate() {
+        let path = format!("{}-{}.rep", output_prefix, i + 1);
+        let single = ReplayFile {
+            version: LATEST_VERSION,
+            recorded_at: replay_file.recorded_at,
+            p1_name: replay_file.p1_name.clone(),
+            p2_name: replay_file.p2_name.clone(),
+            player_matchup: replay_file.player_matchup,
+            battle_settings: replay_file.battle_settings.clone(),
+            rounds: vec![round],
+        };
+        write(Path::new(&path), &single, LATEST_VERSION)?;
+        println!("{}", path);
+    }
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let args: Vec<String> = args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(|x| x.as_str()).collect();
+    match args[..] {
+        ["info", file] => inspect::print(&read(file)?),
+        ["convert", input, output, version] => {
+            write(Path::new(output), &read(input)?, version.parse()?)
+        }
+        ["trim", input, output, first] => trim(input, output, first, None),
+        ["trim", input, output, first, last] => trim(input, output, first, Some(last)),
+        ["split", input, output_prefix] => split(input, output_prefix),
+        ["export", input, "csv"] => export::csv(&read(input)?, &mut stdout().lock()),
+        ["export", input, "json"] => export::json(&read(input)?, &mut stdout().lock()),
+        _ => {
+            eprintln!("{}", USAGE);
+            bail!("invalid arguments");
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::path::PathBuf;
+
+    use junowen_lib::structs::{
+        selection::{Difficulty, PlayerMatchup},
+        settings::GameSettings,
+    };
+    use th19replayplayer_lib::ReplayRound;
+
+    use super::*;
+
+    pub fn replay_file(rounds: Vec<Vec<(u16, u16)>>) -> ReplayFile {
+        ReplayFile {
+            version: LATEST_VERSION,
+            recorded_at: 1_700_000_000,
+            p1_name: "reimu".to_owned(),
+            p2_name: "marisa".to_owned(),
+            player_matchup: PlayerMatchup::HumanVsHuman,
+            battle_settings: GameSettings::new(0x31, 2, 3),
+            rounds: rounds
+                .into_iter()
+                .enumerate()
+                .map(|(i, inputs)| ReplayRound {
+                    started_at: 1_700_000_010 + i as i64,
+                    rand_seed1: i as u16,
+                    difficulty: Difficulty::Lunatic,
+                    p1_character: 1,
+                    p2_character: 2,
+                    inputs: FileInputList::HumanVsHuman(inputs),
+                    ..Default::default()
+                })
+                .collect(),
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "junowen-replay-tool-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn path_str(path: &Path) -> &str {
+        path.to_str().unwrap()
+    }
+
+    fn seeds(replay_file: &ReplayFile) -> Vec<u16> {
+        replay_file
+            .rounds
+            .iter()
+            .map(|round| round.rand_seed1)
+            .collect()
+    }
+
+    #[test]
+    fn parses_round_numbers() {
+        assert_eq!(parse_round_number("1", 3).unwrap(), 1);
+        assert_eq!(parse_round_number("3", 3).unwrap(), 3);
+        assert!(parse_round_number("0", 3).is_err());
+        assert!(parse_round_number("4", 3).is_err());
+        assert!(parse_round_number("-1", 3).is_err());
+        assert!(parse_round_number("x", 3).is_err());
+        assert!(parse_round_number("1", 0).is_err());
+    }
+
+    #[test]
+    fn converts_between_versions() {
+        let dir = temp_dir("convert");
+        let v1 = dir.join("v1.rep");
+        let v2 = dir.join("v2.rep");
+        write(&v1, &replay_file(vec![vec![(0, 0), (1, 2)]]), 1).unwrap();
+
+        let read_v1 = read(path_str(&v1)).unwrap();
+        assert_eq!(read_v1.version, 1);
+        write(&v2, &read_v1, LATEST_VERSION).unwrap();
+        let read_v2 = read(path_str(&v2)).unwrap();
+        assert_eq!(read_v2.version, LATEST_VERSION);
+        let [round] = &read_v2.rounds[..] else {
+            panic!("unexpected rounds: {}", read_v2.rounds.len());
+        };
+        assert_eq!(frames(&round.inputs), [(0, Some(0)), (1, Some(2))]);
+
+        let two_rounds = replay_file(vec![vec![], vec![]]);
+        assert!(write(&dir.join("multi.rep"), &two_rounds, 1).is_err());
+        assert!(write(&dir.join("v3.rep"), &two_rounds, 3).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn trims_rounds() {
+        let dir = temp_dir("trim");
+        let input = dir.join("input.rep");
+        let output = dir.join("output.rep");
+        write(
+            &input,
+            &replay_file(vec![vec![], vec![], vec![], vec![]]),
+            LATEST_VERSION,
+        )
+        .unwrap();
+        let (input, output_str) = (path_str(&input), path_str(&output));
+
+        trim(input, output_str, "2", Some("3")).unwrap();
+        assert_eq!(seeds(&read(output_str).unwrap()), [1, 2]);
+        trim(input, output_str, "3", None).unwrap();
+        assert_eq!(seeds(&read(output_str).unwrap()), [2, 3]);
+
+        assert!(trim(input, output_str, "3", Some("2")).is_err());
+        assert!(trim(input, output_str, "5", None).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn splits_rounds() {
+        let dir = temp_dir("split");
+        let input = dir.join("input.rep");
+        write(
+            &input,
+            &replay_file(vec![vec![(0, 0)], vec![(1, 1)]]),
+            LATEST_VERSION,
+        )
+        .unwrap();
+
+        let prefix = dir.join("round");
+        split(path_str(&input), path_str(&prefix)).unwrap();
+        for (i, seed) in [(1, 0), (2, 1)] {
+            let read = read(path_str(&dir.join(format!("round-{}.rep", i)))).unwrap();
+            assert_eq!(read.p1_name, "reimu");
+            assert_eq!(seeds(&read), [seed]);
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rejects_malformed_files() {
+        let dir = temp_dir("malformed");
+        let path = dir.join("file.rep");
+        assert!(read(path_str(&path)).is_err());
+
+        let mut v1 = Vec::new();
+        replay_file(vec![vec![(0, 0), (1, 2)]])
+            .write_v1_to(&mut v1)
+            .unwrap();
+        let mut v2 = Vec::new();
+        replay_file(vec![vec![(0, 0), (1, 2)]])
+            .write_to(&mut v2)
+            .unwrap();
+        let mut corrupted = v2.clone();
+        let index = corrupted.len() - 5;
+        corrupted[index] ^= 0x01;
+        let malformed = [
+            // 入力の途中で切れている v1
+            v1[..v1.len() - 1].to_vec(),
+            // ヘッダーの途中で切れている v1
+            v1[..5].to_vec(),
+            v2[..v2.len() - 1].to_vec(),
+            corrupted,
+            vec![],
+        ];
+        for data in malformed {
+            fs::write(&path, &data).unwrap();
+            assert!(read(path_str(&path)).is_err(), "{:?}", data);
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,14 +1,21 @@
 mod dynamodb;
 mod file;
+#[cfg(test)]
+mod memory;
 
 use async_trait::async_trait;
 use derive_new::new;
 pub use dynamodb::DynamoDB;
 pub use file::File;
+#[cfg(test)]
+pub use memory::Memory;
 
 use anyhow::Result;
 use getset::{Getters, Setters};
-use junowen_lib::connection::signaling::CompressedSdp;
+use junowen_lib::{
+    connection::signaling::CompressedSdp,
+    signaling_server::ladder::{HistoryEntry, MatchResult, PlayerRating},
+};
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Debug, Deserialize, Getters, Serialize, new)]
@@ -145,4 +152,152 @@ pub trait ReservedRoomTables: Send + Sync + 'static {
     ) -> Result<Option<ReservedRoomSpectatorAnswer>>;
 }
 
-pub trait Database: SharedRoomTables + ReservedRoomTables {}
+/** 招待先ごとの受信箱。1 人につき 1 件まで */
+#[derive(Clone, Debug, Deserialize, Getters, Serialize, new)]
+pub struct Invitation {
+    /// primary, 招待先の公開鍵
+    #[get = "pub"]
+    name: String,
+    /// 招待した側の公開鍵
+    #[get = "pub"]
+    from: String,
+    #[get = "pub"]
+    from_name: String,
+    #[get = "pub"]
+    room_name: String,
+    ttl_sec: u64,
+}
+
+impl Invitation {
+    pub fn is_expired(&self, now_sec: u64) -> bool {
+        now_sec > self.ttl_sec
+    }
+}
+
+#[async_trait]
+pub trait InvitationTables: Send + Sync + 'static {
+    async fn put_invitation(&self, invitation: Invitation) -> Result<(), PutError>;
+    async fn find_invitation(&self, name: String) -> Result<Option<Invitation>>;
+    async fn remove_invitation(&self, name: String) -> Result<Option<Invitation>>;
+}
+
+/** 片方のプレイヤーからの、もう片方の報告を待っている対戦結果 */
+#[derive(Clone, Debug, Deserialize, Getters, Serialize, new)]
+pub struct MatchReport {
+    /// primary, match_id
+    #[get = "pub"]
+    name: String,
+    /// 報告した側の公開鍵
+    #[get = "pub"]
+    reporter: String,
+    #[get = "pub"]
+    result: MatchResult,
+    ttl_sec: u64,
+}
+
+impl MatchReport {
+    pub fn is_expired(&self, now_sec: u64) -> bool {
+        now_sec > self.ttl_sec
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Getters, Serialize, new)]
+pub struct Player {
+    /// primary, 公開鍵
+    #[get = "pub"]
+    name: String,
+    #[get = "pub"]
+    player_name: String,
+    #[get = "pub"]
+    rating: i32,
+    wins: u32,
+    losses: u32,
+    /// 新しい順
+    #[get = "pub"]
+    history: Vec<HistoryEntry>,
+    /// 保存するたびに増やし、読み込んでから他で書き換えられていないか確かめる
+    #[new(default)]
+    #[serde(default)]
+    version: u64,
+}
+
+impl Player {
+    pub fn to_player_rating(&self) -> PlayerRating {
+        PlayerRating::new(
+            self.name.clone(),
+            self.player_name.clone(),
+            self.rating,
+            self.wins,
+            self.losses,
+        )
+    }
+
+    pub fn push_history(&mut self, player_name: String, entry: HistoryEntry, max_len: usize) {
+        self.player_name = player_name;
+        self.rating = entry.rating_after;
+        if entry.wins > entry.opponent_wins {
+            self.wins += 1;
+        } else {
+            self.losses += 1;
+        }
+        self.history.insert(0, entry);
+        self.history.truncate(max_len);
+    }
+}
+
+/** レーティング上位のプレイヤー。全件走査を避けるため、対戦結果を記録するたびに更新する */
+#[derive(Clone, Debug, Deserialize, Getters, Serialize, new)]
+pub struct Leaderboard {
+    /// primary
+    #[get = "pub"]
+    name: String,
+    /// レーティングの高い順
+    #[get = "pub"]
+    players: Vec<PlayerRating>,
+    /// Player の version と同じ
+    #[get = "pub"]
+    #[new(default)]
+    #[serde(default)]
+    version: u64,
+}
+
+impl Leaderboard {
+    pub fn into_players(self) -> Vec<PlayerRating> {
+        self.players
+    }
+
+    /// version は読み込んだときのまま残す
+    pub fn set_players(&mut self, players: Vec<PlayerRating>) {
+        self.players = players;
+    }
+}
+
+#[async_trait]
+pub trait LadderTables: Send + Sync + 'static {
+    async fn put_match_report(&self, report: MatchReport) -> Result<(), PutError>;
+    async fn find_match_report(&self, name: String) -> Result<Option<MatchReport>>;
+    async fn remove_match_report(&self, name: String) -> Result<Option<MatchReport>>;
+
+    async fn find_player(&self, name: String) -> Result<Option<Player>>;
+    async fn list_players(&self) -> Result<Vec<Player>>;
+
+    async fn find_leaderboard(&self, name: String) -> Result<Option<Leaderboard>>;
+    /// 読み込んでから version が変わっていれば Conflict
+    async fn save_leaderboard(&self, leaderboard: Leaderboard) -> Result<(), PutError>;
+
+    /**
+     * 対戦した両者と上位のプレイヤーをまとめて保存する
+     *
+     * どれか一つでも読み込んでから version が変わっていれば、何も保存せずに Conflict
+     */
+    async fn save_match_result(
+        &self,
+        players: [Player; 2],
+        leaderboard: Leaderboard,
+    ) -> Result<(), PutError>;
+}
+
+pub trait Database:
+    SharedRoomTables + ReservedRoomTables + InvitationTables + LadderTables
+{
+}
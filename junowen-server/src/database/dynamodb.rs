@@ -1,12 +1,14 @@
+mod invitation;
+mod ladder;
 mod reserved_room;
 mod shared_room;
 
-use std::env;
+use std::{collections::HashMap, env};
 
 use anyhow::Result;
 use aws_sdk_dynamodb::{
     error::SdkError,
-    types::{AttributeValue, ReturnValue},
+    types::{AttributeValue, Put, ReturnValue, TransactWriteItem},
 };
 use serde::{Deserialize, Serialize};
 use serde_dynamo::{from_item, to_item};
@@ -20,6 +22,10 @@ pub struct DynamoDB {
     table_name_reserved_room: String,
     table_name_reserved_room_opponent_answer: String,
     table_name_reserved_room_spectator_answer: String,
+    table_name_invitation: String,
+    table_name_match_report: String,
+    table_name_player: String,
+    table_name_leaderboard: String,
 }
 
 impl DynamoDB {
@@ -38,6 +44,10 @@ impl DynamoDB {
                 "{}.ReservedRoomSpectatorAnswer",
                 env::var("ENV").unwrap()
             ),
+            table_name_invitation: format!("{}.Invitation", env::var("ENV").unwrap()),
+            table_name_match_report: format!("{}.MatchReport", env::var("ENV").unwrap()),
+            table_name_player: format!("{}.Player", env::var("ENV").unwrap()),
+            table_name_leaderboard: format!("{}.Leaderboard", env::var("ENV").unwrap()),
         }
     }
 
@@ -63,6 +73,53 @@ impl DynamoDB {
         Ok(())
     }
 
+    /// 全てのアイテムの version が読み込んだときのままなら、まとめて上書きする
+    async fn replace_items_if_version(&self, puts: Vec<Put>) -> Result<(), PutError> {
+        let items = puts
+            .into_iter()
+            .map(|put| TransactWriteItem::builder().put(put).build())
+            .collect();
+        let result = self
+            .client
+            .transact_write_items()
+            .set_transact_items(Some(items))
+            .send()
+            .await;
+        if let Err(err) = result {
+            if let SdkError::ServiceError(service_error) = &err {
+                if service_error.err().is_transaction_canceled_exception() {
+                    return Err(PutError::Conflict);
+                }
+            }
+            return Err(PutError::Unknown(err.into()));
+        }
+        Ok(())
+    }
+
+    async fn scan_items<T>(&self, table_name: &str) -> Result<Vec<T>>
+    where
+        T: for<'a> Deserialize<'a>,
+    {
+        let mut items = Vec::new();
+        let mut exclusive_start_key = None;
+        loop {
+            let output = self
+                .client
+                .scan()
+                .table_name(table_name)
+                .set_exclusive_start_key(exclusive_start_key)
+                .send()
+                .await?;
+            for item in output.items().unwrap_or_default() {
+                items.push(from_item(item.to_owned())?);
+            }
+            let Some(last_evaluated_key) = output.last_evaluated_key() else {
+                return Ok(items);
+            };
+            exclusive_start_key = Some(last_evaluated_key.to_owned());
+        }
+    }
+
     async fn find_item_by_name<'a, T>(&self, table_name: &str, name: String) -> Result<Option<T>>
     where
         T: Deserialize<'a>,
@@ -137,4 +194,31 @@ impl DynamoDB {
     }
 }
 
+/**
+ * version が読み込んだときのままの場合だけ上書きする Put を作る
+ *
+ * item の version は読み込んだときの値に 1 を足しておく。0 は未作成か version が無かった頃のアイテム
+ */
+fn versioned_put(table_name: &str, item: impl Serialize, version: u64) -> Result<Put, PutError> {
+    let item = to_item(item).map_err(|err| PutError::Unknown(err.into()))?;
+    let (condition_expression, values) = if version == 0 {
+        ("attribute_not_exists(#version)", None)
+    } else {
+        (
+            "#version = :version",
+            Some(HashMap::from([(
+                ":version".to_owned(),
+                AttributeValue::N(version.to_string()),
+            )])),
+        )
+    };
+    Ok(Put::builder()
+        .table_name(table_name)
+        .set_item(Some(item))
+        .condition_expression(condition_expression)
+        .expression_attribute_names("#version", "version")
+        .set_expression_attribute_values(values)
+        .build())
+}
+
 impl Database for DynamoDB {}
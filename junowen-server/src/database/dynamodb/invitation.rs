@@ -0,0 +1,23 @@
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::database::{self, Invitation, PutError};
+
+use super::DynamoDB;
+
+#[async_trait]
+impl database::InvitationTables for DynamoDB {
+    async fn put_invitation(&self, invitation: Invitation) -> Result<(), PutError> {
+        self.put_item(&self.table_name_invitation, invitation).await
+    }
+
+    async fn find_invitation(&self, name: String) -> Result<Option<Invitation>> {
+        self.find_item_by_name(&self.table_name_invitation, name)
+            .await
+    }
+
+    async fn remove_invitation(&self, name: String) -> Result<Option<Invitation>> {
+        self.remove_item_and_get_old(&self.table_name_invitation, name)
+            .await
+    }
+}
@@ -0,0 +1,68 @@
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::database::{self, Leaderboard, MatchReport, Player, PutError};
+
+use super::{versioned_put, DynamoDB};
+
+#[async_trait]
+impl database::LadderTables for DynamoDB {
+    async fn put_match_report(&self, report: MatchReport) -> Result<(), PutError> {
+        self.put_item(&self.table_name_match_report, report).await
+    }
+
+    async fn find_match_report(&self, name: String) -> Result<Option<MatchReport>> {
+        self.find_item_by_name(&self.table_name_match_report, name)
+            .await
+    }
+
+    async fn remove_match_report(&self, name: String) -> Result<Option<MatchReport>> {
+        self.remove_item_and_get_old(&self.table_name_match_report, name)
+            .await
+    }
+
+    async fn find_player(&self, name: String) -> Result<Option<Player>> {
+        self.find_item_by_name(&self.table_name_player, name).await
+    }
+
+    async fn list_players(&self) -> Result<Vec<Player>> {
+        self.scan_items(&self.table_name_player).await
+    }
+
+    async fn find_leaderboard(&self, name: String) -> Result<Option<Leaderboard>> {
+        self.find_item_by_name(&self.table_name_leaderboard, name)
+            .await
+    }
+
+    async fn save_leaderboard(&self, mut leaderboard: Leaderboard) -> Result<(), PutError> {
+        let version = leaderboard.version;
+        leaderboard.version += 1;
+        self.replace_items_if_version(vec![versioned_put(
+            &self.table_name_leaderboard,
+            leaderboard,
+            version,
+        )?])
+        .await
+    }
+
+    async fn save_match_result(
+        &self,
+        players: [Player; 2],
+        mut leaderboard: Leaderboard,
+    ) -> Result<(), PutError> {
+        let mut puts = Vec::new();
+        for mut player in players {
+            let version = player.version;
+            player.version += 1;
+            puts.push(versioned_put(&self.table_name_player, player, version)?);
+        }
+        let version = leaderboard.version;
+        leaderboard.version += 1;
+        puts.push(versioned_put(
+            &self.table_name_leaderboard,
+            leaderboard,
+            version,
+        )?);
+        self.replace_items_if_version(puts).await
+    }
+}
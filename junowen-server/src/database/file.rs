@@ -5,9 +5,9 @@ use serde_json::Value;
 use tokio::fs;
 
 use super::{
-    Answer, Database, PutError, ReservedRoom, ReservedRoomOpponentAnswer,
-    ReservedRoomSpectatorAnswer, ReservedRoomTables, SharedRoom, SharedRoomOpponentAnswer,
-    SharedRoomTables,
+    Answer, Database, Invitation, InvitationTables, LadderTables, Leaderboard, MatchReport, Player,
+    PutError, ReservedRoom, ReservedRoomOpponentAnswer, ReservedRoomSpectatorAnswer,
+    ReservedRoomTables, SharedRoom, SharedRoomOpponentAnswer, SharedRoomTables,
 };
 
 pub struct File;
@@ -197,4 +197,58 @@ impl ReservedRoomTables for File {
     }
 }
 
+#[async_trait]
+impl InvitationTables for File {
+    async fn put_invitation(&self, _invitation: Invitation) -> Result<(), PutError> {
+        unimplemented!()
+    }
+
+    async fn find_invitation(&self, _name: String) -> Result<Option<Invitation>> {
+        unimplemented!()
+    }
+
+    async fn remove_invitation(&self, _name: String) -> Result<Option<Invitation>> {
+        unimplemented!()
+    }
+}
+
+#[async_trait]
+impl LadderTables for File {
+    async fn put_match_report(&self, _report: MatchReport) -> Result<(), PutError> {
+        unimplemented!()
+    }
+
+    async fn find_match_report(&self, _name: String) -> Result<Option<MatchReport>> {
+        unimplemented!()
+    }
+
+    async fn remove_match_report(&self, _name: String) -> Result<Option<MatchReport>> {
+        unimplemented!()
+    }
+
+    async fn find_player(&self, _name: String) -> Result<Option<Player>> {
+        unimplemented!()
+    }
+
+    async fn list_players(&self) -> Result<Vec<Player>> {
+        unimplemented!()
+    }
+
+    async fn find_leaderboard(&self, _name: String) -> Result<Option<Leaderboard>> {
+        unimplemented!()
+    }
+
+    async fn save_leaderboard(&self, _leaderboard: Leaderboard) -> Result<(), PutError> {
+        unimplemented!()
+    }
+
+    async fn save_match_result(
+        &self,
+        _players: [Player; 2],
+        _leaderboard: Leaderboard,
+    ) -> Result<(), PutError> {
+        unimplemented!()
+    }
+}
+
 impl Database for File {}
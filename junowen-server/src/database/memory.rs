@@ -0,0 +1,273 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    sync::Mutex,
+};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use junowen_lib::connection::signaling::CompressedSdp;
+
+use super::{
+    Database, Invitation, InvitationTables, LadderTables, Leaderboard, MatchReport, Player,
+    PutError, ReservedRoom, ReservedRoomOpponentAnswer, ReservedRoomSpectatorAnswer,
+    ReservedRoomTables, SharedRoom, SharedRoomOpponentAnswer, SharedRoomTables,
+};
+
+type Table<T> = Mutex<HashMap<String, T>>;
+
+fn put_item<T>(table: &Table<T>, name: &str, item: T) -> Result<(), PutError> {
+    match table.lock().unwrap().entry(name.to_owned()) {
+        Entry::Occupied(_) => Err(PutError::Conflict),
+        Entry::Vacant(entry) => {
+            entry.insert(item);
+            Ok(())
+        }
+    }
+}
+
+fn find_item<T: Clone>(table: &Table<T>, name: &str) -> Option<T> {
+    table.lock().unwrap().get(name).cloned()
+}
+
+fn remove_item<T>(table: &Table<T>, name: &str) -> Option<T> {
+    table.lock().unwrap().remove(name)
+}
+
+/** テスト用に、DynamoDB と同じ振る舞いをメモリー上で再現する */
+#[derive(Default)]
+pub struct Memory {
+    shared_rooms: Table<SharedRoom>,
+    shared_room_opponent_answers: Table<SharedRoomOpponentAnswer>,
+    reserved_rooms: Table<ReservedRoom>,
+    reserved_room_opponent_answers: Table<ReservedRoomOpponentAnswer>,
+    reserved_room_spectator_answers: Table<ReservedRoomSpectatorAnswer>,
+    invitations: Table<Invitation>,
+    match_reports: Table<MatchReport>,
+    players: Table<Player>,
+    leaderboards: Table<Leaderboard>,
+}
+
+#[async_trait]
+impl SharedRoomTables for Memory {
+    async fn put_room(&self, room: SharedRoom) -> Result<(), PutError> {
+        put_item(&self.shared_rooms, &room.name.clone(), room)
+    }
+
+    async fn find_room(&self, name: String) -> Result<Option<SharedRoom>> {
+        Ok(find_item(&self.shared_rooms, &name))
+    }
+
+    async fn keep_room(&self, name: String, key: String, ttl_sec: u64) -> Result<bool> {
+        let mut rooms = self.shared_rooms.lock().unwrap();
+        let Some(room) = rooms.get_mut(&name).filter(|room| room.key == key) else {
+            return Ok(false);
+        };
+        room.ttl_sec = ttl_sec;
+        Ok(true)
+    }
+
+    async fn remove_room(&self, name: String, key: Option<String>) -> Result<bool> {
+        let mut rooms = self.shared_rooms.lock().unwrap();
+        if let Some(key) = key {
+            if rooms.get(&name).map(|room| &room.key) != Some(&key) {
+                return Ok(false);
+            }
+        }
+        rooms.remove(&name);
+        Ok(true)
+    }
+
+    async fn put_room_opponent_answer(
+        &self,
+        answer: SharedRoomOpponentAnswer,
+    ) -> Result<(), PutError> {
+        put_item(
+            &self.shared_room_opponent_answers,
+            &answer.name.clone(),
+            answer,
+        )
+    }
+
+    async fn remove_room_opponent_answer(
+        &self,
+        name: String,
+    ) -> Result<Option<SharedRoomOpponentAnswer>> {
+        Ok(remove_item(&self.shared_room_opponent_answers, &name))
+    }
+}
+
+#[async_trait]
+impl ReservedRoomTables for Memory {
+    async fn put_room(&self, room: ReservedRoom) -> Result<(), PutError> {
+        put_item(&self.reserved_rooms, &room.name.clone(), room)
+    }
+
+    async fn find_room(&self, name: String) -> Result<Option<ReservedRoom>> {
+        Ok(find_item(&self.reserved_rooms, &name))
+    }
+
+    async fn keep_room(
+        &self,
+        name: String,
+        key: String,
+        spectator_offer_sdp: Option<CompressedSdp>,
+        ttl_sec: u64,
+    ) -> Result<Option<ReservedRoom>> {
+        let mut rooms = self.reserved_rooms.lock().unwrap();
+        let Some(room) = rooms.get_mut(&name).filter(|room| room.key == key) else {
+            return Ok(None);
+        };
+        room.ttl_sec = ttl_sec;
+        if spectator_offer_sdp.is_some() {
+            room.spectator_offer_sdp = spectator_offer_sdp;
+        }
+        Ok(Some(room.clone()))
+    }
+
+    async fn remove_opponent_offer_sdp_in_room(&self, name: String) -> Result<bool> {
+        let mut rooms = self.reserved_rooms.lock().unwrap();
+        let Some(room) = rooms.get_mut(&name) else {
+            return Ok(false);
+        };
+        room.opponent_offer_sdp = None;
+        Ok(true)
+    }
+
+    async fn remove_spectator_offer_sdp_in_room(&self, name: String) -> Result<bool> {
+        let mut rooms = self.reserved_rooms.lock().unwrap();
+        let Some(room) = rooms.get_mut(&name) else {
+            return Ok(false);
+        };
+        room.spectator_offer_sdp = None;
+        Ok(true)
+    }
+
+    async fn remove_room(&self, name: String, key: Option<String>) -> Result<bool> {
+        let mut rooms = self.reserved_rooms.lock().unwrap();
+        if let Some(key) = key {
+            if rooms.get(&name).map(|room| &room.key) != Some(&key) {
+                return Ok(false);
+            }
+        }
+        rooms.remove(&name);
+        Ok(true)
+    }
+
+    async fn put_room_opponent_answer(
+        &self,
+        answer: ReservedRoomOpponentAnswer,
+    ) -> Result<(), PutError> {
+        put_item(
+            &self.reserved_room_opponent_answers,
+            &answer.0.name.clone(),
+            answer,
+        )
+    }
+
+    async fn remove_room_opponent_answer(
+        &self,
+        name: String,
+    ) -> Result<Option<ReservedRoomOpponentAnswer>> {
+        Ok(remove_item(&self.reserved_room_opponent_answers, &name))
+    }
+
+    async fn put_room_spectator_answer(
+        &self,
+        answer: ReservedRoomSpectatorAnswer,
+    ) -> Result<(), PutError> {
+        put_item(
+            &self.reserved_room_spectator_answers,
+            &answer.0.name.clone(),
+            answer,
+        )
+    }
+
+    async fn remove_room_spectator_answer(
+        &self,
+        name: String,
+    ) -> Result<Option<ReservedRoomSpectatorAnswer>> {
+        Ok(remove_item(&self.reserved_room_spectator_answers, &name))
+    }
+}
+
+#[async_trait]
+impl InvitationTables for Memory {
+    async fn put_invitation(&self, invitation: Invitation) -> Result<(), PutError> {
+        put_item(&self.invitations, &invitation.name.clone(), invitation)
+    }
+
+    async fn find_invitation(&self, name: String) -> Result<Option<Invitation>> {
+        Ok(find_item(&self.invitations, &name))
+    }
+
+    async fn remove_invitation(&self, name: String) -> Result<Option<Invitation>> {
+        Ok(remove_item(&self.invitations, &name))
+    }
+}
+
+#[async_trait]
+impl LadderTables for Memory {
+    async fn put_match_report(&self, report: MatchReport) -> Result<(), PutError> {
+        put_item(&self.match_reports, &report.name.clone(), report)
+    }
+
+    async fn find_match_report(&self, name: String) -> Result<Option<MatchReport>> {
+        Ok(find_item(&self.match_reports, &name))
+    }
+
+    async fn remove_match_report(&self, name: String) -> Result<Option<MatchReport>> {
+        Ok(remove_item(&self.match_reports, &name))
+    }
+
+    async fn find_player(&self, name: String) -> Result<Option<Player>> {
+        Ok(find_item(&self.players, &name))
+    }
+
+    async fn list_players(&self) -> Result<Vec<Player>> {
+        Ok(self.players.lock().unwrap().values().cloned().collect())
+    }
+
+    async fn find_leaderboard(&self, name: String) -> Result<Option<Leaderboard>> {
+        Ok(find_item(&self.leaderboards, &name))
+    }
+
+    async fn save_leaderboard(&self, mut leaderboard: Leaderboard) -> Result<(), PutError> {
+        let mut leaderboards = self.leaderboards.lock().unwrap();
+        let stored = leaderboards.get(&leaderboard.name).map(|x| x.version);
+        if stored.unwrap_or_default() != leaderboard.version {
+            return Err(PutError::Conflict);
+        }
+        leaderboard.version += 1;
+        leaderboards.insert(leaderboard.name.clone(), leaderboard);
+        Ok(())
+    }
+
+    async fn save_match_result(
+        &self,
+        players: [Player; 2],
+        mut leaderboard: Leaderboard,
+    ) -> Result<(), PutError> {
+        let mut stored_players = self.players.lock().unwrap();
+        let mut leaderboards = self.leaderboards.lock().unwrap();
+        let conflicted = players.iter().any(|player| {
+            let stored = stored_players.get(&player.name).map(|x| x.version);
+            stored.unwrap_or_default() != player.version
+        }) || leaderboards
+            .get(&leaderboard.name)
+            .map(|x| x.version)
+            .unwrap_or_default()
+            != leaderboard.version;
+        if conflicted {
+            return Err(PutError::Conflict);
+        }
+        for mut player in players {
+            player.version += 1;
+            stored_players.insert(player.name.clone(), player);
+        }
+        leaderboard.version += 1;
+        leaderboards.insert(leaderboard.name.clone(), leaderboard);
+        Ok(())
+    }
+}
+
+impl Database for Memory {}
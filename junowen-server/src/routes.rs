@@ -1,4 +1,6 @@
 mod custom;
+mod invitation;
+mod ladder;
 mod reserved_room;
 mod room_utils;
 
@@ -90,5 +92,123 @@ pub async fn routes(req: &Request, db: &impl Database) -> Result<impl IntoRespon
             .instrument(info_span!("req", ip_hash = base_yoteichi_mod(ip_hash(req))))
             .await;
     }
+    if let Some(relative_uri) = req.uri().path().strip_prefix("/invitation/") {
+        return invitation::route(relative_uri, req, db)
+            .instrument(info_span!("req", ip_hash = base_yoteichi_mod(ip_hash(req))))
+            .await;
+    }
+    if let Some(relative_uri) = req.uri().path().strip_prefix("/ladder/") {
+        return ladder::route(relative_uri, req, db)
+            .instrument(info_span!("req", ip_hash = base_yoteichi_mod(ip_hash(req))))
+            .await;
+    }
     Ok(to_response(StatusCode::NOT_FOUND, Body::Empty))
 }
+
+#[cfg(test)]
+mod tests {
+    use junowen_lib::{
+        connection::signaling::CompressedSdp,
+        identity::{to_hex, Identity},
+        signaling_server::{
+            invitation::{
+                PostInvitationReceiveRequestBody, PostInvitationReceiveResponseOkBody,
+                PutInvitationRequestBody, PutInvitationResponseCreatedBody,
+            },
+            reserved_room::{
+                GetReservedRoomResponseOkBody, PostReservedRoomKeepRequestBody,
+                PostReservedRoomKeepResponseOkBody,
+            },
+            room::{
+                PostRoomJoinRequestBody, PutRoomRequestBody, PutRoomResponseWaitingBody,
+                MAX_ROOM_NAME_LEN,
+            },
+        },
+    };
+    use lambda_http::{http::Method, Body, IntoResponse, Request};
+    use serde::{de::DeserializeOwned, Serialize};
+
+    use super::*;
+    use crate::{database::Memory, routes::room_utils::now_sec};
+
+    async fn request(
+        db: &Memory,
+        method: Method,
+        uri: &str,
+        body: &impl Serialize,
+    ) -> (StatusCode, String) {
+        let req: Request = lambda_http::http::Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(Body::Text(serde_json::to_string(body).unwrap()))
+            .unwrap();
+        let res = routes(&req, db).await.unwrap().into_response().await;
+        let text = match res.body() {
+            Body::Text(text) => text.clone(),
+            _ => String::new(),
+        };
+        (res.status(), text)
+    }
+
+    fn parse<T: DeserializeOwned>(text: &str) -> T {
+        serde_json::from_str(text).unwrap()
+    }
+
+    fn sdp(text: &str) -> CompressedSdp {
+        parse(&format!("\"{}\"", text))
+    }
+
+    #[tokio::test]
+    async fn join_invitation_room() {
+        let db = Memory::default();
+        let inviter = Identity::generate();
+        let invitee = Identity::generate();
+        let invitee_hex = to_hex(&invitee.public_key());
+
+        let body = PutInvitationRequestBody::new(
+            &inviter,
+            &invitee.public_key(),
+            "reimu".to_owned(),
+            now_sec(),
+        );
+        let uri = format!("/invitation/{}", invitee_hex);
+        let (status, text) = request(&db, Method::PUT, &uri, &body).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let room_name = parse::<PutInvitationResponseCreatedBody>(&text).into_room_name();
+        // 利用者が入力する部屋名の上限より長くても使える
+        assert!(room_name.chars().count() > MAX_ROOM_NAME_LEN);
+
+        let body = PostInvitationReceiveRequestBody::new(&invitee, now_sec());
+        let uri = format!("/invitation/{}/receive", invitee_hex);
+        let (status, text) = request(&db, Method::POST, &uri, &body).await;
+        assert_eq!(status, StatusCode::OK);
+        let invitation = parse::<PostInvitationReceiveResponseOkBody>(&text);
+        assert_eq!(invitation.room_name(), &room_name);
+        assert_eq!(invitation.from_name(), "reimu");
+
+        let room_uri = format!("/reserved-room/{}", room_name);
+        let body = PutRoomRequestBody::new(sdp("offer"));
+        let (status, text) = request(&db, Method::PUT, &room_uri, &body).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let key = parse::<PutRoomResponseWaitingBody>(&text).into_key();
+
+        let (status, text) = request(&db, Method::GET, &room_uri, &()).await;
+        assert_eq!(status, StatusCode::OK);
+        let room = parse::<GetReservedRoomResponseOkBody>(&text);
+        assert_eq!(room.opponent_offer().unwrap().clone().into_inner(), "offer");
+
+        let body = PostRoomJoinRequestBody::new(sdp("answer"));
+        let uri = format!("{}/join", room_uri);
+        let (status, _) = request(&db, Method::POST, &uri, &body).await;
+        assert_eq!(status, StatusCode::CREATED);
+
+        let body = PostReservedRoomKeepRequestBody::new(key, None);
+        let uri = format!("{}/keep", room_uri);
+        let (status, text) = request(&db, Method::POST, &uri, &body).await;
+        assert_eq!(status, StatusCode::OK);
+        let PostReservedRoomKeepResponseOkBody::OpponentAnswer(answer) = parse(&text) else {
+            panic!("unexpected response: {}", text);
+        };
+        assert_eq!(answer.into_opponent_answer().into_inner(), "answer");
+    }
+}
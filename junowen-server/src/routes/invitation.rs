@@ -0,0 +1,140 @@
+use anyhow::{bail, Result};
+use junowen_lib::{
+    identity::{from_hex, to_hex, PublicKey},
+    signaling_server::invitation::{
+        PostInvitationReceiveRequestBody, PostInvitationReceiveResponse,
+        PostInvitationReceiveResponseOkBody, PutInvitationRequestBody, PutInvitationResponse,
+        PutInvitationResponseCreatedBody,
+    },
+};
+use lambda_http::{
+    http::{Method, StatusCode},
+    Body, Request, Response,
+};
+use regex::Regex;
+use tracing::{debug, info};
+use uuid::Uuid;
+
+use crate::database::{Invitation, InvitationTables, PutError};
+
+use super::{
+    room_utils::{from_post_room_keep_response, now_sec, RETRY_AFTER_INTERVAL_SEC},
+    to_response, try_parse,
+};
+
+/// 招待された側がポーリングで気付くまで待つ時間
+const INVITATION_TTL_DURATION_SEC: u64 = 60;
+
+async fn put_invitation(
+    db: &impl InvitationTables,
+    to: &PublicKey,
+    body: PutInvitationRequestBody,
+) -> Result<PutInvitationResponse> {
+    let now_sec = now_sec();
+    let from = match body.verify(to, now_sec) {
+        Ok(from) => from,
+        Err(err) => {
+            debug!("{:?}", err);
+            return Ok(PutInvitationResponse::BadRequest);
+        }
+    };
+    if &from == to {
+        return Ok(PutInvitationResponse::BadRequest);
+    }
+    let name = to_hex(to);
+    if let Some(invitation) = db.find_invitation(name.clone()).await? {
+        if !invitation.is_expired(now_sec) {
+            return Ok(PutInvitationResponse::Conflict);
+        }
+        db.remove_invitation(name.clone()).await?;
+    }
+    let room_name = Uuid::new_v4().to_string();
+    let invitation = Invitation::new(
+        name.clone(),
+        to_hex(&from),
+        body.from_name().clone(),
+        room_name.clone(),
+        now_sec + INVITATION_TTL_DURATION_SEC,
+    );
+    match db.put_invitation(invitation).await {
+        Ok(()) => {}
+        Err(PutError::Conflict) => return Ok(PutInvitationResponse::Conflict),
+        Err(PutError::Unknown(err)) => bail!("{:?}", err),
+    }
+    info!("[Invitation] Sent: {} -> {}", to_hex(&from), name);
+    let body = PutInvitationResponseCreatedBody::new(room_name);
+    Ok(PutInvitationResponse::Created(body))
+}
+
+async fn post_invitation_receive(
+    db: &impl InvitationTables,
+    to: &PublicKey,
+    body: PostInvitationReceiveRequestBody,
+) -> Result<PostInvitationReceiveResponse> {
+    let now_sec = now_sec();
+    if let Err(err) = body.verify(to, now_sec) {
+        debug!("{:?}", err);
+        return Ok(PostInvitationReceiveResponse::BadRequest);
+    }
+    let name = to_hex(to);
+    let Some(invitation) = db.remove_invitation(name.clone()).await? else {
+        let retry_after = RETRY_AFTER_INTERVAL_SEC;
+        return Ok(PostInvitationReceiveResponse::NoContent { retry_after });
+    };
+    if invitation.is_expired(now_sec) {
+        let retry_after = RETRY_AFTER_INTERVAL_SEC;
+        return Ok(PostInvitationReceiveResponse::NoContent { retry_after });
+    }
+    info!("[Invitation] Received: {}", name);
+    let body = PostInvitationReceiveResponseOkBody::new(
+        invitation.from().clone(),
+        invitation.from_name().clone(),
+        invitation.room_name().clone(),
+    );
+    Ok(PostInvitationReceiveResponse::Ok(body))
+}
+
+pub async fn route(
+    relative_uri: &str,
+    req: &Request,
+    db: &impl InvitationTables,
+) -> Result<Response<Body>> {
+    let regex = Regex::new(r"^([0-9a-f]{64})$").unwrap();
+    if let Some(c) = regex.captures(relative_uri) {
+        let to = from_hex(&c[1])?;
+        return Ok(match *req.method() {
+            Method::PUT => match try_parse(req.body()) {
+                Err(err) => {
+                    debug!("{:?}", err);
+                    to_response(StatusCode::BAD_REQUEST, Body::Empty)
+                }
+                Ok(body) => {
+                    let res = put_invitation(db, &to, body).await?;
+                    to_response(
+                        res.status_code(),
+                        res.to_body().map(Body::Text).unwrap_or_else(|| Body::Empty),
+                    )
+                }
+            },
+            _ => to_response(StatusCode::METHOD_NOT_ALLOWED, Body::Empty),
+        });
+    }
+    let regex = Regex::new(r"^([0-9a-f]{64})/receive$").unwrap();
+    if let Some(c) = regex.captures(relative_uri) {
+        let to = from_hex(&c[1])?;
+        return Ok(match *req.method() {
+            Method::POST => match try_parse(req.body()) {
+                Err(err) => {
+                    debug!("{:?}", err);
+                    to_response(StatusCode::BAD_REQUEST, Body::Empty)
+                }
+                Ok(body) => {
+                    let res = post_invitation_receive(db, &to, body).await?;
+                    from_post_room_keep_response(res)
+                }
+            },
+            _ => to_response(StatusCode::METHOD_NOT_ALLOWED, Body::Empty),
+        });
+    }
+    Ok(to_response(StatusCode::NOT_FOUND, Body::Empty))
+}
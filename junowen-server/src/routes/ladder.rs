@@ -0,0 +1,397 @@
+use anyhow::{bail, Result};
+use junowen_lib::{
+    identity::to_hex,
+    signaling_server::ladder::{
+        GetLeaderboardResponseOkBody, GetPlayerHistoryResponseOkBody, HistoryEntry, MatchResult,
+        PlayerRating, PostMatchResultRequestBody, PostMatchResultResponse,
+        PostMatchResultResponseRecordedBody,
+    },
+};
+use lambda_http::{
+    http::{Method, StatusCode},
+    Body, Request, Response,
+};
+use regex::Regex;
+use tracing::{debug, info};
+
+use crate::database::{LadderTables, Leaderboard, MatchReport, Player, PutError};
+
+use super::{room_utils::now_sec, to_response, try_parse};
+
+/// もう片方の報告を待つ時間
+const MATCH_REPORT_TTL_DURATION_SEC: u64 = 10 * 60;
+const INITIAL_RATING: i32 = 1500;
+const K_FACTOR: f64 = 32.0;
+const MAX_HISTORY_LEN: usize = 100;
+const LEADERBOARD_LEN: usize = 100;
+/// 上位のプレイヤーが順位を下げても圏外のプレイヤーと入れ替われるよう、表示するより多く保持する
+const LEADERBOARD_CACHE_LEN: usize = LEADERBOARD_LEN * 2;
+const LEADERBOARD_NAME: &str = "top";
+/// 他の対戦結果と同時に保存しようとして失敗したときに、やり直す回数
+const MAX_SAVE_ATTEMPTS: usize = 5;
+
+fn elo(rating: i32, opponent_rating: i32, score: f64) -> i32 {
+    let expected = 1.0 / (1.0 + 10f64.powf((opponent_rating - rating) as f64 / 400.0));
+    rating + (K_FACTOR * (score - expected)).round() as i32
+}
+
+async fn find_or_new_player(db: &impl LadderTables, public_key: &str) -> Result<Player> {
+    Ok(db
+        .find_player(public_key.to_owned())
+        .await?
+        .unwrap_or_else(|| {
+            Player::new(
+                public_key.to_owned(),
+                String::new(),
+                INITIAL_RATING,
+                0,
+                0,
+                vec![],
+            )
+        }))
+}
+
+fn recorded(p1: &Player, p2: &Player) -> PostMatchResultResponse {
+    let body = PostMatchResultResponseRecordedBody::new(*p1.rating(), *p2.rating());
+    PostMatchResultResponse::Recorded(body)
+}
+
+fn is_recorded(player: &Player, match_id: &str) -> bool {
+    player
+        .history()
+        .iter()
+        .any(|entry| entry.match_id == match_id)
+}
+
+/// 記録済みなら Some
+async fn find_recorded(
+    db: &impl LadderTables,
+    result: &MatchResult,
+) -> Result<Option<PostMatchResultResponse>> {
+    let Some(p1) = db.find_player(result.p1.clone()).await? else {
+        return Ok(None);
+    };
+    if !is_recorded(&p1, &result.match_id) {
+        return Ok(None);
+    }
+    let p2 = find_or_new_player(db, &result.p2).await?;
+    Ok(Some(recorded(&p1, &p2)))
+}
+
+/// 他の対戦結果と同時に保存しようとした場合は、読み込み直してやり直す
+async fn record(db: &impl LadderTables, result: &MatchResult) -> Result<PostMatchResultResponse> {
+    for _ in 0..MAX_SAVE_ATTEMPTS {
+        let mut p1 = find_or_new_player(db, &result.p1).await?;
+        let mut p2 = find_or_new_player(db, &result.p2).await?;
+        if is_recorded(&p1, &result.match_id) {
+            return Ok(recorded(&p1, &p2));
+        }
+        let p1_score = if result.p1_wins > result.p2_wins {
+            1.0
+        } else {
+            0.0
+        };
+        let p1_rating = elo(*p1.rating(), *p2.rating(), p1_score);
+        let p2_rating = elo(*p2.rating(), *p1.rating(), 1.0 - p1_score);
+        let recorded_at = now_sec();
+        p1.push_history(
+            result.p1_name.clone(),
+            HistoryEntry {
+                match_id: result.match_id.clone(),
+                recorded_at,
+                opponent: result.p2.clone(),
+                opponent_name: result.p2_name.clone(),
+                character: result.p1_character,
+                opponent_character: result.p2_character,
+                wins: result.p1_wins,
+                opponent_wins: result.p2_wins,
+                rating_after: p1_rating,
+            },
+            MAX_HISTORY_LEN,
+        );
+        p2.push_history(
+            result.p2_name.clone(),
+            HistoryEntry {
+                match_id: result.match_id.clone(),
+                recorded_at,
+                opponent: result.p1.clone(),
+                opponent_name: result.p1_name.clone(),
+                character: result.p2_character,
+                opponent_character: result.p1_character,
+                wins: result.p2_wins,
+                opponent_wins: result.p1_wins,
+                rating_after: p2_rating,
+            },
+            MAX_HISTORY_LEN,
+        );
+        let res = recorded(&p1, &p2);
+        let leaderboard = updated_leaderboard(db, &[&p1, &p2]).await?;
+        match db.save_match_result([p1, p2], leaderboard).await {
+            Ok(()) => {
+                info!("[Ladder] Recorded: {}", result.match_id);
+                return Ok(res);
+            }
+            Err(PutError::Conflict) => continue,
+            Err(PutError::Unknown(err)) => bail!("{:?}", err),
+        }
+    }
+    bail!("too many conflicts: {}", result.match_id)
+}
+
+async fn post_match_result(
+    db: &impl LadderTables,
+    body: PostMatchResultRequestBody,
+) -> Result<PostMatchResultResponse> {
+    let reporter = match body.verify() {
+        Ok(reporter) => to_hex(&reporter),
+        Err(err) => {
+            debug!("{:?}", err);
+            return Ok(PostMatchResultResponse::BadRequest);
+        }
+    };
+    let result = body.into_result();
+    if result.p1_wins == result.p2_wins {
+        return Ok(PostMatchResultResponse::BadRequest);
+    }
+    if let Some(res) = find_recorded(db, &result).await? {
+        return Ok(res);
+    }
+    let now_sec = now_sec();
+    // 両者が同時に報告した場合は、先に書き込めなかった側がもう一度見直す
+    for _ in 0..2 {
+        if let Some(report) = db.find_match_report(result.match_id.clone()).await? {
+            if !report.is_expired(now_sec) && report.reporter() != &reporter {
+                if report.result() != &result {
+                    // 相手の報告は残し、正しい内容で報告し直せるようにする
+                    info!("[Ladder] Conflicted: {}", result.match_id);
+                    return Ok(PostMatchResultResponse::Conflict);
+                }
+                // 同時に記録しても、後から保存する側は記録済みであることに気付く
+                let res = record(db, &result).await?;
+                db.remove_match_report(result.match_id.clone()).await?;
+                return Ok(res);
+            }
+            db.remove_match_report(result.match_id.clone()).await?;
+        }
+        let report = MatchReport::new(
+            result.match_id.clone(),
+            reporter.clone(),
+            result.clone(),
+            now_sec + MATCH_REPORT_TTL_DURATION_SEC,
+        );
+        match db.put_match_report(report).await {
+            Ok(()) => {
+                info!("[Ladder] Accepted: {}", result.match_id);
+                return Ok(PostMatchResultResponse::Accepted);
+            }
+            Err(PutError::Conflict) => continue,
+            Err(PutError::Unknown(err)) => bail!("{:?}", err),
+        }
+    }
+    Ok(PostMatchResultResponse::Conflict)
+}
+
+fn sort_and_truncate(players: &mut Vec<PlayerRating>) {
+    players.sort_by_key(|player| std::cmp::Reverse(player.rating()));
+    players.truncate(LEADERBOARD_CACHE_LEN);
+}
+
+/** まだ上位のプレイヤーを保存していない場合だけ、全プレイヤーから作る */
+async fn find_or_build_leaderboard(db: &impl LadderTables) -> Result<Leaderboard> {
+    if let Some(leaderboard) = db.find_leaderboard(LEADERBOARD_NAME.to_owned()).await? {
+        return Ok(leaderboard);
+    }
+    let mut players: Vec<_> = db
+        .list_players()
+        .await?
+        .iter()
+        .map(|player| player.to_player_rating())
+        .collect();
+    sort_and_truncate(&mut players);
+    Ok(Leaderboard::new(LEADERBOARD_NAME.to_owned(), players))
+}
+
+async fn updated_leaderboard(db: &impl LadderTables, updated: &[&Player]) -> Result<Leaderboard> {
+    let mut leaderboard = find_or_build_leaderboard(db).await?;
+    let mut players = leaderboard.players().clone();
+    players.retain(|player| {
+        updated
+            .iter()
+            .all(|updated| player.public_key() != updated.name())
+    });
+    players.extend(updated.iter().map(|player| player.to_player_rating()));
+    sort_and_truncate(&mut players);
+    leaderboard.set_players(players);
+    Ok(leaderboard)
+}
+
+async fn get_leaderboard(db: &impl LadderTables) -> Result<GetLeaderboardResponseOkBody> {
+    let leaderboard = find_or_build_leaderboard(db).await?;
+    if *leaderboard.version() == 0 {
+        // 他で先に作られていれば、そちらを使えばいい
+        match db.save_leaderboard(leaderboard.clone()).await {
+            Ok(()) | Err(PutError::Conflict) => {}
+            Err(PutError::Unknown(err)) => bail!("{:?}", err),
+        }
+    }
+    let mut players = leaderboard.into_players();
+    players.truncate(LEADERBOARD_LEN);
+    Ok(GetLeaderboardResponseOkBody::new(players))
+}
+
+pub async fn route(
+    relative_uri: &str,
+    req: &Request,
+    db: &impl LadderTables,
+) -> Result<Response<Body>> {
+    if relative_uri == "match-result" {
+        return Ok(match *req.method() {
+            Method::POST => match try_parse(req.body()) {
+                Err(err) => {
+                    debug!("{:?}", err);
+                    to_response(StatusCode::BAD_REQUEST, Body::Empty)
+                }
+                Ok(body) => {
+                    let res = post_match_result(db, body).await?;
+                    to_response(
+                        res.status_code(),
+                        res.to_body().map(Body::Text).unwrap_or_else(|| Body::Empty),
+                    )
+                }
+            },
+            _ => to_response(StatusCode::METHOD_NOT_ALLOWED, Body::Empty),
+        });
+    }
+    if relative_uri == "leaderboard" {
+        return Ok(match *req.method() {
+            Method::GET => {
+                let body = get_leaderboard(db).await?;
+                to_response(StatusCode::OK, serde_json::to_string(&body)?)
+            }
+            _ => to_response(StatusCode::METHOD_NOT_ALLOWED, Body::Empty),
+        });
+    }
+    let regex = Regex::new(r"^players/([0-9a-f]{64})/history$").unwrap();
+    if let Some(c) = regex.captures(relative_uri) {
+        return Ok(match *req.method() {
+            Method::GET => match db.find_player(c[1].to_owned()).await? {
+                None => to_response(StatusCode::NOT_FOUND, Body::Empty),
+                Some(player) => {
+                    let body = GetPlayerHistoryResponseOkBody::new(
+                        player.to_player_rating(),
+                        player.history().clone(),
+                    );
+                    to_response(StatusCode::OK, serde_json::to_string(&body)?)
+                }
+            },
+            _ => to_response(StatusCode::METHOD_NOT_ALLOWED, Body::Empty),
+        });
+    }
+    Ok(to_response(StatusCode::NOT_FOUND, Body::Empty))
+}
+
+#[cfg(test)]
+mod tests {
+    use junowen_lib::identity::Identity;
+
+    use super::*;
+    use crate::database::Memory;
+
+    fn match_result(p1: &Identity, p2: &Identity, p1_wins: u8, p2_wins: u8) -> MatchResult {
+        MatchResult {
+            match_id: "match".to_owned(),
+            p1: to_hex(&p1.public_key()),
+            p2: to_hex(&p2.public_key()),
+            p1_name: "reimu".to_owned(),
+            p2_name: "marisa".to_owned(),
+            p1_character: 0,
+            p2_character: 1,
+            p1_wins,
+            p2_wins,
+            rules_hash: "rules".to_owned(),
+        }
+    }
+
+    async fn post(
+        db: &Memory,
+        reporter: &Identity,
+        result: &MatchResult,
+    ) -> PostMatchResultResponse {
+        let body = PostMatchResultRequestBody::new(reporter, result.clone());
+        post_match_result(db, body).await.unwrap()
+    }
+
+    fn ratings(res: PostMatchResultResponse) -> (i32, i32) {
+        let PostMatchResultResponse::Recorded(body) = res else {
+            panic!("unexpected response: {:?}", res);
+        };
+        (body.p1_rating, body.p2_rating)
+    }
+
+    #[tokio::test]
+    async fn records_once() {
+        let db = Memory::default();
+        let (p1, p2) = (Identity::generate(), Identity::generate());
+        let result = match_result(&p1, &p2, 2, 1);
+        assert!(matches!(
+            post(&db, &p1, &result).await,
+            PostMatchResultResponse::Accepted
+        ));
+        assert_eq!(ratings(post(&db, &p2, &result).await), (1516, 1484));
+        // 報告し直しても二重には記録しない
+        assert_eq!(ratings(post(&db, &p1, &result).await), (1516, 1484));
+        let player = db.find_player(result.p1.clone()).await.unwrap().unwrap();
+        assert_eq!(player.history().len(), 1);
+        let leaderboard = get_leaderboard(&db).await.unwrap();
+        assert_eq!(leaderboard.players.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn keeps_report_on_conflict() {
+        let db = Memory::default();
+        let (p1, p2) = (Identity::generate(), Identity::generate());
+        let result = match_result(&p1, &p2, 2, 1);
+        post(&db, &p1, &result).await;
+        let wrong = match_result(&p1, &p2, 1, 2);
+        assert!(matches!(
+            post(&db, &p2, &wrong).await,
+            PostMatchResultResponse::Conflict
+        ));
+        assert!(db
+            .find_match_report(result.match_id.clone())
+            .await
+            .unwrap()
+            .is_some());
+        assert_eq!(ratings(post(&db, &p2, &result).await), (1516, 1484));
+        assert!(db
+            .find_match_report(result.match_id.clone())
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn rejects_stale_writes() {
+        let db = Memory::default();
+        let (p1, p2) = (Identity::generate(), Identity::generate());
+        let result = match_result(&p1, &p2, 2, 1);
+        let stale = [
+            find_or_new_player(&db, &result.p1).await.unwrap(),
+            find_or_new_player(&db, &result.p2).await.unwrap(),
+        ];
+        let stale_leaderboard = find_or_build_leaderboard(&db).await.unwrap();
+        post(&db, &p1, &result).await;
+        post(&db, &p2, &result).await;
+
+        assert!(matches!(
+            db.save_match_result(stale, stale_leaderboard.clone()).await,
+            Err(PutError::Conflict)
+        ));
+        assert!(matches!(
+            db.save_leaderboard(stale_leaderboard).await,
+            Err(PutError::Conflict)
+        ));
+        let player = db.find_player(result.p1.clone()).await.unwrap().unwrap();
+        assert_eq!(*player.rating(), 1516);
+    }
+}
@@ -51,6 +51,7 @@ where
     to_response(status_code, body)
 }
 
+/// 招待用にサーバーが生成した部屋名は MAX_ROOM_NAME_LEN より長いので、長さは制限しない
 pub fn decode_room_name(encoded_room_name: &str) -> Result<String, FromUtf8Error> {
     urlencoding::decode(&encoded_room_name.replace('+', "%20")).map(|x| x.to_string())
 }
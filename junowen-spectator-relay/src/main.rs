@@ -0,0 +1,80 @@
+mod relay;
+mod signaling;
+
+use anyhow::Result;
+use junowen_lib::connection::signaling::SignalingCodeType;
+use tokio::{
+    io::{stdin, AsyncBufReadExt, BufReader},
+    select, spawn,
+    task::JoinSet,
+};
+use tracing::{error, info, warn};
+use tracing_subscriber::EnvFilter;
+
+use crate::{
+    relay::Relay,
+    signaling::{accept_spectator, connect_to_spectator_host, read_signaling_code},
+};
+
+fn init_tracing() {
+    const DIRECTIVES: &str = if cfg!(debug_assertions) {
+        concat!(env!("CARGO_CRATE_NAME"), "=trace")
+    } else {
+        concat!(env!("CARGO_CRATE_NAME"), "=info")
+    };
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::new(DIRECTIVES))
+        .with_writer(std::io::stderr)
+        .init();
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    init_tracing();
+
+    let mut lines = BufReader::new(stdin()).lines();
+    let (conn, data_channel) = connect_to_spectator_host(&mut lines).await?;
+    info!("connected to spectator host");
+
+    let (relay, len_tx) = Relay::new(data_channel.message_sender.clone());
+    let mut upstream = {
+        let relay = relay.clone();
+        spawn(async move {
+            relay
+                .recv_from_spectator_host(conn, data_channel, len_tx)
+                .await
+        })
+    };
+    let mut spectators = JoinSet::new();
+    let mut stdin_closed = false;
+    loop {
+        select! {
+            result = &mut upstream => {
+                if let Err(err) = result? {
+                    error!("spectator host error: {:?}", err);
+                }
+                break;
+            }
+            offer_desc = read_signaling_code(&mut lines, SignalingCodeType::SpectatorOffer),
+                if !stdin_closed =>
+            {
+                let Some(offer_desc) = offer_desc? else {
+                    stdin_closed = true;
+                    continue;
+                };
+                let relay = relay.clone();
+                spectators.spawn(async move {
+                    match accept_spectator(offer_desc).await {
+                        Ok((conn, data_channel)) => {
+                            info!("spectator connected");
+                            relay.send_to_spectator(conn, data_channel).await;
+                        }
+                        Err(err) => warn!("failed to accept spectator: {:?}", err),
+                    }
+                });
+            }
+        }
+    }
+    while spectators.join_next().await.is_some() {}
+    Ok(())
+}
@@ -0,0 +1,168 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::{bail, Result};
+use bytes::Bytes;
+use junowen_lib::{
+    connection::{DataChannel, PeerConnection},
+    session_message::SpectatorSessionMessage,
+};
+use tokio::{
+    select,
+    sync::{mpsc, watch},
+};
+use tracing::{debug, info};
+
+/// 保持するメッセージ数の上限。入力だけなら 60 fps で約 30 分
+const MAX_LOG_LEN: usize = 60 * 60 * 30;
+
+/**
+ * 途中から接続した観戦者に送り直すメッセージ
+ *
+ * 観戦者が再現できるのは InitSpectator の時点からなので、新しい InitSpectator を受け取ったら
+ * それより前のメッセージは捨てる。
+ * 上限を超えたら次の InitSpectator まで再現できなくなるので、その間は新しい観戦者に送らない
+ */
+#[derive(Default)]
+struct Log {
+    /// 捨てたメッセージの数
+    base: usize,
+    messages: Vec<Bytes>,
+    overflowed: bool,
+}
+
+impl Log {
+    /// 受信したメッセージの総数
+    fn end(&self) -> usize {
+        self.base + self.messages.len()
+    }
+
+    fn push(&mut self, data: Bytes, checkpoint: bool) {
+        if checkpoint || self.messages.len() >= MAX_LOG_LEN {
+            self.base = self.end();
+            self.messages.clear();
+            self.overflowed = !checkpoint;
+        }
+        self.messages.push(data);
+    }
+
+    /**
+     * sent 番目以降のメッセージ。None なら最初から送れる時点から
+     *
+     * 送れるものがなければ None、送る前に捨ててしまったなら Err
+     */
+    fn since(&self, sent: Option<usize>) -> Result<Option<(&[Bytes], usize)>> {
+        let from = match sent {
+            None if self.overflowed => return Ok(None),
+            None => self.base,
+            Some(sent) if sent < self.base => {
+                bail!("{} messages discarded before sending", self.base - sent)
+            }
+            Some(sent) => sent,
+        };
+        Ok(Some((&self.messages[from - self.base..], self.end())))
+    }
+}
+
+#[derive(Clone)]
+pub struct Relay {
+    log: Arc<Mutex<Log>>,
+    len_rx: watch::Receiver<usize>,
+    /// 観戦者のチャットをプレイヤーに転送する
+    upstream_sender: mpsc::Sender<Bytes>,
+}
+
+impl Relay {
+    pub fn new(upstream_sender: mpsc::Sender<Bytes>) -> (Self, watch::Sender<usize>) {
+        let (len_tx, len_rx) = watch::channel(0);
+        let relay = Self {
+            log: Default::default(),
+            len_rx,
+            upstream_sender,
+        };
+        (relay, len_tx)
+    }
+
+    pub async fn recv_from_spectator_host(
+        &self,
+        _conn: PeerConnection,
+        mut data_channel: DataChannel,
+        len_tx: watch::Sender<usize>,
+    ) -> Result<()> {
+        while let Some(data) = data_channel.recv().await {
+            let msg: SpectatorSessionMessage = rmp_serde::from_slice(&data)?;
+            let mut log = self.log.lock().unwrap();
+            let first = log.end() == 0;
+            match &msg {
+                SpectatorSessionMessage::InitSpectator(init) if first => {
+                    info!("spectating {} vs {}", init.p1_name(), init.p2_name());
+                }
+                _ if first => bail!("unexpected message: {:?}", msg),
+                SpectatorSessionMessage::InitSpectator(_) => {
+                    debug!("checkpoint: {} messages discarded", log.messages.len());
+                }
+                SpectatorSessionMessage::InitRound(round_initial) => {
+                    debug!("round started: {:?}", round_initial);
+                }
+                SpectatorSessionMessage::Inputs(..) => {}
+                SpectatorSessionMessage::Chat(chat) => {
+                    debug!("chat: {}: {}", chat.name, chat.text);
+                }
+            }
+            let checkpoint = matches!(msg, SpectatorSessionMessage::InitSpectator(_));
+            log.push(data, checkpoint);
+            len_tx.send_replace(log.end());
+        }
+        info!("spectator host closed");
+        Ok(())
+    }
+
+    /// 観戦者はチャットしか送れないので、それ以外は捨てる
+    async fn forward_chat(&self, data: Bytes) {
+        match rmp_serde::from_slice(&data) {
+            Ok(SpectatorSessionMessage::Chat(chat)) => {
+                debug!("spectator chat: {}: {}", chat.name, chat.text);
+                let _ = self.upstream_sender.send(data).await;
+            }
+            Ok(msg) => info!("unexpected message from spectator: {:?}", msg),
+            Err(err) => info!("invalid message from spectator: {}", err),
+        }
+    }
+
+    pub async fn send_to_spectator(&self, _conn: PeerConnection, mut data_channel: DataChannel) {
+        let mut len_rx = self.len_rx.clone();
+        let mut sent = None;
+        loop {
+            len_rx.borrow_and_update();
+            let pending = match self.log.lock().unwrap().since(sent) {
+                Ok(pending) => pending.map(|(messages, end)| (messages.to_vec(), end)),
+                Err(err) => {
+                    info!("spectator fell behind: {}", err);
+                    return;
+                }
+            };
+            if let Some((pending, end)) = pending {
+                for data in pending {
+                    if data_channel.message_sender.send(data).await.is_err() {
+                        info!("spectator closed");
+                        return;
+                    }
+                }
+                sent = Some(end);
+            }
+            select! {
+                result = len_rx.changed() => {
+                    if result.is_err() {
+                        return;
+                    }
+                }
+                data = data_channel.recv() => {
+                    let Some(data) = data else {
+                        info!("spectator closed");
+                        return;
+                    };
+                    self.forward_chat(data).await;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use junowen_lib::connection::{
+    signaling::{parse_signaling_code, CompressedSdp, SignalingCodeType},
+    DataChannel, PeerConnection,
+};
+use tokio::io::{AsyncBufRead, Lines};
+use tracing::{info, warn};
+
+const TIMEOUT: Duration = Duration::from_secs(20 * 60);
+
+pub async fn read_signaling_code(
+    lines: &mut Lines<impl AsyncBufRead + Unpin>,
+    expected: SignalingCodeType,
+) -> Result<Option<CompressedSdp>> {
+    loop {
+        let Some(line) = lines.next_line().await? else {
+            return Ok(None);
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        match parse_signaling_code(&line) {
+            Ok((code_type, desc)) if code_type == expected => return Ok(Some(desc)),
+            Ok(_) => warn!("unexpected signaling code type"),
+            Err(err) => warn!("invalid signaling code: {}", err),
+        }
+    }
+}
+
+pub async fn connect_to_spectator_host(
+    lines: &mut Lines<impl AsyncBufRead + Unpin>,
+) -> Result<(PeerConnection, DataChannel)> {
+    let mut conn = PeerConnection::new(TIMEOUT).await?;
+    let offer_desc = conn
+        .start_as_offerer()
+        .await
+        .context("Failed to start as spectator")?;
+    println!(
+        "{}",
+        SignalingCodeType::SpectatorOffer.to_string(&offer_desc)
+    );
+    info!("waiting for the player's <s-answer>...");
+    let Some(answer_desc) = read_signaling_code(lines, SignalingCodeType::SpectatorAnswer).await?
+    else {
+        bail!("stdin closed");
+    };
+    conn.set_answer_desc(answer_desc)
+        .await
+        .context("Failed to set answer desc")?;
+    let data_channel = conn.wait_for_open_data_channel().await?;
+    Ok((conn, data_channel))
+}
+
+pub async fn accept_spectator(offer_desc: CompressedSdp) -> Result<(PeerConnection, DataChannel)> {
+    let mut conn = PeerConnection::new(TIMEOUT).await?;
+    let answer_desc = conn
+        .start_as_answerer(offer_desc)
+        .await
+        .context("Failed to start as spectator host")?;
+    println!(
+        "{}",
+        SignalingCodeType::SpectatorAnswer.to_string(&answer_desc)
+    );
+    let data_channel = conn.wait_for_open_data_channel().await?;
+    Ok((conn, data_channel))
+}
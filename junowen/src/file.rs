@@ -1,14 +1,24 @@
-use std::{io::ErrorKind, path::PathBuf};
+mod config;
 
+use std::{
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
 use derive_new::new;
-use junowen_lib::Th19;
+use junowen_lib::{
+    identity::{from_hex, to_hex, Identity},
+    structs::settings::GameSettings,
+    Th19,
+};
 use serde::Deserialize;
 use tokio::{
     fs::{self, read_to_string},
     io,
 };
-use toml_edit::{Formatted, Item, Value};
-use tracing::error;
+use toml_edit::{value, Array, ArrayOfTables, DocumentMut, Item, Table, Value};
+use tracing::{error, info};
 use windows::{
     core::PCWSTR,
     Win32::{
@@ -18,6 +28,12 @@ use windows::{
     },
 };
 
+use self::config::{
+    get_item, get_strings, migrate, push_value, set_value, stamp_config_version, RulePresetEntry,
+    MAX_REMEMBERED_DELAYS, MAX_ROOM_HISTORY,
+};
+pub use self::config::{Config, DelaysConfig, OverlayConfig, RoomKind};
+
 pub fn to_dll_path(module: HMODULE) -> PathBuf {
     let mut buf = [0u16; u16::MAX as usize];
     if unsafe { GetModuleFileNameW(module, &mut buf) } == 0 {
@@ -75,90 +91,303 @@ pub async fn move_old_log_to_new_path(old_log_path: &str, module_dir: &str, log_
     }
 }
 
+/// 秘密鍵を 16 進数で保存する。無ければ生成する
+pub async fn load_or_create_identity(path: &Path) -> Result<Identity> {
+    match read_to_string(path).await {
+        Ok(hex) => Ok(Identity::from_secret_key(&from_hex(&hex)?)),
+        Err(err) if err.kind() == ErrorKind::NotFound => {
+            let identity = Identity::generate();
+            fs::write(path, to_hex(&identity.secret_key())).await?;
+            info!("identity created: {}", identity.fingerprint());
+            Ok(identity)
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
 #[derive(Debug, Deserialize, PartialEq, serde::Serialize)]
 #[serde(rename_all = "kebab-case")]
 pub enum Features {
-    ShowSettings,
+    Identity,
+    Ladder,
 }
 
-const FEATURES: &str = "features";
-const SHARED_ROOM_NAME: &str = "shared_room_name";
-const RESERVED_ROOM_NAME: &str = "reserved_room_name";
+const ROOMS: &str = "rooms";
+const DELAYS: &str = "delays";
+const RULE_PRESETS: &str = "rule_presets";
+const RULE_PRESET: &str = "rule_preset";
 
-#[derive(new)]
+/** 名前付きの対戦ルール */
+#[derive(Clone, Debug, new)]
+pub struct RulePreset {
+    pub name: String,
+    pub game_settings: GameSettings,
+}
+
+impl RulePreset {
+    fn from_entry(entry: &RulePresetEntry) -> Option<Self> {
+        let mut game_settings = GameSettings::default();
+        game_settings.set_time_limit(entry.time_limit.try_into().ok()?);
+        game_settings.set_round(entry.round.try_into().ok()?);
+        game_settings.set_ability_card(entry.ability_card.try_into().ok()?);
+        game_settings.set_p1_life(entry.p1_life as u32);
+        game_settings.set_p1_barrier(entry.p1_barrier.try_into().ok()?);
+        game_settings.set_p2_life(entry.p2_life as u32);
+        game_settings.set_p2_barrier(entry.p2_barrier.try_into().ok()?);
+        Some(Self::new(entry.name.clone(), game_settings))
+    }
+
+    fn to_table(&self) -> Table {
+        let settings = &self.game_settings;
+        let mut table = Table::new();
+        table.insert("name", value(self.name.as_str()));
+        table.insert("time_limit", value(settings.time_limit() as i64));
+        table.insert("round", value(settings.round() as i64));
+        table.insert("ability_card", value(settings.ability_card() as i64));
+        table.insert("p1_life", value(settings.p1_life() as i64));
+        table.insert("p1_barrier", value(settings.p1_barrier() as i64));
+        table.insert("p2_life", value(settings.p2_life() as i64));
+        table.insert("p2_barrier", value(settings.p2_barrier() as i64));
+        table
+    }
+}
+
+#[derive(Clone, new)]
 pub struct SettingsRepo {
     path: String,
 }
 
 impl SettingsRepo {
-    async fn load(&self) -> toml_edit::DocumentMut {
-        read_to_string(&self.path)
-            .await
-            .unwrap_or_default()
-            .parse()
-            .unwrap_or_default()
+    /// ini の隣に置く秘密鍵のファイル
+    pub fn identity_path(&self) -> PathBuf {
+        PathBuf::from(&self.path).with_extension("key")
     }
 
-    async fn read_string(&self, key: &str) -> Option<String> {
-        self.load()
-            .await
-            .get(key)
-            .and_then(|x| x.as_str())
-            .map(|x| x.to_owned())
+    /// ファイルが無ければ空の文書を返す。読めなければ Err
+    async fn load(&self) -> Result<DocumentMut> {
+        match read_to_string(&self.path).await {
+            Ok(text) => Ok(text.parse()?),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(DocumentMut::new()),
+            Err(err) => Err(err.into()),
+        }
     }
 
-    async fn write_string(&self, key: &str, value: String) {
-        let mut doc = self.load().await;
-        if let Some(item) = doc.as_table_mut().get_mut(key) {
-            *item = Item::Value(Value::String(Formatted::new(value)));
-        } else {
-            let _ = doc.insert(key, Item::Value(Value::String(Formatted::new(value))));
-        }
-        doc.sort_values();
+    async fn save(&self, doc: &mut DocumentMut) {
+        stamp_config_version(doc);
         if let Err(err) = tokio::fs::write(&self.path, doc.to_string()).await {
             error!("{}", err);
         }
     }
 
-    pub async fn features(&self) -> Vec<Features> {
-        self.load()
-            .await
-            .get(FEATURES)
-            .and_then(|x| x.as_array())
-            .map(|x| {
-                x.iter()
-                    .flat_map(|x| x.as_str())
-                    .flat_map(|x| serde_json::from_str(&format!("\"{x}\"")).ok())
-                    .collect::<Vec<_>>()
-            })
-            .unwrap_or_default()
+    /// 古い形式なら書き換えてから読み込む
+    async fn load_document(&self) -> Result<DocumentMut> {
+        let mut doc = self.load().await?;
+        if let Some(old_version) = migrate(&mut doc) {
+            info!("migrated {} from version {}", self.path, old_version);
+            self.save(&mut doc).await;
+        }
+        Ok(doc)
     }
 
-    pub async fn reserved_room_name(&self, th19: &Th19) -> String {
-        match self.read_string(RESERVED_ROOM_NAME).await {
-            Some(value) => value,
-            None => {
-                let value = th19.vs_mode().room_name().to_owned();
-                self.set_reserved_room_name(value.clone()).await;
-                value
+    /**
+     * 文書を読んで f で書き換えて保存する。f が false を返したら保存しない
+     *
+     * 読めなかったファイルは壊さないように書き込まない
+     */
+    async fn edit(&self, f: impl FnOnce(&mut DocumentMut) -> bool) {
+        let mut doc = match self.load().await {
+            Ok(doc) => doc,
+            Err(err) => {
+                error!("invalid {}, not saved: {}", self.path, err);
+                return;
             }
+        };
+        migrate(&mut doc);
+        if f(&mut doc) {
+            self.save(&mut doc).await;
         }
     }
-    pub async fn set_reserved_room_name(&self, value: String) {
-        self.write_string(RESERVED_ROOM_NAME, value).await;
+
+    /// 読み込めない値はログに出して既定値を使う
+    pub async fn load_config(&self) -> Config {
+        let (mut config, mut errors) = match self.load_document().await {
+            Ok(doc) => Config::from_document(&doc),
+            Err(err) => (Config::default(), vec![err.to_string()]),
+        };
+        errors.extend(config.validate());
+        for err in errors {
+            error!("{}: {}", self.path, err);
+        }
+        config
     }
 
-    pub async fn shared_room_name(&self, th19: &Th19) -> String {
-        match self.read_string(SHARED_ROOM_NAME).await {
-            Some(value) => value,
+    async fn write_value(&self, tables: &[&str], key: &str, new_value: impl Into<Value>) {
+        self.edit(|doc| {
+            set_value(doc, tables, key, new_value);
+            true
+        })
+        .await;
+    }
+
+    pub async fn set_rule_presets(&self, presets: &[RulePreset], selected: Option<&str>) {
+        self.edit(|doc| {
+            let mut tables = ArrayOfTables::new();
+            presets
+                .iter()
+                .for_each(|preset| tables.push(preset.to_table()));
+            let _ = doc.insert(RULE_PRESETS, Item::ArrayOfTables(tables));
+            if let Some(selected) = selected {
+                let _ = doc.insert(RULE_PRESET, value(selected));
+            } else {
+                let _ = doc.remove(RULE_PRESET);
+            }
+            true
+        })
+        .await;
+    }
+
+    /**
+     * rooms.key のルーム名。無ければゲームのメニューのルーム名を保存して使う
+     *
+     * 保存された値が読めなければ書き換えずにゲームのメニューのルーム名を使う
+     */
+    async fn room_name(&self, th19: &Th19, key: &str) -> String {
+        let default_name = th19.vs_mode().room_name().to_owned();
+        let mut room_name = None;
+        self.edit(|doc| match get_item(doc, &[ROOMS], key) {
+            Some(item) => {
+                room_name = item.as_str().map(|x| x.to_owned());
+                if room_name.is_none() {
+                    error!("invalid {}.{}: {}", ROOMS, key, item);
+                }
+                false
+            }
             None => {
-                let value = th19.vs_mode().room_name().to_owned();
-                self.set_shared_room_name(value.clone()).await;
-                value
+                set_value(doc, &[ROOMS], key, default_name.as_str());
+                true
             }
-        }
+        })
+        .await;
+        room_name.unwrap_or(default_name)
+    }
+
+    pub async fn reserved_room_name(&self, th19: &Th19) -> String {
+        self.room_name(th19, "reserved").await
+    }
+    pub async fn set_reserved_room_name(&self, value: String) {
+        self.write_value(&[ROOMS], "reserved", value).await;
+    }
+
+    pub async fn shared_room_name(&self, th19: &Th19) -> String {
+        self.room_name(th19, "shared").await
     }
     pub async fn set_shared_room_name(&self, value: String) {
-        self.write_string(SHARED_ROOM_NAME, value).await;
+        self.write_value(&[ROOMS], "shared", value).await;
+    }
+
+    /// お気に入りと最近使ったルーム名
+    pub async fn saved_room_names(&self, kind: RoomKind) -> (Vec<String>, Vec<String>) {
+        let rooms = self.load_config().await.rooms;
+        (rooms.favorites(kind).clone(), rooms.history(kind).clone())
+    }
+
+    /**
+     * rooms.key の一覧を f で書き換えて保存し、更新後の一覧を返す
+     *
+     * 保存された一覧が読めなければ書き換えずに None を返す
+     */
+    async fn edit_room_names(
+        &self,
+        kind: RoomKind,
+        key: &str,
+        f: impl FnOnce(&mut Vec<String>),
+    ) -> Option<Vec<String>> {
+        let key = format!("{}_{}", kind.key(), key);
+        let mut names = None;
+        self.edit(|doc| {
+            let Some(mut list) = get_strings(doc, &[ROOMS], &key) else {
+                error!("invalid {}.{}, not saved", ROOMS, key);
+                return false;
+            };
+            f(&mut list);
+            set_value(doc, &[ROOMS], &key, Array::from_iter(&list));
+            names = Some(list);
+            true
+        })
+        .await;
+        names
+    }
+
+    /// 最近使ったルーム名の先頭に name を移し、更新後の一覧を返す
+    pub async fn push_room_history(&self, kind: RoomKind, name: &str) -> Vec<String> {
+        if name.is_empty() {
+            return self.saved_room_names(kind).await.1;
+        }
+        match self
+            .edit_room_names(kind, "history", |history| {
+                history.retain(|x| x != name);
+                history.insert(0, name.to_owned());
+                history.truncate(MAX_ROOM_HISTORY);
+            })
+            .await
+        {
+            Some(history) => history,
+            None => self.saved_room_names(kind).await.1,
+        }
+    }
+
+    /// name をお気に入りに加えるか外し、更新後の一覧を返す
+    pub async fn set_room_favorite(
+        &self,
+        kind: RoomKind,
+        name: &str,
+        favorite: bool,
+    ) -> Vec<String> {
+        match self
+            .edit_room_names(kind, "favorites", |favorites| {
+                favorites.retain(|x| x != name);
+                if favorite {
+                    favorites.push(name.to_owned());
+                }
+            })
+            .await
+        {
+            Some(favorites) => favorites,
+            None => self.saved_room_names(kind).await.0,
+        }
+    }
+
+    pub async fn remembered_delays(&self) -> DelaysConfig {
+        let Ok(doc) = self.load_document().await else {
+            return DelaysConfig::default();
+        };
+        let mut config = Config::from_document(&doc).0;
+        config.validate();
+        config.delays
+    }
+
+    /// 対戦相手の名前とルーム名それぞれについて、最後に使ったディレイを覚える
+    pub async fn remember_delay(&self, player_name: &str, room_name: Option<&str>, delay: u8) {
+        self.edit(|doc| {
+            let delay = delay as i64;
+            push_value(
+                doc,
+                &[DELAYS, "players"],
+                player_name,
+                delay,
+                MAX_REMEMBERED_DELAYS,
+            );
+            if let Some(room_name) = room_name {
+                push_value(
+                    doc,
+                    &[DELAYS, ROOMS],
+                    room_name,
+                    delay,
+                    MAX_REMEMBERED_DELAYS,
+                );
+            }
+            true
+        })
+        .await;
     }
 }
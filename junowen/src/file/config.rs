@@ -0,0 +1,844 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use junowen_lib::{
+    connection::IceServer, delayed_inputs::MAX_DELAY, signaling_server::room::MAX_ROOM_NAME_LEN,
+};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer};
+use toml_edit::{value, DocumentMut, Item, Key, Table, Value};
+use tracing::error;
+
+use super::{Features, RulePreset};
+
+/// 今の設定ファイルの形式。形式を変えたら上げて migrate に手順を足す
+pub const CONFIG_VERSION: i64 = 1;
+/// ルームごとに覚えておく最近のルーム名の数
+pub const MAX_ROOM_HISTORY: usize = 8;
+/// 対戦相手の名前とルーム名それぞれについて覚えておくディレイの数
+pub const MAX_REMEMBERED_DELAYS: usize = 64;
+
+const CONFIG_VERSION_KEY: &str = "config_version";
+
+fn deserialize_features<'de, D>(deserializer: D) -> Result<Vec<Features>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let names = Vec::<String>::deserialize(deserializer)?;
+    Ok(names
+        .iter()
+        .filter_map(|name| {
+            let feature = serde_json::from_str(&format!("\"{name}\"")).ok();
+            if feature.is_none() {
+                error!("unknown feature: {}", name);
+            }
+            feature
+        })
+        .collect())
+}
+
+/** 対戦ルールのプリセット。値はゲームのメニューの選択肢の番号 */
+#[derive(Clone, Debug, Deserialize)]
+pub struct RulePresetEntry {
+    pub name: String,
+    pub time_limit: u8,
+    pub round: u8,
+    pub ability_card: u8,
+    pub p1_life: u8,
+    pub p1_barrier: u8,
+    pub p2_life: u8,
+    pub p2_barrier: u8,
+}
+
+/** 共用ルームと専有ルームのどちらか。設定ファイルのキーの接頭辞になる */
+#[derive(Clone, Copy, Debug)]
+pub enum RoomKind {
+    Shared,
+    Reserved,
+}
+
+impl RoomKind {
+    pub fn key(self) -> &'static str {
+        match self {
+            Self::Shared => "shared",
+            Self::Reserved => "reserved",
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct RoomsConfig {
+    pub shared: Option<String>,
+    pub reserved: Option<String>,
+    /// 最近使ったルーム名。新しい順
+    pub shared_history: Vec<String>,
+    pub reserved_history: Vec<String>,
+    /// 固定したルーム名
+    pub shared_favorites: Vec<String>,
+    pub reserved_favorites: Vec<String>,
+}
+
+/// 空や長すぎる名前と重複を取り除く
+fn retain_valid_room_names(names: &mut Vec<String>, key: &str, errors: &mut Vec<String>) {
+    let mut seen = vec![];
+    names.retain(|name| {
+        if name.is_empty() || name.chars().count() > MAX_ROOM_NAME_LEN {
+            errors.push(format!("invalid rooms.{}: {}", key, name));
+            return false;
+        }
+        if seen.contains(name) {
+            return false;
+        }
+        seen.push(name.clone());
+        true
+    });
+}
+
+impl RoomsConfig {
+    pub fn history(&self, kind: RoomKind) -> &Vec<String> {
+        match kind {
+            RoomKind::Shared => &self.shared_history,
+            RoomKind::Reserved => &self.reserved_history,
+        }
+    }
+
+    pub fn favorites(&self, kind: RoomKind) -> &Vec<String> {
+        match kind {
+            RoomKind::Shared => &self.shared_favorites,
+            RoomKind::Reserved => &self.reserved_favorites,
+        }
+    }
+
+    fn validate(&mut self, errors: &mut Vec<String>) {
+        for (names, key) in [
+            (&mut self.shared_history, "shared_history"),
+            (&mut self.reserved_history, "reserved_history"),
+            (&mut self.shared_favorites, "shared_favorites"),
+            (&mut self.reserved_favorites, "reserved_favorites"),
+        ] {
+            retain_valid_room_names(names, key, errors);
+        }
+        self.shared_history.truncate(MAX_ROOM_HISTORY);
+        self.reserved_history.truncate(MAX_ROOM_HISTORY);
+    }
+}
+
+/**
+ * ホストとして対戦相手やルームごとに最後に使ったディレイ
+ *
+ * ファイルには使った順に並べ、MAX_REMEMBERED_DELAYS を超えたら古いものから消す
+ */
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct DelaysConfig {
+    /// 対戦相手の名前ごと
+    pub players: BTreeMap<String, u8>,
+    /// ルーム名ごと
+    pub rooms: BTreeMap<String, u8>,
+}
+
+impl DelaysConfig {
+    /// 対戦相手の名前で覚えたディレイを優先する
+    pub fn find(&self, player_name: &str, room_name: Option<&str>) -> Option<u8> {
+        self.players
+            .get(player_name)
+            .or_else(|| room_name.and_then(|room_name| self.rooms.get(room_name)))
+            .copied()
+            .filter(|&delay| delay <= MAX_DELAY)
+    }
+
+    fn validate(&mut self, errors: &mut Vec<String>) {
+        for (delays, key) in [(&mut self.players, "players"), (&mut self.rooms, "rooms")] {
+            delays.retain(|name, &mut delay| {
+                let valid = delay <= MAX_DELAY;
+                if !valid {
+                    errors.push(format!("invalid delays.{}.{}: {}", key, name, delay));
+                }
+                valid
+            });
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct SignalingConfig {
+    /// 既定のシグナリングサーバーの代わりに使うサーバーの origin
+    pub server: Option<String>,
+    /// 空なら既定の STUN サーバーを使う
+    pub ice_servers: Vec<IceServer>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct OverlayConfig {
+    /// キャラクター選択画面で対戦ルールを常に表示する
+    pub game_settings: bool,
+    pub chat: bool,
+}
+
+impl Default for OverlayConfig {
+    fn default() -> Self {
+        Self {
+            game_settings: false,
+            chat: true,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct RecordingConfig {
+    pub enabled: bool,
+    /// None なら modules ディレクトリーの replays に保存する
+    pub dir: Option<PathBuf>,
+}
+
+/**
+ * 観戦者への配信を遅らせる入力のフレーム数
+ *
+ * 整数ならフレーム数、"3s" のような文字列なら 60 フレームを 1 秒として換算したフレーム数。
+ * 時間ではなく送った入力の数で数えるので、ロード中など入力が流れない間は遅延が進まない
+ */
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum SpectatorDelay {
+    Frames(i64),
+    Text(String),
+}
+
+impl Default for SpectatorDelay {
+    fn default() -> Self {
+        Self::Frames(0)
+    }
+}
+
+impl SpectatorDelay {
+    pub fn frames(&self) -> Option<u32> {
+        match self {
+            Self::Frames(frames) => u32::try_from(*frames).ok(),
+            Self::Text(text) => {
+                let text = text.trim();
+                match text.strip_suffix('s') {
+                    Some(secs) => secs
+                        .trim()
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|secs| secs.is_finite() && *secs >= 0.0)
+                        .map(|secs| (secs * 60.0).round() as u32),
+                    None => text.parse().ok(),
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct SpectatorConfig {
+    /// 観戦者に送る入力を遅らせるフレーム数
+    pub delay: SpectatorDelay,
+}
+
+/**
+ * th19_junowen.ini の内容
+ *
+ * 書き込みは toml_edit で該当する値だけを書き換え、利用者のコメントや並びを保つ
+ */
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub config_version: i64,
+    /// ゲーム内の表示言語。"ja" や "en" のようなタグ
+    pub lang: Option<String>,
+    #[serde(deserialize_with = "deserialize_features")]
+    pub features: Vec<Features>,
+    /// ホストとして N 本先取のセットを提案する。0 ならセットとして扱わない
+    pub first_to: Option<u8>,
+    /// ホストとして対戦の開始時に設定するディレイ
+    pub default_delay: Option<u8>,
+    /// ホストとしてゲストが希望するディレイを確認せずに受け入れる
+    pub auto_accept_delay_requests: bool,
+    /// MatchInitial に使うプリセットの名前。None ならゲームのメニューの設定を使う
+    pub rule_preset: Option<String>,
+    pub rule_presets: Vec<RulePresetEntry>,
+    pub rooms: RoomsConfig,
+    pub delays: DelaysConfig,
+    pub signaling: SignalingConfig,
+    pub overlay: OverlayConfig,
+    pub recording: RecordingConfig,
+    pub spectator: SpectatorConfig,
+}
+
+fn is_valid_ice_url(url: &str) -> bool {
+    ["stun:", "turn:", "turns:"]
+        .iter()
+        .any(|scheme| url.starts_with(scheme))
+}
+
+impl Config {
+    /**
+     * 使えない値を取り除いて既定値に戻し、その理由を返す
+     */
+    pub fn validate(&mut self) -> Vec<String> {
+        let mut errors = vec![];
+        if self.config_version > CONFIG_VERSION {
+            errors.push(format!(
+                "{} {} is newer than supported ({})",
+                CONFIG_VERSION_KEY, self.config_version, CONFIG_VERSION
+            ));
+        }
+        if self.first_to == Some(0) {
+            self.first_to = None;
+        }
+        if let Some(delay) = self.default_delay.filter(|&delay| delay > MAX_DELAY) {
+            errors.push(format!("default_delay must be 0-{}: {}", MAX_DELAY, delay));
+            self.default_delay = None;
+        }
+        match self.spectator.delay.frames() {
+            Some(frames) => self.spectator.delay = SpectatorDelay::Frames(frames as i64),
+            None => {
+                errors.push(format!(
+                    "invalid spectator.delay: {:?}",
+                    self.spectator.delay
+                ));
+                self.spectator.delay = SpectatorDelay::default();
+            }
+        }
+        if let Some(server) = &self.signaling.server {
+            if !server.starts_with("https://") && !server.starts_with("http://") {
+                errors.push(format!("invalid signaling.server: {}", server));
+                self.signaling.server = None;
+            }
+        }
+        self.signaling.ice_servers.retain(|server| {
+            let valid =
+                !server.urls.is_empty() && server.urls.iter().all(|url| is_valid_ice_url(url));
+            if !valid {
+                errors.push(format!("invalid signaling.ice_servers: {:?}", server.urls));
+            }
+            valid
+        });
+        self.rooms.validate(&mut errors);
+        self.delays.validate(&mut errors);
+        self.rule_presets.retain(|entry| {
+            let valid = RulePreset::from_entry(entry).is_some();
+            if !valid {
+                errors.push(format!("invalid rule_presets: {}", entry.name));
+            }
+            valid
+        });
+        errors
+    }
+
+    pub fn rule_presets(&self) -> Vec<RulePreset> {
+        self.rule_presets
+            .iter()
+            .filter_map(RulePreset::from_entry)
+            .collect()
+    }
+
+    pub fn spectator_delay_frames(&self) -> u32 {
+        self.spectator.delay.frames().unwrap_or_default()
+    }
+}
+
+enum PathSegment {
+    Key(String),
+    Element(usize),
+}
+
+fn path_to_string(path: &[PathSegment]) -> String {
+    let mut text = String::new();
+    for segment in path {
+        match segment {
+            PathSegment::Key(key) if text.is_empty() => text.push_str(key),
+            PathSegment::Key(key) => text.push_str(&format!(".{}", key)),
+            PathSegment::Element(i) => text.push_str(&format!("[{}]", i)),
+        }
+    }
+    text
+}
+
+/// path の位置に value だけを置いた文書を T として読めるか
+fn is_readable<T: DeserializeOwned>(path: &[PathSegment], value: &toml::Value) -> bool {
+    let mut value = value.clone();
+    for segment in path.iter().rev() {
+        value = match segment {
+            PathSegment::Key(key) => {
+                toml::Value::Table(toml::Table::from_iter([(key.clone(), value)]))
+            }
+            PathSegment::Element(_) => toml::Value::Array(vec![value]),
+        };
+    }
+    value.try_into::<T>().is_ok()
+}
+
+/**
+ * T として読めない値を取り除き、その理由を errors に足す。残せるなら true を返す
+ *
+ * テーブルは読めない値だけを取り除く。配列の要素はまるごと取り除く
+ * 型が違う値はまるごと取り除く
+ */
+fn retain_readable<T: DeserializeOwned>(
+    path: &mut Vec<PathSegment>,
+    value: &mut toml::Value,
+    errors: &mut Vec<String>,
+) -> bool {
+    if is_readable::<T>(path, value) {
+        return true;
+    }
+    let in_array = matches!(path.last(), Some(PathSegment::Element(_)));
+    let empty = match value {
+        toml::Value::Table(_) => toml::Value::Table(toml::Table::new()),
+        toml::Value::Array(_) => toml::Value::Array(vec![]),
+        _ => value.clone(),
+    };
+    match value {
+        // 空のテーブルや配列も読めないなら、値の型が違う
+        _ if !is_readable::<T>(path, &empty) => {}
+        toml::Value::Table(table) if !in_array => {
+            table.retain(|key, value| {
+                path.push(PathSegment::Key(key.to_owned()));
+                let readable = retain_readable::<T>(path, value, errors);
+                path.pop();
+                readable
+            });
+            if is_readable::<T>(path, value) {
+                return true;
+            }
+        }
+        toml::Value::Array(array) => {
+            let mut i = 0;
+            array.retain_mut(|value| {
+                path.push(PathSegment::Element(i));
+                let readable = retain_readable::<T>(path, value, errors);
+                path.pop();
+                i += 1;
+                readable
+            });
+            if is_readable::<T>(path, value) {
+                return true;
+            }
+        }
+        _ => {}
+    }
+    errors.push(format!("invalid {}: {}", path_to_string(path), value));
+    false
+}
+
+impl Config {
+    /**
+     * 文書から設定を読む。読めない値は既定値にして、その理由を返す
+     *
+     * 値ごとに読むので、1 つの値が壊れていても他の値は使える
+     */
+    pub fn from_document(doc: &DocumentMut) -> (Self, Vec<String>) {
+        let mut errors = vec![];
+        let mut root = match toml::from_str::<toml::Table>(&doc.to_string()) {
+            Ok(table) => toml::Value::Table(table),
+            Err(err) => {
+                errors.push(err.to_string());
+                return (Self::default(), errors);
+            }
+        };
+        if let Ok(config) = root.clone().try_into() {
+            return (config, errors);
+        }
+        retain_readable::<Self>(&mut vec![], &mut root, &mut errors);
+        let config = root.try_into().unwrap_or_else(|err: toml::de::Error| {
+            errors.push(err.to_string());
+            Self::default()
+        });
+        (config, errors)
+    }
+}
+
+/// tables.key の値を書き換える。値についたコメントは残す
+pub fn set_value(doc: &mut DocumentMut, tables: &[&str], key: &str, new_value: impl Into<Value>) {
+    let table = tables
+        .iter()
+        .fold(doc.as_table_mut(), |table, name| sub_table(table, name));
+    let mut new_value = new_value.into();
+    match table.get_mut(key) {
+        Some(item) => {
+            if let Some(old_value) = item.as_value() {
+                *new_value.decor_mut() = old_value.decor().clone();
+            }
+            *item = Item::Value(new_value);
+        }
+        None => {
+            table.insert(key, Item::Value(new_value));
+        }
+    }
+}
+
+/**
+ * tables.key の値を書き換えてテーブルの末尾に移す。max を超えたら先頭の値から取り除く
+ *
+ * キーと値についたコメントは残す
+ */
+pub fn push_value(
+    doc: &mut DocumentMut,
+    tables: &[&str],
+    key: &str,
+    new_value: impl Into<Value>,
+    max: usize,
+) {
+    let table = tables
+        .iter()
+        .fold(doc.as_table_mut(), |table, name| sub_table(table, name));
+    let mut new_value = new_value.into();
+    let key = match table.remove_entry(key) {
+        Some((key, item)) => {
+            if let Some(old_value) = item.as_value() {
+                *new_value.decor_mut() = old_value.decor().clone();
+            }
+            key
+        }
+        None => Key::new(key),
+    };
+    table.insert_formatted(&key, Item::Value(new_value));
+    while table.len() > max {
+        let Some(first) = table.iter().next().map(|(key, _)| key.to_owned()) else {
+            break;
+        };
+        table.remove(&first);
+    }
+}
+
+/// tables.key の文字列の配列。無ければ空で、文字列の配列でなければ None
+pub fn get_strings(doc: &DocumentMut, tables: &[&str], key: &str) -> Option<Vec<String>> {
+    let Some(item) = get_item(doc, tables, key) else {
+        return Some(vec![]);
+    };
+    item.as_array()?
+        .iter()
+        .map(|x| x.as_str().map(|x| x.to_owned()))
+        .collect()
+}
+
+pub fn get_item<'a>(doc: &'a DocumentMut, tables: &[&str], key: &str) -> Option<&'a Item> {
+    tables
+        .iter()
+        .try_fold(doc.as_item(), |item, name| item.get(name))?
+        .get(key)
+}
+
+fn implicit_table() -> Item {
+    let mut table = Table::new();
+    table.set_implicit(true);
+    Item::Table(table)
+}
+
+/// name のテーブルを返す。無ければ作る
+pub fn sub_table<'a>(table: &'a mut Table, name: &str) -> &'a mut Table {
+    let item = table.entry(name).or_insert_with(implicit_table);
+    if !item.is_table() {
+        error!("{} is not a table, replaced", name);
+        *item = implicit_table();
+    }
+    item.as_table_mut().unwrap()
+}
+
+/// キーについたコメントを保ったまま値を table_name.to に移す
+fn move_value(doc: &mut DocumentMut, from: &str, table_name: &str, to: &str) {
+    let Some((key, item)) = doc.as_table_mut().remove_entry(from) else {
+        return;
+    };
+    let mut new_key = Key::new(to);
+    *new_key.leaf_decor_mut() = key.leaf_decor().clone();
+    sub_table(doc.as_table_mut(), table_name).insert_formatted(&new_key, item);
+}
+
+/// features から name を取り除く。含まれていれば true
+fn remove_feature(doc: &mut DocumentMut, name: &str) -> bool {
+    let Some(features) = doc.get_mut("features").and_then(|x| x.as_array_mut()) else {
+        return false;
+    };
+    let len = features.len();
+    features.retain(|x| x.as_str() != Some(name));
+    features.len() != len
+}
+
+/// config_version の無い、フラットな形式から移行する
+fn migrate_to_v1(doc: &mut DocumentMut) {
+    move_value(doc, "shared_room_name", "rooms", "shared");
+    move_value(doc, "reserved_room_name", "rooms", "reserved");
+    move_value(doc, "spectator_delay", "spectator", "delay");
+    if remove_feature(doc, "show-settings") {
+        sub_table(doc.as_table_mut(), "overlay").insert("game_settings", value(true));
+    }
+    if remove_feature(doc, "record-replays") {
+        sub_table(doc.as_table_mut(), "recording").insert("enabled", value(true));
+    }
+}
+
+/**
+ * 古い形式の設定を今の形式に書き換える。書き換えたら元の版を返す
+ */
+pub fn migrate(doc: &mut DocumentMut) -> Option<i64> {
+    // 新しいファイルには書き込むときに版を入れる
+    if doc.as_table().is_empty() {
+        return None;
+    }
+    let version = doc
+        .get(CONFIG_VERSION_KEY)
+        .and_then(|x| x.as_integer())
+        .unwrap_or(0);
+    if version >= CONFIG_VERSION {
+        return None;
+    }
+    if version < 1 {
+        migrate_to_v1(doc);
+    }
+    doc.insert(CONFIG_VERSION_KEY, value(CONFIG_VERSION));
+    Some(version)
+}
+
+/// 版の無い新しいファイルに今の版を書く
+pub fn stamp_config_version(doc: &mut DocumentMut) {
+    if doc.get(CONFIG_VERSION_KEY).is_none() {
+        doc.insert(CONFIG_VERSION_KEY, value(CONFIG_VERSION));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> DocumentMut {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn from_document_reads_all_sections() {
+        let doc = parse(
+            r#"
+config_version = 1
+lang = "ja"
+features = ["identity", "unknown"]
+first_to = 3
+default_delay = 2
+
+[[rule_presets]]
+name = "short"
+time_limit = 1
+round = 2
+ability_card = 0
+p1_life = 3
+p1_barrier = 1
+p2_life = 3
+p2_barrier = 1
+
+[rooms]
+shared = "lobby"
+shared_history = ["lobby", "other"]
+
+[delays.players]
+reimu = 3
+
+[delays.rooms]
+lobby = 1
+
+[spectator]
+delay = "3s"
+"#,
+        );
+        let (config, errors) = Config::from_document(&doc);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(config.lang.as_deref(), Some("ja"));
+        assert_eq!(config.features, vec![Features::Identity]);
+        assert_eq!(config.first_to, Some(3));
+        assert_eq!(config.default_delay, Some(2));
+        assert_eq!(config.rule_presets[0].name, "short");
+        assert_eq!(config.rooms.shared.as_deref(), Some("lobby"));
+        assert_eq!(config.rooms.shared_history, vec!["lobby", "other"]);
+        assert_eq!(config.delays.find("reimu", Some("lobby")), Some(3));
+        assert_eq!(config.delays.find("marisa", Some("lobby")), Some(1));
+        assert_eq!(config.spectator_delay_frames(), 180);
+        assert!(config.overlay.chat);
+    }
+
+    #[test]
+    fn from_document_keeps_readable_values() {
+        let doc = parse(
+            r#"
+lang = 1
+default_delay = 2
+
+[rooms]
+shared = "lobby"
+reserved = ["not", "a", "name"]
+shared_history = ["lobby", 2, "other"]
+
+[[signaling.ice_servers]]
+urls = ["stun:example.com"]
+
+[[signaling.ice_servers]]
+username = "no urls"
+"#,
+        );
+        let (config, errors) = Config::from_document(&doc);
+        assert_eq!(errors.len(), 4, "{:?}", errors);
+        assert!(errors
+            .iter()
+            .any(|x| x.starts_with("invalid rooms.reserved:")));
+        assert!(errors.iter().any(|x| x.starts_with("invalid lang:")));
+        assert!(errors
+            .iter()
+            .any(|x| x.starts_with("invalid rooms.shared_history[1]:")));
+        assert!(errors
+            .iter()
+            .any(|x| x.starts_with("invalid signaling.ice_servers[1]:")));
+        assert_eq!(config.lang, None);
+        assert_eq!(config.default_delay, Some(2));
+        assert_eq!(config.rooms.shared.as_deref(), Some("lobby"));
+        assert_eq!(config.rooms.reserved, None);
+        assert_eq!(config.rooms.shared_history, vec!["lobby", "other"]);
+        assert_eq!(config.signaling.ice_servers.len(), 1);
+    }
+
+    #[test]
+    fn set_value_keeps_comments() {
+        let mut doc = parse(
+            r#"# junowen
+[rooms]
+# my room
+shared = "lobby" # keep this
+"#,
+        );
+        set_value(&mut doc, &["rooms"], "shared", "other");
+        set_value(&mut doc, &["delays", "players"], "reimu", 3);
+        assert_eq!(
+            doc.to_string(),
+            r#"# junowen
+[rooms]
+# my room
+shared = "other" # keep this
+
+[delays.players]
+reimu = 3
+"#
+        );
+        let (config, errors) = Config::from_document(&doc);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(config.rooms.shared.as_deref(), Some("other"));
+        assert_eq!(config.delays.find("reimu", None), Some(3));
+    }
+
+    #[test]
+    fn push_value_drops_oldest_values() {
+        let mut doc = parse(
+            r#"[delays.players]
+# first
+a = 1
+b = 2
+c = 3
+"#,
+        );
+        push_value(&mut doc, &["delays", "players"], "a", 4, 3);
+        assert_eq!(
+            doc.to_string(),
+            r#"[delays.players]
+b = 2
+c = 3
+# first
+a = 4
+"#
+        );
+        push_value(&mut doc, &["delays", "players"], "d", 5, 3);
+        push_value(&mut doc, &["delays", "rooms"], "lobby", 6, 3);
+        assert_eq!(
+            doc.to_string(),
+            r#"[delays.players]
+c = 3
+# first
+a = 4
+d = 5
+
+[delays.rooms]
+lobby = 6
+"#
+        );
+    }
+
+    #[test]
+    fn get_strings_rejects_other_values() {
+        let doc = parse(
+            r#"
+[rooms]
+shared_history = ["a", "b"]
+reserved_history = ["a", 1]
+shared_favorites = "a"
+"#,
+        );
+        let strings = |key| get_strings(&doc, &["rooms"], key);
+        assert_eq!(
+            strings("shared_history"),
+            Some(vec!["a".into(), "b".into()])
+        );
+        assert_eq!(strings("reserved_history"), None);
+        assert_eq!(strings("shared_favorites"), None);
+        assert_eq!(strings("reserved_favorites"), Some(vec![]));
+    }
+
+    #[test]
+    fn move_value_keeps_comments() {
+        let mut doc = parse(
+            r#"# room for everyone
+shared_room_name = "lobby"
+lang = "ja"
+"#,
+        );
+        move_value(&mut doc, "shared_room_name", "rooms", "shared");
+        move_value(&mut doc, "missing", "rooms", "reserved");
+        assert_eq!(
+            doc.to_string(),
+            r#"lang = "ja"
+
+[rooms]
+# room for everyone
+shared = "lobby"
+"#
+        );
+    }
+
+    #[test]
+    fn migrate_from_v0() {
+        let mut doc = parse(
+            r#"# settings
+features = ["identity", "show-settings", "record-replays"]
+# shared
+shared_room_name = "lobby"
+reserved_room_name = "mine"
+spectator_delay = 30
+"#,
+        );
+        assert_eq!(migrate(&mut doc), Some(0));
+        let text = doc.to_string();
+        assert!(text.starts_with("# settings\n"), "{}", text);
+        assert!(text.contains("# shared\nshared = \"lobby\""), "{}", text);
+
+        let (mut config, errors) = Config::from_document(&doc);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(config.validate().is_empty());
+        assert_eq!(config.config_version, CONFIG_VERSION);
+        assert_eq!(config.features, vec![Features::Identity]);
+        assert_eq!(config.rooms.shared.as_deref(), Some("lobby"));
+        assert_eq!(config.rooms.reserved.as_deref(), Some("mine"));
+        assert_eq!(config.spectator_delay_frames(), 30);
+        assert!(config.overlay.game_settings);
+        assert!(config.recording.enabled);
+
+        assert_eq!(migrate(&mut doc), None);
+        assert_eq!(doc.to_string(), text);
+    }
+
+    #[test]
+    fn migrate_skips_new_files() {
+        let mut doc = DocumentMut::new();
+        assert_eq!(migrate(&mut doc), None);
+        stamp_config_version(&mut doc);
+        assert_eq!(doc.to_string(), "config_version = 1\n");
+    }
+}
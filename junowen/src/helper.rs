@@ -1,11 +1,71 @@
-use junowen_lib::structs::input_devices::InputDevices;
+use junowen_lib::{
+    delayed_inputs::MAX_DELAY, session_message::Side, structs::input_devices::InputDevices,
+};
 
-pub fn inputed_number(input_devices: &InputDevices) -> Option<u8> {
+/// ホストのディレイ変更の操作
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DelayChange {
+    Set(u8),
+    Increase,
+    Decrease,
+}
+
+impl DelayChange {
+    pub fn apply(self, delay: u8) -> u8 {
+        match self {
+            Self::Set(delay) => delay,
+            Self::Increase => delay.saturating_add(1).min(MAX_DELAY),
+            Self::Decrease => delay.saturating_sub(1),
+        }
+    }
+}
+
+/// 数字キーの 0-9 で値を指定し、+ と - のキー (テンキーを含む) で 1 ずつ増減する
+pub fn inputed_delay_change(input_devices: &InputDevices) -> Option<DelayChange> {
     let raw_keys = input_devices.keyboard_input().raw_keys();
-    (0..=9).find(|i| raw_keys[(b'0' + i) as usize] & 0x80 != 0)
+    let pushed = |key: u8| raw_keys[key as usize] & 0x80 != 0;
+    if let Some(number) = (0..=9).find(|i| pushed(b'0' + i)) {
+        Some(DelayChange::Set(number))
+    } else if pushed(0xbb) || pushed(0x6b) {
+        Some(DelayChange::Increase)
+    } else if pushed(0xbd) || pushed(0x6d) {
+        Some(DelayChange::Decrease)
+    } else {
+        None
+    }
 }
 
 pub fn pushed_f1(input_devices: &InputDevices) -> bool {
     let raw_keys = input_devices.keyboard_input().raw_keys();
     raw_keys[0x70] & 0x80 != 0
 }
+
+/// F2 から F9 のうち押されているキーの番号
+pub fn inputed_function_key(input_devices: &InputDevices) -> Option<u8> {
+    let raw_keys = input_devices.keyboard_input().raw_keys();
+    (2..=9).find(|i| raw_keys[0x70 + (i - 1) as usize] & 0x80 != 0)
+}
+
+/// F11 なら P1、F12 なら P2 の勝ち
+pub fn inputed_match_winner(input_devices: &InputDevices) -> Option<Side> {
+    let raw_keys = input_devices.keyboard_input().raw_keys();
+    if raw_keys[0x7a] & 0x80 != 0 {
+        Some(Side::P1)
+    } else if raw_keys[0x7b] & 0x80 != 0 {
+        Some(Side::P2)
+    } else {
+        None
+    }
+}
+
+/// Y なら承諾、N なら拒否
+pub fn inputed_acceptance(input_devices: &InputDevices) -> Option<bool> {
+    let raw_keys = input_devices.keyboard_input().raw_keys();
+    if raw_keys[b'Y' as usize] & 0x80 != 0 {
+        Some(true)
+    } else if raw_keys[b'N' as usize] & 0x80 != 0 {
+        Some(false)
+    } else {
+        None
+    }
+}
@@ -1,9 +1,16 @@
 mod common_menu;
+mod delay_practice;
+mod friends;
 mod helper;
+mod invitation_flow;
 mod lobby;
+mod match_rules;
 mod pure_p2p_guest;
 mod pure_p2p_offerer;
+mod recent_opponents;
 mod room;
 mod title_menu_modifier;
 
-pub use {lobby::Lobby, title_menu_modifier::TitleMenuModifier};
+pub use {
+    delay_practice::DelayPracticeSettings, lobby::Lobby, title_menu_modifier::TitleMenuModifier,
+};
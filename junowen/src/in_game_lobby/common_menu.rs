@@ -43,6 +43,10 @@ pub enum LobbyScene {
     PureP2pHost,
     PureP2pGuest,
     PureP2pSpectator,
+    MatchRules,
+    Friends,
+    RecentOpponents,
+    DelayPractice,
 }
 
 pub enum OnMenuInputResult {
@@ -180,11 +184,11 @@ impl CommonMenu {
         match self.current_menu_scene() {
             CurrentMenuSceneResult::SubScene { .. } => unreachable!(),
             CurrentMenuSceneResult::Menu(menu) => {
-                render_title(th19, text_renderer, menu.title().as_bytes());
+                render_title(th19, text_renderer, menu.title());
                 menu.on_render_texts(self.base_height, th19, text_renderer);
             }
             CurrentMenuSceneResult::TextInput(label, text_input) => {
-                render_title(th19, text_renderer, label.as_bytes());
+                render_title(th19, text_renderer, label);
                 text_input.on_render_texts(th19, text_renderer);
             }
         }
@@ -75,7 +75,7 @@ impl Menu {
 
     pub fn on_render_texts(&self, base_height: u32, th19: &Th19, text_renderer: *const c_void) {
         for (i, item) in self.items().iter().enumerate() {
-            let label = item.label().as_bytes();
+            let label = item.label();
             let height = base_height + 56 * i as u32;
             render_menu_item(
                 th19,
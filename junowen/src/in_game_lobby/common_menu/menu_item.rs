@@ -80,12 +80,13 @@ impl MenuItem {
         decided_action: u8,
         changed_action: u8,
         name: &'static str,
+        max_len: usize,
     ) -> Self {
         Self::TextInput(MenuTextInputItem {
             label,
             enabled: true,
             decided_action,
-            text_input: Box::new(TextInput::new(changed_action, name)),
+            text_input: Box::new(TextInput::new(changed_action, name, max_len)),
         })
     }
 
@@ -1,66 +1,33 @@
-use std::{ffi::c_void, num::NonZeroU8};
+mod text_input_state;
 
-use getset::Setters;
+use std::ffi::c_void;
+
+use clipboard_win::get_clipboard_string;
 use junowen_lib::Th19;
 use windows::Win32::UI::Input::KeyboardAndMouse::{MapVirtualKeyW, ToUnicode, MAPVK_VK_TO_VSC};
 
 use crate::in_game_lobby::helper::render_label_value;
 
-fn to_ascii(vk: u32, current: &[u8; 256]) -> Option<NonZeroU8> {
-    let mut buf = [0u16; 2];
-    unsafe {
-        let scan_code = MapVirtualKeyW(vk, MAPVK_VK_TO_VSC);
-        ToUnicode(vk, scan_code, Some(current), &mut buf, 0);
-    }
-    NonZeroU8::new(String::from_utf16_lossy(&buf).as_bytes()[0])
-}
+use self::text_input_state::{TextInputBackend, TextInputState, TickResult};
 
-#[derive(Debug)]
-struct TextInputState {
-    prev: [u8; 256],
-    current_vk: u8,
-    current_vk_count: u32,
-}
+struct Win32Backend;
 
-impl TextInputState {
-    pub fn new(current: &[u8; 256]) -> Self {
-        let mut zelf = Self {
-            prev: [0; 256],
-            current_vk: 0,
-            current_vk_count: 0,
+impl TextInputBackend for Win32Backend {
+    fn to_text(&self, vk: u8, keys: &[u8; 256]) -> String {
+        let mut buf = [0u16; 8];
+        let len = unsafe {
+            let scan_code = MapVirtualKeyW(vk as u32, MAPVK_VK_TO_VSC);
+            ToUnicode(vk as u32, scan_code, Some(keys), &mut buf, 0)
         };
-        zelf.tick(current);
-        zelf
+        // 負の値はデッドキー
+        if len <= 0 {
+            return String::new();
+        }
+        String::from_utf16_lossy(&buf[..len as usize])
     }
 
-    pub fn tick(&mut self, current: &[u8; 256]) -> Vec<u8> {
-        let mut result = vec![];
-        for (vk, _) in current
-            .iter()
-            .enumerate()
-            .filter(|&(vk, value)| value & 0x80 != 0 && self.prev[vk] & 0x80 == 0)
-        {
-            if let Some(ascii) = to_ascii(vk as u32, current) {
-                result.push(ascii.get());
-            }
-            let vk = vk as u8;
-            if vk != self.current_vk {
-                self.current_vk = vk;
-                self.current_vk_count = 0;
-            }
-        }
-        if current[self.current_vk as usize] & 0x80 != 0 {
-            if self.current_vk_count > 30 {
-                if let Some(ascii) = to_ascii(self.current_vk as u32, current) {
-                    result.push(ascii.get());
-                }
-            }
-            self.current_vk_count += 1;
-        } else {
-            self.current_vk_count = 0;
-        }
-        self.prev.copy_from_slice(current);
-        result
+    fn clipboard_text(&self) -> Option<String> {
+        get_clipboard_string().ok()
     }
 }
 
@@ -70,20 +37,22 @@ pub enum OnMenuInputResult {
     Decide(u8, String),
 }
 
-#[derive(Debug, Setters)]
+#[derive(Debug)]
 pub struct TextInput {
     changed_action: u8,
     name: &'static str,
-    #[getset(set = "pub")]
+    /// 文字数の上限
+    max_len: usize,
     value: String,
     state: Option<TextInputState>,
 }
 
 impl TextInput {
-    pub fn new(changed_action: u8, name: &'static str) -> Self {
+    pub fn new(changed_action: u8, name: &'static str, max_len: usize) -> Self {
         Self {
             changed_action,
             name,
+            max_len,
             value: String::new(),
             state: None,
         }
@@ -93,37 +62,35 @@ impl TextInput {
         &self.value
     }
 
-    fn state_mut(&mut self) -> &mut TextInputState {
-        self.state.as_mut().unwrap()
+    pub fn set_value(&mut self, value: String) {
+        self.value = value.chars().take(self.max_len).collect();
+        if let Some(state) = &mut self.state {
+            state.reset_cursor(&self.value);
+        }
     }
 
     pub fn on_input_menu(&mut self, th19: &Th19) -> OnMenuInputResult {
-        if self.state.is_none() {
-            self.state = Some(TextInputState::new(
-                th19.input_devices().keyboard_input().raw_keys(),
-            ));
+        let keys = th19.input_devices().keyboard_input().raw_keys();
+        let Some(state) = &mut self.state else {
+            self.state = Some(TextInputState::new(keys, &self.value, self.max_len));
             return OnMenuInputResult::None;
-        }
-        for ascii in self
-            .state_mut()
-            .tick(th19.input_devices().keyboard_input().raw_keys())
-        {
-            if (0x20..0x7f).contains(&ascii) {
-                self.value.push(ascii as char);
-            } else if ascii == 0x08 {
-                self.value.pop();
-            } else if ascii == 0x0d {
-                // CR
-                return OnMenuInputResult::Decide(self.changed_action, self.value.clone());
-            } else if ascii == 0x1b {
-                // ESC
-                return OnMenuInputResult::Cancel;
+        };
+        match state.tick(keys, &mut self.value, &Win32Backend) {
+            TickResult::None => OnMenuInputResult::None,
+            TickResult::Decide => {
+                OnMenuInputResult::Decide(self.changed_action, self.value.clone())
             }
+            TickResult::Cancel => OnMenuInputResult::Cancel,
         }
-        OnMenuInputResult::None
     }
 
     pub fn on_render_texts(&self, th19: &Th19, text_renderer: *const c_void) {
-        render_label_value(th19, text_renderer, 480, 0, self.name, &self.value);
+        let cursor = self
+            .state
+            .as_ref()
+            .map(|state| state.cursor())
+            .unwrap_or(self.value.len());
+        let value = format!("{}|{}", &self.value[..cursor], &self.value[cursor..]);
+        render_label_value(th19, text_renderer, 480, 0, self.name, &value);
     }
 }
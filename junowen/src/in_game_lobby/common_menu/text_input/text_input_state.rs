@@ -0,0 +1,355 @@
+const VK_BACK: u8 = 0x08;
+const VK_RETURN: u8 = 0x0d;
+const VK_CONTROL: u8 = 0x11;
+const VK_ESCAPE: u8 = 0x1b;
+const VK_END: u8 = 0x23;
+const VK_HOME: u8 = 0x24;
+const VK_LEFT: u8 = 0x25;
+const VK_RIGHT: u8 = 0x27;
+const VK_DELETE: u8 = 0x2e;
+const VK_V: u8 = 0x56;
+
+/// キーを押し続けてからリピートが始まるまでのフレーム数
+const REPEAT_DELAY_FRAMES: u32 = 30;
+
+/** キーから文字への変換とクリップボードを提供する。Win32 の呼び出しをここに閉じ込める */
+pub trait TextInputBackend {
+    /// 仮想キーを押したときに入力される文字列
+    fn to_text(&self, vk: u8, keys: &[u8; 256]) -> String;
+    fn clipboard_text(&self) -> Option<String>;
+}
+
+#[derive(Debug, PartialEq)]
+pub enum TickResult {
+    None,
+    Decide,
+    Cancel,
+}
+
+fn is_pressed(keys: &[u8; 256], vk: u8) -> bool {
+    keys[vk as usize] & 0x80 != 0
+}
+
+/**
+ * キーボードの状態を毎フレーム受け取り、文字列を編集する
+ *
+ * cursor は value のバイト位置で、常に文字の境界にある
+ */
+#[derive(Debug)]
+pub struct TextInputState {
+    prev: [u8; 256],
+    current_vk: u8,
+    current_vk_count: u32,
+    cursor: usize,
+    max_len: usize,
+}
+
+impl TextInputState {
+    pub fn new(current: &[u8; 256], value: &str, max_len: usize) -> Self {
+        Self {
+            prev: *current,
+            current_vk: 0,
+            current_vk_count: 0,
+            cursor: value.len(),
+            max_len,
+        }
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// 外から値を置き換えたときに呼ぶ
+    pub fn reset_cursor(&mut self, value: &str) {
+        self.cursor = value.len();
+    }
+
+    /// 新しく押されたキーと、押し続けてリピートしているキー
+    fn triggered_keys(&mut self, current: &[u8; 256]) -> Vec<u8> {
+        let mut keys = vec![];
+        for vk in 0..=u8::MAX {
+            if !is_pressed(current, vk) || is_pressed(&self.prev, vk) {
+                continue;
+            }
+            keys.push(vk);
+            if vk != self.current_vk {
+                self.current_vk = vk;
+                self.current_vk_count = 0;
+            }
+        }
+        if is_pressed(current, self.current_vk) {
+            if self.current_vk_count > REPEAT_DELAY_FRAMES {
+                keys.push(self.current_vk);
+            }
+            self.current_vk_count += 1;
+        } else {
+            self.current_vk_count = 0;
+        }
+        self.prev.copy_from_slice(current);
+        keys
+    }
+
+    pub fn tick(
+        &mut self,
+        current: &[u8; 256],
+        value: &mut String,
+        backend: &impl TextInputBackend,
+    ) -> TickResult {
+        for vk in self.triggered_keys(current) {
+            match vk {
+                VK_RETURN => return TickResult::Decide,
+                VK_ESCAPE => return TickResult::Cancel,
+                VK_BACK => self.backspace(value),
+                VK_DELETE => self.delete(value),
+                VK_LEFT => self.move_left(value),
+                VK_RIGHT => self.move_right(value),
+                VK_HOME => self.cursor = 0,
+                VK_END => self.cursor = value.len(),
+                VK_V if is_pressed(current, VK_CONTROL) => {
+                    if let Some(text) = backend.clipboard_text() {
+                        // 複数行なら最初の行だけ使う
+                        self.insert(value, text.lines().next().unwrap_or_default());
+                    }
+                }
+                _ => self.insert(value, &backend.to_text(vk, current)),
+            }
+        }
+        TickResult::None
+    }
+
+    fn insert(&mut self, value: &mut String, text: &str) {
+        for c in text.chars().filter(|c| !c.is_control()) {
+            if value.chars().count() >= self.max_len {
+                return;
+            }
+            value.insert(self.cursor, c);
+            self.cursor += c.len_utf8();
+        }
+    }
+
+    fn backspace(&mut self, value: &mut String) {
+        let Some(c) = value[..self.cursor].chars().next_back() else {
+            return;
+        };
+        self.cursor -= c.len_utf8();
+        value.remove(self.cursor);
+    }
+
+    fn delete(&mut self, value: &mut String) {
+        if self.cursor < value.len() {
+            value.remove(self.cursor);
+        }
+    }
+
+    fn move_left(&mut self, value: &str) {
+        if let Some(c) = value[..self.cursor].chars().next_back() {
+            self.cursor -= c.len_utf8();
+        }
+    }
+
+    fn move_right(&mut self, value: &str) {
+        if let Some(c) = value[self.cursor..].chars().next() {
+            self.cursor += c.len_utf8();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VK_A: u8 = 0x41;
+    const VK_B: u8 = 0x42;
+    /// テストでは全角の「あ」を入力するキーとして扱う
+    const VK_1: u8 = 0x31;
+    const VK_TAB: u8 = 0x09;
+
+    struct FakeBackend {
+        clipboard: Option<String>,
+    }
+
+    impl TextInputBackend for FakeBackend {
+        fn to_text(&self, vk: u8, _keys: &[u8; 256]) -> String {
+            match vk {
+                VK_A => "a".to_owned(),
+                VK_B => "b".to_owned(),
+                VK_1 => "あ".to_owned(),
+                VK_TAB => "\t".to_owned(),
+                _ => String::new(),
+            }
+        }
+
+        fn clipboard_text(&self) -> Option<String> {
+            self.clipboard.clone()
+        }
+    }
+
+    fn keys(pressed: &[u8]) -> [u8; 256] {
+        let mut keys = [0; 256];
+        for &vk in pressed {
+            keys[vk as usize] = 0x80;
+        }
+        keys
+    }
+
+    struct Editor {
+        state: TextInputState,
+        value: String,
+        backend: FakeBackend,
+    }
+
+    impl Editor {
+        fn new(value: &str, max_len: usize) -> Self {
+            Self {
+                state: TextInputState::new(&keys(&[]), value, max_len),
+                value: value.to_owned(),
+                backend: FakeBackend { clipboard: None },
+            }
+        }
+
+        fn tick(&mut self, pressed: &[u8]) -> TickResult {
+            self.state
+                .tick(&keys(pressed), &mut self.value, &self.backend)
+        }
+
+        /// 押して離す
+        fn press(&mut self, pressed: &[u8]) -> TickResult {
+            let result = self.tick(pressed);
+            self.tick(&[]);
+            result
+        }
+    }
+
+    #[test]
+    fn types_at_cursor() {
+        let mut editor = Editor::new("", 10);
+        editor.press(&[VK_A]);
+        editor.press(&[VK_1]);
+        editor.press(&[VK_B]);
+        assert_eq!(editor.value, "aあb");
+        assert_eq!(editor.state.cursor(), "aあb".len());
+
+        editor.press(&[VK_LEFT]);
+        editor.press(&[VK_LEFT]);
+        editor.press(&[VK_A]);
+        assert_eq!(editor.value, "aaあb");
+        assert_eq!(editor.state.cursor(), 2);
+    }
+
+    #[test]
+    fn backspace_and_delete_multi_byte_characters() {
+        let mut editor = Editor::new("aあいb", 10);
+        editor.press(&[VK_LEFT]);
+        editor.press(&[VK_BACK]);
+        assert_eq!(editor.value, "aあb");
+        assert_eq!(editor.state.cursor(), "aあ".len());
+
+        editor.press(&[VK_HOME]);
+        editor.press(&[VK_RIGHT]);
+        editor.press(&[VK_DELETE]);
+        assert_eq!(editor.value, "ab");
+        assert_eq!(editor.state.cursor(), 1);
+
+        editor.press(&[VK_HOME]);
+        editor.press(&[VK_BACK]);
+        assert_eq!(editor.value, "ab");
+        editor.press(&[VK_END]);
+        editor.press(&[VK_DELETE]);
+        assert_eq!(editor.value, "ab");
+    }
+
+    #[test]
+    fn cursor_stays_on_character_boundaries() {
+        let mut editor = Editor::new("あい", 10);
+        assert_eq!(editor.state.cursor(), 6);
+        editor.press(&[VK_RIGHT]);
+        assert_eq!(editor.state.cursor(), 6);
+        editor.press(&[VK_LEFT]);
+        assert_eq!(editor.state.cursor(), 3);
+        editor.press(&[VK_HOME]);
+        assert_eq!(editor.state.cursor(), 0);
+        editor.press(&[VK_LEFT]);
+        assert_eq!(editor.state.cursor(), 0);
+        editor.press(&[VK_RIGHT]);
+        assert_eq!(editor.state.cursor(), 3);
+        editor.press(&[VK_END]);
+        assert_eq!(editor.state.cursor(), 6);
+    }
+
+    #[test]
+    fn limits_length_in_characters() {
+        let mut editor = Editor::new("ああ", 3);
+        editor.press(&[VK_A]);
+        editor.press(&[VK_B]);
+        assert_eq!(editor.value, "ああa");
+
+        editor.backend.clipboard = Some("xyz".to_owned());
+        editor.press(&[VK_BACK]);
+        editor.press(&[VK_CONTROL, VK_V]);
+        assert_eq!(editor.value, "ああx");
+    }
+
+    #[test]
+    fn pastes_only_the_first_line() {
+        let mut editor = Editor::new("a", 10);
+        editor.backend.clipboard = Some("bc\r\nde\n".to_owned());
+        editor.press(&[VK_CONTROL, VK_V]);
+        assert_eq!(editor.value, "abc");
+        assert_eq!(editor.state.cursor(), 3);
+
+        editor.backend.clipboard = Some("\t\u{7}f".to_owned());
+        editor.press(&[VK_CONTROL, VK_V]);
+        assert_eq!(editor.value, "abcf");
+
+        // Ctrl を押していなければ貼り付けない
+        editor.press(&[VK_V]);
+        assert_eq!(editor.value, "abcf");
+    }
+
+    #[test]
+    fn ignores_control_characters() {
+        let mut editor = Editor::new("", 10);
+        editor.press(&[VK_TAB]);
+        assert_eq!(editor.value, "");
+    }
+
+    #[test]
+    fn repeats_held_keys_after_a_delay() {
+        let mut editor = Editor::new("", 100);
+        for _ in 0..=REPEAT_DELAY_FRAMES {
+            editor.tick(&[VK_A]);
+        }
+        assert_eq!(editor.value, "a");
+        editor.tick(&[VK_A]);
+        assert_eq!(editor.value, "aa");
+        editor.tick(&[VK_A]);
+        assert_eq!(editor.value, "aaa");
+
+        // 離したらリピートをやめ、また押したら最初から数える
+        editor.tick(&[]);
+        editor.tick(&[VK_A]);
+        editor.tick(&[VK_A]);
+        assert_eq!(editor.value, "aaaa");
+    }
+
+    #[test]
+    fn repeats_only_the_last_pressed_key() {
+        let mut editor = Editor::new("", 100);
+        for _ in 0..=REPEAT_DELAY_FRAMES {
+            editor.tick(&[VK_A]);
+        }
+        editor.tick(&[VK_A, VK_B]);
+        assert_eq!(editor.value, "ab");
+        editor.tick(&[VK_A, VK_B]);
+        assert_eq!(editor.value, "ab");
+    }
+
+    #[test]
+    fn decides_and_cancels() {
+        let mut editor = Editor::new("a", 10);
+        assert_eq!(editor.press(&[VK_RETURN]), TickResult::Decide);
+        assert_eq!(editor.press(&[VK_ESCAPE]), TickResult::Cancel);
+        assert_eq!(editor.press(&[VK_B]), TickResult::None);
+        assert_eq!(editor.value, "ab");
+    }
+}
@@ -0,0 +1,103 @@
+use std::ffi::c_void;
+
+use junowen_lib::{
+    delayed_inputs::MAX_DELAY,
+    structs::{input_devices::InputValue, others::RenderingText},
+    Th19,
+};
+
+use crate::lang::tr;
+
+use super::common_menu::{CommonMenu, LobbyScene, Menu, MenuItem, OnMenuInputResult};
+
+const BASE_HEIGHT: u32 = 200;
+const MAX_JITTER: u8 = 3;
+
+const ENABLED: u8 = 0;
+const DELAY: u8 = 1;
+const JITTER: u8 = 2;
+
+fn make_menu() -> CommonMenu {
+    let items = vec![
+        MenuItem::plain("Enabled", ENABLED, true),
+        MenuItem::plain("Delay", DELAY, true),
+        MenuItem::plain("Jitter", JITTER, true),
+    ];
+    CommonMenu::new(
+        false,
+        BASE_HEIGHT,
+        Menu::new("Delay Practice", None, items, 0),
+    )
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct DelayPracticeSettings {
+    pub delay: u8,
+    /// ディレイを delay から delay + jitter の範囲で揺らす
+    pub jitter: u8,
+}
+
+/** ゲームのメニューから始めたローカルの対戦に、ネット対戦と同じディレイをかける */
+pub struct DelayPractice {
+    menu: CommonMenu,
+    enabled: bool,
+    settings: DelayPracticeSettings,
+}
+
+impl DelayPractice {
+    pub fn new() -> Self {
+        Self {
+            menu: make_menu(),
+            enabled: false,
+            settings: DelayPracticeSettings {
+                delay: 2,
+                jitter: 0,
+            },
+        }
+    }
+
+    pub fn settings(&self) -> Option<DelayPracticeSettings> {
+        self.enabled.then_some(self.settings)
+    }
+
+    pub fn on_input_menu(
+        &mut self,
+        current_input: InputValue,
+        prev_input: InputValue,
+        th19: &Th19,
+    ) -> Option<LobbyScene> {
+        match self.menu.on_input_menu(current_input, prev_input, th19) {
+            OnMenuInputResult::None => None,
+            OnMenuInputResult::Cancel => Some(LobbyScene::Root),
+            OnMenuInputResult::SubScene(_) => unreachable!(),
+            OnMenuInputResult::Action(action) => {
+                match action.id() {
+                    ENABLED => self.enabled = !self.enabled,
+                    DELAY => self.settings.delay = self.settings.delay % MAX_DELAY + 1,
+                    JITTER => self.settings.jitter = (self.settings.jitter + 1) % (MAX_JITTER + 1),
+                    _ => unreachable!(),
+                }
+                None
+            }
+        }
+    }
+
+    pub fn on_render_texts(&self, th19: &Th19, text_renderer: *const c_void) {
+        self.menu.on_render_texts(th19, text_renderer);
+        let mut rt = RenderingText::default();
+        rt.color = 0xffffffa0;
+        rt.font_type = 0;
+        rt.horizontal_align = 1;
+        rt.set_x(912, th19.window_inner());
+        let values = [
+            tr(if self.enabled { "On" } else { "Off" }).to_owned(),
+            self.settings.delay.to_string(),
+            self.settings.jitter.to_string(),
+        ];
+        for (i, value) in values.iter().enumerate() {
+            rt.set_str(value);
+            rt.set_y(BASE_HEIGHT + 56 * i as u32, th19.window_inner());
+            th19.render_text(text_renderer, &rt);
+        }
+    }
+}
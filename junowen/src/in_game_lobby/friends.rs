@@ -0,0 +1,279 @@
+use std::ffi::c_void;
+
+use clipboard_win::{get_clipboard_string, set_clipboard_string};
+use junowen_lib::{
+    identity::{fingerprint, from_hex, to_hex, Identity},
+    structs::{input_devices::InputValue, others::RenderingText},
+    Th19,
+};
+use tracing::info;
+
+use crate::{
+    known_players::{Friend, KnownPlayers},
+    lang::tr_format,
+    opponent_history::OpponentHistory,
+    signaling::{
+        invitation::{Invitation, InvitationInbox},
+        waiting_for_match::{WaitingForMatch, WaitingForOpponentInReservedRoom},
+    },
+};
+
+use super::{
+    common_menu::{CommonMenu, LobbyScene, Menu, MenuItem, OnMenuInputResult},
+    helper::render_text_line,
+    invitation_flow::{leave_menu, InvitationFlow, INVITATION_TIMEOUT},
+};
+
+const BASE_HEIGHT: u32 = 200;
+
+const FRIEND: u8 = 0;
+const INVITE: u8 = 1;
+const LEAVE: u8 = 2;
+const ACCEPT: u8 = 3;
+const DECLINE: u8 = 4;
+const ADD: u8 = 5;
+const COPY: u8 = 6;
+const REMOVE: u8 = 7;
+
+/// フレンドがいるときだけ選べる項目の位置
+const FRIEND_ITEMS: [usize; 3] = [0, 1, 6];
+/// 招待を受け取っているときだけ選べる項目の位置
+const INVITATION_ITEMS: [usize; 2] = [2, 3];
+
+fn make_menu() -> CommonMenu {
+    let items = vec![
+        MenuItem::plain("Friend", FRIEND, true),
+        MenuItem::sub_menu("Invite", Some(INVITE), leave_menu("Friends", LEAVE)),
+        MenuItem::sub_menu(
+            "Accept Invitation",
+            Some(ACCEPT),
+            leave_menu("Friends", LEAVE),
+        ),
+        MenuItem::plain("Decline Invitation", DECLINE, true),
+        MenuItem::plain("Add Friend from Clipboard", ADD, true),
+        MenuItem::plain("Copy My Friend Code", COPY, true),
+        MenuItem::plain("Remove Friend", REMOVE, true),
+    ];
+    CommonMenu::new(false, BASE_HEIGHT, Menu::new("Friends", None, items, 4))
+}
+
+/** フレンドを公開鍵で登録し、シグナリングサーバー経由で招待する */
+pub struct Friends {
+    menu: CommonMenu,
+    identity: Identity,
+    known_players: KnownPlayers,
+    opponent_history: OpponentHistory,
+    friends: Vec<Friend>,
+    selected: usize,
+    inbox: InvitationInbox,
+    invitation: Option<Invitation>,
+    flow: InvitationFlow,
+    message: Option<String>,
+}
+
+impl Friends {
+    pub fn new(
+        identity: Identity,
+        known_players: KnownPlayers,
+        opponent_history: OpponentHistory,
+    ) -> Self {
+        let mut zelf = Self {
+            menu: make_menu(),
+            inbox: InvitationInbox::new(identity.clone()),
+            identity,
+            friends: known_players.friends(),
+            known_players,
+            opponent_history,
+            selected: 0,
+            invitation: None,
+            flow: InvitationFlow::default(),
+            message: None,
+        };
+        zelf.update_menu_items();
+        zelf
+    }
+
+    /// ロビーで待ち受けていない間に呼ぶ。フレンドと最近の対戦相手以外からの招待は無視する
+    pub fn poll_invitation(&mut self) {
+        if self
+            .invitation
+            .as_ref()
+            .is_some_and(|invitation| invitation.received_at().elapsed() > INVITATION_TIMEOUT)
+        {
+            self.invitation = None;
+        }
+        let Some(invitation) = self.inbox.update() else {
+            return;
+        };
+        let from = invitation.from();
+        let accepted = self.known_players.is_friend(from)
+            || (!self.known_players.is_blocked(from) && self.opponent_history.contains(from));
+        if !accepted {
+            info!("ignored an invitation from {}", invitation.from_name());
+            return;
+        }
+        self.invitation = Some(invitation);
+    }
+
+    fn reload_friends(&mut self) {
+        self.friends = self.known_players.friends();
+        self.selected = self.selected.min(self.friends.len().saturating_sub(1));
+    }
+
+    fn update_menu_items(&mut self) {
+        let has_friends = !self.friends.is_empty();
+        let has_invitation = self.invitation.is_some();
+        let items = self.menu.menu_mut().items_mut();
+        for &i in &FRIEND_ITEMS {
+            items[i].set_enabled(has_friends);
+        }
+        for &i in &INVITATION_ITEMS {
+            items[i].set_enabled(has_invitation);
+        }
+    }
+
+    fn add_friend_from_clipboard(&mut self) -> bool {
+        let Ok(code) = get_clipboard_string() else {
+            return false;
+        };
+        let Ok(public_key) = from_hex(&code) else {
+            return false;
+        };
+        if public_key == self.identity.public_key() {
+            return false;
+        }
+        if !self.known_players.set_friend(&public_key, true) {
+            return false;
+        }
+        self.reload_friends();
+        if let Some(i) = self
+            .friends
+            .iter()
+            .position(|friend| friend.public_key == public_key)
+        {
+            self.selected = i;
+        }
+        self.message = Some(tr_format("Added {}.", &[&fingerprint(&public_key)]));
+        true
+    }
+
+    pub fn on_input_menu(
+        &mut self,
+        current_input: InputValue,
+        prev_input: InputValue,
+        th19: &Th19,
+        waiting: &mut Option<WaitingForMatch>,
+    ) -> Option<LobbyScene> {
+        if let Some(message) = self.flow.update(&mut self.menu, waiting) {
+            self.message = Some(message);
+        }
+        self.update_menu_items();
+
+        match self.menu.on_input_menu(current_input, prev_input, th19) {
+            OnMenuInputResult::None => None,
+            OnMenuInputResult::Cancel => {
+                self.flow.cancel(waiting);
+                Some(LobbyScene::Root)
+            }
+            OnMenuInputResult::SubScene(_) => unreachable!(),
+            OnMenuInputResult::Action(action) => {
+                if action.id() != LEAVE {
+                    self.message = None;
+                }
+                match action.id() {
+                    FRIEND => {
+                        if !self.friends.is_empty() {
+                            self.selected = (self.selected + 1) % self.friends.len();
+                        }
+                    }
+                    INVITE => match self.friends.get(self.selected) {
+                        Some(friend) => {
+                            self.flow
+                                .send(self.identity.clone(), friend.public_key, th19)
+                        }
+                        None => self.flow.enter(None, waiting),
+                    },
+                    LEAVE => self.flow.leave(&mut self.menu, th19, waiting),
+                    ACCEPT => {
+                        let invitation = self.invitation.take();
+                        let room_name = invitation.map(|x| x.room_name().clone());
+                        self.flow.enter(room_name, waiting);
+                    }
+                    DECLINE => {
+                        self.invitation = None;
+                    }
+                    ADD => {
+                        if !self.add_friend_from_clipboard() {
+                            th19.play_sound(th19.sound_manager(), 0x10, 0);
+                        }
+                    }
+                    COPY => {
+                        set_clipboard_string(&to_hex(&self.identity.public_key())).unwrap();
+                        self.message = Some("Your friend code was copied to Clipboard.".to_owned());
+                    }
+                    REMOVE => {
+                        if let Some(friend) = self.friends.get(self.selected) {
+                            if !self.known_players.set_friend(&friend.public_key, false) {
+                                th19.play_sound(th19.sound_manager(), 0x10, 0);
+                            }
+                            self.reload_friends();
+                        }
+                    }
+                    _ => unreachable!(),
+                }
+                None
+            }
+        }
+    }
+
+    /// ルートメニューでも招待に気付けるようにする
+    pub fn render_invitation_notice(&self, th19: &Th19, text_renderer: *const c_void) {
+        let Some(invitation) = &self.invitation else {
+            return;
+        };
+        let msg = tr_format(
+            "Invitation from {} (open Friends to answer)",
+            &[&invitation.from_name()],
+        );
+        render_text_line(th19, text_renderer, 21, &msg);
+    }
+
+    pub fn on_render_texts(
+        &self,
+        waiting: Option<&WaitingForOpponentInReservedRoom>,
+        th19: &Th19,
+        text_renderer: *const c_void,
+    ) {
+        if self
+            .flow
+            .on_render_texts(&self.menu, waiting, th19, text_renderer)
+        {
+            return;
+        }
+        self.menu.on_render_texts(th19, text_renderer);
+
+        let mut rt = RenderingText::default();
+        rt.color = 0xffffffa0;
+        rt.font_type = 0;
+        rt.horizontal_align = 1;
+        rt.set_x(912, th19.window_inner());
+        if let Some(friend) = self.friends.get(self.selected) {
+            rt.set_str(&friend.label());
+            rt.set_y(BASE_HEIGHT, th19.window_inner());
+            th19.render_text(text_renderer, &rt);
+        }
+        if let Some(invitation) = &self.invitation {
+            let from = format!(
+                "{} [{}]",
+                invitation.from_name(),
+                fingerprint(invitation.from())
+            );
+            rt.set_str(&from);
+            rt.set_y(BASE_HEIGHT + 56 * 2, th19.window_inner());
+            th19.render_text(text_renderer, &rt);
+        }
+        if let Some(message) = &self.message {
+            render_text_line(th19, text_renderer, 13, message);
+        }
+    }
+}
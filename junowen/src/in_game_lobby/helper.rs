@@ -2,9 +2,11 @@ use std::ffi::c_void;
 
 use junowen_lib::{structs::others::RenderingText, Th19};
 
-pub fn render_title(th19: &Th19, text_renderer: *const c_void, text: &[u8]) {
+use crate::lang::{pad_end, tr};
+
+pub fn render_title(th19: &Th19, text_renderer: *const c_void, text: &str) {
     let mut rt = RenderingText::default();
-    rt.set_text(text);
+    rt.set_str(tr(text));
     rt.set_x(640, th19.window_inner());
     rt.set_y(64, th19.window_inner());
     rt.color = 0xff000000;
@@ -21,13 +23,13 @@ pub fn render_title(th19: &Th19, text_renderer: *const c_void, text: &[u8]) {
 pub fn render_menu_item(
     th19: &Th19,
     text_renderer: *const c_void,
-    text: &[u8],
+    text: &str,
     y: u32,
     enabled: bool,
     selected: bool,
 ) {
     let mut rt = RenderingText::default();
-    rt.set_text(text);
+    rt.set_str(tr(text));
     rt.set_x(640, th19.window_inner());
     rt.set_y(y, th19.window_inner());
     rt.color = menu_item_color(9, enabled, selected);
@@ -40,9 +42,9 @@ pub fn render_menu_item(
     th19.render_text(text_renderer, &rt);
 }
 
-pub fn render_text_line(th19: &Th19, text_renderer: *const c_void, line: u32, text: &[u8]) {
+pub fn render_text_line(th19: &Th19, text_renderer: *const c_void, line: u32, text: &str) {
     let mut rt = RenderingText::default();
-    rt.set_text(text);
+    rt.set_str(tr(text));
     rt.set_x(32, th19.window_inner());
     rt.set_y(160 + line * 32, th19.window_inner());
     rt.color = 0xff000000;
@@ -94,7 +96,7 @@ pub fn render_label_value(
     value: &str,
 ) {
     let mut rt = RenderingText::default();
-    rt.set_text(format!("{:<11}:", label).as_bytes());
+    rt.set_str(&format!("{}:", pad_end(tr(label), 11)));
     rt.set_x(320, th19.window_inner());
     rt.set_y(height, th19.window_inner());
     rt.color = 0xffffffff;
@@ -103,7 +105,7 @@ pub fn render_label_value(
     rt.vertical_align = vertical_align;
     th19.render_text(text_renderer, &rt);
 
-    rt.set_text(value.as_bytes());
+    rt.set_str(value);
     rt.color = 0xffffffa0;
     rt.set_x(544, th19.window_inner());
     th19.render_text(text_renderer, &rt);
@@ -0,0 +1,158 @@
+use std::{ffi::c_void, time::Duration};
+
+use junowen_lib::{
+    identity::{Identity, PublicKey},
+    Th19,
+};
+
+use crate::{
+    lang::tr_format,
+    signaling::{
+        invitation::SendingInvitation,
+        waiting_for_match::{
+            WaitingForMatch, WaitingForOpponent, WaitingForOpponentInReservedRoom,
+        },
+    },
+};
+
+use super::{
+    common_menu::{CommonMenu, Menu, MenuItem},
+    helper::render_text_line,
+    room::on_render_texts,
+};
+
+/// 招待した側が部屋で待っていると見込める時間。招待された側もこの時間を過ぎた招待は捨てる
+pub const INVITATION_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// 部屋で待っている間のサブメニュー
+pub fn leave_menu(title: &'static str, leave: u8) -> Menu {
+    Menu::new(
+        title,
+        Some(leave),
+        vec![MenuItem::plain("Leave", leave, false)],
+        0,
+    )
+}
+
+/**
+ * 招待を送るか受けるかして、予約部屋で相手を待つ流れ
+ *
+ * 送信中、部屋で待っている間、送信に失敗したときや相手が来なかったときにサブメニューから戻す処理をまとめる
+ */
+#[derive(Default)]
+pub struct InvitationFlow {
+    sending: Option<SendingInvitation>,
+    /// 自分が送った招待の部屋で待っている
+    inviting: bool,
+    /// サブメニューに入っている
+    entered: bool,
+}
+
+impl InvitationFlow {
+    /// 招待を送り、届いたらその部屋で待つ
+    pub fn send(&mut self, identity: Identity, to: PublicKey, th19: &Th19) {
+        self.entered = true;
+        self.inviting = true;
+        self.sending = Some(SendingInvitation::new(
+            identity,
+            to,
+            th19.vs_mode().player_name().to_owned(),
+        ));
+    }
+
+    /// 受けた招待や前回の部屋に入る。room_name が無ければサブメニューに入ってすぐに戻す
+    pub fn enter(&mut self, room_name: Option<String>, waiting: &mut Option<WaitingForMatch>) {
+        self.entered = true;
+        self.inviting = false;
+        if let Some(room_name) = room_name {
+            *waiting = Some(WaitingForMatch::Opponent(WaitingForOpponent::ReservedRoom(
+                WaitingForOpponentInReservedRoom::new(room_name),
+            )));
+        }
+    }
+
+    /// メニューを閉じるときに呼ぶ
+    pub fn cancel(&mut self, waiting: &mut Option<WaitingForMatch>) {
+        self.entered = false;
+        self.inviting = false;
+        self.sending = None;
+        *waiting = None;
+    }
+
+    /// サブメニューの Leave を選んだときに呼ぶ
+    pub fn leave(
+        &mut self,
+        menu: &mut CommonMenu,
+        th19: &Th19,
+        waiting: &mut Option<WaitingForMatch>,
+    ) {
+        self.cancel(waiting);
+        th19.play_sound(th19.sound_manager(), 0x09, 0);
+        menu.controller_mut().force_cancel();
+    }
+
+    /**
+     * メニューの入力を処理する前に毎フレーム呼ぶ
+     *
+     * 表示すべきメッセージがあれば返す
+     */
+    pub fn update(
+        &mut self,
+        menu: &mut CommonMenu,
+        waiting: &mut Option<WaitingForMatch>,
+    ) -> Option<String> {
+        let mut message = None;
+        if let Some(result) = self.sending.as_mut().and_then(|sending| sending.try_recv()) {
+            self.sending = None;
+            match result {
+                Ok(room_name) => {
+                    *waiting = Some(WaitingForMatch::Opponent(WaitingForOpponent::ReservedRoom(
+                        WaitingForOpponentInReservedRoom::new(room_name),
+                    )));
+                }
+                Err(err) => message = Some(tr_format("Failed: {}", &[&err])),
+            }
+        }
+        match waiting {
+            Some(WaitingForMatch::Opponent(WaitingForOpponent::ReservedRoom(room))) => {
+                room.recv();
+                // 相手は INVITATION_TIMEOUT を過ぎた招待を捨てるので、それ以上は待たない
+                if self.inviting
+                    && room.acceptance().is_none()
+                    && room.elapsed() > INVITATION_TIMEOUT
+                {
+                    *waiting = None;
+                    message = Some("No response to the invitation.".to_owned());
+                }
+            }
+            _ => {
+                *waiting = None;
+            }
+        }
+        // 招待の送信に失敗した場合などは、サブメニューに入ってから戻す
+        if self.entered && waiting.is_none() && self.sending.is_none() && menu.menu().decided() {
+            self.entered = false;
+            self.inviting = false;
+            assert!(menu.menu_mut().bury());
+        }
+        message
+    }
+
+    /// サブメニューに入っている間の表示。入っていなければ false を返し、何もしない
+    pub fn on_render_texts(
+        &self,
+        menu: &CommonMenu,
+        waiting: Option<&WaitingForOpponentInReservedRoom>,
+        th19: &Th19,
+        text_renderer: *const c_void,
+    ) -> bool {
+        if !menu.menu().decided() {
+            return false;
+        }
+        on_render_texts(menu, waiting, None, th19, text_renderer);
+        if self.sending.is_some() {
+            render_text_line(th19, text_renderer, 13, "Sending an invitation...");
+        }
+        true
+    }
+}
@@ -2,12 +2,16 @@ use std::ffi::c_void;
 
 use getset::{Getters, MutGetters};
 use junowen_lib::{
+    identity::Identity,
     structs::input_devices::{InputFlags, InputValue},
     Th19,
 };
 
 use crate::{
-    file::SettingsRepo,
+    file::{RulePreset, SettingsRepo},
+    known_players::KnownPlayers,
+    lang::tr_format,
+    opponent_history::OpponentHistory,
     session::{battle::BattleSession, spectator::SpectatorSession},
     signaling::waiting_for_match::{
         WaitingForMatch, WaitingForOpponent, WaitingForOpponentInReservedRoom,
@@ -17,18 +21,25 @@ use crate::{
 
 use super::{
     common_menu::{CommonMenu, LobbyScene, Menu, MenuItem, OnMenuInputResult},
+    delay_practice::{DelayPractice, DelayPracticeSettings},
+    friends::Friends,
+    helper::render_text_line,
+    match_rules::MatchRules,
     pure_p2p_guest::PureP2pGuest,
     pure_p2p_offerer::{pure_p2p_host, pure_p2p_spectator, PureP2pOfferer},
+    recent_opponents::RecentOpponents,
     room::{reserved::ReservedRoom, shared::SharedRoom},
 };
 
 pub struct Root {
     common_menu: CommonMenu,
+    /// 自分の鍵の指紋
+    fingerprint: Option<String>,
 }
 
 impl Root {
-    pub fn new() -> Self {
-        let menu = Menu::new(
+    pub fn new(fingerprint: Option<String>) -> Self {
+        let mut menu = Menu::new(
             "Ju.N.Owen",
             None,
             vec![
@@ -51,11 +62,18 @@ impl Root {
                         0,
                     ),
                 ),
+                MenuItem::sub_scene("Match Rules", LobbyScene::MatchRules),
+                MenuItem::sub_scene("Friends", LobbyScene::Friends),
+                MenuItem::sub_scene("Recent Opponents", LobbyScene::RecentOpponents),
+                MenuItem::sub_scene("Delay Practice", LobbyScene::DelayPractice),
             ],
             0,
         );
+        // フレンドの招待には鍵が必要
+        menu.items_mut()[4].set_enabled(fingerprint.is_some());
         Self {
             common_menu: CommonMenu::new(true, 240, menu),
+            fingerprint,
         }
     }
 
@@ -81,6 +99,10 @@ impl Root {
 
     pub fn on_render_texts(&self, th19: &Th19, text_renderer: *const c_void) {
         self.common_menu.on_render_texts(th19, text_renderer);
+        if let Some(fingerprint) = &self.fingerprint {
+            let msg = tr_format("Your fingerprint: {}", &[fingerprint]);
+            render_text_line(th19, text_renderer, 22, &msg);
+        }
     }
 }
 
@@ -92,6 +114,11 @@ pub struct Lobby {
     root: Root,
     shared_room: SharedRoom,
     reserved_room: ReservedRoom,
+    match_rules: MatchRules,
+    /// identity 機能が無効なら None
+    friends: Option<Friends>,
+    recent_opponents: RecentOpponents,
+    delay_practice: DelayPractice,
     pure_p2p_host: Option<PureP2pOfferer<BattleSession>>,
     pure_p2p_guest: Option<PureP2pGuest>,
     pure_p2p_spectator: Option<PureP2pOfferer<SpectatorSession>>,
@@ -101,15 +128,31 @@ pub struct Lobby {
 }
 
 impl Lobby {
-    pub fn new(settings_repo: SettingsRepo) -> Self {
+    pub fn new(
+        settings_repo: SettingsRepo,
+        rule_presets: Vec<RulePreset>,
+        rule_preset: Option<&str>,
+        identity: Option<Identity>,
+        known_players: KnownPlayers,
+        opponent_history: OpponentHistory,
+    ) -> Self {
         Self {
             settings_repo,
             scene: LobbyScene::Root,
             prev_scene: LobbyScene::Root,
-            root: Root::new(),
+            root: Root::new(identity.as_ref().map(|identity| identity.fingerprint())),
             waiting_for_match: None,
             shared_room: SharedRoom::new(),
             reserved_room: ReservedRoom::new(),
+            match_rules: MatchRules::new(rule_presets, rule_preset),
+            recent_opponents: RecentOpponents::new(
+                identity.clone(),
+                known_players.clone(),
+                opponent_history.clone(),
+            ),
+            friends: identity
+                .map(|identity| Friends::new(identity, known_players, opponent_history)),
+            delay_practice: DelayPractice::new(),
             pure_p2p_host: None,
             pure_p2p_guest: None,
             pure_p2p_spectator: None,
@@ -117,6 +160,16 @@ impl Lobby {
         }
     }
 
+    /// ホストとして MatchInitial に使うルール。None ならゲームのメニューの設定を使う
+    pub fn rule_preset(&self) -> Option<&RulePreset> {
+        self.match_rules.selected_preset()
+    }
+
+    /// 無効なら None
+    pub fn delay_practice(&self) -> Option<DelayPracticeSettings> {
+        self.delay_practice.settings()
+    }
+
     pub fn reset_depth(&mut self) {
         // self.scene = LobbyScene::Root;
         self.prev_input = InputValue::full();
@@ -127,6 +180,12 @@ impl Lobby {
         let current_input = th19.menu_input().current();
         th19.menu_input_mut().set_current(InputValue::empty());
 
+        if self.waiting_for_match.is_none() {
+            if let Some(friends) = &mut self.friends {
+                friends.poll_invitation();
+            }
+        }
+
         if let Some(scene) = match self.scene {
             LobbyScene::Root => self
                 .root
@@ -157,6 +216,28 @@ impl Lobby {
                 th19,
                 &mut self.waiting_for_match,
             ),
+            LobbyScene::MatchRules => self.match_rules.on_input_menu(
+                &self.settings_repo,
+                current_input,
+                self.prev_input,
+                th19,
+            ),
+            LobbyScene::Friends => self.friends.as_mut().unwrap().on_input_menu(
+                current_input,
+                self.prev_input,
+                th19,
+                &mut self.waiting_for_match,
+            ),
+            LobbyScene::RecentOpponents => self.recent_opponents.on_input_menu(
+                current_input,
+                self.prev_input,
+                th19,
+                &mut self.waiting_for_match,
+            ),
+            LobbyScene::DelayPractice => {
+                self.delay_practice
+                    .on_input_menu(current_input, self.prev_input, th19)
+            }
             LobbyScene::PureP2pHost => {
                 if self.pure_p2p_host.is_none() {
                     self.waiting_for_match = None;
@@ -218,6 +299,9 @@ impl Lobby {
                 ret
             }
         } {
+            if matches!(scene, LobbyScene::RecentOpponents) {
+                self.recent_opponents.reload();
+            }
             self.scene = scene;
             self.prev_input = InputValue::full();
         } else {
@@ -227,7 +311,12 @@ impl Lobby {
 
     pub fn on_render_texts(&self, th19: &Th19, text_renderer: *const c_void) {
         match self.prev_scene {
-            LobbyScene::Root => self.root.on_render_texts(th19, text_renderer),
+            LobbyScene::Root => {
+                self.root.on_render_texts(th19, text_renderer);
+                if let Some(friends) = &self.friends {
+                    friends.render_invitation_notice(th19, text_renderer);
+                }
+            }
             LobbyScene::SharedRoom => {
                 let waiting = self.waiting_for_match.as_ref().and_then(|x| match x {
                     WaitingForMatch::Opponent(WaitingForOpponent::SharedRoom(waiting)) => {
@@ -255,6 +344,30 @@ impl Lobby {
                         .on_render_texts(none, th19, text_renderer);
                 }
             },
+            LobbyScene::MatchRules => self.match_rules.on_render_texts(th19, text_renderer),
+            LobbyScene::Friends => {
+                let waiting = self.waiting_for_match.as_ref().and_then(|x| match x {
+                    WaitingForMatch::Opponent(WaitingForOpponent::ReservedRoom(waiting)) => {
+                        Some(waiting)
+                    }
+                    _ => None,
+                });
+                self.friends
+                    .as_ref()
+                    .unwrap()
+                    .on_render_texts(waiting, th19, text_renderer);
+            }
+            LobbyScene::RecentOpponents => {
+                let waiting = self.waiting_for_match.as_ref().and_then(|x| match x {
+                    WaitingForMatch::Opponent(WaitingForOpponent::ReservedRoom(waiting)) => {
+                        Some(waiting)
+                    }
+                    _ => None,
+                });
+                self.recent_opponents
+                    .on_render_texts(waiting, th19, text_renderer);
+            }
+            LobbyScene::DelayPractice => self.delay_practice.on_render_texts(th19, text_renderer),
             LobbyScene::PureP2pHost => self
                 .pure_p2p_host
                 .as_ref()
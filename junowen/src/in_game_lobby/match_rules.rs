@@ -0,0 +1,255 @@
+use std::ffi::c_void;
+
+use junowen_lib::{
+    structs::{input_devices::InputValue, others::RenderingText},
+    Th19,
+};
+
+use crate::{
+    file::{RulePreset, SettingsRepo},
+    lang::tr,
+    TOKIO_RUNTIME,
+};
+
+use super::common_menu::{CommonMenu, LobbyScene, Menu, MenuItem, OnMenuInputResult};
+
+const BASE_HEIGHT: u32 = 200;
+const MAX_PRESETS: usize = 8;
+const MAX_PRESET_NAME_LEN: usize = 16;
+/// ライフは 1 から 5 の範囲で切り替える
+const LIFE_VALUES: u32 = 5;
+
+const SELECT: u8 = 0;
+const EDIT: u8 = 1;
+const TIME_LIMIT: u8 = 2;
+const ROUND: u8 = 3;
+const ABILITY_CARD: u8 = 4;
+const P1_LIFE: u8 = 5;
+const P1_BARRIER: u8 = 6;
+const P2_LIFE: u8 = 7;
+const P2_BARRIER: u8 = 8;
+const ADD: u8 = 9;
+const DELETE: u8 = 10;
+const RENAME_DECIDED: u8 = 11;
+const RENAME_CHANGED: u8 = 12;
+
+/// プリセットがあるときだけ選べる項目の位置
+const EDITING_ITEMS: [usize; 10] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 11];
+const ADD_ITEM: usize = 10;
+
+fn make_menu() -> CommonMenu {
+    let items = vec![
+        MenuItem::plain("Use for Matches", SELECT, true),
+        MenuItem::plain("Edit Preset", EDIT, true),
+        MenuItem::text_input(
+            "Change Preset Name",
+            RENAME_DECIDED,
+            RENAME_CHANGED,
+            "Name",
+            MAX_PRESET_NAME_LEN,
+        ),
+        MenuItem::plain("Time Limit", TIME_LIMIT, true),
+        MenuItem::plain("Round", ROUND, true),
+        MenuItem::plain("Ability Card", ABILITY_CARD, true),
+        MenuItem::plain("P1 Life", P1_LIFE, true),
+        MenuItem::plain("P1 Barrier", P1_BARRIER, true),
+        MenuItem::plain("P2 Life", P2_LIFE, true),
+        MenuItem::plain("P2 Barrier", P2_BARRIER, true),
+        MenuItem::plain("Add Preset", ADD, true),
+        MenuItem::plain("Delete Preset", DELETE, true),
+    ];
+    CommonMenu::new(false, BASE_HEIGHT, Menu::new("Match Rules", None, items, 0))
+}
+
+fn next<T: TryFrom<u8>>(value: u8, count: u8) -> T {
+    match T::try_from((value + 1) % count) {
+        Ok(value) => value,
+        Err(_) => unreachable!(),
+    }
+}
+
+fn unique_name(presets: &[RulePreset]) -> String {
+    (1..)
+        .map(|i| format!("Preset {}", i))
+        .find(|name| presets.iter().all(|preset| &preset.name != name))
+        .unwrap()
+}
+
+/** ホストとして MatchInitial に使うルールのプリセットを編集、選択する */
+pub struct MatchRules {
+    menu: CommonMenu,
+    presets: Vec<RulePreset>,
+    /// 対戦に使うプリセット。None ならゲームのメニューの設定を使う
+    selected: Option<usize>,
+    editing: usize,
+}
+
+impl MatchRules {
+    pub fn new(presets: Vec<RulePreset>, selected_name: Option<&str>) -> Self {
+        let selected =
+            selected_name.and_then(|name| presets.iter().position(|preset| preset.name == name));
+        let mut zelf = Self {
+            menu: make_menu(),
+            presets,
+            selected,
+            editing: selected.unwrap_or_default(),
+        };
+        zelf.update_menu_items();
+        zelf
+    }
+
+    pub fn selected_preset(&self) -> Option<&RulePreset> {
+        self.selected.map(|i| &self.presets[i])
+    }
+
+    fn update_menu_items(&mut self) {
+        let has_presets = !self.presets.is_empty();
+        let items = self.menu.menu_mut().items_mut();
+        for &i in &EDITING_ITEMS {
+            items[i].set_enabled(has_presets);
+        }
+        items[ADD_ITEM].set_enabled(self.presets.len() < MAX_PRESETS);
+    }
+
+    fn save(&self, settings_repo: &SettingsRepo) {
+        TOKIO_RUNTIME.block_on(settings_repo.set_rule_presets(
+            &self.presets,
+            self.selected_preset().map(|preset| preset.name.as_str()),
+        ));
+    }
+
+    fn edit(&mut self, id: u8) {
+        let settings = &mut self.presets[self.editing].game_settings;
+        match id {
+            TIME_LIMIT => settings.set_time_limit(next(settings.time_limit() as u8, 7)),
+            ROUND => settings.set_round(next(settings.round() as u8, 3)),
+            ABILITY_CARD => settings.set_ability_card(next(settings.ability_card() as u8, 4)),
+            P1_LIFE => settings.set_p1_life((settings.p1_life() + 1) % LIFE_VALUES),
+            P1_BARRIER => settings.set_p1_barrier(next(settings.p1_barrier() as u8, 4)),
+            P2_LIFE => settings.set_p2_life((settings.p2_life() + 1) % LIFE_VALUES),
+            P2_BARRIER => settings.set_p2_barrier(next(settings.p2_barrier() as u8, 4)),
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn on_input_menu(
+        &mut self,
+        settings_repo: &SettingsRepo,
+        current_input: InputValue,
+        prev_input: InputValue,
+        th19: &Th19,
+    ) -> Option<LobbyScene> {
+        match self.menu.on_input_menu(current_input, prev_input, th19) {
+            OnMenuInputResult::None => None,
+            OnMenuInputResult::Cancel => Some(LobbyScene::Root),
+            OnMenuInputResult::SubScene(_) => unreachable!(),
+            OnMenuInputResult::Action(action) => {
+                // 削除などで無効になった項目にカーソルが残っている場合は無視する
+                if !self.menu.menu().selected_item().enabled() {
+                    return None;
+                }
+                match action.id() {
+                    SELECT => {
+                        self.selected = match self.selected {
+                            None if !self.presets.is_empty() => Some(0),
+                            None => None,
+                            Some(i) if i + 1 < self.presets.len() => Some(i + 1),
+                            Some(_) => None,
+                        };
+                        if let Some(selected) = self.selected {
+                            self.editing = selected;
+                        }
+                    }
+                    EDIT => {
+                        self.editing = (self.editing + 1) % self.presets.len();
+                        return None;
+                    }
+                    id @ TIME_LIMIT..=P2_BARRIER => self.edit(id),
+                    ADD => {
+                        // 新しいプリセットはゲームのメニューの設定から始める
+                        let game_settings = th19.game_settings_in_menu().unwrap_or_default();
+                        let name = unique_name(&self.presets);
+                        self.presets.push(RulePreset::new(name, game_settings));
+                        self.editing = self.presets.len() - 1;
+                        self.update_menu_items();
+                    }
+                    DELETE => {
+                        self.presets.remove(self.editing);
+                        self.selected = match self.selected {
+                            Some(i) if i == self.editing => None,
+                            Some(i) if i > self.editing => Some(i - 1),
+                            selected => selected,
+                        };
+                        self.editing = self.editing.min(self.presets.len().saturating_sub(1));
+                        self.update_menu_items();
+                    }
+                    RENAME_DECIDED => {
+                        let name = self.presets[self.editing].name.clone();
+                        let MenuItem::TextInput(text_input_item) =
+                            self.menu.menu_mut().selected_item_mut()
+                        else {
+                            unreachable!()
+                        };
+                        text_input_item.text_input_mut().set_value(name);
+                        return None;
+                    }
+                    RENAME_CHANGED => {
+                        let name = action.value().unwrap().trim().to_owned();
+                        let duplicated = self
+                            .presets
+                            .iter()
+                            .enumerate()
+                            .any(|(i, preset)| i != self.editing && preset.name == name);
+                        if name.is_empty() || duplicated {
+                            th19.play_sound(th19.sound_manager(), 0x10, 0);
+                            return None;
+                        }
+                        self.presets[self.editing].name = name;
+                    }
+                    _ => unreachable!(),
+                }
+                self.save(settings_repo);
+                None
+            }
+        }
+    }
+
+    fn values(&self) -> Vec<(usize, String)> {
+        let selected = self
+            .selected_preset()
+            .map(|preset| preset.name.clone())
+            .unwrap_or_else(|| tr("(Online VS menu)").to_owned());
+        let Some(editing) = self.presets.get(self.editing) else {
+            return vec![(0, selected)];
+        };
+        let settings = &editing.game_settings;
+        vec![
+            (0, selected),
+            (1, editing.name.clone()),
+            (3, settings.time_limit().to_string()),
+            (4, settings.round().to_string()),
+            (5, settings.ability_card().to_string()),
+            (6, (settings.p1_life() + 1).to_string()),
+            (7, settings.p1_barrier().to_string()),
+            (8, (settings.p2_life() + 1).to_string()),
+            (9, settings.p2_barrier().to_string()),
+        ]
+    }
+
+    pub fn on_render_texts(&self, th19: &Th19, text_renderer: *const c_void) {
+        self.menu.on_render_texts(th19, text_renderer);
+        if self.menu.menu().decided() {
+            return;
+        }
+        let mut rt = RenderingText::default();
+        rt.color = 0xffffffa0;
+        rt.font_type = 0;
+        rt.horizontal_align = 1;
+        rt.set_x(912, th19.window_inner());
+        for (i, value) in self.values() {
+            rt.set_str(&value);
+            rt.set_y(BASE_HEIGHT + 56 * i as u32, th19.window_inner());
+            th19.render_text(text_renderer, &rt);
+        }
+    }
+}
@@ -142,7 +142,7 @@ impl PureP2pGuest {
 
         let mut line = 0;
         'a: {
-            render_text_line(th19, text_renderer, line, b"Host's signaling code:");
+            render_text_line(th19, text_renderer, line, "Host's signaling code:");
             line += 2;
             let Some(offer) = self.offer.as_ref() else {
                 break 'a;
@@ -153,7 +153,7 @@ impl PureP2pGuest {
                 render_small_text_line(th19, text_renderer, line * 2 + i as u32, chunk);
             });
             line += offer_len + 1;
-            render_text_line(th19, text_renderer, line, b"Your signaling code:");
+            render_text_line(th19, text_renderer, line, "Your signaling code:");
             let Some(answer) = &self.signaling.answer() else {
                 break 'a;
             };
@@ -165,19 +165,19 @@ impl PureP2pGuest {
                 render_small_text_line(th19, text_renderer, line * 2 + i as u32, chunk);
             });
             line += answer_len + 1;
-            render_text_line(th19, text_renderer, line, b"It was copied to Clipboard.");
+            render_text_line(th19, text_renderer, line, "It was copied to Clipboard.");
             render_text_line(
                 th19,
                 text_renderer,
                 line + 1,
-                b"Share your signaling code with host.",
+                "Share your signaling code with host.",
             );
             line += 3;
-            render_text_line(th19, text_renderer, line, b"Waiting for host to connect...");
+            render_text_line(th19, text_renderer, line, "Waiting for host to connect...");
         }
         if let Some(err) = self.signaling.error() {
             line += 2;
-            render_text_line(th19, text_renderer, line, err.to_string().as_bytes());
+            render_text_line(th19, text_renderer, line, &err.to_string());
         }
     }
 
@@ -157,7 +157,7 @@ where
         let mut line = 0;
         'a: {
             let Some(offer) = &self.signaling.offer() else {
-                render_text_line(th19, text_renderer, 0, b"Preparing...");
+                render_text_line(th19, text_renderer, 0, "Preparing...");
                 break 'a;
             };
             let text = if [2, 3].contains(&self.copy_state) {
@@ -165,7 +165,7 @@ where
             } else {
                 "Your signaling code:"
             };
-            render_text_line(th19, text_renderer, line, text.as_bytes());
+            render_text_line(th19, text_renderer, line, text);
             line += 2;
             let offer = self.offer_type.to_string(offer);
             let chunks = offer.as_bytes().chunks(100);
@@ -175,12 +175,12 @@ where
             });
             line += offer_len + 1;
             if [1, 3].contains(&self.copy_state) {
-                render_text_line(th19, text_renderer, line, b"It was copied to Clipboard.");
-                let text = self.messages[0].as_bytes();
+                render_text_line(th19, text_renderer, line, "It was copied to Clipboard.");
+                let text = self.messages[0];
                 render_text_line(th19, text_renderer, line + 1, text);
             }
             line += 3;
-            render_text_line(th19, text_renderer, line, self.messages[1].as_bytes());
+            render_text_line(th19, text_renderer, line, self.messages[1]);
             let Some(answer) = &self.answer else {
                 break 'a;
             };
@@ -191,12 +191,12 @@ where
                 render_small_text_line(th19, text_renderer, line * 2 + i as u32, chunk);
             });
             line += answer_len + 1;
-            let text = self.messages[2].as_bytes();
+            let text = self.messages[2];
             render_text_line(th19, text_renderer, line, text);
         }
         if let Some(err) = self.signaling.error() {
             line += 1;
-            render_text_line(th19, text_renderer, line, err.to_string().as_bytes());
+            render_text_line(th19, text_renderer, line, &err.to_string());
         }
     }
 
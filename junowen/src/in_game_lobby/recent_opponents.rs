@@ -0,0 +1,255 @@
+use std::ffi::c_void;
+
+use junowen_lib::{
+    identity::Identity,
+    structs::{input_devices::InputValue, others::RenderingText},
+    Th19,
+};
+
+use crate::{
+    known_players::KnownPlayers,
+    lang::{tr, tr_format},
+    opponent_history::{OpponentHistory, OpponentRoom, RecentOpponent},
+    signaling::waiting_for_match::{WaitingForMatch, WaitingForOpponentInReservedRoom},
+};
+
+use super::{
+    common_menu::{CommonMenu, LobbyScene, Menu, MenuItem, OnMenuInputResult},
+    helper::render_text_line,
+    invitation_flow::{leave_menu, InvitationFlow},
+};
+
+const BASE_HEIGHT: u32 = 240;
+
+const OPPONENT: u8 = 0;
+const REMATCH: u8 = 1;
+const LEAVE: u8 = 2;
+const ENTER_ROOM: u8 = 3;
+const BLOCK: u8 = 4;
+
+fn make_menu() -> CommonMenu {
+    let items = vec![
+        MenuItem::plain("Opponent", OPPONENT, true),
+        MenuItem::sub_menu(
+            "Send Rematch Invitation",
+            Some(REMATCH),
+            leave_menu("Recent Opponents", LEAVE),
+        ),
+        MenuItem::sub_menu(
+            "Enter the Same Room",
+            Some(ENTER_ROOM),
+            leave_menu("Recent Opponents", LEAVE),
+        ),
+        MenuItem::plain("Block", BLOCK, true),
+    ];
+    CommonMenu::new(
+        false,
+        BASE_HEIGHT,
+        Menu::new("Recent Opponents", None, items, 0),
+    )
+}
+
+/**
+ * 最近の対戦相手を選び、同じ専有ルームに入り直すか再戦の招待を送る
+ *
+ * 招待とブロックは相手の鍵を照合できた場合だけできる
+ */
+pub struct RecentOpponents {
+    menu: CommonMenu,
+    /// identity 機能が無効なら None
+    identity: Option<Identity>,
+    known_players: KnownPlayers,
+    opponent_history: OpponentHistory,
+    opponents: Vec<RecentOpponent>,
+    selected: usize,
+    /// 選択中の相手をブロックしているか。毎フレームファイルを読まないように保持する
+    blocked: bool,
+    flow: InvitationFlow,
+    message: Option<String>,
+}
+
+impl RecentOpponents {
+    pub fn new(
+        identity: Option<Identity>,
+        known_players: KnownPlayers,
+        opponent_history: OpponentHistory,
+    ) -> Self {
+        Self {
+            menu: make_menu(),
+            identity,
+            known_players,
+            opponents: vec![],
+            opponent_history,
+            selected: 0,
+            blocked: false,
+            flow: InvitationFlow::default(),
+            message: None,
+        }
+    }
+
+    /// シーンに入るときに呼ぶ
+    pub fn reload(&mut self) {
+        self.opponents = self.opponent_history.opponents();
+        self.selected = 0;
+        self.update_blocked();
+    }
+
+    fn update_blocked(&mut self) {
+        self.blocked = self
+            .selected_opponent()
+            .and_then(|x| x.public_key())
+            .is_some_and(|x| self.known_players.is_blocked(&x));
+    }
+
+    fn selected_opponent(&self) -> Option<&RecentOpponent> {
+        self.opponents.get(self.selected)
+    }
+
+    fn update_menu_items(&mut self) {
+        let opponent = self.selected_opponent();
+        let has_opponents = opponent.is_some();
+        let public_key = opponent.and_then(|x| x.public_key());
+        let blocked = self.blocked;
+        let can_invite = self.identity.is_some() && public_key.is_some() && !blocked;
+        let has_reserved_room =
+            opponent.is_some_and(|x| matches!(x.room, OpponentRoom::Reserved(_)));
+        let items = self.menu.menu_mut().items_mut();
+        items[0].set_enabled(has_opponents);
+        items[1].set_enabled(can_invite);
+        items[2].set_enabled(has_reserved_room);
+        items[3].set_enabled(public_key.is_some());
+        items[3].set_label(if blocked { "Unblock" } else { "Block" });
+    }
+
+    fn toggle_blocked(&mut self, th19: &Th19) {
+        let Some(opponent) = self.selected_opponent() else {
+            return;
+        };
+        let Some(public_key) = opponent.public_key() else {
+            return;
+        };
+        let name = opponent.name.clone();
+        let blocked = !self.blocked;
+        if !self.known_players.set_blocked(&public_key, blocked) {
+            th19.play_sound(th19.sound_manager(), 0x10, 0);
+            return;
+        }
+        self.blocked = blocked;
+        self.message = Some(if blocked {
+            tr_format("Blocked {}.", &[&name])
+        } else {
+            tr_format("Unblocked {}.", &[&name])
+        });
+    }
+
+    pub fn on_input_menu(
+        &mut self,
+        current_input: InputValue,
+        prev_input: InputValue,
+        th19: &Th19,
+        waiting: &mut Option<WaitingForMatch>,
+    ) -> Option<LobbyScene> {
+        if let Some(message) = self.flow.update(&mut self.menu, waiting) {
+            self.message = Some(message);
+        }
+        self.update_menu_items();
+
+        match self.menu.on_input_menu(current_input, prev_input, th19) {
+            OnMenuInputResult::None => None,
+            OnMenuInputResult::Cancel => {
+                self.flow.cancel(waiting);
+                Some(LobbyScene::Root)
+            }
+            OnMenuInputResult::SubScene(_) => unreachable!(),
+            OnMenuInputResult::Action(action) => {
+                if action.id() != LEAVE {
+                    self.message = None;
+                }
+                match action.id() {
+                    OPPONENT => {
+                        if !self.opponents.is_empty() {
+                            self.selected = (self.selected + 1) % self.opponents.len();
+                        }
+                        self.update_blocked();
+                    }
+                    REMATCH => {
+                        let public_key = self.selected_opponent().and_then(|x| x.public_key());
+                        match (&self.identity, public_key) {
+                            (Some(identity), Some(public_key)) => {
+                                self.flow.send(identity.clone(), public_key, th19)
+                            }
+                            _ => self.flow.enter(None, waiting),
+                        }
+                    }
+                    LEAVE => self.flow.leave(&mut self.menu, th19, waiting),
+                    ENTER_ROOM => {
+                        let room_name = match self.selected_opponent().map(|x| &x.room) {
+                            Some(OpponentRoom::Reserved(room_name)) => Some(room_name.clone()),
+                            _ => None,
+                        };
+                        self.flow.enter(room_name, waiting);
+                    }
+                    BLOCK => self.toggle_blocked(th19),
+                    _ => unreachable!(),
+                }
+                None
+            }
+        }
+    }
+
+    fn render_details(&self, th19: &Th19, text_renderer: *const c_void) {
+        let Some(opponent) = self.selected_opponent() else {
+            return;
+        };
+        let played_at = tr_format("Last played: {}", &[&opponent.played_at_label()]);
+        render_text_line(th19, text_renderer, 13, &played_at);
+        let room = match &opponent.room {
+            OpponentRoom::Shared(room_name) => tr_format("Shared Room: {}", &[room_name]),
+            OpponentRoom::Reserved(room_name) => tr_format("Reserved Room: {}", &[room_name]),
+            OpponentRoom::PureP2p => tr("Pure P2P").to_owned(),
+        };
+        render_text_line(th19, text_renderer, 14, &room);
+        let results = match opponent.set_score {
+            Some((wins, losses)) => tr_format(
+                "Matches: {}  Set: {}-{}",
+                &[&opponent.matches, &wins, &losses],
+            ),
+            None => tr_format("Matches: {}", &[&opponent.matches]),
+        };
+        render_text_line(th19, text_renderer, 15, &results);
+    }
+
+    pub fn on_render_texts(
+        &self,
+        waiting: Option<&WaitingForOpponentInReservedRoom>,
+        th19: &Th19,
+        text_renderer: *const c_void,
+    ) {
+        if self
+            .flow
+            .on_render_texts(&self.menu, waiting, th19, text_renderer)
+        {
+            return;
+        }
+        self.menu.on_render_texts(th19, text_renderer);
+
+        if let Some(opponent) = self.selected_opponent() {
+            let mut rt = RenderingText::default();
+            rt.color = 0xffffffa0;
+            rt.font_type = 0;
+            rt.horizontal_align = 1;
+            rt.set_x(912, th19.window_inner());
+            rt.set_y(BASE_HEIGHT, th19.window_inner());
+            if self.blocked {
+                rt.set_str(&tr_format("{} (blocked)", &[&opponent.name]));
+            } else {
+                rt.set_str(&opponent.name);
+            }
+            th19.render_text(text_renderer, &rt);
+        }
+        self.render_details(th19, text_renderer);
+        if let Some(message) = &self.message {
+            render_text_line(th19, text_renderer, 17, message);
+        }
+    }
+}
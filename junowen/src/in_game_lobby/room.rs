@@ -5,7 +5,12 @@ use std::{f64::consts::PI, ffi::c_void};
 
 use junowen_lib::{structs::others::RenderingText, Th19};
 
-use crate::signaling::waiting_for_match::WaitingInRoom;
+use crate::{
+    file::{RoomKind, SettingsRepo},
+    lang::tr_format,
+    signaling::waiting_for_match::WaitingInRoom,
+    TOKIO_RUNTIME,
+};
 
 use super::{
     common_menu::CommonMenu,
@@ -127,10 +132,120 @@ pub fn on_render_texts<T>(
         let elapsed = waiting.elapsed();
         render_progress(th19, text_renderer, elapsed.as_secs_f64() / 4.0);
         for (i, error) in waiting.errors().iter().rev().enumerate() {
-            let error_msg = format!("Failed: {}", error);
-            render_text_line(th19, text_renderer, 13 + i as u32, error_msg.as_bytes());
+            let error_msg = tr_format("Failed: {}", &[error]);
+            render_text_line(th19, text_renderer, 13 + i as u32, &error_msg);
         }
     } else if let Some(room_name) = room_name {
         render_label_value(th19, text_renderer, 240 - 56, 1, "Room name", room_name);
     }
 }
+
+/** お気に入りと最近使ったルーム名から選ぶ */
+pub struct SavedRooms {
+    kind: RoomKind,
+    favorites: Vec<String>,
+    history: Vec<String>,
+    selected: usize,
+}
+
+impl SavedRooms {
+    pub fn new(kind: RoomKind) -> Self {
+        Self {
+            kind,
+            favorites: vec![],
+            history: vec![],
+            selected: 0,
+        }
+    }
+
+    pub fn load(&mut self, settings_repo: &SettingsRepo) {
+        (self.favorites, self.history) =
+            TOKIO_RUNTIME.block_on(settings_repo.saved_room_names(self.kind));
+        self.selected = 0;
+    }
+
+    /// お気に入りを先に、残りを新しい順に並べる
+    fn names(&self) -> Vec<&String> {
+        let history = self
+            .history
+            .iter()
+            .filter(|name| !self.favorites.contains(name));
+        self.favorites.iter().chain(history).collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.favorites.is_empty() && self.history.is_empty()
+    }
+
+    pub fn selected(&self) -> Option<&str> {
+        self.names().get(self.selected).map(|name| name.as_str())
+    }
+
+    pub fn selected_is_favorite(&self) -> bool {
+        self.selected()
+            .is_some_and(|name| self.favorites.iter().any(|x| x == name))
+    }
+
+    pub fn select_next(&mut self) {
+        let len = self.names().len();
+        if len > 0 {
+            self.selected = (self.selected + 1) % len;
+        }
+    }
+
+    /// 並びが変わっても同じ名前を選んだままにする
+    fn reselect(&mut self, name: &str) {
+        self.selected = self
+            .names()
+            .iter()
+            .position(|x| x.as_str() == name)
+            .unwrap_or_default();
+    }
+
+    /// 入ったルームや設定したルーム名を最近使ったルーム名に加える
+    pub fn push(&mut self, settings_repo: &SettingsRepo, name: &str) {
+        let selected = self.selected().map(|name| name.to_owned());
+        self.history = TOKIO_RUNTIME.block_on(settings_repo.push_room_history(self.kind, name));
+        if let Some(selected) = selected {
+            self.reselect(&selected);
+        }
+    }
+
+    pub fn toggle_favorite(&mut self, settings_repo: &SettingsRepo) {
+        let Some(name) = self.selected().map(|name| name.to_owned()) else {
+            return;
+        };
+        let favorite = !self.selected_is_favorite();
+        self.favorites =
+            TOKIO_RUNTIME.block_on(settings_repo.set_room_favorite(self.kind, &name, favorite));
+        self.reselect(&name);
+    }
+
+    pub fn favorite_label(&self) -> &'static str {
+        if self.selected_is_favorite() {
+            "Remove from Favorites"
+        } else {
+            "Add to Favorites"
+        }
+    }
+
+    /// 選んでいる名前を項目の右に表示する。お気に入りには * を付ける
+    pub fn render_selected(&self, th19: &Th19, text_renderer: *const c_void, y: u32) {
+        let Some(name) = self.selected() else {
+            return;
+        };
+        let mark = if self.selected_is_favorite() {
+            "* "
+        } else {
+            ""
+        };
+        let mut rt = RenderingText::default();
+        rt.set_str(&format!("{}{}", mark, name));
+        rt.set_x(912, th19.window_inner());
+        rt.set_y(y, th19.window_inner());
+        rt.color = 0xffffffa0;
+        rt.font_type = 0;
+        rt.horizontal_align = 1;
+        th19.render_text(text_renderer, &rt);
+    }
+}
@@ -1,9 +1,11 @@
 use std::ffi::c_void;
 
-use junowen_lib::{structs::input_devices::InputValue, Th19};
+use junowen_lib::{
+    signaling_server::room::MAX_ROOM_NAME_LEN, structs::input_devices::InputValue, Th19,
+};
 
 use crate::{
-    file::SettingsRepo,
+    file::{RoomKind, SettingsRepo},
     signaling::waiting_for_match::{
         WaitingForMatch, WaitingForOpponent, WaitingForOpponentInReservedRoom,
         WaitingForSpectatorHost, WaitingForSpectatorHostInReservedRoom, WaitingInRoom,
@@ -13,9 +15,14 @@ use crate::{
 
 use super::{
     super::common_menu::{CommonMenu, LobbyScene, Menu, MenuItem, OnMenuInputResult},
-    on_render_texts,
+    on_render_texts, SavedRooms,
 };
 
+const BASE_HEIGHT: u32 = 240 + 56;
+
+/// 保存したルーム名があるときだけ選べる項目の位置
+const SAVED_ROOM_ITEMS: [usize; 3] = [3, 4, 5];
+
 fn make_menu() -> CommonMenu {
     let menu = Menu::new(
         "Reserved Room",
@@ -41,17 +48,21 @@ fn make_menu() -> CommonMenu {
                     0,
                 ),
             ),
-            MenuItem::text_input("Change Room Name", 11, 12, "Room name"),
+            MenuItem::text_input("Change Room Name", 11, 12, "Room name", MAX_ROOM_NAME_LEN),
+            MenuItem::plain("Saved Room", 13, true),
+            MenuItem::plain("Use Saved Room", 14, true),
+            MenuItem::plain("Add to Favorites", 15, true),
         ],
         0,
     );
-    CommonMenu::new(false, 240 + 56, menu)
+    CommonMenu::new(false, BASE_HEIGHT, menu)
 }
 
 pub struct ReservedRoom {
     menu: CommonMenu,
     enter: bool,
     room_name: Option<String>,
+    saved_rooms: SavedRooms,
 }
 
 impl ReservedRoom {
@@ -60,6 +71,7 @@ impl ReservedRoom {
             menu: make_menu(),
             enter: false,
             room_name: None,
+            saved_rooms: SavedRooms::new(RoomKind::Reserved),
         }
     }
 
@@ -67,6 +79,22 @@ impl ReservedRoom {
         self.room_name.as_ref().unwrap()
     }
 
+    fn update_menu_items(&mut self) {
+        let has_saved_rooms = !self.saved_rooms.is_empty();
+        let favorite_label = self.saved_rooms.favorite_label();
+        let items = self.menu.menu_mut().items_mut();
+        for &i in &SAVED_ROOM_ITEMS {
+            items[i].set_enabled(has_saved_rooms);
+        }
+        items[5].set_label(favorite_label);
+    }
+
+    fn set_room_name(&mut self, settings_repo: &SettingsRepo, room_name: String) {
+        self.saved_rooms.push(settings_repo, &room_name);
+        self.room_name = Some(room_name.clone());
+        TOKIO_RUNTIME.block_on(settings_repo.set_reserved_room_name(room_name));
+    }
+
     pub fn on_input_menu(
         &mut self,
         settings_repo: &SettingsRepo,
@@ -77,6 +105,7 @@ impl ReservedRoom {
     ) -> Option<LobbyScene> {
         if self.room_name.is_none() {
             self.room_name = Some(TOKIO_RUNTIME.block_on(settings_repo.reserved_room_name(th19)));
+            self.saved_rooms.load(settings_repo);
         }
         if waiting.is_none() && self.enter {
             self.enter = false;
@@ -95,6 +124,7 @@ impl ReservedRoom {
                 *waiting = None;
             }
         }
+        self.update_menu_items();
 
         match self.menu.on_input_menu(current_input, prev_input, th19) {
             OnMenuInputResult::None => None,
@@ -107,8 +137,10 @@ impl ReservedRoom {
             OnMenuInputResult::Action(action) => match action.id() {
                 0 => {
                     self.enter = true;
+                    let room_name = self.room_name().to_owned();
+                    self.saved_rooms.push(settings_repo, &room_name);
                     *waiting = Some(WaitingForMatch::Opponent(WaitingForOpponent::ReservedRoom(
-                        WaitingForOpponentInReservedRoom::new(self.room_name().to_owned()),
+                        WaitingForOpponentInReservedRoom::new(room_name),
                     )));
                     None
                 }
@@ -121,9 +153,11 @@ impl ReservedRoom {
                 }
                 3 => {
                     self.enter = true;
+                    let room_name = self.room_name().to_owned();
+                    self.saved_rooms.push(settings_repo, &room_name);
                     *waiting = Some(WaitingForMatch::SpectatorHost(
                         WaitingForSpectatorHost::ReservedRoom(
-                            WaitingForSpectatorHostInReservedRoom::new(self.room_name().to_owned()),
+                            WaitingForSpectatorHostInReservedRoom::new(room_name),
                         ),
                     ));
                     None
@@ -140,8 +174,22 @@ impl ReservedRoom {
                 }
                 12 => {
                     let new_room_name = action.value().unwrap().to_owned();
-                    self.room_name = Some(new_room_name.clone());
-                    TOKIO_RUNTIME.block_on(settings_repo.set_reserved_room_name(new_room_name));
+                    self.set_room_name(settings_repo, new_room_name);
+                    None
+                }
+                13 => {
+                    self.saved_rooms.select_next();
+                    None
+                }
+                14 => {
+                    if let Some(room_name) = self.saved_rooms.selected() {
+                        let room_name = room_name.to_owned();
+                        self.set_room_name(settings_repo, room_name);
+                    }
+                    None
+                }
+                15 => {
+                    self.saved_rooms.toggle_favorite(settings_repo);
                     None
                 }
                 _ => unreachable!(),
@@ -162,5 +210,9 @@ impl ReservedRoom {
             room_name = None;
         }
         on_render_texts(&self.menu, waiting, room_name, th19, text_renderer);
+        if !self.menu.menu().decided() {
+            self.saved_rooms
+                .render_selected(th19, text_renderer, BASE_HEIGHT + 56 * 3);
+        }
     }
 }
@@ -1,28 +1,43 @@
 use std::ffi::c_void;
 
-use junowen_lib::{structs::input_devices::InputValue, Th19};
+use junowen_lib::{
+    signaling_server::room::MAX_ROOM_NAME_LEN, structs::input_devices::InputValue, Th19,
+};
 
 use crate::{
-    file::SettingsRepo, signaling::waiting_for_match::WaitingForOpponentInSharedRoom, TOKIO_RUNTIME,
+    file::{RoomKind, SettingsRepo},
+    signaling::waiting_for_match::WaitingForOpponentInSharedRoom,
+    TOKIO_RUNTIME,
 };
 
 use super::{
     super::common_menu::{CommonMenu, LobbyScene, Menu, MenuItem, OnMenuInputResult},
-    on_render_texts,
+    on_render_texts, SavedRooms,
 };
 
+const BASE_HEIGHT: u32 = 240 + 56;
+
+/// ルームに入っていないときだけ選べる項目の位置
+const OUTSIDE_ITEMS: [usize; 4] = [1, 2, 3, 4];
+/// 保存したルーム名があるときだけ選べる項目の位置
+const SAVED_ROOM_ITEMS: [usize; 3] = [2, 3, 4];
+
 fn make_menu() -> CommonMenu {
     let items = vec![
         MenuItem::plain("Enter the Room", 0, true),
-        MenuItem::text_input("Change Room Name", 11, 12, "Room name"),
+        MenuItem::text_input("Change Room Name", 11, 12, "Room name", MAX_ROOM_NAME_LEN),
+        MenuItem::plain("Saved Room", 13, true),
+        MenuItem::plain("Use Saved Room", 14, true),
+        MenuItem::plain("Add to Favorites", 15, true),
     ];
-    CommonMenu::new(false, 240 + 56, Menu::new("Shared Room", None, items, 0))
+    CommonMenu::new(false, BASE_HEIGHT, Menu::new("Shared Room", None, items, 0))
 }
 
 pub struct SharedRoom {
     menu: CommonMenu,
     enter: bool,
     room_name: Option<String>,
+    saved_rooms: SavedRooms,
 }
 
 impl SharedRoom {
@@ -31,6 +46,7 @@ impl SharedRoom {
             menu: make_menu(),
             enter: false,
             room_name: None,
+            saved_rooms: SavedRooms::new(RoomKind::Shared),
         }
     }
 
@@ -42,15 +58,32 @@ impl SharedRoom {
         self.enter = false;
         let item = &mut self.menu.menu_mut().items_mut()[0];
         item.set_label("Enter the Room");
-        let item = &mut self.menu.menu_mut().items_mut()[1];
-        item.set_enabled(true);
     }
     fn change_menu_to_leave(&mut self) {
         self.enter = true;
         let item = &mut self.menu.menu_mut().items_mut()[0];
         item.set_label("Leave the Room");
-        let item = &mut self.menu.menu_mut().items_mut()[1];
-        item.set_enabled(false);
+    }
+
+    fn update_menu_items(&mut self) {
+        let has_saved_rooms = !self.saved_rooms.is_empty();
+        let favorite_label = self.saved_rooms.favorite_label();
+        let items = self.menu.menu_mut().items_mut();
+        for &i in &OUTSIDE_ITEMS {
+            items[i].set_enabled(!self.enter);
+        }
+        if !self.enter {
+            for &i in &SAVED_ROOM_ITEMS {
+                items[i].set_enabled(has_saved_rooms);
+            }
+        }
+        items[4].set_label(favorite_label);
+    }
+
+    fn set_room_name(&mut self, settings_repo: &SettingsRepo, room_name: String) {
+        self.saved_rooms.push(settings_repo, &room_name);
+        self.room_name = Some(room_name.clone());
+        TOKIO_RUNTIME.block_on(settings_repo.set_shared_room_name(room_name));
     }
 
     pub fn on_input_menu(
@@ -63,6 +96,7 @@ impl SharedRoom {
     ) -> Option<LobbyScene> {
         if self.room_name.is_none() {
             self.room_name = Some(TOKIO_RUNTIME.block_on(settings_repo.shared_room_name(th19)));
+            self.saved_rooms.load(settings_repo);
         }
         if waiting.is_some() != self.enter {
             if waiting.is_some() {
@@ -71,6 +105,7 @@ impl SharedRoom {
                 self.change_menu_to_enter();
             }
         }
+        self.update_menu_items();
 
         if let Some(waiting) = waiting {
             waiting.recv();
@@ -83,6 +118,7 @@ impl SharedRoom {
                 0 => {
                     if waiting.is_none() {
                         let room_name = self.room_name().to_owned();
+                        self.saved_rooms.push(settings_repo, &room_name);
                         *waiting = Some(WaitingForOpponentInSharedRoom::new(room_name));
                         self.change_menu_to_leave();
                     } else {
@@ -103,8 +139,22 @@ impl SharedRoom {
                 }
                 12 => {
                     let new_room_name = action.value().unwrap().to_owned();
-                    self.room_name = Some(new_room_name.clone());
-                    TOKIO_RUNTIME.block_on(settings_repo.set_shared_room_name(new_room_name));
+                    self.set_room_name(settings_repo, new_room_name);
+                    None
+                }
+                13 => {
+                    self.saved_rooms.select_next();
+                    None
+                }
+                14 => {
+                    if let Some(room_name) = self.saved_rooms.selected() {
+                        let room_name = room_name.to_owned();
+                        self.set_room_name(settings_repo, room_name);
+                    }
+                    None
+                }
+                15 => {
+                    self.saved_rooms.toggle_favorite(settings_repo);
                     None
                 }
                 _ => unreachable!(),
@@ -125,5 +175,9 @@ impl SharedRoom {
             th19,
             text_renderer,
         );
+        if waiting.is_none() {
+            self.saved_rooms
+                .render_selected(th19, text_renderer, BASE_HEIGHT + 56 * 2);
+        }
     }
 }
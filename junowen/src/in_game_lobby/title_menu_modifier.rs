@@ -48,7 +48,16 @@ impl TitleMenuModifier {
         }
     }
 
-    pub fn on_input_menu(&mut self, main_menu: &mut MainMenu, th19: &mut Th19) {
+    pub fn on_input_menu(&mut self, th19: &mut Th19) {
+        let menu_input = th19.menu_input();
+        let decide = menu_input.decide();
+        let up = direction(menu_input, InputFlags::UP);
+        let down = direction(menu_input, InputFlags::DOWN);
+        let main_menu = th19
+            .app_mut()
+            .main_loop_tasks_mut()
+            .find_main_menu_mut()
+            .unwrap();
         debug_assert_eq!(main_menu.screen_id(), ScreenId::Title);
         let menu = main_menu.menu_mut();
         if menu.num_disabled() > 0 {
@@ -62,26 +71,27 @@ impl TitleMenuModifier {
         }
         match (menu.cursor(), self.selected_junowen) {
             (2, false) => {
-                if direction(th19.menu_input(), InputFlags::DOWN) {
+                if down {
                     self.selected_junowen = true;
                     menu.set_cursor(1);
                 }
             }
             (2, true) => {
-                if th19.menu_input().decide() {
-                    th19.menu_input_mut().set_current(InputFlags::SHOT.into());
+                if decide {
                     menu.set_cursor(1);
+                    th19.menu_input_mut().set_current(InputFlags::SHOT.into());
+                    return;
                 }
-                if direction(th19.menu_input(), InputFlags::UP) {
+                if up {
                     self.selected_junowen = false;
                     menu.set_cursor(3);
                 }
-                if direction(th19.menu_input(), InputFlags::DOWN) {
+                if down {
                     self.selected_junowen = false;
                 }
             }
             (3, _) => {
-                if direction(th19.menu_input(), InputFlags::UP) {
+                if up {
                     self.selected_junowen = true;
                 }
             }
@@ -0,0 +1,317 @@
+use std::{fs, io::ErrorKind, path::PathBuf};
+
+use anyhow::Result;
+
+use derive_new::new;
+use junowen_lib::identity::{fingerprint, from_hex, to_hex, PublicKey};
+use toml_edit::{value, DocumentMut, Item, Table};
+use tracing::error;
+
+const PLAYERS: &str = "players";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PlayerTrust {
+    /// 初めて見る鍵
+    New,
+    Known,
+    /// 知っている鍵だが、前回と名前が違う
+    Renamed(String),
+    /// 初めて見る鍵だが、別の鍵で知っている名前を名乗っている。その鍵がフレンドなら true
+    NameConflict(bool),
+    /// blocked = true にされている
+    Blocked,
+}
+
+#[derive(Clone, Debug)]
+pub struct Friend {
+    pub public_key: PublicKey,
+    /// フレンドコードから追加してまだ対戦していない場合は None
+    pub name: Option<String>,
+}
+
+impl Friend {
+    pub fn label(&self) -> String {
+        self.name
+            .clone()
+            .unwrap_or_else(|| fingerprint(&self.public_key))
+    }
+}
+
+/**
+ * 対戦したことのあるプレイヤーの公開鍵と名前の一覧
+ *
+ * `[players.<公開鍵>]` に name と blocked と friend を記録する
+ */
+#[derive(Clone, new)]
+pub struct KnownPlayers {
+    path: PathBuf,
+}
+
+fn get_bool(player: &Item, key: &str) -> bool {
+    player.get(key).and_then(|x| x.as_bool()) == Some(true)
+}
+
+impl KnownPlayers {
+    /// ファイルが無ければ空、読めなければ Err
+    fn load(&self) -> Result<DocumentMut> {
+        match fs::read_to_string(&self.path) {
+            Ok(text) => Ok(text.parse()?),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(DocumentMut::new()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// 参照するだけなら、読めないファイルは空として扱う
+    fn read(&self) -> DocumentMut {
+        self.load().unwrap_or_else(|err| {
+            error!("invalid {}: {}", self.path.display(), err);
+            DocumentMut::new()
+        })
+    }
+
+    /**
+     * `[players]` を書き換えて保存する
+     *
+     * 読めないファイルを上書きすると記録した鍵が全て消えるので、その場合は f を呼ばずに false を返す
+     */
+    fn edit(&self, f: impl FnOnce(&mut Table)) -> bool {
+        let mut doc = match self.load() {
+            Ok(doc) => doc,
+            Err(err) => {
+                error!("invalid {}, not saved: {}", self.path.display(), err);
+                return false;
+            }
+        };
+        let Some(players) = Self::players_mut(&mut doc) else {
+            return false;
+        };
+        f(players);
+        if let Err(err) = fs::write(&self.path, doc.to_string()) {
+            error!("{}", err);
+        }
+        true
+    }
+
+    fn players_mut(doc: &mut DocumentMut) -> Option<&mut Table> {
+        if !doc.contains_key(PLAYERS) {
+            let mut players = Table::new();
+            players.set_implicit(true);
+            let _ = doc.insert(PLAYERS, Item::Table(players));
+        }
+        let players = doc[PLAYERS].as_table_mut();
+        if players.is_none() {
+            error!("invalid {}", PLAYERS);
+        }
+        players
+    }
+
+    fn player_mut<'a>(players: &'a mut Table, public_key: &PublicKey) -> &'a mut Item {
+        let player = players
+            .entry(&to_hex(public_key))
+            .or_insert_with(|| Item::Table(Table::new()));
+        if player.get("blocked").is_none() {
+            player["blocked"] = value(false);
+        }
+        player
+    }
+
+    fn player(&self, public_key: &PublicKey) -> Option<Table> {
+        self.read()
+            .get(PLAYERS)
+            .and_then(|players| players.get(to_hex(public_key)))
+            .and_then(|player| player.as_table())
+            .cloned()
+    }
+
+    pub fn friends(&self) -> Vec<Friend> {
+        let doc = self.read();
+        let Some(players) = doc.get(PLAYERS).and_then(|x| x.as_table()) else {
+            return vec![];
+        };
+        players
+            .iter()
+            .filter(|(_, player)| get_bool(player, "friend"))
+            .filter_map(|(key, player)| {
+                Some(Friend {
+                    public_key: from_hex(key).ok()?,
+                    name: player
+                        .get("name")
+                        .and_then(|x| x.as_str())
+                        .map(|x| x.to_owned()),
+                })
+            })
+            .collect()
+    }
+
+    /// ブロックしているプレイヤーはフレンドとして扱わない
+    pub fn is_friend(&self, public_key: &PublicKey) -> bool {
+        self.player(public_key).is_some_and(|player| {
+            let player = Item::Table(player);
+            get_bool(&player, "friend") && !get_bool(&player, "blocked")
+        })
+    }
+
+    pub fn is_blocked(&self, public_key: &PublicKey) -> bool {
+        self.player(public_key)
+            .is_some_and(|player| get_bool(&Item::Table(player), "blocked"))
+    }
+
+    /// 保存できなければ false
+    pub fn set_friend(&self, public_key: &PublicKey, friend: bool) -> bool {
+        self.edit(|players| {
+            Self::player_mut(players, public_key)["friend"] = value(friend);
+        })
+    }
+
+    /// 保存できなければ false
+    pub fn set_blocked(&self, public_key: &PublicKey, blocked: bool) -> bool {
+        self.edit(|players| {
+            Self::player_mut(players, public_key)["blocked"] = value(blocked);
+        })
+    }
+
+    fn check(players: &Table, public_key: &PublicKey, name: &str) -> PlayerTrust {
+        match players.get(&to_hex(public_key)) {
+            Some(player) if get_bool(player, "blocked") => PlayerTrust::Blocked,
+            Some(player) => match player.get("name").and_then(|x| x.as_str()) {
+                Some(old_name) if old_name != name => PlayerTrust::Renamed(old_name.to_owned()),
+                _ => PlayerTrust::Known,
+            },
+            None => {
+                let conflicted: Vec<_> = players
+                    .iter()
+                    .map(|(_, player)| player)
+                    .filter(|player| player.get("name").and_then(|x| x.as_str()) == Some(name))
+                    .collect();
+                if conflicted.is_empty() {
+                    PlayerTrust::New
+                } else {
+                    PlayerTrust::NameConflict(
+                        conflicted.iter().any(|player| {
+                            get_bool(player, "friend") && !get_bool(player, "blocked")
+                        }),
+                    )
+                }
+            }
+        }
+    }
+
+    /// 照合した上で今回の名前を記録する。ブロックしている相手は記録を変えない
+    pub fn check_and_remember(&self, public_key: &PublicKey, name: &str) -> PlayerTrust {
+        let mut trust = None;
+        self.edit(|players| {
+            let checked = Self::check(players, public_key, name);
+            if checked != PlayerTrust::Blocked {
+                Self::player_mut(players, public_key)["name"] = value(name);
+            }
+            trust = Some(checked);
+        });
+        trust.unwrap_or_else(|| {
+            // 保存できないファイルでも、読める範囲で照合する
+            let doc = self.read();
+            match doc.get(PLAYERS).and_then(|x| x.as_table()) {
+                Some(players) => Self::check(players, public_key, name),
+                None => PlayerTrust::New,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use junowen_lib::identity::Identity;
+
+    use super::*;
+
+    fn known_players(name: &str) -> KnownPlayers {
+        let path = env::temp_dir().join(format!(
+            "junowen-known-players-{}-{}.toml",
+            std::process::id(),
+            name
+        ));
+        let _ = fs::remove_file(&path);
+        KnownPlayers::new(path)
+    }
+
+    #[test]
+    fn remembers_names() {
+        let known_players = known_players("remembers_names");
+        let alice = Identity::generate().public_key();
+        assert_eq!(
+            known_players.check_and_remember(&alice, "alice"),
+            PlayerTrust::New
+        );
+        assert_eq!(
+            known_players.check_and_remember(&alice, "alice"),
+            PlayerTrust::Known
+        );
+        assert_eq!(
+            known_players.check_and_remember(&alice, "alice2"),
+            PlayerTrust::Renamed("alice".to_owned())
+        );
+        assert_eq!(
+            known_players.check_and_remember(&alice, "alice2"),
+            PlayerTrust::Known
+        );
+    }
+
+    #[test]
+    fn detects_name_conflicts() {
+        let known_players = known_players("detects_name_conflicts");
+        let alice = Identity::generate().public_key();
+        let bob = Identity::generate().public_key();
+        let mallory = Identity::generate().public_key();
+        known_players.check_and_remember(&alice, "alice");
+        known_players.check_and_remember(&bob, "bob");
+        assert!(known_players.set_friend(&bob, true));
+        assert_eq!(
+            known_players.check_and_remember(&mallory, "alice"),
+            PlayerTrust::NameConflict(false)
+        );
+        assert_eq!(
+            known_players.check_and_remember(&Identity::generate().public_key(), "bob"),
+            PlayerTrust::NameConflict(true)
+        );
+    }
+
+    #[test]
+    fn blocks_players() {
+        let known_players = known_players("blocks_players");
+        let alice = Identity::generate().public_key();
+        known_players.check_and_remember(&alice, "alice");
+        assert!(known_players.set_friend(&alice, true));
+        assert!(known_players.is_friend(&alice));
+        assert!(known_players.set_blocked(&alice, true));
+        assert!(known_players.is_blocked(&alice));
+        assert!(!known_players.is_friend(&alice));
+        assert_eq!(
+            known_players.check_and_remember(&alice, "renamed"),
+            PlayerTrust::Blocked
+        );
+        assert_eq!(known_players.friends()[0].name.as_deref(), Some("alice"));
+        assert!(known_players.set_blocked(&alice, false));
+        assert!(known_players.is_friend(&alice));
+    }
+
+    #[test]
+    fn keeps_unreadable_files() {
+        let known_players = known_players("keeps_unreadable_files");
+        let alice = Identity::generate().public_key();
+        let text = format!(
+            "[players.{}]\nname = \"alice\"\nblocked = true\n[",
+            to_hex(&alice)
+        );
+        fs::write(&known_players.path, &text).unwrap();
+
+        assert!(!known_players.set_friend(&alice, true));
+        assert!(!known_players.set_blocked(&alice, false));
+        assert_eq!(
+            known_players.check_and_remember(&Identity::generate().public_key(), "bob"),
+            PlayerTrust::New
+        );
+        assert!(known_players.friends().is_empty());
+        assert_eq!(fs::read_to_string(&known_players.path).unwrap(), text);
+    }
+}
@@ -0,0 +1,96 @@
+use std::{collections::HashMap, fmt::Display, sync::OnceLock};
+
+use junowen_lib::lang::Lang;
+use sys_locale::get_locales;
+use tracing::{error, info};
+
+static LANG: OnceLock<Lang> = OnceLock::new();
+
+fn to_lang_source(tag: &str) -> Option<&'static str> {
+    match tag {
+        "ja" => Some(include_str!("lang/ja.toml")),
+        "en" => Some(""),
+        _ => None,
+    }
+}
+
+fn primary_lang(tag: &str) -> &str {
+    tag.split(['-', '_']).next().unwrap_or(tag)
+}
+
+/**
+ * ゲーム内で表示する文字列の言語を決める
+ *
+ * ini で指定されていればそれを使い、無ければシステムのロケールから選ぶ
+ */
+pub fn init_lang(setting: Option<&str>) {
+    let from_system = || {
+        get_locales().find_map(|tag| {
+            to_lang_source(&tag)
+                .or_else(|| to_lang_source(primary_lang(&tag)))
+                .map(|source| (tag.clone(), source))
+        })
+    };
+    let (tag, source) = setting
+        .and_then(|tag| {
+            let source = to_lang_source(tag).or_else(|| to_lang_source(primary_lang(tag)));
+            if source.is_none() {
+                error!("unsupported lang: {}", tag);
+            }
+            source.map(|source| (tag.to_owned(), source))
+        })
+        .or_else(from_system)
+        .unwrap_or_else(|| ("en".to_owned(), ""));
+    let lang: HashMap<String, String> = toml::from_str(source).unwrap();
+    info!("lang: {}", tag);
+    let _ = LANG.set(Lang::new(lang));
+}
+
+/// 英語の文字列をキーにして訳を引く
+pub fn tr(msg: &str) -> &str {
+    match LANG.get() {
+        Some(lang) => lang.get(msg),
+        None => msg,
+    }
+}
+
+/**
+ * 訳した template の `{}` を前から順に args で置き換える
+ *
+ * 桁揃えなどの書式は呼び出し側で済ませておく
+ */
+pub fn tr_format(template: &str, args: &[&dyn Display]) -> String {
+    let mut args = args.iter();
+    let mut parts = tr(template).split("{}");
+    let mut msg = parts.next().unwrap_or_default().to_owned();
+    for part in parts {
+        if let Some(arg) = args.next() {
+            msg += &arg.to_string();
+        }
+        msg += part;
+    }
+    msg
+}
+
+/// 全角文字を 2 として数えた表示幅
+pub fn text_width(text: &str) -> usize {
+    text.chars()
+        .map(|c| match c as u32 {
+            0x1100..=0x115f
+            | 0x2e80..=0xa4cf
+            | 0xac00..=0xd7a3
+            | 0xf900..=0xfaff
+            | 0xfe30..=0xfe4f
+            | 0xff00..=0xff60
+            | 0xffe0..=0xffe6
+            | 0x20000..=0x3fffd => 2,
+            _ => 1,
+        })
+        .sum()
+}
+
+/// 表示幅が width になるまで後ろを空白で埋める
+pub fn pad_end(text: &str, width: usize) -> String {
+    let padding = width.saturating_sub(text_width(text));
+    format!("{}{}", text, " ".repeat(padding))
+}
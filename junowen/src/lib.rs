@@ -1,6 +1,9 @@
 mod file;
 mod helper;
 mod in_game_lobby;
+mod known_players;
+mod lang;
+mod opponent_history;
 mod session;
 mod signaling;
 mod state;
@@ -9,7 +12,8 @@ mod tracing_helper;
 use std::{ffi::c_void, slice};
 
 use junowen_lib::{
-    hook_utils::{calc_th19_hash, show_warn_dialog, WELL_KNOWN_VERSION_HASHES},
+    addresses::find_game_version,
+    hook_utils::{calc_th19_hash, show_warn_dialog},
     structs::{others::RenderingText, selection::Selection},
     Fn009fa0, Fn011560, Fn0b7d40, Fn0d5ae0, Fn0d6e10, Fn1049e0, Fn10f720, FnOfHookAssembly, Th19,
 };
@@ -123,10 +127,7 @@ extern "thiscall" fn on_loaded_game_settings(this: *const c_void, arg1: u32) ->
 }
 
 fn check_version(hash: &[u8]) -> bool {
-    WELL_KNOWN_VERSION_HASHES
-        .all_v100a()
-        .iter()
-        .any(|&valid_hash| valid_hash == hash)
+    find_game_version(hash).is_ok()
 }
 
 async fn init(dll_stem: &str, old_log_dir_path: Option<&str>) {
@@ -173,7 +174,7 @@ async fn init(dll_stem: &str, old_log_dir_path: Option<&str>) {
             old_fn_from_13f9d0_0345,
             old_fn_from_13f9d0_0446,
         });
-        STATE = Some(State::new(SettingsRepo::new(ini_file_path), th19).await);
+        STATE = Some(State::new(SettingsRepo::new(ini_file_path), &module_dir, th19).await);
     }
     let th19 = &mut state_mut().th19_mut();
     apply_hook_on_input_players(th19);
@@ -195,8 +196,8 @@ fn launch_init(dll_stem: &str, old_log_dir_path: Option<&str>) {
 fn self_init() -> bool {
     let hash = calc_th19_hash();
     let dll_path = to_dll_path(unsafe { MODULE });
-    if !check_version(&hash) {
-        show_warn_dialog(&format!("Hash mismatch: {}", dll_path.to_string_lossy()));
+    if let Err(err) = find_game_version(&hash) {
+        show_warn_dialog(&format!("{}: {}", err, dll_path.to_string_lossy()));
         return false;
     }
     let dll_stem = dll_path.file_stem().unwrap().to_string_lossy().to_string();
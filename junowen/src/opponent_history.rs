@@ -0,0 +1,211 @@
+use std::{fs, io::ErrorKind, path::PathBuf};
+
+use anyhow::Result;
+use derive_new::new;
+use junowen_lib::identity::{from_hex, to_hex, PublicKey};
+use serde::{Deserialize, Serialize};
+use time::{macros::format_description, OffsetDateTime, UtcOffset};
+use tracing::error;
+
+/// 覚えておく対戦相手の数
+const MAX_RECENT_OPPONENTS: usize = 20;
+
+/** 対戦相手と出会った場所 */
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(tag = "type", content = "name", rename_all = "kebab-case")]
+pub enum OpponentRoom {
+    Shared(String),
+    Reserved(String),
+    PureP2p,
+}
+
+impl OpponentRoom {
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            Self::Shared(name) | Self::Reserved(name) => Some(name),
+            Self::PureP2p => None,
+        }
+    }
+}
+
+/** 最近の対戦相手 1 人分の記録 */
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RecentOpponent {
+    pub name: String,
+    /// 鍵を照合できた場合だけ記録する
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+    /// 対戦を終えた UNIX 時刻
+    pub played_at: i64,
+    /// 終えた試合の数
+    pub matches: u32,
+    /// セットの自分と相手の勝ち数。セットでなければ None
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub set_score: Option<(u8, u8)>,
+    pub room: OpponentRoom,
+}
+
+impl RecentOpponent {
+    pub fn new(
+        name: String,
+        public_key: Option<&PublicKey>,
+        matches: u32,
+        set_score: Option<(u8, u8)>,
+        room: OpponentRoom,
+    ) -> Self {
+        Self {
+            name,
+            public_key: public_key.map(|public_key| to_hex(public_key)),
+            played_at: OffsetDateTime::now_utc().unix_timestamp(),
+            matches,
+            set_score,
+            room,
+        }
+    }
+
+    pub fn public_key(&self) -> Option<PublicKey> {
+        from_hex(self.public_key.as_ref()?).ok()
+    }
+
+    /// 同じ鍵、鍵が無ければ同じ名前なら同じ相手とみなす
+    fn is_same_player(&self, other: &Self) -> bool {
+        match (&self.public_key, &other.public_key) {
+            (Some(a), Some(b)) => a == b,
+            (None, None) => self.name == other.name,
+            _ => false,
+        }
+    }
+
+    /// 対戦を終えた日時をローカル時刻で
+    pub fn played_at_label(&self) -> String {
+        let Ok(played_at) = OffsetDateTime::from_unix_timestamp(self.played_at) else {
+            return "".to_owned();
+        };
+        let offset = UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC);
+        played_at
+            .to_offset(offset)
+            .format(format_description!("[year]-[month]-[day] [hour]:[minute]"))
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Default, Deserialize, Serialize)]
+struct OpponentHistoryFile {
+    #[serde(default)]
+    opponents: Vec<RecentOpponent>,
+}
+
+/**
+ * 最近の対戦相手の一覧
+ *
+ * 相手ごとに最後の対戦だけを新しい順に `[[opponents]]` へ記録する
+ */
+#[derive(Clone, new)]
+pub struct OpponentHistory {
+    path: PathBuf,
+}
+
+impl OpponentHistory {
+    /// ファイルが無ければ空、読めなければ Err
+    fn load(&self) -> Result<OpponentHistoryFile> {
+        match fs::read_to_string(&self.path) {
+            Ok(text) => Ok(toml::from_str(&text)?),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(OpponentHistoryFile::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// 参照するだけなら、読めないファイルは空として扱う
+    fn read(&self) -> OpponentHistoryFile {
+        self.load().unwrap_or_else(|err| {
+            error!("invalid {}: {}", self.path.display(), err);
+            OpponentHistoryFile::default()
+        })
+    }
+
+    fn save(&self, file: &OpponentHistoryFile) {
+        let text = match toml::to_string(file) {
+            Ok(text) => text,
+            Err(err) => {
+                error!("{}", err);
+                return;
+            }
+        };
+        if let Err(err) = fs::write(&self.path, text) {
+            error!("{}", err);
+        }
+    }
+
+    /// 新しい順
+    pub fn opponents(&self) -> Vec<RecentOpponent> {
+        self.read().opponents
+    }
+
+    /// 読めないファイルは、記録を消さないように書き換えない
+    pub fn push(&self, opponent: RecentOpponent) {
+        let mut file = match self.load() {
+            Ok(file) => file,
+            Err(err) => {
+                error!("invalid {}, not saved: {}", self.path.display(), err);
+                return;
+            }
+        };
+        file.opponents.retain(|x| !x.is_same_player(&opponent));
+        file.opponents.insert(0, opponent);
+        file.opponents.truncate(MAX_RECENT_OPPONENTS);
+        self.save(&file);
+    }
+
+    pub fn contains(&self, public_key: &PublicKey) -> bool {
+        let public_key = to_hex(public_key);
+        self.read()
+            .opponents
+            .iter()
+            .any(|x| x.public_key.as_ref() == Some(&public_key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::*;
+
+    fn opponent_history(name: &str) -> OpponentHistory {
+        let path = env::temp_dir().join(format!(
+            "junowen-opponent-history-{}-{}.toml",
+            std::process::id(),
+            name
+        ));
+        let _ = fs::remove_file(&path);
+        OpponentHistory::new(path)
+    }
+
+    fn opponent(name: &str) -> RecentOpponent {
+        RecentOpponent::new(name.to_owned(), None, 1, None, OpponentRoom::PureP2p)
+    }
+
+    #[test]
+    fn keeps_latest_first() {
+        let opponent_history = opponent_history("keeps_latest_first");
+        opponent_history.push(opponent("alice"));
+        opponent_history.push(opponent("bob"));
+        opponent_history.push(opponent("alice"));
+        let names: Vec<_> = opponent_history
+            .opponents()
+            .into_iter()
+            .map(|x| x.name)
+            .collect();
+        assert_eq!(names, ["alice", "bob"]);
+    }
+
+    #[test]
+    fn keeps_unreadable_files() {
+        let opponent_history = opponent_history("keeps_unreadable_files");
+        let text = "[[opponents]]\nname = \"alice\"\n[";
+        fs::write(&opponent_history.path, text).unwrap();
+        opponent_history.push(opponent("bob"));
+        assert!(opponent_history.opponents().is_empty());
+        assert_eq!(fs::read_to_string(&opponent_history.path).unwrap(), text);
+    }
+}
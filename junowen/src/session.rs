@@ -1,6 +1,10 @@
+pub mod acceptance;
 pub mod battle;
-mod delayed_inputs;
-mod session_message;
+pub mod chat;
+pub mod identity;
+pub mod loopback;
+pub mod recorder;
+pub mod set_score;
 pub mod spectator;
 pub mod spectator_host;
 
@@ -11,22 +15,23 @@ use bytes::Bytes;
 use junowen_lib::connection::DataChannel;
 use rmp_serde::decode::Error;
 use serde::Serialize;
-use tokio::spawn;
-use tracing::debug;
+use tokio::{spawn, task::JoinHandle};
+use tracing::{debug, error};
 
-pub use session_message::{MatchInitial, RoundInitial};
+pub use junowen_lib::session_message::{MatchInitial, RoundInitial};
 
+/// 3 つめは送信側のタスクで、Sender を全て drop すると残りを送り切ってから終わる
 fn to_channel<T>(
     mut data_channel: DataChannel,
     decode: fn(input: &[u8]) -> Result<T, Error>,
-) -> (mpsc::Sender<T>, mpsc::Receiver<T>)
+) -> (mpsc::Sender<T>, mpsc::Receiver<T>, JoinHandle<()>)
 where
     T: Serialize + Send + 'static,
 {
     let (hook_outgoing_tx, hook_outgoing_rx) = std::sync::mpsc::channel();
     let data_channel_message_sender = data_channel.message_sender.clone();
 
-    spawn(async move {
+    let outgoing = spawn(async move {
         let mut hook_outgoing_rx = hook_outgoing_rx;
         loop {
             let (msg, reusable) =
@@ -55,12 +60,19 @@ where
             let Some(data) = data_channel.recv().await else {
                 return;
             };
-            let msg = decode(&data).unwrap();
+            let msg = match decode(&data) {
+                Ok(msg) => msg,
+                Err(err) => {
+                    // 相手が壊れたメッセージを送ってきても落ちないよう、切断として扱う
+                    error!("decode incoming msg error: {}", err);
+                    return;
+                }
+            };
             if let Err(err) = hook_incoming_tx.send(msg) {
                 debug!("send hook incoming msg error: {}", err);
                 return;
             }
         }
     });
-    (hook_outgoing_tx, hook_incoming_rx)
+    (hook_outgoing_tx, hook_incoming_rx, outgoing)
 }
@@ -0,0 +1,188 @@
+use std::{
+    sync::mpsc::RecvError,
+    time::{Duration, Instant},
+};
+
+use getset::{CopyGetters, Getters};
+use junowen_lib::session_message::{AcceptanceMessage, MatchInitial, MatchProposal};
+
+use super::battle::BattleSession;
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+const PING_INTERVAL: Duration = Duration::from_secs(1);
+/// 断ったことが相手に届くのを待つ時間
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AcceptanceState {
+    Pending,
+    Accepted,
+    /**
+     * どちらかが断ったが、まだ切断できない
+     *
+     * 断った側は相手が受け取ったと答えるまで、断られた側は相手が切断するまで待つ
+     */
+    Declining,
+    /// どちらかが断った、または時間切れ。切断してよい
+    Declined,
+}
+
+/**
+ * 対戦相手と接続してから対戦を始めるまでの確認
+ *
+ * 互いに名前とルールを提示し、双方が承諾したら対戦を始める。
+ * すぐに切断すると断ったことが相手に届かないことがあるので、届いたのを確かめてから切断する
+ */
+#[derive(CopyGetters, Getters)]
+pub struct Acceptance {
+    created_at: Instant,
+    #[get = "pub"]
+    local_proposal: MatchProposal,
+    #[get = "pub"]
+    remote_proposal: Option<MatchProposal>,
+    #[get_copy = "pub"]
+    local_answer: Option<bool>,
+    answer_sent: bool,
+    #[get_copy = "pub"]
+    remote_answer: Option<bool>,
+    /// 自分が断ったことを相手が受け取った
+    decline_received: bool,
+    disconnected: bool,
+    declined_at: Option<Instant>,
+    ping_nonce: u32,
+    ping_sent_at: Option<Instant>,
+    #[get_copy = "pub"]
+    rtt: Option<Duration>,
+}
+
+impl Acceptance {
+    pub fn new(session: &mut BattleSession, local_proposal: MatchProposal) -> Self {
+        session.send_acceptance(AcceptanceMessage::Proposal(local_proposal.clone()));
+        Self {
+            created_at: Instant::now(),
+            local_proposal,
+            remote_proposal: None,
+            local_answer: None,
+            answer_sent: false,
+            remote_answer: None,
+            decline_received: false,
+            disconnected: false,
+            declined_at: None,
+            ping_nonce: 0,
+            ping_sent_at: None,
+            rtt: None,
+        }
+    }
+
+    /// ホストが提示したルール
+    pub fn rules(&self) -> Option<&MatchInitial> {
+        self.local_proposal.rules.as_ref().or_else(|| {
+            self.remote_proposal
+                .as_ref()
+                .and_then(|proposal| proposal.rules.as_ref())
+        })
+    }
+
+    pub fn remaining(&self) -> Duration {
+        TIMEOUT.saturating_sub(self.created_at.elapsed())
+    }
+
+    /// 相手の提示を受け取るまでは答えられない。相手が先に断った場合も答えない
+    pub fn answer(&mut self, accept: bool) {
+        if self.remote_proposal.is_none()
+            || self.local_answer.is_some()
+            || self.state() != AcceptanceState::Pending
+        {
+            return;
+        }
+        self.local_answer = Some(accept);
+    }
+
+    pub fn state(&self) -> AcceptanceState {
+        match (self.local_answer, self.remote_answer) {
+            (Some(false), _) | (_, Some(false)) => {
+                if self.can_close() {
+                    AcceptanceState::Declined
+                } else {
+                    AcceptanceState::Declining
+                }
+            }
+            (Some(true), Some(true)) => AcceptanceState::Accepted,
+            _ => AcceptanceState::Pending,
+        }
+    }
+
+    fn can_close(&self) -> bool {
+        self.disconnected
+            || (self.local_answer == Some(false) && self.decline_received)
+            || self
+                .declined_at
+                .is_some_and(|declined_at| declined_at.elapsed() >= CLOSE_TIMEOUT)
+    }
+
+    fn ping(&mut self, session: &mut BattleSession) {
+        if self
+            .ping_sent_at
+            .is_some_and(|sent_at| sent_at.elapsed() < PING_INTERVAL)
+        {
+            return;
+        }
+        self.ping_nonce = self.ping_nonce.wrapping_add(1);
+        self.ping_sent_at = Some(Instant::now());
+        session.send_acceptance(AcceptanceMessage::Ping(self.ping_nonce));
+    }
+
+    /// 断る前に相手が切断した場合は Err を返す
+    pub fn update(&mut self, session: &mut BattleSession) -> Result<AcceptanceState, RecvError> {
+        if self.local_answer.is_none() && self.remaining().is_zero() {
+            self.local_answer = Some(false);
+        }
+        if !self.answer_sent {
+            if let Some(answer) = self.local_answer {
+                self.answer_sent = true;
+                session.send_acceptance(AcceptanceMessage::Answer(answer));
+            }
+        }
+        // 確認が済んだ後のメッセージは対戦のためのものなので読まない
+        loop {
+            match self.state() {
+                AcceptanceState::Pending => self.ping(session),
+                AcceptanceState::Declining => {}
+                AcceptanceState::Accepted | AcceptanceState::Declined => break,
+            }
+            let msg = match session.try_recv_acceptance() {
+                Ok(Some(msg)) => msg,
+                Ok(None) => break,
+                Err(err) => {
+                    if self.state() == AcceptanceState::Pending {
+                        return Err(err);
+                    }
+                    self.disconnected = true;
+                    break;
+                }
+            };
+            match msg {
+                AcceptanceMessage::Proposal(proposal) => self.remote_proposal = Some(proposal),
+                AcceptanceMessage::Ping(nonce) => {
+                    session.send_acceptance(AcceptanceMessage::Pong(nonce));
+                }
+                AcceptanceMessage::Pong(nonce) => {
+                    if nonce == self.ping_nonce {
+                        self.rtt = self.ping_sent_at.map(|sent_at| sent_at.elapsed());
+                    }
+                }
+                AcceptanceMessage::Answer(answer) => {
+                    self.remote_answer = Some(answer);
+                    if !answer {
+                        session.send_acceptance(AcceptanceMessage::DeclineReceived);
+                    }
+                }
+                AcceptanceMessage::DeclineReceived => self.decline_received = true,
+            }
+        }
+        if self.declined_at.is_none() && self.state() == AcceptanceState::Declining {
+            self.declined_at = Some(Instant::now());
+        }
+        Ok(self.state())
+    }
+}
@@ -1,14 +1,33 @@
-use std::sync::mpsc::RecvError;
+use std::{mem, sync::mpsc::RecvError};
 
 use anyhow::Result;
 use getset::{CopyGetters, Getters, Setters};
-use junowen_lib::connection::{DataChannel, PeerConnection};
-use tracing::{info, trace};
+use junowen_lib::{
+    connection::{DataChannel, PeerConnection},
+    delay_request::DelayRequest,
+    delayed_inputs::DelayedInputs,
+    identity::{new_nonce, to_hex, Identity},
+    session_message::{
+        AcceptanceMessage, IdentityMessage, InitMatch, SessionMessage, PROTOCOL_VERSION,
+    },
+    signaling_server::ladder::{match_id, rules_hash, MatchResult},
+    structs::selection::Selection,
+};
+use tracing::{error, info, trace};
+
+use crate::{
+    file::{DelaysConfig, RulePreset},
+    known_players::KnownPlayers,
+    opponent_history::{OpponentRoom, RecentOpponent},
+    signaling::match_result::report_match_result,
+};
 
 use super::{
-    delayed_inputs::DelayedInputs,
-    session_message::{MatchInitial, RoundInitial},
-    to_channel,
+    chat::{Chat, ChatMessage},
+    identity::RemoteIdentity,
+    recorder::Recorder,
+    set_score::{SetScore, Side},
+    to_channel, MatchInitial, RoundInitial,
 };
 
 #[derive(CopyGetters, Getters, Setters)]
@@ -19,8 +38,52 @@ pub struct BattleSession {
     #[getset(get_copy = "pub")]
     host: bool,
     delayed_inputs: DelayedInputs,
-    #[getset(set = "pub")]
     match_initial: Option<MatchInitial>,
+    #[getset(set = "pub")]
+    recorder: Option<Recorder>,
+    #[getset(get = "pub")]
+    chat: Chat,
+    new_chats: Vec<ChatMessage>,
+    /// ホストとして提案するセットの長さ
+    #[getset(get_copy = "pub", set = "pub")]
+    first_to: Option<u8>,
+    /// ホストとして提案するルール。None ならゲームのメニューの設定を使う
+    #[getset(get = "pub", set = "pub")]
+    rule_preset: Option<RulePreset>,
+    /// ホストとして最初の入力と一緒に送るディレイ
+    #[getset(set = "pub")]
+    default_delay: Option<u8>,
+    /// ホストとして対戦相手やルームごとに提案するディレイ
+    #[getset(set = "pub")]
+    remembered_delays: DelaysConfig,
+    /// ホストとしてゲストが希望するディレイを確認せずに受け入れる
+    #[getset(set = "pub")]
+    auto_accept_delay_requests: bool,
+    /// ラウンドが終わると取り下げる
+    delay_request: DelayRequest,
+    /// ホストとして受け入れ、次の入力と一緒に送るディレイ
+    accepted_delay: Option<u8>,
+    set_score: Option<SetScore>,
+    /// None なら鍵を持たないプレイヤーとして振る舞う
+    #[getset(set = "pub")]
+    identity: Option<Identity>,
+    #[getset(set = "pub")]
+    known_players: Option<KnownPlayers>,
+    #[getset(get = "pub")]
+    remote_identity: RemoteIdentity,
+    /// セットの結果をラダーに報告する
+    #[getset(set = "pub")]
+    ladder: bool,
+    player_name: String,
+    match_id: Option<String>,
+    rules_hash: Option<String>,
+    /// 最後に始まったラウンドの p1, p2 のキャラクター
+    characters: (u8, u8),
+    /// 対戦相手と出会った場所
+    #[getset(set = "pub")]
+    room: Option<OpponentRoom>,
+    /// 終えた試合の数
+    matches_played: u32,
 }
 
 impl Drop for BattleSession {
@@ -31,7 +94,7 @@ impl Drop for BattleSession {
 
 impl BattleSession {
     pub fn new(conn: PeerConnection, data_channel: DataChannel, host: bool) -> Self {
-        let (hook_outgoing_tx, hook_incoming_rx) =
+        let (hook_outgoing_tx, hook_incoming_rx, _) =
             to_channel(data_channel, |input| rmp_serde::from_slice(input));
         Self {
             _conn: conn,
@@ -39,6 +102,27 @@ impl BattleSession {
             host,
             delayed_inputs: DelayedInputs::new(hook_outgoing_tx, hook_incoming_rx, host),
             match_initial: None,
+            recorder: None,
+            chat: Chat::default(),
+            new_chats: Vec::new(),
+            first_to: None,
+            rule_preset: None,
+            default_delay: None,
+            remembered_delays: DelaysConfig::default(),
+            auto_accept_delay_requests: false,
+            delay_request: DelayRequest::default(),
+            accepted_delay: None,
+            set_score: None,
+            identity: None,
+            known_players: None,
+            remote_identity: RemoteIdentity::None,
+            ladder: false,
+            player_name: "".to_owned(),
+            match_id: None,
+            rules_hash: None,
+            characters: (0, 0),
+            room: None,
+            matches_played: 0,
         }
     }
 
@@ -46,24 +130,158 @@ impl BattleSession {
         self.match_initial.as_ref()
     }
 
+    pub fn set_score(&self) -> Option<&SetScore> {
+        self.set_score.as_ref()
+    }
+
+    /// 指紋と照合結果を添えた相手の名前
+    pub fn remote_player_label(&self) -> String {
+        self.remote_identity.label(&self.remote_player_name)
+    }
+
     pub fn delay(&self) -> u8 {
         self.delayed_inputs.delay()
     }
 
+    /// ホストが変更を指示し、まだ反映されていないディレイ
+    pub fn pending_delay(&self) -> Option<u8> {
+        self.delayed_inputs.pending_delay()
+    }
+
+    /**
+     * ゲストが名前を送り、互いの鍵を確かめてから、ホストが覚えているディレイを添えてルールを送る
+     *
+     * ブロックしている相手とはルールを交換せずに切断する
+     */
     pub fn init_match(
         &mut self,
         player_name: String,
-        init: Option<MatchInitial>,
+        mut init: Option<MatchInitial>,
     ) -> Result<(String, Option<MatchInitial>), RecvError> {
         debug_assert!(self.host == init.is_some());
-        if let Some(init) = init {
-            self.delayed_inputs
-                .send_init_match((player_name, Some(init)));
-            self.delayed_inputs.recv_init_match()
+        let game_settings = init.as_ref().map(|init| init.game_settings.clone());
+        let first_to = init.as_ref().and_then(|init| init.first_to);
+        let local_rules_hash = init.as_ref().map(rules_hash);
+
+        let guest_name = if self.host {
+            let remote_init = self.delayed_inputs.recv_init_match()?;
+            if remote_init.initial.is_some() {
+                error!("unexpected match initial: {:?}", remote_init.initial);
+                return Err(RecvError);
+            }
+            Some(remote_init.name)
         } else {
-            self.delayed_inputs.send_init_match((player_name, None));
-            self.delayed_inputs.recv_init_match()
+            self.delayed_inputs.send_init_match(InitMatch {
+                name: player_name.clone(),
+                initial: None,
+                protocol_version: PROTOCOL_VERSION,
+            });
+            None
+        };
+        let signed_name = self.exchange_identity(&player_name)?;
+        self.player_name = player_name.clone();
+        if self.remote_identity.rejected() {
+            // 切断はセッションの中断として扱う
+            info!(
+                "rejected player: {}",
+                self.remote_identity.label(&signed_name)
+            );
+            return Err(RecvError);
         }
+
+        let (remote_player_name, remote_init) = if let Some(init) = &mut init {
+            let remote_player_name = guest_name.unwrap();
+            init.delay = self
+                .remembered_delays
+                .find(&remote_player_name, self.room_name())
+                .or(self.default_delay);
+            self.default_delay = init.delay;
+            self.delayed_inputs.send_init_match(InitMatch {
+                name: player_name.clone(),
+                initial: Some(init.clone()),
+                protocol_version: PROTOCOL_VERSION,
+            });
+            (remote_player_name, None)
+        } else {
+            let remote_init = self.delayed_inputs.recv_init_match()?;
+            if remote_init.initial.is_none() {
+                error!("match initial is missing");
+                return Err(RecvError);
+            }
+            (remote_init.name, remote_init.initial)
+        };
+        if remote_player_name != signed_name {
+            error!(
+                "name mismatch: {} signed as {}",
+                remote_player_name, signed_name
+            );
+            return Err(RecvError);
+        }
+        self.rules_hash = local_rules_hash.or_else(|| remote_init.as_ref().map(rules_hash));
+
+        if let Some(recorder) = &mut self.recorder {
+            let game_settings = game_settings
+                .or_else(|| remote_init.as_ref().map(|init| init.game_settings.clone()))
+                .unwrap();
+            let (p1_name, p2_name) = if self.host {
+                (player_name, remote_player_name.clone())
+            } else {
+                (remote_player_name.clone(), player_name)
+            };
+            recorder.start_match(p1_name, p2_name, game_settings);
+        }
+        let first_to = first_to.or_else(|| remote_init.as_ref().and_then(|init| init.first_to));
+        let local_side = if self.host { Side::P1 } else { Side::P2 };
+        self.set_score = first_to
+            .filter(|&n| n > 0)
+            .map(|first_to| SetScore::new(first_to, local_side));
+        self.match_initial = init.or_else(|| remote_init.clone());
+        self.receive_unsynced();
+        Ok((remote_player_name, remote_init))
+    }
+
+    /// 相手が署名した名前を返す
+    fn exchange_identity(&mut self, player_name: &str) -> Result<String, RecvError> {
+        let nonce = new_nonce();
+        self.delayed_inputs.send_identity(IdentityMessage::Hello {
+            name: player_name.to_owned(),
+            public_key: self.identity.as_ref().map(|identity| identity.public_key()),
+            nonce,
+        });
+        let IdentityMessage::Hello {
+            name: remote_player_name,
+            public_key: remote_public_key,
+            nonce: remote_nonce,
+        } = self.delayed_inputs.recv_identity()?
+        else {
+            error!("unexpected identity message");
+            return Err(RecvError);
+        };
+        let proof = self
+            .identity
+            .as_ref()
+            .map(|identity| identity.sign_handshake(&remote_nonce, player_name));
+        self.delayed_inputs
+            .send_identity(IdentityMessage::Proof(proof));
+        let IdentityMessage::Proof(remote_proof) = self.delayed_inputs.recv_identity()? else {
+            error!("unexpected identity message");
+            return Err(RecvError);
+        };
+        if let Some(known_players) = &self.known_players {
+            self.remote_identity = RemoteIdentity::verify(
+                remote_public_key,
+                remote_proof,
+                &nonce,
+                &remote_player_name,
+                known_players,
+            );
+        }
+        self.match_id = Some(if self.host {
+            match_id(&nonce, &remote_nonce)
+        } else {
+            match_id(&remote_nonce, &nonce)
+        });
+        Ok(remote_player_name)
     }
 
     pub fn init_round(
@@ -72,8 +290,14 @@ impl BattleSession {
     ) -> Result<Option<RoundInitial>, RecvError> {
         debug_assert!(self.host == init.is_some());
         trace!("init_round");
+        let local_init = init.clone();
         self.delayed_inputs.send_init_round(init);
-        self.delayed_inputs.recv_init_round()
+        let remote_init = self.delayed_inputs.recv_init_round()?;
+        if let Some(recorder) = &mut self.recorder {
+            recorder.set_next_round_initial(local_init.or(remote_init.clone()).unwrap());
+        }
+        self.receive_unsynced();
+        Ok(remote_init)
     }
 
     pub fn enqueue_input_and_dequeue(
@@ -81,6 +305,255 @@ impl BattleSession {
         input: u16,
         delay: Option<u8>,
     ) -> Result<(u16, u16), RecvError> {
-        self.delayed_inputs.enqueue_input_and_dequeue(input, delay)
+        let delay = if self.host {
+            delay
+                .or_else(|| self.default_delay.take())
+                .or_else(|| self.accepted_delay.take())
+        } else {
+            delay
+        };
+        let (p1, p2) = self
+            .delayed_inputs
+            .enqueue_input_and_dequeue(input, delay)?;
+        if !self.host {
+            self.delay_request.on_delay_changed(self.delay());
+        }
+        if let Some(recorder) = &mut self.recorder {
+            recorder.record_inputs(p1, p2);
+        }
+        self.receive_unsynced();
+        Ok((p1, p2))
+    }
+
+    pub fn send_chat(&mut self, player_name: &str, text: &str) {
+        let Some(message) = self.chat.send(player_name, text) else {
+            return;
+        };
+        self.delayed_inputs.send_chat(message.text.clone());
+        self.new_chats.push(message);
+    }
+
+    /// 自分の観戦者が送ったメッセージを表示し、対戦相手にも転送する
+    pub fn relay_spectator_chat(&mut self, message: ChatMessage) {
+        let Some((message, labeled)) = self.chat.receive_spectator(message) else {
+            return;
+        };
+        self.delayed_inputs.send_spectator_chat(message);
+        self.new_chats.push(labeled);
+    }
+
+    /// ホストなら答えていないゲストの希望、ゲストなら送って答えを待っている希望
+    pub fn delay_request(&self) -> Option<u8> {
+        self.delay_request.pending()
+    }
+
+    /// ゲストとしてホストにディレイの変更を頼む
+    pub fn request_delay(&mut self, delay: u8) {
+        debug_assert!(!self.host);
+        if !self.delay_request.request(delay, self.delay()) {
+            return;
+        }
+        info!("delay requested: {}", delay);
+        self.delayed_inputs.send_delay_request(delay);
+    }
+
+    /// ホストとしてゲストの希望に答える。受け入れたら次の入力と一緒に送り、断ったらすぐに伝える
+    pub fn answer_delay_request(&mut self, accept: bool) {
+        debug_assert!(self.host);
+        let Some(delay) = self.delay_request.answer() else {
+            return;
+        };
+        info!(
+            "delay request {}: {}",
+            if accept { "accepted" } else { "declined" },
+            delay
+        );
+        if accept {
+            self.accepted_delay = Some(delay);
+        } else {
+            self.delayed_inputs.send_delay_request_declined(delay);
+        }
+    }
+
+    pub fn send_acceptance(&mut self, msg: AcceptanceMessage) {
+        self.delayed_inputs.send_acceptance(msg);
+    }
+
+    pub fn try_recv_acceptance(&mut self) -> Result<Option<AcceptanceMessage>, RecvError> {
+        self.delayed_inputs.try_recv_acceptance()
+    }
+
+    fn receive_unsynced(&mut self) {
+        for msg in self.delayed_inputs.take_remote_unsynced() {
+            match msg {
+                SessionMessage::Chat(text) => {
+                    let message = ChatMessage {
+                        name: self.remote_player_name.clone(),
+                        text,
+                    };
+                    if let Some(message) = self.chat.receive(message) {
+                        self.new_chats.push(message);
+                    }
+                }
+                SessionMessage::SpectatorChat(message) => {
+                    if let Some((_, labeled)) = self.chat.receive_spectator(message) {
+                        self.new_chats.push(labeled);
+                    }
+                }
+                SessionMessage::MatchResult(winner) => self.apply_match_result(winner),
+                SessionMessage::DelayRequest(delay) => {
+                    if !self.host {
+                        continue;
+                    }
+                    if !self.delay_request.on_requested(delay) {
+                        info!("delay request ignored: {}", delay);
+                        continue;
+                    }
+                    if self.auto_accept_delay_requests {
+                        self.answer_delay_request(true);
+                    }
+                }
+                SessionMessage::DelayRequestDeclined(delay) => {
+                    if !self.host && self.delay_request.on_declined(delay) {
+                        info!("delay request declined: {}", delay);
+                    }
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    pub fn on_round_over(&mut self) {
+        self.delay_request.cancel();
+        if let Some(set_score) = &mut self.set_score {
+            set_score.on_round_over();
+        }
+    }
+
+    pub fn on_match_over(&mut self) {
+        self.matches_played += 1;
+        if let Some(set_score) = &mut self.set_score {
+            set_score.on_match_over();
+        }
+    }
+
+    /// 相手も同じ勝者を報告したら記録する。報告し直すと前の報告を取り消す
+    pub fn report_match_result(&mut self, winner: Side) {
+        let Some(set_score) = &mut self.set_score else {
+            return;
+        };
+        if !set_score.report_local(winner) {
+            return;
+        }
+        self.delayed_inputs.send_match_result(winner);
+        self.settle_match_result();
+    }
+
+    fn apply_match_result(&mut self, winner: Side) {
+        let Some(set_score) = &mut self.set_score else {
+            return;
+        };
+        set_score.report_remote(winner);
+        self.settle_match_result();
+    }
+
+    fn settle_match_result(&mut self) {
+        let Some(set_score) = &mut self.set_score else {
+            return;
+        };
+        let reported = set_score.local_report().is_some();
+        if !set_score.settle() {
+            if reported && set_score.local_report().is_none() {
+                info!("match result reports disagree");
+            }
+            return;
+        }
+        if set_score.winner().is_some() {
+            info!("set over: {}", set_score);
+            self.report_set_result();
+        } else {
+            info!("set score: {}", set_score);
+        }
+    }
+
+    /// 両者が鍵を持っている場合だけ報告できる
+    fn report_set_result(&self) {
+        if !self.ladder {
+            return;
+        }
+        let (Some(identity), Some(remote_public_key), Some(set_score)) = (
+            &self.identity,
+            self.remote_identity.verified_public_key(),
+            &self.set_score,
+        ) else {
+            return;
+        };
+        let (Some(match_id), Some(rules_hash)) = (&self.match_id, &self.rules_hash) else {
+            return;
+        };
+        let local = (to_hex(&identity.public_key()), self.player_name.clone());
+        let remote = (to_hex(remote_public_key), self.remote_player_name.clone());
+        let ((p1, p1_name), (p2, p2_name)) = if self.host {
+            (local, remote)
+        } else {
+            (remote, local)
+        };
+        let result = MatchResult {
+            match_id: match_id.clone(),
+            p1,
+            p2,
+            p1_name,
+            p2_name,
+            p1_character: self.characters.0,
+            p2_character: self.characters.1,
+            p1_wins: set_score.p1_wins(),
+            p2_wins: set_score.p2_wins(),
+            rules_hash: rules_hash.clone(),
+        };
+        report_match_result(identity.clone(), result);
+    }
+
+    fn room_name(&self) -> Option<&str> {
+        self.room.as_ref().and_then(|room| room.name())
+    }
+
+    /// 最近の対戦相手として記録する内容。名前を交換する前に終わった場合は None
+    pub fn recent_opponent(&self) -> Option<RecentOpponent> {
+        let room = self.room.clone()?;
+        if self.remote_player_name.is_empty() {
+            return None;
+        }
+        let set_score = self
+            .set_score
+            .as_ref()
+            .map(|set_score| (set_score.local_wins(), set_score.remote_wins()));
+        Some(RecentOpponent::new(
+            self.remote_player_name.clone(),
+            self.remote_identity.verified_public_key(),
+            self.matches_played,
+            set_score,
+            room,
+        ))
+    }
+
+    /// 観戦者に転送するための、前回以降に送受信したメッセージ
+    pub fn take_new_chats(&mut self) -> Vec<ChatMessage> {
+        mem::take(&mut self.new_chats)
+    }
+
+    pub fn record_round_start(&mut self, selection: &Selection) {
+        self.characters = (
+            selection.p1().character as u8,
+            selection.p2().character as u8,
+        );
+        if let Some(recorder) = &mut self.recorder {
+            recorder.start_round(selection);
+        }
+    }
+
+    pub fn record_round_end(&mut self) {
+        if let Some(recorder) = &mut self.recorder {
+            recorder.end_round();
+        }
     }
 }
@@ -0,0 +1,182 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+pub use junowen_lib::session_message::ChatMessage;
+
+use crate::lang::tr_format;
+
+/** F2 から F9 に割り当てる定型文 */
+pub const CANNED_PHRASES: [&str; 8] = [
+    "gg",
+    "Good luck!",
+    "Nice!",
+    "Thanks!",
+    "One more?",
+    "Last one.",
+    "Sorry, lag.",
+    "Brb",
+];
+
+/// 描画用のバッファーに収まるよう、名前と本文をそれぞれバイト数で制限する
+const MAX_TEXT_BYTES: usize = 100;
+const MAX_LOG_LINES: usize = 6;
+const RATE_LIMIT_PERIOD: Duration = Duration::from_secs(5);
+const LOCAL_RATE_LIMIT: usize = 3;
+/// 観戦者には両プレイヤーのメッセージが届くので多めに許容する
+const REMOTE_RATE_LIMIT: usize = LOCAL_RATE_LIMIT * 2;
+/// 観戦者のメッセージは対戦相手のものとは別に数え、観戦者が多くても相手のメッセージを押し出さないようにする
+const SPECTATOR_RATE_LIMIT: usize = LOCAL_RATE_LIMIT * 2;
+
+/** 一定時間内に扱うメッセージの数を制限する */
+struct RateLimiter {
+    limit: usize,
+    accepted_at: VecDeque<Instant>,
+}
+
+impl RateLimiter {
+    fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            accepted_at: VecDeque::new(),
+        }
+    }
+
+    fn try_accept(&mut self) -> bool {
+        let now = Instant::now();
+        while self
+            .accepted_at
+            .front()
+            .is_some_and(|&accepted_at| now.duration_since(accepted_at) >= RATE_LIMIT_PERIOD)
+        {
+            self.accepted_at.pop_front();
+        }
+        if self.accepted_at.len() >= self.limit {
+            return false;
+        }
+        self.accepted_at.push_back(now);
+        true
+    }
+}
+
+fn sanitize(text: &str) -> String {
+    let mut sanitized = String::new();
+    for c in text.chars().filter(|c| !c.is_control()) {
+        if sanitized.len() + c.len_utf8() > MAX_TEXT_BYTES {
+            break;
+        }
+        sanitized.push(c);
+    }
+    sanitized
+}
+
+pub struct Chat {
+    log: VecDeque<ChatMessage>,
+    local_rate_limiter: RateLimiter,
+    remote_rate_limiter: RateLimiter,
+    spectator_rate_limiter: RateLimiter,
+}
+
+impl Default for Chat {
+    fn default() -> Self {
+        Self {
+            log: VecDeque::new(),
+            local_rate_limiter: RateLimiter::new(LOCAL_RATE_LIMIT),
+            remote_rate_limiter: RateLimiter::new(REMOTE_RATE_LIMIT),
+            spectator_rate_limiter: RateLimiter::new(SPECTATOR_RATE_LIMIT),
+        }
+    }
+}
+
+impl Chat {
+    pub fn log(&self) -> &VecDeque<ChatMessage> {
+        &self.log
+    }
+
+    /// 送信してよい場合は記録した上で、送信すべきメッセージを返す
+    pub fn send(&mut self, name: &str, text: &str) -> Option<ChatMessage> {
+        let message = self.compose(name, text)?;
+        self.push(message.clone());
+        Some(message)
+    }
+
+    /// 送信してよい場合は送信すべきメッセージを返す。送り返されてくる場合に使い、記録はしない
+    pub fn compose(&mut self, name: &str, text: &str) -> Option<ChatMessage> {
+        if !self.local_rate_limiter.try_accept() {
+            return None;
+        }
+        Some(ChatMessage {
+            name: sanitize(name),
+            text: sanitize(text),
+        })
+    }
+
+    /// 制限を超えたメッセージは捨てる
+    pub fn receive(&mut self, message: ChatMessage) -> Option<ChatMessage> {
+        if !self.remote_rate_limiter.try_accept() {
+            return None;
+        }
+        let message = ChatMessage {
+            name: sanitize(&message.name),
+            text: sanitize(&message.text),
+        };
+        self.push(message.clone());
+        Some(message)
+    }
+
+    /**
+     * 対戦中の観戦者のメッセージ。制限を超えたものは捨てる
+     *
+     * 転送すべきメッセージと、観戦者のものと分かるよう名前に印を付けて記録したものを返す
+     */
+    pub fn receive_spectator(
+        &mut self,
+        message: ChatMessage,
+    ) -> Option<(ChatMessage, ChatMessage)> {
+        if !self.spectator_rate_limiter.try_accept() {
+            return None;
+        }
+        let message = ChatMessage {
+            name: sanitize(&message.name),
+            text: sanitize(&message.text),
+        };
+        let labeled = ChatMessage {
+            name: tr_format("{} (spectator)", &[&message.name]),
+            text: message.text.clone(),
+        };
+        self.push(labeled.clone());
+        Some((message, labeled))
+    }
+
+    fn push(&mut self, message: ChatMessage) {
+        if self.log.len() >= MAX_LOG_LINES {
+            self.log.pop_front();
+        }
+        self.log.push_back(message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(name: &str) -> ChatMessage {
+        ChatMessage {
+            name: name.to_owned(),
+            text: "hi".to_owned(),
+        }
+    }
+
+    #[test]
+    fn spectators_do_not_use_up_the_opponent_limit() {
+        let mut chat = Chat::default();
+        for _ in 0..SPECTATOR_RATE_LIMIT {
+            let (forwarded, labeled) = chat.receive_spectator(message("alice")).unwrap();
+            assert_eq!(forwarded.name, "alice");
+            assert_ne!(labeled.name, "alice");
+        }
+        assert!(chat.receive_spectator(message("alice")).is_none());
+        assert_eq!(chat.receive(message("bob")).unwrap().name, "bob");
+    }
+}
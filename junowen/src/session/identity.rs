@@ -0,0 +1,86 @@
+use junowen_lib::identity::{fingerprint, verify_handshake, Nonce, PublicKey};
+use tracing::info;
+
+use crate::{
+    known_players::{KnownPlayers, PlayerTrust},
+    lang::tr_format,
+};
+
+/** 対戦相手が名乗った名前と鍵を照合した結果 */
+#[derive(Clone, Debug, Default)]
+pub enum RemoteIdentity {
+    /// 相手が鍵を持っていない
+    #[default]
+    None,
+    /// 署名が正しくない
+    Invalid,
+    Verified {
+        public_key: PublicKey,
+        fingerprint: String,
+        trust: PlayerTrust,
+    },
+}
+
+impl RemoteIdentity {
+    pub fn verify(
+        public_key: Option<PublicKey>,
+        proof: Option<Vec<u8>>,
+        local_nonce: &Nonce,
+        name: &str,
+        known_players: &KnownPlayers,
+    ) -> Self {
+        let (Some(public_key), Some(proof)) = (public_key, proof) else {
+            return Self::None;
+        };
+        if let Err(err) = verify_handshake(&public_key, local_nonce, name, &proof) {
+            info!("invalid identity proof: {}", err);
+            return Self::Invalid;
+        }
+        Self::Verified {
+            public_key,
+            fingerprint: fingerprint(&public_key),
+            trust: known_players.check_and_remember(&public_key, name),
+        }
+    }
+
+    /// ブロックしている相手、署名が正しくない相手、フレンドの名前を騙る相手とは対戦しない
+    pub fn rejected(&self) -> bool {
+        matches!(
+            self,
+            Self::Invalid
+                | Self::Verified {
+                    trust: PlayerTrust::Blocked | PlayerTrust::NameConflict(true),
+                    ..
+                }
+        )
+    }
+
+    pub fn verified_public_key(&self) -> Option<&PublicKey> {
+        match self {
+            Self::Verified { public_key, .. } => Some(public_key),
+            Self::None | Self::Invalid => None,
+        }
+    }
+
+    /// 名前に指紋と照合結果を添える
+    pub fn label(&self, name: &str) -> String {
+        match self {
+            Self::None => name.to_owned(),
+            Self::Invalid => tr_format("{} (invalid key!)", &[&name]),
+            Self::Verified {
+                fingerprint, trust, ..
+            } => match trust {
+                PlayerTrust::New => tr_format("{} [{}] (new)", &[&name, fingerprint]),
+                PlayerTrust::Known | PlayerTrust::Blocked => {
+                    format!("{} [{}]", name, fingerprint)
+                }
+                PlayerTrust::Renamed(old_name) => {
+                    tr_format("{} [{}] (was {})", &[&name, fingerprint, old_name])
+                }
+                PlayerTrust::NameConflict(_) => {
+                    tr_format("{} [{}] (not the known {}!)", &[&name, fingerprint, &name])
+                }
+            },
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use std::sync::mpsc::{self, RecvError};
+
+use junowen_lib::delayed_inputs::DelayedInputs;
+use rand::Rng;
+
+/// ジッターでディレイを揺らす間隔
+const JITTER_INTERVAL_FRAMES: u32 = 60;
+
+/**
+ * ホストとゲストの DelayedInputs を直結し、ネット対戦と同じ経路で入力を遅らせる
+ *
+ * 両者を同じスレッドで交互に進めるので、ディレイは 1 以上でなければならない
+ */
+pub struct LoopbackSession {
+    host: DelayedInputs,
+    guest: DelayedInputs,
+    delay: u8,
+    jitter: u8,
+    frame: u32,
+}
+
+impl LoopbackSession {
+    pub fn new(delay: u8, jitter: u8) -> Self {
+        debug_assert!(delay >= 1);
+        let (host_tx, guest_rx) = mpsc::channel();
+        let (guest_tx, host_rx) = mpsc::channel();
+        Self {
+            host: DelayedInputs::new(host_tx, host_rx, true),
+            guest: DelayedInputs::new(guest_tx, guest_rx, false),
+            delay,
+            jitter,
+            frame: 0,
+        }
+    }
+
+    fn next_delay(&mut self) -> Option<u8> {
+        let frame = self.frame;
+        self.frame += 1;
+        if frame == 0 {
+            return Some(self.delay);
+        }
+        if self.jitter == 0 || frame % JITTER_INTERVAL_FRAMES != 0 {
+            return None;
+        }
+        Some(rand::thread_rng().gen_range(self.delay..=self.delay + self.jitter))
+    }
+
+    pub fn enqueue_input_and_dequeue(&mut self, p1: u16, p2: u16) -> Result<(u16, u16), RecvError> {
+        let delay = self.next_delay();
+        let delayed = self.host.enqueue_input_and_dequeue(p1, delay)?;
+        let guest_delayed = self.guest.enqueue_input_and_dequeue(p2, None)?;
+        debug_assert_eq!(delayed, guest_delayed);
+        Ok(delayed)
+    }
+}
@@ -0,0 +1,208 @@
+use std::{
+    fs::{self, File},
+    io::{BufWriter, ErrorKind},
+    path::{Path, PathBuf},
+    sync::mpsc,
+};
+
+use anyhow::Result;
+use junowen_lib::structs::{
+    selection::{PlayerMatchup, Selection},
+    settings::GameSettings,
+};
+use th19replayplayer_lib::{FileInputList, ReplayFile, ReplayFileAppender, ReplayRound};
+use time::{macros::format_description, OffsetDateTime};
+use tracing::{error, info};
+
+use crate::TOKIO_RUNTIME;
+
+use super::RoundInitial;
+
+/** 同じ秒に始まった記録を上書きしないよう、既にあれば連番を付けて新しく作る */
+fn create_file(replay_dir: &Path, file_stem: &str) -> Result<(PathBuf, File)> {
+    fs::create_dir_all(replay_dir)?;
+    let mut suffix = 1;
+    loop {
+        let file_name = if suffix == 1 {
+            format!("{}.rep", file_stem)
+        } else {
+            format!("{}-{}.rep", file_stem, suffix)
+        };
+        let path = replay_dir.join(file_name);
+        match File::options()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)
+        {
+            Ok(file) => return Ok((path, file)),
+            Err(err) if err.kind() == ErrorKind::AlreadyExists => suffix += 1,
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
+
+/** ヘッダーを書き、以降は受け取ったラウンドを追記する */
+fn spawn_writer(path: PathBuf, file: File, header: ReplayFile) -> mpsc::Sender<ReplayRound> {
+    let (tx, rx) = mpsc::channel::<ReplayRound>();
+    TOKIO_RUNTIME.spawn_blocking(move || {
+        let mut appender = match ReplayFileAppender::new(BufWriter::new(file), &header) {
+            Ok(appender) => appender,
+            Err(err) => {
+                error!("failed to write {}: {}", path.to_string_lossy(), err);
+                return;
+            }
+        };
+        while let Ok(round) = rx.recv() {
+            if let Err(err) = appender.append_round(&round) {
+                error!("failed to write {}: {}", path.to_string_lossy(), err);
+                return;
+            }
+        }
+    });
+    tx
+}
+
+/**
+ * 対戦・観戦の内容をラウンドごとにファイルへ記録する
+ *
+ * ヘッダーは最初のラウンドの終わりに一度だけ書き、以降はラウンドを追記する。
+ * ゲームのスレッドを止めないよう書き込みは別スレッドで行う
+ */
+pub struct Recorder {
+    path: PathBuf,
+    /// 最初のラウンドを書くまでの間、予約したファイルを持っておく
+    file: Option<File>,
+    header: ReplayFile,
+    next_round_initial: Option<RoundInitial>,
+    current_round: Option<ReplayRound>,
+    recorded_rounds: usize,
+    writer_tx: Option<mpsc::Sender<ReplayRound>>,
+}
+
+impl Recorder {
+    pub fn new(replay_dir: &Path) -> Self {
+        let now = OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc());
+        let file_stem = now
+            .format(format_description!(
+                "[year][month][day]T[hour][minute][second]"
+            ))
+            .unwrap();
+        let (path, file) = match create_file(replay_dir, &file_stem) {
+            Ok((path, file)) => (path, Some(file)),
+            Err(err) => {
+                let path = replay_dir.join(file_stem).with_extension("rep");
+                error!("failed to create {}: {}", path.to_string_lossy(), err);
+                (path, None)
+            }
+        };
+        Self {
+            path,
+            file,
+            header: ReplayFile {
+                recorded_at: now.unix_timestamp(),
+                player_matchup: PlayerMatchup::HumanVsHuman,
+                ..Default::default()
+            },
+            next_round_initial: None,
+            current_round: None,
+            recorded_rounds: 0,
+            writer_tx: None,
+        }
+    }
+
+    pub fn start_match(&mut self, p1_name: String, p2_name: String, game_settings: GameSettings) {
+        self.header.p1_name = p1_name;
+        self.header.p2_name = p2_name;
+        self.header.battle_settings = game_settings;
+    }
+
+    pub fn set_next_round_initial(&mut self, round_initial: RoundInitial) {
+        self.next_round_initial = Some(round_initial);
+    }
+
+    /// 既にラウンドが始まっている場合は何もしない
+    pub fn start_round(&mut self, selection: &Selection) {
+        if self.current_round.is_some() {
+            return;
+        }
+        let Some(round_initial) = self.next_round_initial.take() else {
+            return;
+        };
+        self.current_round = Some(ReplayRound {
+            started_at: OffsetDateTime::now_utc().unix_timestamp(),
+            rand_seed1: round_initial.seed1,
+            rand_seed2: round_initial.seed2,
+            rand_seed3: round_initial.seed3,
+            rand_seed4: round_initial.seed4,
+            difficulty: selection.difficulty,
+            p1_character: selection.p1().character as u8,
+            p1_card: selection.p1().card as u8,
+            p2_character: selection.p2().character as u8,
+            p2_card: selection.p2().card as u8,
+            // 入力の記録は 1 フレーム目から始まるので 0 フレーム目を埋めておく
+            inputs: FileInputList::HumanVsHuman(vec![(0, 0)]),
+        });
+    }
+
+    pub fn record_inputs(&mut self, p1_input: u16, p2_input: u16) {
+        let Some(current_round) = &mut self.current_round else {
+            return;
+        };
+        let FileInputList::HumanVsHuman(inputs) = &mut current_round.inputs else {
+            unreachable!();
+        };
+        inputs.push((p1_input, p2_input));
+    }
+
+    pub fn end_round(&mut self) {
+        let Some(current_round) = self.current_round.take() else {
+            return;
+        };
+        if let Some(file) = self.file.take() {
+            let header = std::mem::take(&mut self.header);
+            self.writer_tx = Some(spawn_writer(self.path.clone(), file, header));
+        }
+        let Some(writer_tx) = &self.writer_tx else {
+            return;
+        };
+        if writer_tx.send(current_round).is_ok() {
+            self.recorded_rounds += 1;
+        }
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        self.end_round();
+        if self.recorded_rounds > 0 {
+            info!("recorded to {}", self.path.to_string_lossy());
+        } else if self.file.is_some() {
+            // 1 ラウンドも記録しなかったので、予約したファイルを消す
+            self.file = None;
+            if let Err(err) = fs::remove_file(&self.path) {
+                error!("failed to remove {}: {}", self.path.to_string_lossy(), err);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn creates_unique_files() {
+        let dir = std::env::temp_dir().join(format!("junowen-recorder-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let (first, _) = create_file(&dir, "20240101T000000").unwrap();
+        let (second, _) = create_file(&dir, "20240101T000000").unwrap();
+        let (third, _) = create_file(&dir, "20240101T000000").unwrap();
+        assert_eq!(first, dir.join("20240101T000000.rep"));
+        assert_eq!(second, dir.join("20240101T000000-2.rep"));
+        assert_eq!(third, dir.join("20240101T000000-3.rep"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,207 @@
+use std::{
+    fmt::Display,
+    time::{Duration, Instant},
+};
+
+use getset::CopyGetters;
+
+pub use junowen_lib::session_message::Side;
+
+use crate::lang::tr_format;
+
+/**
+ * N 本先取のセットの成績
+ *
+ * 試合やラウンドの勝者を示すアドレスは分かっておらず、ゲームのメモリーから読み取れない。
+ * そのため両プレイヤーの報告が一致したときに記録する。
+ * 食い違った場合は両方の報告を取り消して、報告し直してもらう
+ *
+ * 成績は P1/P2 ではなく自分と相手のどちらが勝ったかで持ち、表示や報告のときに側へ対応付ける
+ */
+#[derive(CopyGetters)]
+pub struct SetScore {
+    #[get_copy = "pub"]
+    first_to: u8,
+    /// 自分の側。セッションの間は変わらない
+    local_side: Side,
+    #[get_copy = "pub"]
+    local_wins: u8,
+    #[get_copy = "pub"]
+    remote_wins: u8,
+    /// セット全体で終了したラウンドの数
+    #[get_copy = "pub"]
+    rounds_played: u32,
+    /// 試合が終わり、勝者の報告を待っている
+    #[get_copy = "pub"]
+    awaiting_result: bool,
+    #[get_copy = "pub"]
+    local_report: Option<Side>,
+    /// 自分の試合が終わる前に届くこともある
+    remote_report: Option<Side>,
+    decided_at: Option<Instant>,
+}
+
+impl SetScore {
+    pub fn new(first_to: u8, local_side: Side) -> Self {
+        Self {
+            first_to,
+            local_side,
+            local_wins: 0,
+            remote_wins: 0,
+            rounds_played: 0,
+            awaiting_result: false,
+            local_report: None,
+            remote_report: None,
+            decided_at: None,
+        }
+    }
+
+    fn wins(&self, side: Side) -> u8 {
+        if side == self.local_side {
+            self.local_wins
+        } else {
+            self.remote_wins
+        }
+    }
+
+    pub fn p1_wins(&self) -> u8 {
+        self.wins(Side::P1)
+    }
+
+    pub fn p2_wins(&self) -> u8 {
+        self.wins(Side::P2)
+    }
+
+    pub fn winner(&self) -> Option<Side> {
+        [Side::P1, Side::P2]
+            .into_iter()
+            .find(|&side| self.wins(side) >= self.first_to)
+    }
+
+    /// セットの勝敗が決まってから duration 以上経った
+    pub fn is_over_for(&self, duration: Duration) -> bool {
+        self.decided_at
+            .is_some_and(|decided_at| decided_at.elapsed() >= duration)
+    }
+
+    pub fn on_round_over(&mut self) {
+        self.rounds_played += 1;
+    }
+
+    pub fn on_match_over(&mut self) {
+        if self.winner().is_none() {
+            self.awaiting_result = true;
+        }
+    }
+
+    /// 報告を待っていない場合は何もせず false を返す
+    pub fn report_local(&mut self, winner: Side) -> bool {
+        if !self.awaiting_result || self.local_report == Some(winner) {
+            return false;
+        }
+        self.local_report = Some(winner);
+        true
+    }
+
+    pub fn report_remote(&mut self, winner: Side) {
+        if self.winner().is_some() {
+            return;
+        }
+        self.remote_report = Some(winner);
+    }
+
+    /// 両者の報告が一致したら記録して true を返す
+    pub fn settle(&mut self) -> bool {
+        if !self.awaiting_result {
+            return false;
+        }
+        let (Some(local), Some(remote)) = (self.local_report, self.remote_report) else {
+            return false;
+        };
+        self.local_report = None;
+        self.remote_report = None;
+        if local != remote {
+            return false;
+        }
+        self.awaiting_result = false;
+        if local == self.local_side {
+            self.local_wins += 1;
+        } else {
+            self.remote_wins += 1;
+        }
+        if self.winner().is_some() {
+            self.decided_at = Some(Instant::now());
+        }
+        true
+    }
+
+    pub fn summary(&self, p1_name: &str, p2_name: &str) -> Option<String> {
+        let winner = match self.winner()? {
+            Side::P1 => p1_name,
+            Side::P2 => p2_name,
+        };
+        Some(tr_format(
+            "{} won the set {}-{} ({} rounds)",
+            &[
+                &winner,
+                &self.local_wins.max(self.remote_wins),
+                &self.local_wins.min(self.remote_wins),
+                &self.rounds_played,
+            ],
+        ))
+    }
+}
+
+impl Display for SetScore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "FT{}: {}-{}",
+            self.first_to,
+            self.p1_wins(),
+            self.p2_wins()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(set_score: &mut SetScore, local: Side, remote: Side) -> bool {
+        set_score.on_match_over();
+        assert!(set_score.report_local(local));
+        set_score.report_remote(remote);
+        set_score.settle()
+    }
+
+    #[test]
+    fn counts_wins_of_each_player() {
+        let mut set_score = SetScore::new(2, Side::P2);
+        assert!(report(&mut set_score, Side::P2, Side::P2));
+        assert!(report(&mut set_score, Side::P1, Side::P1));
+        assert_eq!((set_score.local_wins(), set_score.remote_wins()), (1, 1));
+        assert_eq!((set_score.p1_wins(), set_score.p2_wins()), (1, 1));
+        assert!(set_score.winner().is_none());
+
+        assert!(report(&mut set_score, Side::P2, Side::P2));
+        assert_eq!((set_score.local_wins(), set_score.remote_wins()), (2, 1));
+        assert_eq!(set_score.winner(), Some(Side::P2));
+        assert_eq!(set_score.to_string(), "FT2: 1-2");
+        assert!(!set_score.awaiting_result());
+    }
+
+    #[test]
+    fn asks_again_when_reports_disagree() {
+        let mut set_score = SetScore::new(3, Side::P1);
+        assert!(!report(&mut set_score, Side::P1, Side::P2));
+        assert!(set_score.awaiting_result());
+        assert!(set_score.local_report().is_none());
+        assert_eq!((set_score.local_wins(), set_score.remote_wins()), (0, 0));
+
+        assert!(set_score.report_local(Side::P2));
+        set_score.report_remote(Side::P2);
+        assert!(set_score.settle());
+        assert_eq!((set_score.local_wins(), set_score.remote_wins()), (0, 1));
+    }
+}
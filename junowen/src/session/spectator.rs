@@ -1,76 +1,49 @@
-use std::sync::mpsc::RecvError;
+use std::{collections::VecDeque, sync::mpsc::RecvError};
 
 use anyhow::Result;
-use derive_new::new;
 use getset::{CopyGetters, Getters, Setters};
 use junowen_lib::{
     connection::{DataChannel, PeerConnection},
-    structs::settings::GameSettings,
+    structs::selection::Selection,
 };
-use serde::{Deserialize, Serialize};
 use tracing::{error, info};
 
-use super::{session_message::RoundInitial, to_channel};
-
-#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
-pub enum Screen {
-    DifficultySelect,
-    CharacterSelect,
-    Game,
-}
-
-#[derive(new, Clone, Debug, Deserialize, CopyGetters, Serialize)]
-pub struct InitialState {
-    #[get_copy = "pub"]
-    screen: Screen,
-    #[get_copy = "pub"]
-    difficulty: u8,
-    #[get_copy = "pub"]
-    p1_character: u8,
-    #[get_copy = "pub"]
-    p1_card: u8,
-    #[get_copy = "pub"]
-    p2_character: u8,
-    #[get_copy = "pub"]
-    p2_card: u8,
-}
-
-#[derive(new, Clone, Debug, Deserialize, Getters, Serialize)]
-pub struct SpectatorInitial {
-    #[get = "pub"]
-    p1_name: String,
-    #[get = "pub"]
-    p2_name: String,
-    #[get = "pub"]
-    game_settings: GameSettings,
-    #[get = "pub"]
-    initial_state: InitialState,
-}
+pub use junowen_lib::session_message::{
+    InitialState, Screen, SpectatorInitial, SpectatorSessionMessage,
+};
 
-#[derive(Debug, Deserialize, Serialize)]
-pub enum SpectatorSessionMessage {
-    InitSpectator(SpectatorInitial),
-    InitRound(RoundInitial),
-    Inputs(u16, u16),
-}
+use super::{chat::Chat, recorder::Recorder, to_channel, RoundInitial};
 
 #[derive(CopyGetters, Getters, Setters)]
 pub struct SpectatorSession {
     _conn: PeerConnection,
+    hook_outgoing_tx: std::sync::mpsc::Sender<SpectatorSessionMessage>,
     hook_incoming_rx: std::sync::mpsc::Receiver<SpectatorSessionMessage>,
+    incoming_buffer: VecDeque<SpectatorSessionMessage>,
     spectator_initial: Option<SpectatorInitial>,
     round_initial: Option<RoundInitial>,
+    /// 区切りの InitSpectator に続く InitRound を読み飛ばす
+    skipping_checkpoint: bool,
+    #[getset(set = "pub")]
+    recorder: Option<Recorder>,
+    #[getset(get = "pub")]
+    chat: Chat,
 }
 
 impl SpectatorSession {
     pub fn new(conn: PeerConnection, data_channel: DataChannel) -> Self {
-        let (_hook_outgoing_tx, hook_incoming_rx) =
+        let (hook_outgoing_tx, hook_incoming_rx, _) =
             to_channel(data_channel, |input| rmp_serde::from_slice(input));
         Self {
             _conn: conn,
+            hook_outgoing_tx,
             hook_incoming_rx,
+            incoming_buffer: VecDeque::new(),
             spectator_initial: None,
             round_initial: None,
+            skipping_checkpoint: false,
+            recorder: None,
+            chat: Chat::default(),
         }
     }
 
@@ -78,48 +51,122 @@ impl SpectatorSession {
         self.spectator_initial.as_ref()
     }
 
+    /**
+     * 観戦中に届く InitSpectator と続く InitRound は、途中から参加する観戦者のための区切りなので読み飛ばす
+     */
+    fn recv(&mut self) -> Result<SpectatorSessionMessage, RecvError> {
+        loop {
+            let msg = match self.incoming_buffer.pop_front() {
+                Some(msg) => msg,
+                None => self.hook_incoming_rx.recv()?,
+            };
+            match msg {
+                SpectatorSessionMessage::InitSpectator(_) if self.spectator_initial.is_some() => {
+                    self.skipping_checkpoint = true;
+                }
+                SpectatorSessionMessage::InitRound(_) if self.skipping_checkpoint => {
+                    self.skipping_checkpoint = false;
+                }
+                msg => return Ok(msg),
+            }
+        }
+    }
+
+    /// ホストが他の観戦者と一緒に送り返してくるので、その時に表示する
+    pub fn send_chat(&mut self, name: &str, text: &str) {
+        let Some(message) = self.chat.compose(name, text) else {
+            return;
+        };
+        let _ = self
+            .hook_outgoing_tx
+            .send(SpectatorSessionMessage::Chat(message));
+    }
+
+    /// 受信済みで未処理のメッセージの数
+    pub fn pending_messages(&mut self) -> usize {
+        self.incoming_buffer
+            .extend(self.hook_incoming_rx.try_iter());
+        self.incoming_buffer.len()
+    }
+
     pub fn recv_init_spectator(&mut self) -> Result<(), RecvError> {
-        let init = match self.hook_incoming_rx.recv()? {
+        let init = match self.recv()? {
             SpectatorSessionMessage::InitSpectator(init) => init,
             msg => {
                 error!("unexpected message: {:?}", msg);
                 return Err(RecvError);
             }
         };
+        if let Some(recorder) = &mut self.recorder {
+            recorder.start_match(
+                init.p1_name().clone(),
+                init.p2_name().clone(),
+                init.game_settings().clone(),
+            );
+        }
         self.spectator_initial = Some(init);
         Ok(())
     }
 
     pub fn dequeue_init_round(&mut self) -> Result<RoundInitial, RecvError> {
-        if let Some(round_initial) = self.round_initial.take() {
-            return Ok(round_initial);
+        let round_initial = match self.round_initial.take() {
+            Some(round_initial) => round_initial,
+            None => loop {
+                match self.recv()? {
+                    SpectatorSessionMessage::InitSpectator(init) => {
+                        error!("unexpected init spectator message: {:?}", init);
+                        return Err(RecvError);
+                    }
+                    SpectatorSessionMessage::InitRound(round_initial) => break round_initial,
+                    SpectatorSessionMessage::Inputs(..) => continue,
+                    SpectatorSessionMessage::Chat(message) => {
+                        self.chat.receive(message);
+                    }
+                }
+            },
+        };
+        if let Some(recorder) = &mut self.recorder {
+            recorder.set_next_round_initial(round_initial.clone());
+        }
+        Ok(round_initial)
+    }
+
+    pub fn dequeue_inputs(&mut self) -> Result<(u16, u16), RecvError> {
+        if self.round_initial.is_some() {
+            return Ok((0, 0));
         }
         loop {
-            match self.hook_incoming_rx.recv()? {
+            match self.recv()? {
                 SpectatorSessionMessage::InitSpectator(init) => {
                     error!("unexpected init spectator message: {:?}", init);
                     return Err(RecvError);
                 }
-                SpectatorSessionMessage::InitRound(round_initial) => return Ok(round_initial),
-                SpectatorSessionMessage::Inputs(..) => continue,
+                SpectatorSessionMessage::InitRound(round_initial) => {
+                    self.round_initial = Some(round_initial);
+                    return Ok((0, 0));
+                }
+                SpectatorSessionMessage::Inputs(p1, p2) => {
+                    if let Some(recorder) = &mut self.recorder {
+                        recorder.record_inputs(p1, p2);
+                    }
+                    return Ok((p1, p2));
+                }
+                SpectatorSessionMessage::Chat(message) => {
+                    self.chat.receive(message);
+                }
             }
         }
     }
 
-    pub fn dequeue_inputs(&mut self) -> Result<(u16, u16), RecvError> {
-        if self.round_initial.is_some() {
-            return Ok((0, 0));
+    pub fn record_round_start(&mut self, selection: &Selection) {
+        if let Some(recorder) = &mut self.recorder {
+            recorder.start_round(selection);
         }
-        match self.hook_incoming_rx.recv()? {
-            SpectatorSessionMessage::InitSpectator(init) => {
-                error!("unexpected init spectator message: {:?}", init);
-                Err(RecvError)
-            }
-            SpectatorSessionMessage::InitRound(round_initial) => {
-                self.round_initial = Some(round_initial);
-                Ok((0, 0))
-            }
-            SpectatorSessionMessage::Inputs(p1, p2) => Ok((p1, p2)),
+    }
+
+    pub fn record_round_end(&mut self) {
+        if let Some(recorder) = &mut self.recorder {
+            recorder.end_round();
         }
     }
 }
@@ -1,26 +1,34 @@
 use anyhow::Result;
 use getset::{CopyGetters, Getters, Setters};
 use junowen_lib::connection::{DataChannel, PeerConnection};
+use tokio::task::JoinHandle;
 use tracing::info;
 
+use crate::TOKIO_RUNTIME;
+
 use super::{
+    chat::ChatMessage,
     spectator::{SpectatorInitial, SpectatorSessionMessage},
-    to_channel, session_message::RoundInitial,
+    to_channel, RoundInitial,
 };
 
 #[derive(CopyGetters, Getters, Setters)]
 pub struct SpectatorHostSession {
-    _conn: PeerConnection,
+    conn: Option<PeerConnection>,
     hook_outgoing_tx: std::sync::mpsc::Sender<SpectatorSessionMessage>,
+    hook_incoming_rx: std::sync::mpsc::Receiver<SpectatorSessionMessage>,
+    outgoing: Option<JoinHandle<()>>,
 }
 
 impl SpectatorHostSession {
     pub fn new(conn: PeerConnection, data_channel: DataChannel) -> Self {
-        let (hook_outgoing_tx, _hook_incoming_rx) =
+        let (hook_outgoing_tx, hook_incoming_rx, outgoing) =
             to_channel(data_channel, |input| rmp_serde::from_slice(input));
         Self {
-            _conn: conn,
+            conn: Some(conn),
             hook_outgoing_tx,
+            hook_incoming_rx,
+            outgoing: Some(outgoing),
         }
     }
 
@@ -41,10 +49,37 @@ impl SpectatorHostSession {
             .hook_outgoing_tx
             .send(SpectatorSessionMessage::Inputs(p1_input, p2_input))?)
     }
+
+    pub fn send_chat(&self, message: ChatMessage) -> Result<()> {
+        Ok(self
+            .hook_outgoing_tx
+            .send(SpectatorSessionMessage::Chat(message))?)
+    }
+
+    /// 観戦者が送ったチャット。観戦者はチャット以外を送れないので、それ以外は捨てる
+    pub fn recv_chats(&self) -> Vec<ChatMessage> {
+        self.hook_incoming_rx
+            .try_iter()
+            .filter_map(|msg| match msg {
+                SpectatorSessionMessage::Chat(message) => Some(message),
+                msg => {
+                    info!("unexpected message from spectator: {:?}", msg);
+                    None
+                }
+            })
+            .collect()
+    }
 }
 
 impl Drop for SpectatorHostSession {
     fn drop(&mut self) {
         info!("spectator session host closed");
+        // 送信待ちのメッセージを送り切ってから接続を閉じる
+        let conn = self.conn.take();
+        let outgoing = self.outgoing.take().unwrap();
+        TOKIO_RUNTIME.spawn(async move {
+            let _ = outgoing.await;
+            drop(conn);
+        });
     }
 }
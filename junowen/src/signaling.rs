@@ -1,5 +1,9 @@
+pub mod invitation;
+pub mod match_result;
 pub mod waiting_for_match;
 
+use std::sync::OnceLock;
+
 use anyhow::Error;
 use getset::{CopyGetters, Getters, MutGetters};
 use junowen_lib::connection::{
@@ -17,6 +21,23 @@ use tracing::info;
 
 use crate::TOKIO_RUNTIME;
 
+static SIGNALING_SERVER_ORIGIN: OnceLock<String> = OnceLock::new();
+
+/// 設定ファイルで指定されたシグナリングサーバーを使う。二度目以降の呼び出しは無視する
+pub fn set_signaling_server_origin(origin: String) {
+    let _ = SIGNALING_SERVER_ORIGIN.set(origin);
+}
+
+pub fn signaling_server_origin() -> &'static str {
+    if let Some(origin) = SIGNALING_SERVER_ORIGIN.get() {
+        origin
+    } else if cfg!(debug_assertions) {
+        "https://qayvs4nki2nl72kf4tn5h5yati0maxpe.lambda-url.ap-northeast-1.on.aws"
+    } else {
+        "https://wxvo3rgklveqwyig4b3q5qupbq0mgvik.lambda-url.ap-northeast-1.on.aws"
+    }
+}
+
 #[derive(CopyGetters, Getters, MutGetters)]
 pub struct Signaling {
     offer_rx: oneshot::Receiver<CompressedSdp>,
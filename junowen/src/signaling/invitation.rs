@@ -0,0 +1,156 @@
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, bail, Result};
+use getset::Getters;
+use junowen_lib::{
+    identity::{from_hex, to_hex, Identity, PublicKey},
+    signaling_server::invitation::{
+        PostInvitationReceiveRequestBody, PostInvitationReceiveResponse, PutInvitationRequestBody,
+        PutInvitationResponse,
+    },
+};
+use tokio::sync::oneshot::{self, error::TryRecvError};
+use tracing::info;
+
+use crate::TOKIO_RUNTIME;
+
+use super::{signaling_server_origin, waiting_for_match::socket::retry_after};
+
+const ERROR_RETRY_AFTER: Duration = Duration::from_secs(10);
+
+fn now_sec() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+#[derive(Debug, Getters)]
+pub struct Invitation {
+    #[get = "pub"]
+    from: PublicKey,
+    #[get = "pub"]
+    from_name: String,
+    /// サーバーが生成した予約部屋の名前
+    #[get = "pub"]
+    room_name: String,
+    #[get = "pub"]
+    received_at: Instant,
+}
+
+async fn send_invitation(identity: Identity, to: PublicKey, from_name: String) -> Result<String> {
+    let url = format!("{}/invitation/{}", signaling_server_origin(), to_hex(&to));
+    let body = PutInvitationRequestBody::new(&identity, &to, from_name, now_sec());
+    info!("PUT {}", url);
+    let res = reqwest::Client::new().put(&url).json(&body).send().await?;
+    info!("{:?}", res);
+    let status = res.status();
+    let text = res.text().await.ok();
+    match PutInvitationResponse::parse(status, text.as_deref())? {
+        PutInvitationResponse::Created(body) => Ok(body.into_room_name()),
+        PutInvitationResponse::BadRequest => bail!("bad request"),
+        PutInvitationResponse::Conflict => bail!("The friend has another invitation"),
+    }
+}
+
+/** 招待を送り、サーバーが生成した部屋の名前を受け取る */
+pub struct SendingInvitation {
+    result_rx: oneshot::Receiver<Result<String>>,
+}
+
+impl SendingInvitation {
+    pub fn new(identity: Identity, to: PublicKey, from_name: String) -> Self {
+        let (result_tx, result_rx) = oneshot::channel();
+        TOKIO_RUNTIME.spawn(async move {
+            let _ = result_tx.send(send_invitation(identity, to, from_name).await);
+        });
+        Self { result_rx }
+    }
+
+    /// 送信中なら None
+    pub fn try_recv(&mut self) -> Option<Result<String>> {
+        match self.result_rx.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Closed) => Some(Err(anyhow!("aborted"))),
+        }
+    }
+}
+
+async fn receive_invitation(identity: Identity) -> Result<(Option<Invitation>, u32)> {
+    let public_key = identity.public_key();
+    let url = format!(
+        "{}/invitation/{}/receive",
+        signaling_server_origin(),
+        to_hex(&public_key)
+    );
+    let body = PostInvitationReceiveRequestBody::new(&identity, now_sec());
+    let res = reqwest::Client::new().post(&url).json(&body).send().await?;
+    let status = res.status();
+    let retry_after = retry_after(&res);
+    let text = res.text().await.ok();
+    match PostInvitationReceiveResponse::parse(status, retry_after, text.as_deref())? {
+        PostInvitationReceiveResponse::BadRequest => bail!("bad request"),
+        PostInvitationReceiveResponse::NoContent { retry_after } => Ok((None, retry_after)),
+        PostInvitationReceiveResponse::Ok(body) => {
+            info!("received an invitation from {}", body.from_name());
+            let invitation = Invitation {
+                from: from_hex(body.from())?,
+                from_name: body.from_name().clone(),
+                room_name: body.room_name().clone(),
+                received_at: Instant::now(),
+            };
+            Ok((Some(invitation), 0))
+        }
+    }
+}
+
+/**
+ * 自分宛ての招待を受け取る
+ *
+ * update() を呼んでいる間、つまりロビーにいる間だけポーリングする
+ */
+pub struct InvitationInbox {
+    identity: Identity,
+    result_rx: Option<oneshot::Receiver<Result<(Option<Invitation>, u32)>>>,
+    next_poll_at: Instant,
+}
+
+impl InvitationInbox {
+    pub fn new(identity: Identity) -> Self {
+        Self {
+            identity,
+            result_rx: None,
+            next_poll_at: Instant::now(),
+        }
+    }
+
+    pub fn update(&mut self) -> Option<Invitation> {
+        let mut invitation = None;
+        if let Some(result_rx) = &mut self.result_rx {
+            let next_poll_after = match result_rx.try_recv() {
+                Err(TryRecvError::Empty) => return None,
+                Ok(Ok((received, retry_after))) => {
+                    invitation = received;
+                    Duration::from_secs(retry_after as u64)
+                }
+                Ok(Err(err)) => {
+                    info!("failed to receive invitations: {}", err);
+                    ERROR_RETRY_AFTER
+                }
+                Err(TryRecvError::Closed) => ERROR_RETRY_AFTER,
+            };
+            self.result_rx = None;
+            self.next_poll_at = Instant::now() + next_poll_after;
+        }
+        if Instant::now() >= self.next_poll_at {
+            let (result_tx, result_rx) = oneshot::channel();
+            let identity = self.identity.clone();
+            TOKIO_RUNTIME.spawn(async move {
+                let _ = result_tx.send(receive_invitation(identity).await);
+            });
+            self.result_rx = Some(result_rx);
+        }
+        invitation
+    }
+}
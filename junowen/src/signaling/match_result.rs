@@ -0,0 +1,38 @@
+use anyhow::Result;
+use junowen_lib::{
+    identity::Identity,
+    signaling_server::ladder::{MatchResult, PostMatchResultRequestBody, PostMatchResultResponse},
+};
+use tracing::{info, warn};
+
+use crate::TOKIO_RUNTIME;
+
+use super::signaling_server_origin;
+
+async fn post_match_result(identity: Identity, result: MatchResult) -> Result<()> {
+    let url = format!("{}/ladder/match-result", signaling_server_origin());
+    let body = PostMatchResultRequestBody::new(&identity, result);
+    info!("POST {}", url);
+    let res = reqwest::Client::new().post(&url).json(&body).send().await?;
+    let status = res.status();
+    let text = res.text().await.ok();
+    match PostMatchResultResponse::parse(status, text.as_deref())? {
+        PostMatchResultResponse::Accepted => info!("match result accepted"),
+        PostMatchResultResponse::Recorded(body) => info!(
+            "match result recorded: p1 {}, p2 {}",
+            body.p1_rating, body.p2_rating
+        ),
+        PostMatchResultResponse::BadRequest => warn!("match result rejected"),
+        PostMatchResultResponse::Conflict => warn!("match result did not match the opponent's"),
+    }
+    Ok(())
+}
+
+/// 結果を待たずに報告する。両者の報告が一致したときだけサーバーに記録される
+pub fn report_match_result(identity: Identity, result: MatchResult) {
+    TOKIO_RUNTIME.spawn(async move {
+        if let Err(err) = post_match_result(identity, result).await {
+            warn!("failed to report match result: {}", err);
+        }
+    });
+}
@@ -2,14 +2,15 @@ mod reserved_room_opponent_socket;
 mod reserved_room_spectator_host_socket;
 mod reserved_room_spectator_socket;
 mod shared_room_opponent_socket;
-mod socket;
+pub mod socket;
 pub mod waiting_for_spectator;
 mod waiting_in_room;
 
 use derive_new::new;
+use junowen_lib::session_message::MatchProposal;
 use tokio::sync::mpsc;
 
-use crate::session::{battle::BattleSession, spectator::SpectatorSession};
+use crate::session::{acceptance::Acceptance, battle::BattleSession, spectator::SpectatorSession};
 
 pub use waiting_for_spectator::{WaitingForPureP2pSpectator, WaitingForSpectator};
 pub use waiting_in_room::{
@@ -33,12 +34,22 @@ pub enum WaitingForOpponent {
 }
 
 impl WaitingForOpponent {
+    pub fn acceptance_mut(&mut self) -> Option<&mut Acceptance> {
+        match self {
+            Self::SharedRoom(waiting) => waiting.acceptance_mut(),
+            Self::ReservedRoom(waiting) => waiting.acceptance_mut(),
+            Self::PureP2p(_) => None,
+        }
+    }
+
+    /// 部屋で接続した場合は、対戦前の確認で双方が承諾するまでセッションを返さない
     pub fn try_into_session_and_waiting_for_spectator(
         self,
+        make_proposal: impl FnOnce(bool) -> MatchProposal,
     ) -> Result<(BattleSession, WaitingForSpectator), Self> {
         match self {
             Self::SharedRoom(waiting) => waiting
-                .try_into_session()
+                .try_into_accepted_session(make_proposal)
                 .map(|session| {
                     (
                         session,
@@ -47,7 +58,7 @@ impl WaitingForOpponent {
                 })
                 .map_err(WaitingForOpponent::SharedRoom),
             Self::ReservedRoom(waiting) => waiting
-                .try_into_session_and_waiting_for_spectator()
+                .try_into_session_and_waiting_for_spectator(make_proposal)
                 .map_err(WaitingForOpponent::ReservedRoom),
             Self::PureP2p(mut waiting) => waiting
                 .battle_session_rx
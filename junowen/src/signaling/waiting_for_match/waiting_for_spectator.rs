@@ -7,7 +7,6 @@ use junowen_lib::{
         parse_signaling_code, socket::async_read_write_socket::SignalingServerMessage,
         SignalingCodeType,
     },
-    structs::app::{MainMenu, ScreenId},
     Th19,
 };
 use tokio::sync::mpsc::{self, error::TryRecvError};
@@ -85,19 +84,8 @@ impl WaitingForPureP2pSpectator {
         }
     }
 
-    fn update_inner(
-        &mut self,
-        current_pushed: bool,
-        main_menu: Option<&MainMenu>,
-        th19: &Th19,
-    ) -> Result<()> {
-        let selection = th19.selection();
-        self.set_ready(
-            main_menu.is_some()
-                && main_menu.unwrap().screen_id() == ScreenId::DifficultySelect
-                && selection.p1().card == 0
-                && selection.p2().card == 0,
-        );
+    fn update_inner(&mut self, current_pushed: bool, ready: bool, th19: &Th19) -> Result<()> {
+        self.set_ready(ready);
 
         match self {
             Self::Standby { pushed, .. } => {
@@ -159,8 +147,8 @@ impl WaitingForPureP2pSpectator {
         }
     }
 
-    pub fn update(&mut self, pushed: bool, menu: Option<&MainMenu>, th19: &Th19) {
-        if let Err(err) = self.update_inner(pushed, menu, th19) {
+    pub fn update(&mut self, pushed: bool, ready: bool, th19: &Th19) {
+        if let Err(err) = self.update_inner(pushed, ready, th19) {
             info!("spectator host error: {:?}", err);
             *self = Self::Standby {
                 ready: false,
@@ -179,12 +167,12 @@ impl WaitingForSpectator {
     pub fn try_recv_session(
         &mut self,
         pushed: bool,
-        menu: Option<&MainMenu>,
+        ready: bool,
         th19: &Th19,
     ) -> Option<SpectatorHostSession> {
         match self {
             Self::PureP2p(waiting) => {
-                waiting.update(pushed, menu, th19);
+                waiting.update(pushed, ready, th19);
                 match waiting {
                     WaitingForPureP2pSpectator::Standby { .. }
                     | WaitingForPureP2pSpectator::SignalingCodeRecved { .. } => None,
@@ -1,8 +1,14 @@
-use std::time::{Duration, Instant};
+use std::{
+    mem,
+    time::{Duration, Instant},
+};
 
-use anyhow::Error;
+use anyhow::{anyhow, Error};
 use getset::Getters;
-use junowen_lib::connection::{signaling::socket::SignalingSocket, DataChannel, PeerConnection};
+use junowen_lib::{
+    connection::{signaling::socket::SignalingSocket, DataChannel, PeerConnection},
+    session_message::MatchProposal,
+};
 use tokio::{
     sync::{
         mpsc::{self},
@@ -16,13 +22,19 @@ use tracing::{debug, debug_span, info, Instrument};
 
 use crate::{
     session::{
-        battle::BattleSession, spectator::SpectatorSession, spectator_host::SpectatorHostSession,
+        acceptance::{Acceptance, AcceptanceState},
+        battle::BattleSession,
+        spectator::SpectatorSession,
+        spectator_host::SpectatorHostSession,
     },
-    signaling::waiting_for_match::{
-        reserved_room_opponent_socket::SignalingServerReservedRoomOpponentSocket,
-        reserved_room_spectator_host_socket::SignalingServerReservedRoomSpectatorHostSocket,
-        shared_room_opponent_socket::SignalingServerSharedRoomOpponentSocket,
-        waiting_for_spectator::WaitingForPureP2pSpectator,
+    signaling::{
+        signaling_server_origin,
+        waiting_for_match::{
+            reserved_room_opponent_socket::SignalingServerReservedRoomOpponentSocket,
+            reserved_room_spectator_host_socket::SignalingServerReservedRoomSpectatorHostSocket,
+            shared_room_opponent_socket::SignalingServerSharedRoomOpponentSocket,
+            waiting_for_spectator::WaitingForPureP2pSpectator,
+        },
     },
     TOKIO_RUNTIME,
 };
@@ -31,6 +43,9 @@ use super::{
     reserved_room_spectator_socket::SignalingServerReservedRoomSpectatorSocket, WaitingForSpectator,
 };
 
+/// 対戦を断った相手と、同じ共有部屋で再びマッチしないようにする時間
+const DECLINED_OPPONENT_COOLDOWN: Duration = Duration::from_secs(60);
+
 pub struct RoomKey(String);
 
 #[derive(Getters)]
@@ -42,6 +57,10 @@ pub struct WaitingInRoom<TSession> {
     error_rx: mpsc::Receiver<Error>,
     session_rx: oneshot::Receiver<TSession>,
     abort_tx: watch::Sender<bool>,
+    /// 接続した相手と対戦前の確認をしている間のセッション
+    accepting: Option<(TSession, Acceptance)>,
+    /// 対戦が断られた相手の名前と断られた時刻
+    declined_opponents: Vec<(String, Instant)>,
 }
 
 pub type WaitingForOpponentInSharedRoom = WaitingInRoom<BattleSession>;
@@ -73,11 +92,7 @@ where
         let handle = {
             let room_name = room_name.clone();
             TOKIO_RUNTIME.spawn(async move {
-                let origin = if cfg!(debug_assertions) {
-                    "https://qayvs4nki2nl72kf4tn5h5yati0maxpe.lambda-url.ap-northeast-1.on.aws"
-                } else {
-                    "https://wxvo3rgklveqwyig4b3q5qupbq0mgvik.lambda-url.ap-northeast-1.on.aws"
-                };
+                let origin = signaling_server_origin();
                 let mut socket = create_socket(origin.into(), &room_name, abort_rx.clone());
                 let (conn, dc, host) = loop {
                     match socket.receive_signaling().await {
@@ -107,6 +122,8 @@ where
             error_rx,
             session_rx,
             abort_tx,
+            accepting: None,
+            declined_opponents: vec![],
         }
     }
 }
@@ -119,6 +136,13 @@ impl WaitingForOpponentInSharedRoom {
             room_name,
         )
     }
+
+    pub fn try_into_accepted_session(
+        self,
+        make_proposal: impl FnOnce(bool) -> MatchProposal,
+    ) -> Result<BattleSession, Self> {
+        self.try_into_session_with_acceptance(|session| session, make_proposal, Self::new, true)
+    }
 }
 
 impl WaitingForOpponentInReservedRoom {
@@ -136,13 +160,18 @@ impl WaitingForOpponentInReservedRoom {
     }
 
     pub fn try_into_session_and_waiting_for_spectator(
-        mut self,
+        self,
+        make_proposal: impl FnOnce(bool) -> MatchProposal,
     ) -> Result<(BattleSession, WaitingForSpectator), Self> {
-        let Ok((session, key)) = self.session_rx.try_recv() else {
-            return Err(self);
-        };
+        let room_name = self.room_name.clone();
+        let (session, key) = self.try_into_session_with_acceptance(
+            |session| &mut session.0,
+            make_proposal,
+            Self::new,
+            false,
+        )?;
         let waiting = if let Some(key) = key {
-            let waiting = WaitingForSpectatorInReservedRoom::new(self.room_name.clone(), key.0);
+            let waiting = WaitingForSpectatorInReservedRoom::new(room_name, key.0);
             WaitingForSpectator::ReservedRoom(waiting)
         } else {
             WaitingForSpectator::PureP2p(WaitingForPureP2pSpectator::standby())
@@ -216,6 +245,78 @@ impl<TSession> WaitingInRoom<TSession> {
     pub fn try_into_session(mut self) -> Result<TSession, Self> {
         self.session_rx.try_recv().map_err(|_| self)
     }
+
+    pub fn acceptance(&self) -> Option<&Acceptance> {
+        self.accepting.as_ref().map(|(_, acceptance)| acceptance)
+    }
+
+    pub fn acceptance_mut(&mut self) -> Option<&mut Acceptance> {
+        self.accepting.as_mut().map(|(_, acceptance)| acceptance)
+    }
+
+    fn is_declined_opponent(&self, name: &str) -> bool {
+        self.declined_opponents
+            .iter()
+            .any(|(declined, at)| declined == name && at.elapsed() < DECLINED_OPPONENT_COOLDOWN)
+    }
+
+    /// 接続した相手と対戦前の確認をして、双方が承諾したらセッションを返す。
+    /// 断られたり切断されたりした場合は同じ部屋で待ち受けをやり直す。
+    /// exclude_declined の場合、しばらくの間は断り合った相手を自動で断る
+    fn try_into_session_with_acceptance(
+        mut self,
+        battle_session: fn(&mut TSession) -> &mut BattleSession,
+        make_proposal: impl FnOnce(bool) -> MatchProposal,
+        restart: fn(String) -> Self,
+        exclude_declined: bool,
+    ) -> Result<TSession, Self> {
+        if self.accepting.is_none() {
+            let Ok(mut session) = self.session_rx.try_recv() else {
+                return Err(self);
+            };
+            let proposal = make_proposal(battle_session(&mut session).host());
+            let acceptance = Acceptance::new(battle_session(&mut session), proposal);
+            self.accepting = Some((session, acceptance));
+        }
+        let remote_name = self
+            .acceptance()
+            .and_then(|acceptance| acceptance.remote_proposal().as_ref())
+            .map(|proposal| proposal.name.clone());
+        let excluded = exclude_declined
+            && self
+                .acceptance()
+                .is_some_and(|acceptance| acceptance.local_answer().is_none())
+            && remote_name
+                .as_deref()
+                .is_some_and(|name| self.is_declined_opponent(name));
+        let (session, acceptance) = self.accepting.as_mut().unwrap();
+        if excluded {
+            info!("declined opponent excluded");
+            acceptance.answer(false);
+        }
+        let (error, declined) = match acceptance.update(battle_session(session)) {
+            Ok(AcceptanceState::Pending | AcceptanceState::Declining) => return Err(self),
+            Ok(AcceptanceState::Accepted) => return Ok(self.accepting.take().unwrap().0),
+            Ok(AcceptanceState::Declined) => (
+                (!excluded && acceptance.remote_answer() == Some(false))
+                    .then(|| anyhow!("Declined by the opponent")),
+                !excluded,
+            ),
+            Err(_) => (Some(anyhow!("The opponent disconnected")), false),
+        };
+        info!("match declined");
+        let mut waiting = restart(self.room_name.clone());
+        waiting.errors = mem::take(&mut self.errors);
+        waiting.errors.extend(error);
+        waiting.declined_opponents = mem::take(&mut self.declined_opponents);
+        waiting
+            .declined_opponents
+            .retain(|(_, at)| at.elapsed() < DECLINED_OPPONENT_COOLDOWN);
+        if let (true, Some(name)) = (declined, remote_name) {
+            waiting.declined_opponents.push((name, Instant::now()));
+        }
+        Err(waiting)
+    }
 }
 
 impl<TSession> Drop for WaitingInRoom<TSession> {
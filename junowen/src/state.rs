@@ -1,57 +1,174 @@
 mod battle_session_state;
+mod delay_practice;
 mod junowen_state;
 mod prepare;
 mod render_parts;
 mod spectator_session_state;
 
-use std::{ffi::c_void, fmt::Display};
+use std::{ffi::c_void, fmt::Display, path::PathBuf};
 
-use getset::{Getters, MutGetters};
+use getset::{CopyGetters, Getters, MutGetters, Setters};
 use junowen_lib::{
+    connection::set_ice_servers,
+    identity::Identity,
+    session_message::MatchInitial,
     structs::{others::RenderingText, selection::Selection},
     Fn011560, Fn0b7d40, Fn0d5ae0, Fn10f720, Th19,
 };
-use tracing::debug;
+use tracing::{debug, error};
 
-use self::junowen_state::JunowenState;
+use self::{delay_practice::DelayPracticeState, junowen_state::JunowenState};
 use crate::{
-    file::{Features, SettingsRepo},
+    file::{load_or_create_identity, Features, OverlayConfig, RulePreset, SettingsRepo},
     in_game_lobby::{Lobby, TitleMenuModifier},
+    known_players::KnownPlayers,
+    lang::init_lang,
+    opponent_history::OpponentHistory,
+    signaling::set_signaling_server_origin,
 };
 
+/// ホストとして提示するルール。プリセットがなければゲームのメニューの設定を使う
+fn host_match_initial(
+    th19: &Th19,
+    rule_preset: Option<&RulePreset>,
+    first_to: Option<u8>,
+) -> MatchInitial {
+    match rule_preset {
+        Some(preset) => MatchInitial {
+            game_settings: preset.game_settings.clone(),
+            first_to,
+            rule_preset: Some(preset.name.clone()),
+            delay: None,
+        },
+        None => MatchInitial {
+            game_settings: th19.game_settings_in_menu().unwrap(),
+            first_to,
+            rule_preset: None,
+            delay: None,
+        },
+    }
+}
+
+#[derive(CopyGetters, Getters, Setters)]
+pub struct SessionConfig {
+    #[get_copy = "pub"]
+    spectator_delay_frames: u32,
+    /// ホストとして提案する N 本先取のセットの長さ
+    #[get_copy = "pub"]
+    first_to: Option<u8>,
+    /// ホストとして対戦の開始時に設定するディレイ
+    #[get_copy = "pub"]
+    default_delay: Option<u8>,
+    /// ホストとしてゲストが希望するディレイを確認せずに受け入れる
+    #[get_copy = "pub"]
+    auto_accept_delay_requests: bool,
+    /// ホストとして MatchInitial に使うルール。None ならゲームのメニューの設定を使う
+    #[getset(get = "pub", set = "pub")]
+    rule_preset: Option<RulePreset>,
+    /** 記録しない場合は None */
+    #[get = "pub"]
+    replay_dir: Option<PathBuf>,
+    /// identity 機能が無効なら None
+    #[get = "pub"]
+    identity: Option<Identity>,
+    #[get = "pub"]
+    known_players: KnownPlayers,
+    #[get = "pub"]
+    opponent_history: OpponentHistory,
+    /// 署名した対戦結果をシグナリングサーバーに報告する
+    #[get_copy = "pub"]
+    ladder: bool,
+    #[get = "pub"]
+    overlay: OverlayConfig,
+    #[get = "pub"]
+    settings_repo: SettingsRepo,
+}
+
 #[derive(Getters, MutGetters)]
 pub struct State {
-    features: Vec<Features>,
+    session_config: SessionConfig,
     #[getset(get_mut = "pub")]
     th19: Th19,
     title_menu_modifier: TitleMenuModifier,
     lobby: Lobby,
     junowen_state: JunowenState,
+    delay_practice: DelayPracticeState,
 }
 
 impl State {
-    pub async fn new(settings_repo: SettingsRepo, th19: Th19) -> Self {
+    pub async fn new(settings_repo: SettingsRepo, module_dir: &str, th19: Th19) -> Self {
+        let config = settings_repo.load_config().await;
+        init_lang(config.lang.as_deref());
+        if let Some(origin) = &config.signaling.server {
+            set_signaling_server_origin(origin.clone());
+        }
+        if !config.signaling.ice_servers.is_empty() {
+            set_ice_servers(config.signaling.ice_servers.clone());
+        }
+        let features = &config.features;
+        let identity = if features.contains(&Features::Identity) {
+            load_or_create_identity(&settings_repo.identity_path())
+                .await
+                .map_err(|err| error!("failed to load identity: {}", err))
+                .ok()
+        } else {
+            None
+        };
+        let known_players = KnownPlayers::new(PathBuf::from(module_dir).join("known_players.toml"));
+        let opponent_history =
+            OpponentHistory::new(PathBuf::from(module_dir).join("recent_opponents.toml"));
+        let lobby = Lobby::new(
+            settings_repo.clone(),
+            config.rule_presets(),
+            config.rule_preset.as_deref(),
+            identity.clone(),
+            known_players.clone(),
+            opponent_history.clone(),
+        );
+        let session_config = SessionConfig {
+            spectator_delay_frames: config.spectator_delay_frames(),
+            first_to: config.first_to,
+            default_delay: config.default_delay,
+            auto_accept_delay_requests: config.auto_accept_delay_requests,
+            rule_preset: lobby.rule_preset().cloned(),
+            replay_dir: config.recording.enabled.then(|| {
+                config
+                    .recording
+                    .dir
+                    .clone()
+                    .unwrap_or_else(|| PathBuf::from(module_dir).join("replays"))
+            }),
+            identity,
+            known_players,
+            opponent_history,
+            ladder: features.contains(&Features::Ladder),
+            overlay: config.overlay.clone(),
+            settings_repo,
+        };
         Self {
-            features: settings_repo.features().await,
+            session_config,
             th19,
             title_menu_modifier: TitleMenuModifier::new(),
-            lobby: Lobby::new(settings_repo),
+            lobby,
             junowen_state: JunowenState::Standby,
+            delay_practice: DelayPracticeState::default(),
         }
     }
 
     fn abort_session(&mut self, err: impl Display) {
         debug!("session aborted: {}", err);
+        self.junowen_state.remember_opponent(&self.session_config);
         self.junowen_state.abort_session(&mut self.th19);
         self.lobby.reset_depth();
     }
 
     pub fn on_input_players(&mut self) {
         let has_session = self.junowen_state.has_session();
-        match self
-            .junowen_state
-            .on_input_players(&mut self.th19, self.lobby.waiting_for_match_mut())
-        {
+        match self.junowen_state.on_input_players(
+            &mut self.th19,
+            self.lobby.waiting_for_match_mut(),
+            &self.session_config,
+        ) {
             Ok(_) => {
                 if has_session && self.junowen_state.has_session() {
                     self.lobby.reset_depth();
@@ -61,6 +178,10 @@ impl State {
                 self.abort_session(err);
             }
         }
+        let settings = (!self.junowen_state.has_session())
+            .then(|| self.lobby.delay_practice())
+            .flatten();
+        self.delay_practice.update_th19(settings, &mut self.th19);
     }
 
     pub fn on_input_menu(&mut self) {
@@ -71,6 +192,8 @@ impl State {
         ) {
             self.abort_session(err);
         }
+        self.session_config
+            .set_rule_preset(self.lobby.rule_preset().cloned());
     }
 
     pub fn render_object(&self, old: Fn0b7d40, obj_renderer: *const c_void, obj: *const c_void) {
@@ -95,7 +218,7 @@ impl State {
 
     pub fn on_render_texts(&self, text_renderer: *const c_void) {
         self.junowen_state.on_render_texts(
-            &self.features,
+            self.session_config.overlay(),
             &self.th19,
             &self.title_menu_modifier,
             &self.lobby,
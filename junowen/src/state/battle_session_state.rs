@@ -4,17 +4,13 @@ mod in_session;
 mod spectator_host;
 mod utils;
 
-use std::{ffi::c_void, mem, sync::mpsc::RecvError};
+use std::{ffi::c_void, mem, sync::mpsc::RecvError, time::Duration};
 
 use anyhow::Result;
-use junowen_lib::{
-    structs::app::{MainMenu, ScreenId},
-    structs::settings::GameSettings,
-    Th19,
-};
+use junowen_lib::{structs::app::ScreenId, structs::settings::GameSettings, Th19};
 
 use crate::{
-    file::Features, session::battle::BattleSession,
+    file::OverlayConfig, session::battle::BattleSession,
     signaling::waiting_for_match::WaitingForSpectator,
 };
 
@@ -24,6 +20,9 @@ use super::prepare::Prepare;
 
 use {battle_game::BattleGame, battle_select::BattleSelect, spectator_host::SpectatorHostState};
 
+/// セットの勝敗が決まってからセッションを終えるまで、結果を表示しておく時間
+const SET_RESULT_DISPLAY_DURATION: Duration = Duration::from_secs(5);
+
 pub enum BattleSessionState {
     Null,
     Prepare(Prepare<(BattleSession, SpectatorHostState)>),
@@ -40,25 +39,31 @@ pub enum BattleSessionState {
 }
 
 impl BattleSessionState {
-    pub fn prepare(session: BattleSession, waiting: WaitingForSpectator) -> Self {
-        Self::Prepare(Prepare::new((session, SpectatorHostState::new(waiting))))
+    pub fn prepare(
+        session: BattleSession,
+        waiting: WaitingForSpectator,
+        spectator_delay_frames: u32,
+    ) -> Self {
+        Self::Prepare(Prepare::new((
+            session,
+            SpectatorHostState::new(waiting, spectator_delay_frames),
+        )))
     }
 
-    pub fn game_settings(&self) -> Option<&GameSettings> {
+    pub fn session(&self) -> &BattleSession {
         match self {
             Self::Null => unreachable!(),
-            Self::GameLoading { session, .. } | Self::BackToSelect { session, .. } => {
-                session.match_initial().map(|x| &x.game_settings)
-            }
-            Self::Prepare(i) => {
-                let (session, _) = i.session();
-                session.match_initial().map(|x| &x.game_settings)
-            }
-            Self::Select(i) => i.session().match_initial().map(|x| &x.game_settings),
-            Self::Game(i) => i.session().match_initial().map(|x| &x.game_settings),
+            Self::GameLoading { session, .. } | Self::BackToSelect { session, .. } => session,
+            Self::Prepare(i) => &i.session().0,
+            Self::Select(i) => i.session(),
+            Self::Game(i) => i.session(),
         }
     }
 
+    pub fn game_settings(&self) -> Option<&GameSettings> {
+        self.session().match_initial().map(|x| &x.game_settings)
+    }
+
     pub fn change_to_select(&mut self) {
         let old = mem::replace(self, Self::Null);
         let (session, spectator_host_state) = match old {
@@ -108,7 +113,7 @@ impl BattleSessionState {
         }
     }
 
-    pub fn update_state(&mut self, th19: &Th19) -> Option<Option<&'static MainMenu>> {
+    pub fn update_state(&mut self, th19: &Th19) -> Option<Option<ScreenId>> {
         match self {
             Self::Null => unreachable!(),
             Self::Prepare(prepare) => {
@@ -118,17 +123,24 @@ impl BattleSessionState {
                 if prepare.update_state(main_menu, th19) {
                     self.change_to_select();
                 }
-                Some(Some(main_menu))
+                Some(Some(main_menu.screen_id()))
             }
-            Self::Select { .. } => {
+            Self::Select(select) => {
+                if select
+                    .session()
+                    .set_score()
+                    .is_some_and(|set_score| set_score.is_over_for(SET_RESULT_DISPLAY_DURATION))
+                {
+                    return None;
+                }
                 let main_menu = th19.app().main_loop_tasks().find_main_menu().unwrap();
                 match main_menu.screen_id() {
                     ScreenId::GameLoading => {
                         self.change_to_game_loading();
-                        Some(Some(main_menu))
+                        Some(Some(main_menu.screen_id()))
                     }
                     ScreenId::PlayerMatchupSelect => None,
-                    _ => Some(Some(main_menu)),
+                    _ => Some(Some(main_menu.screen_id())),
                 }
             }
             Self::GameLoading { .. } => {
@@ -141,10 +153,12 @@ impl BattleSessionState {
                 self.change_to_game();
                 Some(None)
             }
-            Self::Game { .. } => {
+            Self::Game(game) => {
                 if th19.round_frame().is_some() {
                     return Some(None);
                 }
+                game.session_mut().record_round_end();
+                game.session_mut().on_match_over();
                 self.change_to_back_to_select();
                 Some(None)
             }
@@ -153,23 +167,23 @@ impl BattleSessionState {
                     return Some(None);
                 };
                 if main_menu.screen_id() != ScreenId::CharacterSelect {
-                    return Some(Some(main_menu));
+                    return Some(Some(main_menu.screen_id()));
                 }
                 self.change_to_select();
-                Some(Some(main_menu))
+                Some(Some(main_menu.screen_id()))
             }
         }
     }
 
     pub fn update_th19_on_input_players(
         &mut self,
-        menu: Option<&MainMenu>,
+        screen: Option<ScreenId>,
         th19: &mut Th19,
     ) -> Result<(), RecvError> {
         match self {
             Self::Null => unreachable!(),
             Self::Prepare(prepare) => prepare.update_th19_on_input_players(th19),
-            Self::Select(select) => select.update_th19_on_input_players(menu.unwrap(), th19)?,
+            Self::Select(select) => select.update_th19_on_input_players(screen.unwrap(), th19)?,
             Self::GameLoading { .. } => {}
             Self::Game(game) => game.update_th19(th19)?,
             Self::BackToSelect { .. } => {}
@@ -191,7 +205,7 @@ impl BattleSessionState {
 
     pub fn on_render_texts(
         &self,
-        features: &[Features],
+        overlay: &OverlayConfig,
         th19: &Th19,
         text_renderer: *const c_void,
     ) {
@@ -214,34 +228,40 @@ impl BattleSessionState {
                 } => (session, Some(spectator_host_state)),
             }
         };
+        let remote_player_label = session.remote_player_label();
         let (p1_name, p2_name) = if session.host() {
-            (
-                th19.vs_mode().player_name(),
-                session.remote_player_name().as_str(),
-            )
+            (th19.vs_mode().player_name(), remote_player_label.as_str())
         } else {
-            (
-                session.remote_player_name().as_str(),
-                th19.vs_mode().player_name(),
-            )
+            (remote_player_label.as_str(), th19.vs_mode().player_name())
         };
 
         let game_settings = 'ret: {
-            if !features.contains(&Features::ShowSettings) {
-                break 'ret None;
-            }
             let Self::Select(select) = self else {
                 break 'ret None;
             };
-            select.session().match_initial().map(|x| &x.game_settings)
+            let Some(match_initial) = select.session().match_initial() else {
+                break 'ret None;
+            };
+            // ホストがプリセットを選んでいる場合はゲームのメニューと異なりうるので常に表示する
+            if !overlay.game_settings && match_initial.rule_preset.is_none() {
+                break 'ret None;
+            }
+            Some(&match_initial.game_settings)
         };
         let status = RenderingStatus {
             host: session.host(),
             delay: session.delay(),
+            proposed_delay: (!session.host())
+                .then(|| session.match_initial().and_then(|x| x.delay))
+                .flatten(),
+            pending_delay: session.pending_delay(),
+            delay_request: session.delay_request(),
             p1_name,
             p2_name,
             game_settings,
             spectator_host_state,
+            chat: (overlay.chat && !matches!(self, Self::Game(_))).then(|| session.chat()),
+            set_score: session.set_score(),
         };
         in_session::on_render_texts(th19, text_renderer, status);
     }
@@ -5,9 +5,15 @@ use derive_new::new;
 use getset::{Getters, MutGetters};
 use junowen_lib::{structs::input_devices::InputValue, Th19};
 
-use crate::{helper::inputed_number, session::battle::BattleSession};
+use crate::{
+    helper::{pushed_f1, DelayChange},
+    session::battle::BattleSession,
+};
 
-use super::{spectator_host::SpectatorHostState, utils::init_round};
+use super::{
+    spectator_host::SpectatorHostState,
+    utils::{init_round, inputed_delay},
+};
 
 #[derive(new, Getters, MutGetters)]
 pub struct BattleGame {
@@ -15,6 +21,8 @@ pub struct BattleGame {
     session: BattleSession,
     #[getset(get = "pub")]
     spectator_host_state: SpectatorHostState,
+    #[new(default)]
+    prev_delay_change: Option<DelayChange>,
 }
 
 impl BattleGame {
@@ -34,12 +42,14 @@ impl BattleGame {
                 .set_current(InputValue::empty());
             return Ok(());
         }
+        self.session.record_round_start(th19.selection());
+        let current_pushed = pushed_f1(th19.input_devices());
         let input_devices = th19.input_devices_mut();
-        let delay = if self.session.host() {
-            inputed_number(input_devices)
-        } else {
-            None
-        };
+        let delay = inputed_delay(
+            input_devices,
+            &mut self.session,
+            &mut self.prev_delay_change,
+        );
         let (p1, p2) = self
             .session
             .enqueue_input_and_dequeue(input_devices.p1_input().current().bits() as u16, delay)?;
@@ -51,12 +61,14 @@ impl BattleGame {
             .set_current((p2 as u32).try_into().unwrap());
 
         self.spectator_host_state
-            .update(false, None, th19, &self.session, p1, p2);
+            .update(current_pushed, None, th19, &mut self.session, p1, p2);
 
         Ok(())
     }
 
     pub fn on_round_over(&mut self, th19: &mut Th19) -> Result<(), RecvError> {
+        self.session.record_round_end();
+        self.session.on_round_over();
         init_round(th19, &mut self.session, &mut self.spectator_host_state)
     }
 }
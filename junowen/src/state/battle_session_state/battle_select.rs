@@ -3,39 +3,39 @@ use std::sync::mpsc::RecvError;
 use anyhow::Result;
 use derive_new::new;
 use getset::{Getters, MutGetters};
-use junowen_lib::{
-    structs::app::{MainMenu, ScreenId},
-    th19_helpers::reset_cursors,
-    Th19,
-};
+use junowen_lib::{structs::app::ScreenId, th19_helpers::reset_cursors, Th19};
 use tracing::trace;
 
 use crate::{
-    helper::{inputed_number, pushed_f1},
-    session::{battle::BattleSession, MatchInitial},
+    helper::{inputed_function_key, inputed_match_winner, pushed_f1, DelayChange},
+    session::{battle::BattleSession, chat::CANNED_PHRASES},
+    state::host_match_initial,
 };
 
-use super::{spectator_host::SpectatorHostState, utils::init_round};
+use super::{
+    spectator_host::SpectatorHostState,
+    utils::{init_round, inputed_delay},
+};
 
 fn init_match(th19: &mut Th19, battle_session: &mut BattleSession) -> Result<(), RecvError> {
     trace!("init_match");
     th19.set_no_wait(false);
     reset_cursors(th19);
     if battle_session.host() {
-        let init = MatchInitial {
-            game_settings: th19.game_settings_in_menu().unwrap(),
-        };
-        let (remote_player_name, opt) = battle_session
-            .init_match(th19.vs_mode().player_name().to_string(), Some(init.clone()))?;
+        let init = host_match_initial(
+            th19,
+            battle_session.rule_preset().as_ref(),
+            battle_session.first_to(),
+        );
+        let (remote_player_name, opt) =
+            battle_session.init_match(th19.vs_mode().player_name().to_string(), Some(init))?;
         battle_session.set_remote_player_name(remote_player_name);
         debug_assert!(opt.is_none());
-        battle_session.set_match_initial(Some(init));
     } else {
         let (remote_player_name, opt) =
             battle_session.init_match(th19.vs_mode().player_name().to_string(), None)?;
         battle_session.set_remote_player_name(remote_player_name);
         debug_assert!(opt.is_some());
-        battle_session.set_match_initial(opt);
     }
     Ok(())
 }
@@ -48,6 +48,10 @@ pub struct BattleSelect {
     spectator_host_state: SpectatorHostState,
     #[new(value = "true")]
     first_time: bool,
+    #[new(default)]
+    prev_function_key: Option<u8>,
+    #[new(default)]
+    prev_delay_change: Option<DelayChange>,
 }
 
 impl BattleSelect {
@@ -55,9 +59,19 @@ impl BattleSelect {
         (self.session, self.spectator_host_state)
     }
 
+    /// F2 から F9 で定型文を送信する
+    fn send_canned_phrase(&mut self, th19: &Th19) {
+        let key = inputed_function_key(th19.input_devices());
+        if let Some(key) = key.filter(|&key| Some(key) != self.prev_function_key) {
+            let phrase = CANNED_PHRASES[(key - 2) as usize];
+            self.session.send_chat(th19.vs_mode().player_name(), phrase);
+        }
+        self.prev_function_key = key;
+    }
+
     pub fn update_th19_on_input_players(
         &mut self,
-        main_menu: &MainMenu,
+        screen: ScreenId,
         th19: &mut Th19,
     ) -> Result<(), RecvError> {
         if self.first_time {
@@ -68,16 +82,23 @@ impl BattleSelect {
             init_round(th19, &mut self.session, &mut self.spectator_host_state)?;
         }
 
-        if main_menu.screen_id() == ScreenId::DifficultySelect {
+        if screen == ScreenId::DifficultySelect {
             return Ok(());
         }
+        if screen == ScreenId::CharacterSelect {
+            self.send_canned_phrase(th19);
+            if let Some(winner) = inputed_match_winner(th19.input_devices()) {
+                self.session.report_match_result(winner);
+            }
+        }
 
+        let current_pushed = pushed_f1(th19.input_devices());
         let input_devices = th19.input_devices_mut();
-        let delay = if self.session.host() {
-            inputed_number(input_devices)
-        } else {
-            None
-        };
+        let delay = inputed_delay(
+            input_devices,
+            &mut self.session,
+            &mut self.prev_delay_change,
+        );
         let (p1, p2) = self
             .session
             .enqueue_input_and_dequeue(input_devices.p1_input().current().bits() as u16, delay)?;
@@ -88,24 +109,36 @@ impl BattleSelect {
             .p2_input_mut()
             .set_current((p2 as u32).try_into().unwrap());
 
-        self.spectator_host_state
-            .update(false, Some(main_menu), th19, &self.session, p1, p2);
+        self.spectator_host_state.update(
+            current_pushed,
+            Some(screen),
+            th19,
+            &mut self.session,
+            p1,
+            p2,
+        );
 
         Ok(())
     }
 
     pub fn update_th19_on_input_menu(&mut self, th19: &mut Th19) -> Result<(), RecvError> {
-        let main_menu = th19.app().main_loop_tasks().find_main_menu().unwrap();
-        if main_menu.screen_id() != ScreenId::DifficultySelect {
+        let screen = th19
+            .app()
+            .main_loop_tasks()
+            .find_main_menu()
+            .unwrap()
+            .screen_id();
+        if screen != ScreenId::DifficultySelect {
             return Ok(());
         }
 
         let input_devices = th19.input_devices();
-        let delay = if self.session.host() {
-            inputed_number(input_devices)
-        } else {
-            None
-        };
+        let current_pushed = pushed_f1(input_devices);
+        let delay = inputed_delay(
+            input_devices,
+            &mut self.session,
+            &mut self.prev_delay_change,
+        );
         let menu_input = th19.menu_input_mut();
         let (p1, p2) = self
             .session
@@ -113,12 +146,11 @@ impl BattleSelect {
         let input = if p1 != 0 { p1 } else { p2 };
         menu_input.set_current((input as u32).try_into().unwrap());
 
-        let current_pushed = pushed_f1(input_devices);
         self.spectator_host_state.update(
             current_pushed,
-            Some(main_menu),
+            Some(screen),
             th19,
-            &self.session,
+            &mut self.session,
             p1,
             p2,
         );
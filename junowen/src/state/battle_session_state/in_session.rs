@@ -1,10 +1,15 @@
-use std::{borrow::Cow, ffi::c_void};
+use std::ffi::c_void;
 
 use junowen_lib::{structs::settings::GameSettings, Th19};
 
 use crate::{
+    lang::{pad_end, text_width, tr, tr_format},
+    session::{chat::Chat, set_score::SetScore},
     signaling::waiting_for_match::{WaitingForPureP2pSpectator, WaitingForSpectator},
-    state::render_parts::{render_footer, render_game_settings, render_names},
+    state::render_parts::{
+        render_chat_log, render_footer, render_game_settings, render_names, render_set_score,
+        underline,
+    },
 };
 
 use super::spectator_host::SpectatorHostState;
@@ -12,26 +17,43 @@ use super::spectator_host::SpectatorHostState;
 pub struct RenderingStatus<'a> {
     pub host: bool,
     pub delay: u8,
+    /// ゲストに見せる、ホストが開始時に提案したディレイ
+    pub proposed_delay: Option<u8>,
+    /// ホストが変更を指示し、まだ反映されていないディレイ
+    pub pending_delay: Option<u8>,
+    /// ホストなら答えていないゲストの希望、ゲストなら送った希望
+    pub delay_request: Option<u8>,
     pub p1_name: &'a str,
     pub p2_name: &'a str,
     pub game_settings: Option<&'a GameSettings>,
     pub spectator_host_state: Option<&'a SpectatorHostState>,
+    pub chat: Option<&'a Chat>,
+    pub set_score: Option<&'a SetScore>,
 }
 
 pub fn on_render_texts(th19: &Th19, text_renderer: *const c_void, status: RenderingStatus) {
     render_names(th19, text_renderer, status.p1_name, status.p2_name);
+    if let Some(set_score) = status.set_score {
+        render_set_score(
+            th19,
+            text_renderer,
+            set_score,
+            status.p1_name,
+            status.p2_name,
+        );
+    }
     if let Some(game_settings) = status.game_settings {
         render_game_settings(th19, text_renderer, game_settings);
     }
+    if let Some(chat) = status.chat {
+        render_chat_log(th19, text_renderer, chat);
+    }
 
-    let (msg2_rear, msg2_front) = if let Some(spectator_host_state) = status.spectator_host_state {
+    let msg2 = if let Some(spectator_host_state) = status.spectator_host_state {
         if spectator_host_state.count_spectators() > 0 {
-            (
-                "               ",
-                Cow::Owned(format!(
-                    "Spectator(s): {}",
-                    spectator_host_state.count_spectators()
-                )),
+            tr_format(
+                "Spectator(s): {}",
+                &[&spectator_host_state.count_spectators()],
             )
         } else {
             match spectator_host_state.waiting() {
@@ -39,31 +61,57 @@ pub fn on_render_texts(th19: &Th19, text_renderer: *const c_void, status: Render
                     WaitingForPureP2pSpectator::Standby { ready: false, .. }
                     | WaitingForPureP2pSpectator::SignalingCodeRecved { ready: false, .. }
                     | WaitingForPureP2pSpectator::SignalingCodeSent { ready: false, .. } => {
-                        ("", "".into())
+                        String::new()
+                    }
+                    WaitingForPureP2pSpectator::Standby { .. } => {
+                        tr("(Press F1 to accept spectator from clipboard)").to_owned()
+                    }
+                    WaitingForPureP2pSpectator::SignalingCodeRecved { .. } => {
+                        tr("(Generating signaling code...)").to_owned()
+                    }
+                    WaitingForPureP2pSpectator::SignalingCodeSent { .. } => {
+                        tr("(Your signaling code has been copied to the clipboard)").to_owned()
                     }
-                    WaitingForPureP2pSpectator::Standby { .. } => (
-                        "       __                                    ",
-                        "(Press F1 to accept spectator from clipboard)".into(),
-                    ),
-                    WaitingForPureP2pSpectator::SignalingCodeRecved { .. } => (
-                        "                              ",
-                        "(Generating signaling code...)".into(),
-                    ),
-                    WaitingForPureP2pSpectator::SignalingCodeSent { .. } => (
-                        "                                                      ",
-                        "(Your signaling code has been copied to the clipboard)".into(),
-                    ),
                 },
-                WaitingForSpectator::ReservedRoom(_) => ("", "".into()),
+                WaitingForSpectator::ReservedRoom(_) => String::new(),
             }
         }
     } else {
-        ("", "".into())
+        String::new()
     };
 
-    let delay_underline = if status.host { "_" } else { " " };
-    let msg_front/* _ */= format!("Delay: {} {}", status.delay, msg2_front);
-    let msg_rear/* __ */= format!("       {} {}", delay_underline, msg2_rear);
+    let delay = status.delay.to_string();
+    let msg_delay = match (
+        status.pending_delay,
+        status.delay_request,
+        status.proposed_delay,
+    ) {
+        (Some(pending_delay), _, _) => {
+            tr_format("Delay: {} (changing to {})", &[&delay, &pending_delay])
+        }
+        (None, Some(delay_request), _) if status.host => tr_format(
+            "Delay: {} (guest requests {}: Y/N)",
+            &[&delay, &delay_request],
+        ),
+        (None, Some(delay_request), _) => {
+            tr_format("Delay: {} (requested: {})", &[&delay, &delay_request])
+        }
+        (None, None, Some(proposed_delay)) => {
+            tr_format("Delay: {} (proposed: {})", &[&delay, &proposed_delay])
+        }
+        (None, None, None) => tr_format("Delay: {}", &[&delay]),
+    };
+    let delay_underline = if status.host {
+        underline(&msg_delay, &delay)
+    } else {
+        String::new()
+    };
+    let msg_front = format!("{} {}", msg_delay, msg2);
+    let msg_rear = format!(
+        "{} {}",
+        pad_end(&delay_underline, text_width(&msg_delay)),
+        underline(&msg2, "F1")
+    );
 
     render_footer(th19, text_renderer, &msg_front, &msg_rear);
 }
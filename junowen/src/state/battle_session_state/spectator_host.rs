@@ -1,15 +1,14 @@
+use std::{collections::VecDeque, mem};
+
 use anyhow::{bail, Result};
 use getset::Getters;
-use junowen_lib::{
-    structs::app::{MainMenu, ScreenId},
-    structs::selection::Selection,
-    Th19,
-};
+use junowen_lib::{structs::app::ScreenId, structs::selection::Selection, Th19};
 use tracing::info;
 
 use crate::{
     session::{
         battle::BattleSession,
+        chat::ChatMessage,
         spectator::{self, InitialState, SpectatorInitial},
         spectator_host::SpectatorHostSession,
         RoundInitial,
@@ -22,6 +21,7 @@ fn create_spectator_initial(
     selection: &Selection,
     battle_session: &BattleSession,
     local_player_name: String,
+    delay_frames: u32,
 ) -> SpectatorInitial {
     let p1_name = if battle_session.host() {
         local_player_name.to_owned()
@@ -54,21 +54,76 @@ fn create_spectator_initial(
             selection.p2().character as u8,
             selection.p2().card as u8,
         ),
+        delay_frames,
     )
 }
 
+fn current_round_initial(th19: &Th19) -> RoundInitial {
+    RoundInitial {
+        seed1: th19.rand_seed1().unwrap(),
+        seed2: th19.rand_seed2().unwrap(),
+        seed3: th19.rand_seed3().unwrap(),
+        seed4: th19.rand_seed4().unwrap(),
+    }
+}
+
+/** 途中から参加した観戦者に送るための、最後に再現できる時点から現在までの記録 */
+struct CatchUp {
+    spectator_initial: SpectatorInitial,
+    rounds: Vec<(RoundInitial, Vec<(u16, u16)>)>,
+}
+
+impl CatchUp {
+    fn send_to(&self, session: &SpectatorHostSession) -> Result<()> {
+        session.send_init_spectator(self.spectator_initial.clone())?;
+        for (round_initial, inputs) in &self.rounds {
+            session.send_init_round(round_initial.clone())?;
+            for &(p1_input, p2_input) in inputs {
+                session.send_inputs(p1_input, p2_input)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/** 配信遅延のために送信を保留しているメッセージ */
+enum Outgoing {
+    /// checkpoint なら接続中の観戦者にも送る
+    CatchUp {
+        spectator_initial: SpectatorInitial,
+        round_initial: RoundInitial,
+        checkpoint: bool,
+    },
+    InitRound(RoundInitial),
+    Inputs(u16, u16),
+    Chat(ChatMessage),
+}
+
 #[derive(Getters)]
 pub struct SpectatorHostState {
     #[get = "pub"]
     waiting: WaitingForSpectator,
     sessions: Vec<SpectatorHostSession>,
+    catch_up: Option<CatchUp>,
+    /// 前のフレームが難易度選択画面の再現できる時点だったか
+    reproducible: bool,
+    prev_screen: Option<ScreenId>,
+    delay_frames: u32,
+    outgoing: VecDeque<Outgoing>,
+    outgoing_inputs: u32,
 }
 
 impl SpectatorHostState {
-    pub fn new(waiting: WaitingForSpectator) -> Self {
+    pub fn new(waiting: WaitingForSpectator, delay_frames: u32) -> Self {
         Self {
             waiting,
             sessions: Vec::new(),
+            catch_up: None,
+            reproducible: false,
+            prev_screen: None,
+            delay_frames,
+            outgoing: VecDeque::new(),
+            outgoing_inputs: 0,
         }
     }
 
@@ -77,13 +132,14 @@ impl SpectatorHostState {
     }
 
     pub fn send_init_round_if_connected(&mut self, th19: &Th19) {
+        self.outgoing
+            .push_back(Outgoing::InitRound(current_round_initial(th19)));
+        self.release_outgoing();
+    }
+
+    fn send_to_sessions(&mut self, send: impl Fn(&SpectatorHostSession) -> Result<()>) {
         self.sessions.retain(|session| {
-            if let Err(err) = session.send_init_round(RoundInitial {
-                seed1: th19.rand_seed1().unwrap(),
-                seed2: th19.rand_seed2().unwrap(),
-                seed3: th19.rand_seed3().unwrap(),
-                seed4: th19.rand_seed4().unwrap(),
-            }) {
+            if let Err(err) = send(session) {
                 info!("spectator host error: {:?}", err);
                 false
             } else {
@@ -92,65 +148,154 @@ impl SpectatorHostState {
         });
     }
 
-    fn init_session(
-        &self,
-        session: &SpectatorHostSession,
+    /// 遅延フレーム数を超えて保留している入力と、その間に挟まるメッセージを送信する
+    fn release_outgoing(&mut self) {
+        self.release_outgoing_until(self.delay_frames);
+    }
+
+    /// 保留している入力が remaining 以下になるまで送信する
+    fn release_outgoing_until(&mut self, remaining: u32) {
+        loop {
+            match self.outgoing.front() {
+                None => return,
+                Some(Outgoing::Inputs(..)) if self.outgoing_inputs <= remaining => return,
+                Some(_) => {}
+            }
+            match self.outgoing.pop_front().unwrap() {
+                Outgoing::CatchUp {
+                    spectator_initial,
+                    round_initial,
+                    checkpoint,
+                } => {
+                    // 接続中の観戦者は読み飛ばすが、中継ノードはここより前の記録を捨てられる
+                    if checkpoint {
+                        self.send_to_sessions(|session| {
+                            session.send_init_spectator(spectator_initial.clone())?;
+                            session.send_init_round(round_initial.clone())
+                        });
+                    }
+                    self.catch_up = Some(CatchUp {
+                        spectator_initial,
+                        rounds: vec![(round_initial, Vec::new())],
+                    });
+                }
+                Outgoing::InitRound(round_initial) => {
+                    if let Some(catch_up) = &mut self.catch_up {
+                        catch_up.rounds.push((round_initial.clone(), Vec::new()));
+                    }
+                    self.send_to_sessions(|session| session.send_init_round(round_initial.clone()));
+                }
+                Outgoing::Inputs(p1_input, p2_input) => {
+                    self.outgoing_inputs -= 1;
+                    if let Some(catch_up) = &mut self.catch_up {
+                        catch_up
+                            .rounds
+                            .last_mut()
+                            .unwrap()
+                            .1
+                            .push((p1_input, p2_input));
+                    }
+                    self.send_to_sessions(|session| session.send_inputs(p1_input, p2_input));
+                }
+                Outgoing::Chat(message) => {
+                    self.send_to_sessions(|session| session.send_chat(message.clone()));
+                }
+            }
+        }
+    }
+
+    /**
+     * 観戦者側で再現できる時点から記録し直す
+     *
+     * 難易度選択画面で両者のカードが未選択の間は毎フレーム、キャラクター選択画面は入った時点。
+     * 試合中は記録し直せないので、途中から参加した観戦者は現在の試合のキャラクター選択から早送りする
+     */
+    fn enqueue_catch_up(
+        &mut self,
         battle_session: &BattleSession,
-        main_menu: Option<&MainMenu>,
+        screen: Option<ScreenId>,
         th19: &Th19,
-    ) -> Result<()> {
-        let Some(main_menu) = main_menu else {
+    ) {
+        let prev_screen = mem::replace(&mut self.prev_screen, screen);
+        let vs_mode = th19.vs_mode();
+        let checkpoint = match screen {
+            Some(ScreenId::DifficultySelect)
+                if vs_mode.p1_card() == 0 && vs_mode.p2_card() == 0 =>
+            {
+                !mem::replace(&mut self.reproducible, true)
+            }
+            Some(ScreenId::CharacterSelect) if prev_screen != screen => {
+                self.reproducible = false;
+                true
+            }
+            _ => {
+                self.reproducible = false;
+                return;
+            }
+        };
+        self.outgoing.push_back(Outgoing::CatchUp {
+            spectator_initial: create_spectator_initial(
+                screen.unwrap(),
+                th19.selection(),
+                battle_session,
+                vs_mode.player_name().to_string(),
+                self.delay_frames,
+            ),
+            round_initial: current_round_initial(th19),
+            checkpoint,
+        });
+    }
+
+    fn init_session(&self, session: &SpectatorHostSession) -> Result<()> {
+        let Some(catch_up) = &self.catch_up else {
             bail!("spectator not supported yet.");
         };
-        let vs_mode = th19.vs_mode();
-        if main_menu.screen_id() != ScreenId::DifficultySelect
-            || vs_mode.p1_card() != 0
-            || vs_mode.p2_card() != 0
-        {
-            bail!(
-                "spectator not supported yet. screen={:?}, p1_card={}, p2_card={}",
-                main_menu.screen_id(),
-                vs_mode.p1_card(),
-                vs_mode.p2_card()
-            );
-        }
-        session.send_init_spectator(create_spectator_initial(
-            main_menu.screen_id(),
-            th19.selection(),
-            battle_session,
-            th19.vs_mode().player_name().to_string(),
-        ))?;
-        session.send_init_round(RoundInitial {
-            seed1: th19.rand_seed1().unwrap(),
-            seed2: th19.rand_seed2().unwrap(),
-            seed3: th19.rand_seed3().unwrap(),
-            seed4: th19.rand_seed4().unwrap(),
-        })?;
-        Ok(())
+        catch_up.send_to(session)
     }
 
     pub fn update(
         &mut self,
         pushed: bool,
-        main_menu: Option<&MainMenu>,
+        screen: Option<ScreenId>,
         th19: &Th19,
-        battle_session: &BattleSession,
+        battle_session: &mut BattleSession,
         p1_input: u16,
         p2_input: u16,
     ) {
-        if let Some(session) = self.waiting.try_recv_session(pushed, main_menu, th19) {
-            if let Err(err) = self.init_session(&session, battle_session, main_menu, th19) {
+        self.enqueue_catch_up(battle_session, screen, th19);
+        let spectator_chats: Vec<_> = self
+            .sessions
+            .iter()
+            .flat_map(|session| session.recv_chats())
+            .collect();
+        for message in spectator_chats {
+            battle_session.relay_spectator_chat(message);
+        }
+        self.outgoing.extend(
+            battle_session
+                .take_new_chats()
+                .into_iter()
+                .map(Outgoing::Chat),
+        );
+        self.outgoing
+            .push_back(Outgoing::Inputs(p1_input, p2_input));
+        self.outgoing_inputs += 1;
+        self.release_outgoing();
+
+        let ready = self.catch_up.is_some();
+        if let Some(session) = self.waiting.try_recv_session(pushed, ready, th19) {
+            if let Err(err) = self.init_session(&session) {
                 info!("initialize spectator failed: {:?}", err);
             } else {
                 self.sessions.push(session);
             }
         }
-        self.sessions.retain(|session| {
-            if let Err(err) = session.send_inputs(p1_input, p2_input) {
-                info!("spectator host error: {:?}", err);
-                return false;
-            }
-            true
-        });
+    }
+}
+
+impl Drop for SpectatorHostState {
+    /// 対戦が終わっても、配信遅延のために保留していた最後の数秒を観戦者に届ける
+    fn drop(&mut self) {
+        self.release_outgoing_until(0);
     }
 }
@@ -1,9 +1,12 @@
-use std::sync::mpsc::RecvError;
+use std::{mem, sync::mpsc::RecvError};
 
 use anyhow::Result;
-use junowen_lib::Th19;
+use junowen_lib::{structs::input_devices::InputDevices, Th19};
 
-use crate::session::{battle::BattleSession, RoundInitial};
+use crate::{
+    helper::{inputed_acceptance, inputed_delay_change, DelayChange},
+    session::{battle::BattleSession, RoundInitial},
+};
 
 use super::spectator_host::SpectatorHostState;
 
@@ -30,3 +33,34 @@ pub fn init_round(
     spectator_host_state.send_init_round_if_connected(th19);
     Ok(())
 }
+
+/**
+ * キー入力から次のディレイを決める。押し続けても 1 回しか変えない
+ *
+ * ゲストは変更の代わりにホストへ希望を送り、ホストは届いた希望に Y か N で答える
+ */
+pub fn inputed_delay(
+    input_devices: &InputDevices,
+    battle_session: &mut BattleSession,
+    prev_delay_change: &mut Option<DelayChange>,
+) -> Option<u8> {
+    if battle_session.host() && battle_session.delay_request().is_some() {
+        if let Some(accept) = inputed_acceptance(input_devices) {
+            battle_session.answer_delay_request(accept);
+        }
+    }
+    let change = inputed_delay_change(input_devices);
+    let prev_change = mem::replace(prev_delay_change, change);
+    let change = change.filter(|&change| Some(change) != prev_change)?;
+    if !battle_session.host() {
+        let delay = battle_session
+            .delay_request()
+            .unwrap_or(battle_session.delay());
+        battle_session.request_delay(change.apply(delay));
+        return None;
+    }
+    let delay = battle_session
+        .pending_delay()
+        .unwrap_or(battle_session.delay());
+    Some(change.apply(delay))
+}
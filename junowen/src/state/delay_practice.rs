@@ -0,0 +1,62 @@
+use junowen_lib::{
+    structs::selection::{GameMode, PlayerMatchup},
+    Th19,
+};
+use tracing::debug;
+
+use crate::{in_game_lobby::DelayPracticeSettings, session::loopback::LoopbackSession};
+
+/** セッションがない間、ローカルの対戦の入力を LoopbackSession に通す */
+#[derive(Default)]
+pub struct DelayPracticeState {
+    session: Option<LoopbackSession>,
+}
+
+impl DelayPracticeState {
+    pub fn update_th19(&mut self, settings: Option<DelayPracticeSettings>, th19: &mut Th19) {
+        let Some(settings) = settings else {
+            self.session = None;
+            return;
+        };
+        let selection = th19.selection();
+        let matchup = selection.player_matchup;
+        if selection.game_mode != GameMode::Versus
+            || !matches!(
+                matchup,
+                PlayerMatchup::HumanVsHuman | PlayerMatchup::HumanVsCpu
+            )
+        {
+            self.session = None;
+            return;
+        }
+        // -1フレーム目、0フレーム目は複数回呼ばれ、回数が不定なのでラウンドごとに作り直す
+        if th19
+            .round_frame()
+            .map_or(true, |round_frame| round_frame.frame < 1)
+        {
+            self.session = None;
+            return;
+        }
+        let session = self.session.get_or_insert_with(|| {
+            debug!(
+                "delay practice started: delay={}, jitter={}",
+                settings.delay, settings.jitter
+            );
+            LoopbackSession::new(settings.delay, settings.jitter)
+        });
+        let input_devices = th19.input_devices_mut();
+        let p1 = input_devices.p1_input().current().bits() as u16;
+        let p2 = input_devices.p2_input().current().bits() as u16;
+        // 両端のチャンネルを自分で持っているので切断されることはない
+        let (p1, p2) = session.enqueue_input_and_dequeue(p1, p2).unwrap();
+        input_devices
+            .p1_input_mut()
+            .set_current((p1 as u32).try_into().unwrap());
+        // CPU の入力はゲームが直接扱うので触らない
+        if matchup == PlayerMatchup::HumanVsHuman {
+            input_devices
+                .p2_input_mut()
+                .set_current((p2 as u32).try_into().unwrap());
+        }
+    }
+}
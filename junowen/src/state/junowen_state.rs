@@ -5,21 +5,26 @@ use std::{ffi::c_void, sync::mpsc::RecvError};
 
 use anyhow::Result;
 use junowen_lib::{
-    structs::app::{MainMenu, ScreenId},
+    session_message::MatchProposal,
+    structs::app::ScreenId,
     structs::{others::RenderingText, selection::Selection, settings::GameSettings},
     Fn011560, Fn0b7d40, Fn0d5ae0, Fn10f720, Th19,
 };
 use tracing::trace;
 
 use crate::{
-    file::Features,
+    file::OverlayConfig,
+    helper::inputed_acceptance,
     in_game_lobby::{Lobby, TitleMenuModifier},
-    session::{battle::BattleSession, spectator::SpectatorSession},
-    signaling::waiting_for_match::{WaitingForMatch, WaitingForSpectator},
+    opponent_history::OpponentRoom,
+    session::{battle::BattleSession, recorder::Recorder, spectator::SpectatorSession},
+    signaling::waiting_for_match::{WaitingForMatch, WaitingForOpponent, WaitingForSpectator},
+    TOKIO_RUNTIME,
 };
 
 use super::{
-    battle_session_state::BattleSessionState, spectator_session_state::SpectatorSessionState,
+    battle_session_state::BattleSessionState, host_match_initial,
+    spectator_session_state::SpectatorSessionState, SessionConfig,
 };
 
 use self::on_rewrite_controller_assignments::on_rewrite_controller_assignments;
@@ -45,10 +50,46 @@ impl JunowenState {
 
     fn start_battle_session(
         &mut self,
-        battle_session: BattleSession,
+        mut battle_session: BattleSession,
         waiting: WaitingForSpectator,
+        room: OpponentRoom,
+        session_config: &SessionConfig,
     ) {
-        *self = Self::BattleSession(BattleSessionState::prepare(battle_session, waiting));
+        battle_session.set_room(Some(room));
+        if let Some(replay_dir) = session_config.replay_dir() {
+            battle_session.set_recorder(Some(Recorder::new(replay_dir)));
+        }
+        battle_session.set_first_to(session_config.first_to());
+        battle_session.set_default_delay(session_config.default_delay());
+        battle_session.set_auto_accept_delay_requests(session_config.auto_accept_delay_requests());
+        battle_session.set_remembered_delays(
+            TOKIO_RUNTIME.block_on(session_config.settings_repo().remembered_delays()),
+        );
+        battle_session.set_rule_preset(session_config.rule_preset().clone());
+        battle_session.set_identity(session_config.identity().clone());
+        battle_session.set_known_players(Some(session_config.known_players().clone()));
+        battle_session.set_ladder(session_config.ladder());
+        *self = Self::BattleSession(BattleSessionState::prepare(
+            battle_session,
+            waiting,
+            session_config.spectator_delay_frames(),
+        ));
+    }
+
+    /// 対戦していたなら相手を最近の対戦相手として記録し、最後のディレイを覚える
+    pub fn remember_opponent(&self, session_config: &SessionConfig) {
+        let Self::BattleSession(session_state) = self else {
+            return;
+        };
+        let session = session_state.session();
+        if let Some(opponent) = session.recent_opponent() {
+            TOKIO_RUNTIME.block_on(session_config.settings_repo().remember_delay(
+                &opponent.name,
+                opponent.room.name(),
+                session.delay(),
+            ));
+            session_config.opponent_history().push(opponent);
+        }
     }
 
     fn end_session(&mut self) {
@@ -60,7 +101,14 @@ impl JunowenState {
         th19.set_no_wait(false);
     }
 
-    pub fn start_spectator_session(&mut self, session: SpectatorSession) {
+    pub fn start_spectator_session(
+        &mut self,
+        mut session: SpectatorSession,
+        session_config: &SessionConfig,
+    ) {
+        if let Some(replay_dir) = session_config.replay_dir() {
+            session.set_recorder(Some(Recorder::new(replay_dir)));
+        }
         *self = Self::SpectatorSession(SpectatorSessionState::prepare(session));
     }
 
@@ -68,7 +116,8 @@ impl JunowenState {
         &mut self,
         th19: &Th19,
         waiting_for_match: &mut Option<WaitingForMatch>,
-    ) -> (bool, Option<&'static MainMenu>) {
+        session_config: &SessionConfig,
+    ) -> (bool, Option<ScreenId>) {
         match self {
             Self::Standby => {
                 let Some(old_waiting) = waiting_for_match.take() else {
@@ -80,11 +129,35 @@ impl JunowenState {
                     }
                 }
                 match old_waiting {
-                    WaitingForMatch::Opponent(waiting) => {
-                        match waiting.try_into_session_and_waiting_for_spectator() {
+                    WaitingForMatch::Opponent(mut waiting) => {
+                        if let Some(acceptance) = waiting.acceptance_mut() {
+                            if let Some(answer) = inputed_acceptance(th19.input_devices()) {
+                                acceptance.answer(answer);
+                            }
+                        }
+                        let room = match &waiting {
+                            WaitingForOpponent::SharedRoom(waiting) => {
+                                OpponentRoom::Shared(waiting.room_name().to_owned())
+                            }
+                            WaitingForOpponent::ReservedRoom(waiting) => {
+                                OpponentRoom::Reserved(waiting.room_name().to_owned())
+                            }
+                            WaitingForOpponent::PureP2p(_) => OpponentRoom::PureP2p,
+                        };
+                        let make_proposal = |host: bool| MatchProposal {
+                            name: th19.vs_mode().player_name().to_owned(),
+                            rules: host.then(|| {
+                                host_match_initial(
+                                    th19,
+                                    session_config.rule_preset().as_ref(),
+                                    session_config.first_to(),
+                                )
+                            }),
+                        };
+                        match waiting.try_into_session_and_waiting_for_spectator(make_proposal) {
                             Ok((session, waiting)) => {
                                 trace!("session received");
-                                self.start_battle_session(session, waiting);
+                                self.start_battle_session(session, waiting, room, session_config);
                                 (true, None)
                             }
                             Err(waiting) => {
@@ -96,7 +169,7 @@ impl JunowenState {
                     WaitingForMatch::SpectatorHost(waiting) => match waiting.try_into_session() {
                         Ok(session) => {
                             trace!("session received");
-                            self.start_spectator_session(session);
+                            self.start_spectator_session(session, session_config);
                             (true, None)
                         }
                         Err(waiting) => {
@@ -108,6 +181,7 @@ impl JunowenState {
             }
             Self::BattleSession(session_state) => {
                 let Some(menu_opt) = session_state.update_state(th19) else {
+                    self.remember_opponent(session_config);
                     self.end_session();
                     return (true, None);
                 };
@@ -126,7 +200,7 @@ impl JunowenState {
     fn update_th19_on_input_players(
         &mut self,
         changed: bool,
-        menu: Option<&MainMenu>,
+        screen: Option<ScreenId>,
         th19: &mut Th19,
     ) -> Result<(), RecvError> {
         match self {
@@ -137,10 +211,10 @@ impl JunowenState {
                 Ok(())
             }
             Self::BattleSession(session_state) => {
-                session_state.update_th19_on_input_players(menu, th19)
+                session_state.update_th19_on_input_players(screen, th19)
             }
             Self::SpectatorSession(session_state) => {
-                session_state.update_th19_on_input_players(menu, th19)
+                session_state.update_th19_on_input_players(screen, th19)
             }
         }
     }
@@ -149,9 +223,10 @@ impl JunowenState {
         &mut self,
         th19: &mut Th19,
         waiting_for_match: &mut Option<WaitingForMatch>,
+        session_config: &SessionConfig,
     ) -> Result<(), RecvError> {
-        let (changed, menu_opt) = self.update_state(th19, waiting_for_match);
-        self.update_th19_on_input_players(changed, menu_opt, th19)
+        let (changed, screen) = self.update_state(th19, waiting_for_match, session_config);
+        self.update_th19_on_input_players(changed, screen, th19)
     }
 
     pub fn on_input_menu(
@@ -204,7 +279,7 @@ impl JunowenState {
 
     pub fn on_render_texts(
         &self,
-        features: &[Features],
+        overlay: &OverlayConfig,
         th19: &Th19,
         title_menu_modifier: &TitleMenuModifier,
         lobby: &Lobby,
@@ -215,10 +290,10 @@ impl JunowenState {
                 standby::on_render_texts(th19, title_menu_modifier, lobby, text_renderer);
             }
             Self::BattleSession(session_state) => {
-                session_state.on_render_texts(features, th19, text_renderer)
+                session_state.on_render_texts(overlay, th19, text_renderer)
             }
             Self::SpectatorSession(session_state) => {
-                session_state.on_render_texts(th19, text_renderer)
+                session_state.on_render_texts(overlay, th19, text_renderer)
             }
         }
     }
@@ -7,6 +7,8 @@ use junowen_lib::{
 };
 
 use crate::in_game_lobby::{Lobby, TitleMenuModifier};
+use crate::lang::{text_width, tr, tr_format};
+use crate::session::acceptance::{Acceptance, AcceptanceState};
 use crate::signaling::waiting_for_match::{WaitingForMatch, WaitingForOpponent, WaitingInRoom};
 
 fn is_title(main_menu: &MainMenu) -> bool {
@@ -22,11 +24,11 @@ pub fn update_th19_on_input_menu(
     title_menu_modifier: &mut TitleMenuModifier,
     lobby: &mut Lobby,
 ) {
-    let Some(main_menu) = th19.app_mut().main_loop_tasks_mut().find_main_menu_mut() else {
+    let Some(main_menu) = th19.app().main_loop_tasks().find_main_menu() else {
         return;
     };
     if is_title(main_menu) {
-        title_menu_modifier.on_input_menu(main_menu, th19);
+        title_menu_modifier.on_input_menu(th19);
     } else if title_menu_modifier.start_lobby(main_menu) {
         lobby.on_input_menu(th19);
     }
@@ -46,26 +48,110 @@ pub fn render_text(
 }
 
 fn render_message(text_renderer: *const c_void, th19: &Th19, msg: &str, color: u32) {
+    render_message_line(text_renderer, th19, 0, msg, color);
+}
+
+fn render_message_line(
+    text_renderer: *const c_void,
+    th19: &Th19,
+    line: u32,
+    msg: &str,
+    color: u32,
+) {
     let mut text = RenderingText::default();
-    text.set_text(msg.as_bytes());
+    text.set_str(msg);
     text.set_x(16, th19.window_inner());
-    text.set_y(4, th19.window_inner());
+    text.set_y(4 + line * 32, th19.window_inner());
     text.color = color;
     th19.render_text(text_renderer, &text);
 }
 
+/// 相手が送ってきた文字列が描画用のバッファーに収まるようにする
+fn truncate(text: &str, max_chars: usize) -> String {
+    text.chars()
+        .filter(|c| !c.is_control())
+        .take(max_chars)
+        .collect()
+}
+
+fn render_acceptance(acceptance: &Acceptance, th19: &Th19, text_renderer: *const c_void) {
+    let Some(remote) = acceptance.remote_proposal() else {
+        render_message(
+            text_renderer,
+            th19,
+            tr("Connected. Waiting for the opponent..."),
+            0xffc0c0c0,
+        );
+        return;
+    };
+    let ping = acceptance
+        .rtt()
+        .map(|rtt| format!("{} ms", rtt.as_millis()))
+        .unwrap_or_else(|| "-".to_owned());
+    let msg = tr_format(
+        "Opponent: {}  Ping: {}",
+        &[&truncate(&remote.name, 32), &ping],
+    );
+    render_message_line(text_renderer, th19, 0, &msg, 0xffffffff);
+
+    if let Some(rules) = acceptance.rules() {
+        let name = rules
+            .rule_preset
+            .as_deref()
+            .map(|name| truncate(name, 32))
+            .unwrap_or_else(|| tr("Online VS settings").to_owned());
+        let settings = &rules.game_settings;
+        let mut msg = tr_format(
+            "Rules: {} / {} / {} / Card: {} / Life: {}-{} / Barrier: {}-{}",
+            &[
+                &name,
+                &settings.time_limit(),
+                &settings.round(),
+                &settings.ability_card(),
+                &(settings.p1_life() + 1),
+                &(settings.p2_life() + 1),
+                &settings.p1_barrier(),
+                &settings.p2_barrier(),
+            ],
+        );
+        if let Some(first_to) = rules.first_to {
+            msg += &format!(" / FT{}", first_to);
+        }
+        render_message_line(text_renderer, th19, 1, &msg, 0xffffffff);
+    }
+
+    let msg = match acceptance.local_answer() {
+        _ if acceptance.state() == AcceptanceState::Declining => {
+            tr("The match was declined. Disconnecting...").to_owned()
+        }
+        None => tr_format(
+            "Press Y to accept or N to decline ({}s)",
+            &[&acceptance.remaining().as_secs()],
+        ),
+        Some(_) => tr("Waiting for the opponent to accept...").to_owned(),
+    };
+    render_message_line(text_renderer, th19, 2, &msg, 0xffffffa0);
+}
+
 fn render_waiting_message<T>(
     room_type: &str,
     room: &WaitingInRoom<T>,
     th19: &Th19,
     text_renderer: *const c_void,
 ) {
+    if let Some(acceptance) = room.acceptance() {
+        render_acceptance(acceptance, th19, text_renderer);
+        return;
+    }
     let room_name = room.room_name();
     let dot = ".".repeat((room.elapsed().as_secs() % 4) as usize);
-    let msg = format!("Waiting in {} Room: {} {:<3}", room_type, room_name, dot);
+    let msg = tr_format(
+        "Waiting in {} Room: {} {}",
+        &[&tr(room_type), &room_name, &format!("{:<3}", dot)],
+    );
     render_message(text_renderer, th19, &msg, 0xffc0c0c0);
     if !room.errors().is_empty() {
-        let padding = " ".repeat(msg.chars().count());
+        let padding = " ".repeat(text_width(&msg));
         let msg = format!("{} E({})", padding, room.errors().len());
         render_message(text_renderer, th19, &msg, 0xffff2800);
     }
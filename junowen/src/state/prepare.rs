@@ -36,10 +36,11 @@ impl<T> Prepare<T> {
     }
 
     pub fn update_th19_on_input_menu(&self, th19: &mut Th19) {
-        let Some(main_menu) = th19.app_mut().main_loop_tasks_mut().find_main_menu_mut() else {
+        let Some(main_menu) = th19.app().main_loop_tasks().find_main_menu() else {
             return;
         };
-        let no_wait = to_automatic_inputs(self.state).on_input_menu(th19, main_menu);
+        let screen_id = main_menu.screen_id();
+        let no_wait = to_automatic_inputs(self.state).on_input_menu(th19, screen_id);
         th19.set_no_wait(no_wait);
     }
 
@@ -5,34 +5,68 @@ use junowen_lib::{
     Th19,
 };
 
+use crate::{
+    lang::{text_width, tr, tr_format},
+    session::{chat::Chat, set_score::SetScore},
+};
+
 pub fn render_names(th19: &Th19, text_renderer: *const c_void, p1_name: &str, p2_name: &str) {
     let mut text = RenderingText::default();
-    text.set_text(p1_name.as_bytes());
+    text.set_str(p1_name);
     text.set_x(16, th19.window_inner());
     text.set_y(4, th19.window_inner());
     text.color = 0xffff8080;
     th19.render_text(text_renderer, &text);
 
-    text.set_text(p2_name.as_bytes());
+    text.set_str(p2_name);
     text.set_x(1264, th19.window_inner());
     text.color = 0xff8080ff;
     text.horizontal_align = 2;
     th19.render_text(text_renderer, &text);
 }
 
+pub fn render_set_score(
+    th19: &Th19,
+    text_renderer: *const c_void,
+    set_score: &SetScore,
+    p1_name: &str,
+    p2_name: &str,
+) {
+    let mut text = RenderingText::default();
+    text.set_str(&set_score.to_string());
+    text.set_x(640, th19.window_inner());
+    text.set_y(4, th19.window_inner());
+    text.color = 0xffffffff;
+    text.horizontal_align = 0;
+    th19.render_text(text_renderer, &text);
+
+    let msg = if let Some(summary) = set_score.summary(p1_name, p2_name) {
+        summary
+    } else if !set_score.awaiting_result() {
+        return;
+    } else if set_score.local_report().is_none() {
+        tr("(Report the winner: F11 = P1, F12 = P2)").to_owned()
+    } else {
+        tr("(Waiting for the opponent to confirm the winner)").to_owned()
+    };
+    text.set_str(&msg);
+    text.set_y(4 + 32, th19.window_inner());
+    th19.render_text(text_renderer, &text);
+}
+
 fn render_game_common_settings(
     th19: &Th19,
     text_renderer: *const c_void,
     game_settings: &GameSettings,
 ) {
     let mut text = RenderingText::default();
-    text.set_text(format!("Time Limit: {}", game_settings.time_limit()).as_bytes());
+    text.set_str(&tr_format("Time Limit: {}", &[&game_settings.time_limit()]));
     text.set_x(16, th19.window_inner());
     text.set_y(4 + 32, th19.window_inner());
     text.color = 0xffffffff;
     th19.render_text(text_renderer, &text);
 
-    text.set_text(format!("Round: {}", game_settings.round()).as_bytes());
+    text.set_str(&tr_format("Round: {}", &[&game_settings.round()]));
     text.set_x(1280 - 16, th19.window_inner());
     text.horizontal_align = 2;
     th19.render_text(text_renderer, &text);
@@ -47,22 +81,22 @@ fn render_game_players_settings(
 
     let y = 870;
     let msg = format!(
-        "Life: {}\nBarrier: {}",
-        game_settings.p1_life() + 1,
-        game_settings.p1_barrier()
+        "{}\n{}",
+        tr_format("Life: {}", &[&(game_settings.p1_life() + 1)]),
+        tr_format("Barrier: {}", &[&game_settings.p1_barrier()])
     );
-    text.set_text(msg.as_bytes());
+    text.set_str(&msg);
     text.set_x(16, th19.window_inner());
     text.set_y(y, th19.window_inner());
     text.horizontal_align = 1;
     th19.render_text(text_renderer, &text);
 
-    text.set_text(format!("Life: {}", game_settings.p2_life() + 1,).as_bytes());
+    text.set_str(&tr_format("Life: {}", &[&(game_settings.p2_life() + 1)]));
     text.set_x(1280 - 16, th19.window_inner());
     text.horizontal_align = 2;
     th19.render_text(text_renderer, &text);
 
-    text.set_text(format!("Barrier: {}", game_settings.p2_barrier()).as_bytes());
+    text.set_str(&tr_format("Barrier: {}", &[&game_settings.p2_barrier()]));
     text.set_x(1280 - 16, th19.window_inner());
     text.set_y(y + 28, th19.window_inner());
     th19.render_text(text_renderer, &text);
@@ -77,6 +111,32 @@ pub fn render_game_settings(
     render_game_players_settings(th19, text_renderer, game_settings);
 }
 
+pub fn render_chat_log(th19: &Th19, text_renderer: *const c_void, chat: &Chat) {
+    let log = chat.log();
+    let bottom = 840;
+    let mut text = RenderingText::default();
+    text.set_x(16, th19.window_inner());
+    text.color = 0xffffffff;
+    for (i, message) in log.iter().enumerate() {
+        let y = bottom - (log.len() - 1 - i) as u32 * 28;
+        text.set_str(&format!("{}: {}", message.name, message.text));
+        text.set_y(y, th19.window_inner());
+        th19.render_text(text_renderer, &text);
+    }
+}
+
+/// text の中の最後の key の下に引く下線。全角文字は 2 文字分として数える
+pub fn underline(text: &str, key: &str) -> String {
+    let Some(pos) = text.rfind(key) else {
+        return String::new();
+    };
+    format!(
+        "{}{}",
+        " ".repeat(text_width(&text[..pos])),
+        "_".repeat(text_width(key))
+    )
+}
+
 pub fn render_footer(th19: &Th19, text_renderer: *const c_void, msg_front: &str, msg_rear: &str) {
     let version = env!("CARGO_PKG_VERSION");
     let version_blank = (0..version.len()).map(|_| " ").collect::<String>();
@@ -85,14 +145,14 @@ pub fn render_footer(th19: &Th19, text_renderer: *const c_void, msg_front: &str,
     let msg_rear/* ___ */= format!("           {} {}", version_blank, msg_rear);
 
     let mut text = RenderingText::default();
-    text.set_text(msg_rear.as_bytes());
+    text.set_str(&msg_rear);
     text.set_x(16, th19.window_inner());
     text.set_y(944, th19.window_inner());
     text.color = 0xffffffff;
     text.font_type = 1;
     th19.render_text(text_renderer, &text);
 
-    text.set_text(msg_front.as_bytes());
+    text.set_str(&msg_front);
     text.set_y(940, th19.window_inner());
     th19.render_text(text_renderer, &text);
 }
@@ -7,14 +7,11 @@ use std::{ffi::c_void, mem, sync::mpsc::RecvError};
 use anyhow::Result;
 use junowen_lib::{
     structs::settings::GameSettings,
-    structs::{
-        app::{MainMenu, ScreenId},
-        input_devices::InputFlags,
-    },
+    structs::{app::ScreenId, input_devices::InputFlags},
     Th19,
 };
 
-use crate::session::spectator::SpectatorSession;
+use crate::{file::OverlayConfig, session::spectator::SpectatorSession};
 
 use super::prepare::Prepare;
 
@@ -78,7 +75,7 @@ impl SpectatorSessionState {
         }
     }
 
-    pub fn update_state(&mut self, th19: &Th19) -> Option<Option<&'static MainMenu>> {
+    pub fn update_state(&mut self, th19: &Th19) -> Option<Option<ScreenId>> {
         match self {
             Self::Null => unreachable!(),
             Self::Prepare(prepare) => {
@@ -88,7 +85,7 @@ impl SpectatorSessionState {
                 if prepare.update_state(main_menu, th19) {
                     self.change_to_select();
                 }
-                Some(Some(main_menu))
+                Some(Some(main_menu.screen_id()))
             }
             Self::Select { .. } => {
                 let main_menu = th19.app().main_loop_tasks().find_main_menu().unwrap();
@@ -98,13 +95,13 @@ impl SpectatorSessionState {
                         if th19.input_devices().p1_input().current().0 & InputFlags::PAUSE != None {
                             return None;
                         }
-                        Some(Some(main_menu))
+                        Some(Some(main_menu.screen_id()))
                     }
                     ScreenId::GameLoading => {
                         self.change_to_game_loading();
-                        Some(Some(main_menu))
+                        Some(Some(main_menu.screen_id()))
                     }
-                    _ => Some(Some(main_menu)),
+                    _ => Some(Some(main_menu.screen_id())),
                 }
             }
             Self::GameLoading { .. } => {
@@ -117,13 +114,14 @@ impl SpectatorSessionState {
                 self.change_to_game();
                 Some(None)
             }
-            Self::Game { .. } => {
+            Self::Game(game) => {
                 if th19.input_devices().p1_input().current().0 & InputFlags::PAUSE != None {
                     return None;
                 }
                 if th19.round_frame().is_some() {
                     return Some(None);
                 }
+                game.session_mut().record_round_end();
                 self.change_to_back_to_select();
                 Some(None)
             }
@@ -132,23 +130,23 @@ impl SpectatorSessionState {
                     return Some(None);
                 };
                 if main_menu.screen_id() != ScreenId::CharacterSelect {
-                    return Some(Some(main_menu));
+                    return Some(Some(main_menu.screen_id()));
                 }
                 self.change_to_select();
-                Some(Some(main_menu))
+                Some(Some(main_menu.screen_id()))
             }
         }
     }
 
     pub fn update_th19_on_input_players(
         &mut self,
-        menu: Option<&MainMenu>,
+        screen: Option<ScreenId>,
         th19: &mut Th19,
     ) -> Result<(), RecvError> {
         match self {
             Self::Null => unreachable!(),
             Self::Prepare(prepare) => prepare.update_th19_on_input_players(th19),
-            Self::Select(select) => select.update_th19_on_input_players(menu.unwrap(), th19)?,
+            Self::Select(select) => select.update_th19_on_input_players(screen.unwrap(), th19)?,
             Self::GameLoading { .. } => {
                 if th19.no_wait() {
                     th19.set_no_wait(false);
@@ -165,17 +163,13 @@ impl SpectatorSessionState {
             Self::Null => unreachable!(),
             Self::Prepare(prepare) => prepare.update_th19_on_input_menu(th19),
             Self::Select(select) => {
-                let main_menu = th19
-                    .app_mut()
-                    .main_loop_tasks_mut()
-                    .find_main_menu_mut()
-                    .unwrap();
+                let main_menu = th19.app().main_loop_tasks().find_main_menu().unwrap();
                 if main_menu.screen_id() == ScreenId::DifficultySelect
                     && th19.menu_input().current().0 & InputFlags::PAUSE != None
                 {
                     return Ok(false);
                 }
-                select.update_th19_on_input_menu(main_menu, th19)?;
+                select.update_th19_on_input_menu(th19)?;
             }
             Self::GameLoading { .. } => {}
             Self::Game { .. } => {}
@@ -184,7 +178,12 @@ impl SpectatorSessionState {
         Ok(true)
     }
 
-    pub fn on_render_texts(&self, th19: &Th19, text_renderer: *const c_void) {
+    pub fn on_render_texts(
+        &self,
+        overlay: &OverlayConfig,
+        th19: &Th19,
+        text_renderer: *const c_void,
+    ) {
         let session = {
             match self {
                 Self::Null => unreachable!(),
@@ -202,6 +201,8 @@ impl SpectatorSessionState {
             text_renderer,
             initial.p1_name(),
             initial.p2_name(),
+            initial.delay_frames(),
+            (overlay.chat && !matches!(self, Self::Game(_))).then(|| session.chat()),
         );
     }
 
@@ -2,14 +2,33 @@ use std::ffi::c_void;
 
 use junowen_lib::Th19;
 
-use crate::state::render_parts::{render_footer, render_names};
+use crate::{
+    lang::{tr, tr_format},
+    session::chat::Chat,
+    state::render_parts::{render_chat_log, render_footer, render_names},
+};
 
 pub fn on_render_texts_spectator(
     th19: &Th19,
     text_renderer: *const c_void,
     p1_name: &str,
     p2_name: &str,
+    delay_frames: u32,
+    chat: Option<&Chat>,
 ) {
     render_names(th19, text_renderer, p1_name, p2_name);
-    render_footer(th19, text_renderer, "(Spectating)", "");
+    if let Some(chat) = chat {
+        render_chat_log(th19, text_renderer, chat);
+    }
+    if delay_frames == 0 {
+        render_footer(th19, text_renderer, tr("(Spectating)"), "");
+    } else {
+        // 遅延は入力のフレーム数なので、秒数は 60 fps で動いているときの目安
+        let secs = format!("{:.1}", delay_frames as f64 / 60.0);
+        let msg = tr_format(
+            "(Spectating) Broadcast delay: {} frames (about {}s)",
+            &[&delay_frames, &secs],
+        );
+        render_footer(th19, text_renderer, &msg, "");
+    }
 }
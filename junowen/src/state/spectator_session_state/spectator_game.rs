@@ -7,6 +7,9 @@ use junowen_lib::{structs::input_devices::InputValue, Th19};
 
 use crate::session::spectator::SpectatorSession;
 
+/** 途中から観戦を始めた場合など、これ以上の入力が溜まっていたら早送りする */
+const FAST_FORWARD_THRESHOLD: usize = 60;
+
 #[derive(new, Getters, MutGetters)]
 pub struct SpectatorGame {
     #[getset(get = "pub", get_mut = "pub")]
@@ -30,6 +33,7 @@ impl SpectatorGame {
                 .set_current(InputValue::empty());
             return Ok(());
         }
+        self.session.record_round_start(th19.selection());
         let input_devices = th19.input_devices_mut();
         let (p1, p2) = self.session.dequeue_inputs()?;
         input_devices
@@ -38,10 +42,16 @@ impl SpectatorGame {
         input_devices
             .p2_input_mut()
             .set_current((p2 as u32).try_into().unwrap());
+
+        let fast_forward = self.session.pending_messages() > FAST_FORWARD_THRESHOLD;
+        if th19.no_wait() != fast_forward {
+            th19.set_no_wait(fast_forward);
+        }
         Ok(())
     }
 
     pub fn on_round_over(&mut self, th19: &mut Th19) -> Result<(), RecvError> {
+        self.session.record_round_end();
         let init = self.session.dequeue_init_round()?;
         th19.set_rand_seed1(init.seed1).unwrap();
         th19.set_rand_seed2(init.seed2).unwrap();
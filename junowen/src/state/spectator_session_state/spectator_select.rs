@@ -4,16 +4,29 @@ use anyhow::Result;
 use derive_new::new;
 use getset::{Getters, MutGetters};
 use junowen_lib::{
-    structs::{
-        app::{MainMenu, ScreenId},
-        input_devices::InputValue,
-    },
-    th19_helpers::reset_cursors,
+    structs::{app::ScreenId, input_devices::InputValue},
+    th19_helpers::{reset_cursors, shot_repeatedly},
     Th19,
 };
 use tracing::trace;
 
-use crate::session::spectator::{self, SpectatorSession};
+use crate::{
+    helper::inputed_function_key,
+    session::{
+        chat::CANNED_PHRASES,
+        spectator::{self, SpectatorSession},
+        RoundInitial,
+    },
+};
+
+fn set_difficulty_cursor(th19: &mut Th19, cursor: u32) {
+    th19.app_mut()
+        .main_loop_tasks_mut()
+        .find_main_menu_mut()
+        .unwrap()
+        .menu_mut()
+        .set_cursor(cursor);
+}
 
 #[derive(new, Getters, MutGetters)]
 pub struct SpectatorSelect {
@@ -21,6 +34,11 @@ pub struct SpectatorSelect {
     session: SpectatorSession,
     #[new(value = "0")]
     initializing_state: u8,
+    /// キャラクター選択画面から始める場合に、画面に入ってから設定し直す乱数
+    #[new(default)]
+    round_initial: Option<RoundInitial>,
+    #[new(default)]
+    prev_function_key: Option<u8>,
 }
 
 impl SpectatorSelect {
@@ -28,9 +46,19 @@ impl SpectatorSelect {
         self.session
     }
 
+    /// F2 から F9 で定型文を送信する
+    fn send_canned_phrase(&mut self, th19: &Th19) {
+        let key = inputed_function_key(th19.input_devices());
+        if let Some(key) = key.filter(|&key| Some(key) != self.prev_function_key) {
+            let phrase = CANNED_PHRASES[(key - 2) as usize];
+            self.session.send_chat(th19.vs_mode().player_name(), phrase);
+        }
+        self.prev_function_key = key;
+    }
+
     pub fn update_th19_on_input_players(
         &mut self,
-        main_menu: &MainMenu,
+        screen: ScreenId,
         th19: &mut Th19,
     ) -> Result<(), RecvError> {
         if self.initializing_state == 0 {
@@ -46,8 +74,9 @@ impl SpectatorSelect {
             th19.set_rand_seed2(round_initial.seed2).unwrap();
             th19.set_rand_seed3(round_initial.seed3).unwrap();
             th19.set_rand_seed4(round_initial.seed4).unwrap();
+            self.round_initial = Some(round_initial);
         }
-        if main_menu.screen_id() == ScreenId::DifficultySelect {
+        if screen == ScreenId::DifficultySelect {
             return Ok(());
         }
         if self.initializing_state == 1 {
@@ -56,10 +85,21 @@ impl SpectatorSelect {
                 spectator::Screen::DifficultySelect => {
                     return Ok(());
                 }
-                spectator::Screen::CharacterSelect => unimplemented!(),
+                spectator::Screen::CharacterSelect => {
+                    // 難易度選択画面の決定で乱数が進んでも合うように設定し直す
+                    let round_initial = self.round_initial.take().unwrap();
+                    th19.set_rand_seed1(round_initial.seed1).unwrap();
+                    th19.set_rand_seed2(round_initial.seed2).unwrap();
+                    th19.set_rand_seed3(round_initial.seed3).unwrap();
+                    th19.set_rand_seed4(round_initial.seed4).unwrap();
+                    self.initializing_state = 2;
+                }
                 spectator::Screen::Game => unimplemented!(),
             }
         }
+        if screen == ScreenId::CharacterSelect {
+            self.send_canned_phrase(th19);
+        }
         if !th19.no_wait() {
             th19.set_no_wait(true);
         }
@@ -76,23 +116,24 @@ impl SpectatorSelect {
         Ok(())
     }
 
-    pub fn update_th19_on_input_menu(
-        &mut self,
-        main_menu: &mut MainMenu,
-        th19: &mut Th19,
-    ) -> Result<(), RecvError> {
+    pub fn update_th19_on_input_menu(&mut self, th19: &mut Th19) -> Result<(), RecvError> {
+        let main_menu = th19
+            .app_mut()
+            .main_loop_tasks_mut()
+            .find_main_menu_mut()
+            .unwrap();
         if main_menu.screen_id() != ScreenId::DifficultySelect {
             return Ok(());
         }
-        let menu = main_menu.menu_mut();
+        let cursor = main_menu.menu().cursor();
         if self.initializing_state == 1 {
             let init = self.session.spectator_initial().unwrap();
             trace!("spectator_initial: {:?}", init);
             let initial_state = init.initial_state();
             match initial_state.screen() {
                 spectator::Screen::DifficultySelect => {
-                    if menu.cursor() != initial_state.difficulty() as u32 {
-                        menu.set_cursor(initial_state.difficulty() as u32);
+                    if cursor != initial_state.difficulty() as u32 {
+                        set_difficulty_cursor(th19, initial_state.difficulty() as u32);
                         th19.menu_input_mut().set_current(InputValue::empty());
                         return Ok(());
                     }
@@ -102,7 +143,22 @@ impl SpectatorSelect {
                     th19.set_no_wait(false);
                     self.initializing_state = 2;
                 }
-                spectator::Screen::CharacterSelect => unimplemented!(),
+                spectator::Screen::CharacterSelect => {
+                    if cursor != initial_state.difficulty() as u32 {
+                        set_difficulty_cursor(th19, initial_state.difficulty() as u32);
+                        th19.menu_input_mut().set_current(InputValue::empty());
+                        return Ok(());
+                    }
+                    let selection = th19.selection_mut();
+                    selection.p1_mut().character = initial_state.p1_character() as u32;
+                    selection.p1_mut().card = initial_state.p1_card() as u32;
+                    selection.p2_mut().character = initial_state.p2_character() as u32;
+                    selection.p2_mut().card = initial_state.p2_card() as u32;
+                    // 記録はキャラクター選択画面に入った時点からなので、決定だけして入力は読まない
+                    let input = shot_repeatedly(th19.menu_input().prev());
+                    th19.menu_input_mut().set_current(input);
+                    return Ok(());
+                }
                 spectator::Screen::Game => unimplemented!(),
             }
         }